@@ -0,0 +1,370 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+    time::Instant,
+};
+
+use log::error;
+
+use calculator::{
+    Context, Number, Rounding, Settings, Statement, evaluate_in, parse_statement, strip_comment,
+};
+
+use crate::session::Session;
+
+/// Fractional digits set by the math library option, as `bc -l` does
+const MATHLIB_SCALE: u32 = 20;
+
+/// Functions of the math library of `bc -l` as the built-in ones they stand for, the
+/// Bessel function `j` left out
+const MATHLIB: &[(&str, &str)] = &[
+    ("s", "sin(x)"),
+    ("c", "cos(x)"),
+    ("a", "atan(x)"),
+    ("l", "ln(x)"),
+    ("e", "exp(x)"),
+];
+
+///
+/// Interpreter of the subset of `bc` scripts the calculator understands: expressions,
+/// variables, `scale` and functions defined with `define` returning a single expression
+///
+pub(crate) struct Bc<'a> {
+    context: Context,
+    settings: Settings,
+    /// Lines of a definition whose braces are not closed yet
+    pending: String,
+    /// Session the evaluations are recorded in
+    session: &'a Mutex<Session>,
+}
+
+///
+/// Outcome of a statement, as bc shows it
+///
+#[derive(Debug, PartialEq)]
+pub(crate) enum Reply {
+    /// Value of an expression, written as bc writes it
+    Value(String),
+    /// Assignments and definitions print nothing
+    Nothing,
+    Quit,
+}
+
+impl<'a> Bc<'a> {
+    pub(crate) fn new(mathlib: bool, session: &'a Mutex<Session>) -> Self {
+        let mut context = Context::default();
+        if mathlib {
+            for (name, body) in MATHLIB {
+                context
+                    .define(name, &["x"], body)
+                    .expect("The math library calls built-in functions");
+            }
+        }
+        Self {
+            context,
+            // bc truncates every result to its scale, 0 unless set otherwise
+            settings: Settings {
+                decimals: Some(if mathlib { MATHLIB_SCALE } else { 0 }),
+                rounding: Rounding::Down,
+                ..Default::default()
+            },
+            pending: String::new(),
+            session,
+        }
+    }
+
+    ///
+    /// Run the statements of a line of script, separated by `;`, in order
+    ///
+    pub(crate) fn line(&mut self, line: &str) -> Vec<Result<Reply, String>> {
+        let mut line = strip_block_comments(strip_comment(line));
+        if !self.pending.is_empty() || line.trim_start().starts_with("define") {
+            self.pending.push_str(&line);
+            self.pending.push('\n');
+            let Some(end) = closing_brace(&self.pending) else {
+                return vec![];
+            };
+            let definition = std::mem::take(&mut self.pending);
+            line = definition[end + 1..].to_owned();
+            let mut replies = vec![self.define(&definition[..=end]).map(|()| Reply::Nothing)];
+            replies.extend(self.line(&line));
+            return replies;
+        }
+        let mut replies = vec![];
+        for statement in line.split(';') {
+            let reply = self.statement(statement.trim());
+            let quit = reply == Ok(Reply::Quit);
+            replies.push(reply);
+            if quit {
+                break;
+            }
+        }
+        replies
+    }
+
+    fn statement(&mut self, statement: &str) -> Result<Reply, String> {
+        match statement {
+            "" => return Ok(Reply::Nothing),
+            "quit" => return Ok(Reply::Quit),
+            "scale" => {
+                let scale = self
+                    .settings
+                    .decimals
+                    .expect("bc always computes with decimals");
+                return Ok(Reply::Value(scale.to_string()));
+            }
+            _ => {}
+        }
+        match parse_statement(statement).map_err(|e| e.to_string())? {
+            Statement::Assignment("scale", expr) => {
+                let scale = expr
+                    .parse()
+                    .map_err(|_| format!("scale is set to a number of digits, not {expr}"))?;
+                self.settings.decimals = Some(scale);
+                Ok(Reply::Nothing)
+            }
+            Statement::Assignment(variable, expr) => {
+                let result = self.evaluate(expr)?;
+                self.context.assign(variable, result);
+                Ok(Reply::Nothing)
+            }
+            Statement::Definition { name, params, body } => {
+                self.context
+                    .define(name, &params, body)
+                    .map_err(|e| e.to_string())?;
+                Ok(Reply::Nothing)
+            }
+            Statement::Expression(expr) => Ok(Reply::Value(bc_number(&self.evaluate(expr)?))),
+        }
+    }
+
+    fn evaluate(&mut self, expr: &str) -> Result<Number, String> {
+        let start = Instant::now();
+        let outcome = evaluate_in(expr, &self.settings, &self.context);
+        self.session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record(&outcome, start.elapsed());
+        let evaluation = outcome.map_err(|e| e.to_string())?;
+        self.context.push(evaluation.result.clone());
+        Ok(evaluation.result)
+    }
+
+    ///
+    /// Define a function written as `define f(x, y) { return (x * y) }`, whose body is a
+    /// single return statement
+    ///
+    fn define(&mut self, definition: &str) -> Result<(), String> {
+        let unsupported =
+            || "only functions returning a single expression are supported".to_owned();
+        let (head, body) = definition
+            .trim()
+            .strip_prefix("define")
+            .and_then(|definition| definition.split_once('{'))
+            .ok_or_else(unsupported)?;
+        let body = body.strip_suffix('}').ok_or_else(unsupported)?;
+        let [statement] = body
+            .split([';', '\n'])
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty())
+            .collect::<Vec<&str>>()[..]
+        else {
+            return Err(unsupported());
+        };
+        let expr = statement.strip_prefix("return").ok_or_else(unsupported)?;
+        match parse_statement(&format!("{} = {expr}", head.trim())) {
+            Ok(Statement::Definition { name, params, body }) => self
+                .context
+                .define(name, &params, body)
+                .map_err(|e| e.to_string()),
+            Ok(_) => Err(unsupported()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+///
+/// Run the script files in order, then the script read from standard input, printing
+/// values on standard output and errors on standard error as bc does
+///
+pub(crate) fn run(mathlib: bool, files: &[PathBuf], session: &Mutex<Session>) {
+    let mut bc = Bc::new(mathlib, session);
+    for path in files {
+        match fs::read_to_string(path) {
+            Ok(script) => {
+                for line in script.lines() {
+                    if !print(bc.line(line)) {
+                        return;
+                    }
+                }
+            }
+            Err(e) => error!("Error reading script {} {e}", path.display()),
+        }
+    }
+    for line in io::stdin().lines() {
+        match line {
+            Ok(line) if print(bc.line(&line)) => {}
+            Ok(_) => return,
+            Err(e) => {
+                error!("Error reading input data {e}");
+                return;
+            }
+        }
+    }
+}
+
+///
+/// Print the replies of a line, false when the script quits
+///
+fn print(replies: Vec<Result<Reply, String>>) -> bool {
+    for reply in replies {
+        match reply {
+            Ok(Reply::Value(value)) => println!("{value}"),
+            Ok(Reply::Nothing) => {}
+            Ok(Reply::Quit) => return false,
+            Err(e) => eprintln!("Cannot run statement due to error {e}"),
+        }
+    }
+    true
+}
+
+///
+/// Number written as bc writes it, without the 0 before the point
+///
+fn bc_number(n: &Number) -> String {
+    let digits = n.to_string();
+    match digits.strip_prefix('-') {
+        Some(magnitude) if magnitude.starts_with("0.") => format!("-{}", &magnitude[1..]),
+        None if digits.starts_with("0.") => digits[1..].to_owned(),
+        _ => digits,
+    }
+}
+
+///
+/// Drop the `/* ... */` comments of a line
+///
+fn strip_block_comments(line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find("*/") {
+            Some(end) => rest = &rest[start + end + 2..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+///
+/// Position of the brace closing the first one opened, if it is closed
+///
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::{Bc, Reply};
+    use crate::session::Session;
+
+    ///
+    /// Values printed by the script, up to its end or to `quit`
+    ///
+    fn values(bc: &mut Bc, script: &str) -> Vec<String> {
+        let mut values = vec![];
+        for line in script.lines() {
+            for reply in bc.line(line) {
+                match reply.unwrap() {
+                    Reply::Value(value) => values.push(value),
+                    Reply::Nothing => {}
+                    Reply::Quit => return values,
+                }
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn test_scale() {
+        let session = Mutex::new(Session::new());
+        let mut bc = Bc::new(false, &session);
+        assert_eq!(
+            values(&mut bc, "7 / 2\nscale = 4; 1 / 3; -1 / 4\nscale"),
+            ["3", ".3333", "-.25", "4"]
+        );
+        assert_eq!(
+            values(&mut Bc::new(true, &session), "scale; 2 / 3"),
+            ["20", ".66666666666666666666"]
+        );
+        assert!(bc.line("scale = x").remove(0).is_err());
+    }
+
+    #[test]
+    fn test_mathlib() {
+        let session = Mutex::new(Session::new());
+        let mut bc = Bc::new(true, &session);
+        assert_eq!(
+            values(
+                &mut bc,
+                "e(1); l(2); s(0); c(0); 4 * a(1)\nscale = 5; s(1); l(e(3))"
+            ),
+            [
+                "2.71828182845904523536",
+                ".69314718055994530941",
+                "0",
+                "1",
+                "3.14159265358979323844",
+                ".84147",
+                "2.99999"
+            ]
+        );
+        assert!(Bc::new(false, &session).line("l(2)").remove(0).is_err());
+    }
+
+    #[test]
+    fn test_variables_and_functions() {
+        let session = Mutex::new(Session::new());
+        let mut bc = Bc::new(true, &session);
+        let script = "x = 3 /* side */\n\
+                      define area(a, b) {\n  return (a * b);\n}\n\
+                      define double(a) { return 2 * a } double(x)\n\
+                      area(x, 4) # comment\n\
+                      sqrt(area(x, 3))\n\
+                      quit\n\
+                      x";
+        assert_eq!(values(&mut bc, script), ["6", "12", "3"]);
+        assert!(
+            bc.line("define f(a) { auto b; return a }")
+                .remove(0)
+                .is_err()
+        );
+    }
+    #[test]
+    fn test_session() {
+        let session = Mutex::new(Session::new());
+        let mut bc = Bc::new(false, &session);
+        for line in ["x = 6; x / 2", "scale = 2; 1 / 0", "2 + 2"] {
+            bc.line(line);
+        }
+
+        let summary = session.into_inner().unwrap().to_string();
+        assert!(
+            summary.starts_with("Expressions evaluated: 4\nErrors: 1 (25.0%)\n"),
+            "{summary}"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+
+use calculator::{
+    AstFormat, DEFAULT_ANSWERS, DecimalSeparator, IntWidth, Overflow, Rounding, Settings,
+};
+
+use crate::{format::SignDisplay, quiz::Difficulty, trace::TraceFormat};
+
+///
+/// Calculator reading math expressions from standard input
+///
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub(crate) struct Args {
+    /// Fail on divisions that would truncate the result instead of warning about them
+    #[arg(long)]
+    pub(crate) exact: bool,
+    /// Behavior of integer operations whose result does not fit in the integer width
+    #[arg(long, value_enum, default_value_t = Overflow::Checked)]
+    pub(crate) overflow: Overflow,
+    /// Number of bits of the integers used by the calculator
+    #[arg(long, value_enum, default_value_t = IntWidth::W64)]
+    pub(crate) int_width: IntWidth,
+    /// Use unsigned integers, where negative results overflow (up to 64 bits)
+    #[arg(long)]
+    pub(crate) unsigned: bool,
+    /// Treat integers as bit patterns of the integer width: literals and results wrap
+    /// around in two's complement, and results are also shown in hexadecimal and binary
+    #[arg(long, conflicts_with = "overflow")]
+    pub(crate) programmer: bool,
+    /// Compute with arbitrary precision decimals rounded to N fractional digits instead of
+    /// integers
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(..=1000),
+        conflicts_with_all = ["exact", "overflow", "int_width", "unsigned", "programmer"]
+    )]
+    pub(crate) decimals: Option<u32>,
+    /// How decimal results are rounded to their fractional digits
+    #[arg(long, value_enum, default_value_t = Rounding::HalfEven, requires = "decimals")]
+    pub(crate) rounding: Rounding,
+    /// Print token count, tree depth and operation count of each expression
+    #[arg(long)]
+    pub(crate) stats: bool,
+    /// Whether positive results are printed with a leading +
+    #[arg(long, value_enum, default_value_t = SignDisplay::Auto)]
+    pub(crate) sign: SignDisplay,
+    /// Separator of the fractional digits in expressions and results. With a comma, a comma
+    /// between digits is the decimal point, arguments being separated by a comma and a
+    /// space, and a dot groups thousands, as in `1.234,5`
+    #[arg(long, value_enum, default_value_t = DecimalSeparator::Point)]
+    pub(crate) decimal_separator: DecimalSeparator,
+    /// Significant digits results are printed with at most, integral digits being always
+    /// printed. Results are still computed with all of their digits
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub(crate) precision: Option<u32>,
+    /// Print the Reverse Polish Notation each result was computed from
+    #[arg(long)]
+    pub(crate) show_rpn: bool,
+    /// Read expressions in Reverse Polish Notation, as in `3 4 + 2 *`
+    #[arg(long)]
+    pub(crate) rpn: bool,
+    /// Explain how precedence groups each expression when it is not written explicitly
+    #[arg(long)]
+    pub(crate) explain: bool,
+    /// Print the structure of each expression before its result
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub(crate) dump_ast: Option<AstFormat>,
+    /// Trace each evaluation step, showing the value stack after every RPN token
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub(crate) trace_format: Option<TraceFormat>,
+    /// Write JSON traces to this file instead of standard output
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "PATH", requires = "trace_format")]
+    pub(crate) trace_file: Option<PathBuf>,
+    /// Evaluate each expression again by walking its tree and report disagreements with RPN,
+    /// which is generated from the same tree
+    #[arg(long)]
+    pub(crate) verify: bool,
+    /// Number of results kept in the answers stack, referred to as ans(1), ans(2), ...
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_ANSWERS)]
+    pub(crate) answers: usize,
+    /// Print a summary of the evaluated expressions when the session ends
+    #[arg(long)]
+    pub(crate) summary: bool,
+    /// Neither load the lines typed in previous sessions nor save the ones of this session
+    #[arg(long)]
+    pub(crate) no_history: bool,
+    /// Neither define the functions saved with `:define` nor save new ones
+    #[arg(long)]
+    pub(crate) no_definitions: bool,
+    /// Seed of the random functions, to draw the same numbers in every run
+    #[arg(long, value_name = "N")]
+    pub(crate) seed: Option<u64>,
+    /// Evaluate this line and exit instead of reading standard input. May be repeated,
+    /// the lines running in order
+    #[arg(short = 'e', long = "expression", value_name = "EXPR")]
+    pub(crate) expressions: Vec<String>,
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+///
+/// Alternative modes to the expression evaluation loop
+///
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum Command {
+    /// Practice mental arithmetic answering randomly generated expressions
+    Quiz {
+        /// How hard the generated expressions are
+        #[arg(long, value_enum, default_value_t = Difficulty::Medium)]
+        difficulty: Difficulty,
+        /// Number of questions to ask
+        #[arg(long, default_value_t = 10)]
+        questions: usize,
+    },
+    /// Run bc scripts: expressions, variables, scale and functions returning a single
+    /// expression, reading the given files and then standard input
+    Bc {
+        /// Start with 20 fractional digits and the math library functions s, c, a, l and e,
+        /// as bc -l does
+        #[arg(short = 'l', long)]
+        mathlib: bool,
+        /// Scripts run before standard input
+        files: Vec<PathBuf>,
+    },
+}
+
+///
+/// Notation the REPL reads expressions in
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Notation {
+    /// Operators between their operands, as in `(3 + 4) * 2`
+    Infix,
+    /// Operators after their operands, as in `3 4 + 2 *`
+    Rpn,
+}
+
+impl Args {
+    pub(crate) fn settings(&self) -> Settings {
+        Settings {
+            exact: self.exact,
+            overflow: if self.programmer {
+                Overflow::Wrap
+            } else {
+                self.overflow
+            },
+            int_width: self.int_width,
+            unsigned: self.unsigned,
+            trace: self.trace_format.is_some(),
+            decimals: self.decimals,
+            rounding: self.rounding,
+            programmer: self.programmer,
+        }
+    }
+
+    ///
+    /// Parse command line arguments, exiting on combinations clap cannot validate alone
+    ///
+    pub(crate) fn parse_validated() -> Self {
+        let args = Self::parse();
+        if args.unsigned && args.int_width == IntWidth::W128 {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--unsigned supports integer widths up to 64 bits",
+                )
+                .exit();
+        }
+        args
+    }
+}
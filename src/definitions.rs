@@ -0,0 +1,254 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use calculator::{Context, Statement, parse_statement};
+use log::error;
+
+///
+/// Functions defined with `:define`, kept in a file and defined again in every session,
+/// unlike the ones defined with `name(parameters) = body` which end with the session
+///
+#[derive(Debug, Default)]
+pub(crate) struct Definitions {
+    path: Option<PathBuf>,
+    /// Definitions as they are saved, as in `area(w, h) = w * h`
+    lines: Vec<String>,
+}
+
+///
+/// What became of a definition once its function is defined
+///
+#[derive(Debug, PartialEq)]
+pub(crate) enum Saving {
+    Saved,
+    /// Without a file, as with `--no-definitions`, the function only lasts for the session
+    Unsaved,
+    /// The file could not be written, the function lasting for the session
+    Failed(String),
+}
+
+impl Definitions {
+    ///
+    /// Definitions kept in `$XDG_CONFIG_HOME/calculator/definitions`, by default
+    /// `~/.config/calculator/definitions`, defining the functions of the file in the
+    /// context. Without a file definitions only last for the session
+    ///
+    pub(crate) fn open(context: &mut Context, persistent: bool) -> Definitions {
+        let path = persistent
+            .then(|| {
+                location(
+                    std::env::var_os("XDG_CONFIG_HOME"),
+                    std::env::var_os("HOME"),
+                )
+            })
+            .flatten();
+        Definitions::load(context, path)
+    }
+
+    ///
+    /// Definitions of the given file, defining its functions in the context
+    ///
+    fn load(context: &mut Context, path: Option<PathBuf>) -> Definitions {
+        let mut definitions = Definitions {
+            path,
+            lines: vec![],
+        };
+        let saved = match &definitions.path {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(saved) => saved,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(e) => {
+                    error!("Error loading definitions {e}");
+                    String::new()
+                }
+            },
+            None => String::new(),
+        };
+        for line in saved.lines().filter(|line| !line.trim().is_empty()) {
+            if let Err(e) = definitions.add(context, line) {
+                error!("Error loading definition {line}: {e}");
+            }
+        }
+        definitions
+    }
+
+    ///
+    /// Define a function and save its definition, which replaces the saved one of the same
+    /// name and number of parameters. Tells the function defined, as in `area(w, h)`, and
+    /// whether its definition was saved
+    ///
+    pub(crate) fn define(
+        &mut self,
+        context: &mut Context,
+        line: &str,
+    ) -> Result<(String, Saving), String> {
+        let signature = self.add(context, line)?;
+        let saving = match &self.path {
+            Some(path) => match save(&self.lines, path) {
+                Ok(()) => Saving::Saved,
+                Err(e) => {
+                    error!("Error saving definitions {e}");
+                    Saving::Failed(e.to_string())
+                }
+            },
+            None => Saving::Unsaved,
+        };
+        Ok((signature, saving))
+    }
+
+    ///
+    /// Define the saved functions again, as after the context forgot its functions
+    ///
+    pub(crate) fn restore(&self, context: &mut Context) {
+        for line in &self.lines {
+            if let Ok((name, params, body)) = definition(line)
+                && let Err(e) = context.define(name, &params, body)
+            {
+                error!("Error restoring definition {line}: {e}");
+            }
+        }
+    }
+
+    ///
+    /// Definitions as they are saved, in the order they were first made
+    ///
+    pub(crate) fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    fn add(&mut self, context: &mut Context, line: &str) -> Result<String, String> {
+        let (name, params, body) = definition(line.trim())?;
+        context
+            .define(name, &params, body)
+            .map_err(|e| e.to_string())?;
+        let saved = self.lines.iter().position(|saved| {
+            definition(saved).is_ok_and(|(saved, saved_params, _)| {
+                (saved, saved_params.len()) == (name, params.len())
+            })
+        });
+        let line = format!("{name}({}) = {}", params.join(", "), body.trim());
+        match saved {
+            Some(index) => self.lines[index] = line,
+            None => self.lines.push(line),
+        }
+        Ok(format!("{name}({})", params.join(", ")))
+    }
+}
+
+///
+/// Name, parameters and body of a function definition, an error for any other statement
+///
+fn definition(line: &str) -> Result<(&str, Vec<&str>, &str), String> {
+    match parse_statement(line) {
+        Ok(Statement::Definition { name, params, body }) => Ok((name, params, body)),
+        Ok(_) => Err(format!("{line} is not a function definition")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn save(lines: &[String], path: &Path) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let mut saved = lines.join("\n");
+    saved.push('\n');
+    fs::write(path, saved)
+}
+
+///
+/// File of the definitions, within the configuration directory or `.config` in the home one
+///
+fn location(config: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let config = match config.filter(|config| !config.is_empty()) {
+        Some(config) => PathBuf::from(config),
+        None => PathBuf::from(home.filter(|home| !home.is_empty())?).join(".config"),
+    };
+    Some(config.join("calculator").join("definitions"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use calculator::{Context, Settings, evaluate_in};
+
+    use super::{Definitions, Saving, location};
+
+    #[test]
+    fn test_location() {
+        assert_eq!(
+            location(None, Some("/home/user".into())),
+            Some(PathBuf::from("/home/user/.config/calculator/definitions"))
+        );
+        assert_eq!(
+            location(Some("/config".into()), Some("/home/user".into())),
+            Some(PathBuf::from("/config/calculator/definitions"))
+        );
+        assert_eq!(location(Some("".into()), None), None);
+    }
+
+    #[test]
+    fn test_definitions() {
+        let path = std::env::temp_dir()
+            .join(format!("calculator-{}", std::process::id()))
+            .join("definitions");
+        let mut context = Context::default();
+        let mut definitions = Definitions {
+            path: Some(path.clone()),
+            lines: vec![],
+        };
+        let evaluate = |context: &Context| {
+            evaluate_in("area(2, 3) + double(4)", &Settings::default(), context)
+                .unwrap()
+                .result
+                .to_string()
+        };
+
+        assert_eq!(
+            definitions.define(&mut context, "area(w, h) = w * h"),
+            Ok(("area(w, h)".to_owned(), Saving::Saved))
+        );
+        definitions.define(&mut context, " double(x)=2*x ").unwrap();
+        assert_eq!(evaluate(&context), "14");
+        // A definition of the same name and number of parameters replaces the saved one
+        definitions
+            .define(&mut context, "area(w, h) = w * h / 2")
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "area(w, h) = w * h / 2\ndouble(x) = 2*x\n"
+        );
+        for line in ["x = 4", "1 + 2", "f(x) = f(x)"] {
+            assert!(definitions.define(&mut context, line).is_err(), "{line}");
+        }
+        assert_eq!(definitions.lines().len(), 2);
+
+        // Another session defines the saved functions again, as does clearing the context
+        let mut context = Context::default();
+        let loaded = Definitions::load(&mut context, Some(path.clone()));
+        assert_eq!(loaded.lines(), definitions.lines());
+        assert_eq!(evaluate(&context), "11");
+        context.reset();
+        loaded.restore(&mut context);
+        assert_eq!(evaluate(&context), "11");
+
+        // Definitions which cannot be written only last for the session
+        let mut blocked = Definitions {
+            path: Some(path.join("definitions")),
+            lines: vec![],
+        };
+        assert!(matches!(
+            blocked.define(&mut context, "triple(x) = 3 * x"),
+            Ok((_, Saving::Failed(_)))
+        ));
+        assert_eq!(
+            Definitions::default().define(&mut context, "triple(x) = 3 * x"),
+            Ok(("triple(x)".to_owned(), Saving::Unsaved))
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+use calculator::Error;
+
+///
+/// Expression with the span of a parsing error underlined as `^~~~`, followed by a hint.
+/// None for the errors that do not point to a part of the expression
+///
+pub(crate) fn render(expr: &str, error: &Error) -> Option<String> {
+    let span = error.span()?;
+    // Tabs are kept so that the underline lines up with the expression
+    let padding: String = expr
+        .chars()
+        .chain(std::iter::repeat(' '))
+        .take(span.start)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underline = format!("^{}", "~".repeat(span.end - span.start - 1));
+    Some(format!("  {expr}\n  {padding}{underline} {}", hint(error)))
+}
+
+fn hint(error: &Error) -> String {
+    match error {
+        Error::InvalidSyntax { found, .. } => match found.as_str() {
+            "," => "commas only separate the arguments of a function".to_owned(),
+            ")" | "]" | "}" => "a closing bracket has to follow a complete value".to_owned(),
+            "|" => "this absolute value is never closed".to_owned(),
+            "?" | ":" => "a conditional is written as condition ? value : value".to_owned(),
+            _ => "a value or an operator is missing around here".to_owned(),
+        },
+        Error::TrailingOperator { .. } => "add the operand this operator is missing".to_owned(),
+        Error::MismatchedBracket { opener, .. } => {
+            let expected = match opener {
+                '(' => ')',
+                '[' => ']',
+                '{' => '}',
+                _ => '|',
+            };
+            format!("'{opener}' is closed by '{expected}'")
+        }
+        // The message is already in the error, written as a sentence
+        Error::UnmatchedParen { bracket, .. } => match bracket {
+            '(' | '[' | '{' => "this bracket is never closed".to_owned(),
+            _ => "this bracket closes nothing".to_owned(),
+        },
+        Error::InvalidExpression { message, .. } => {
+            let mut chars = message.chars();
+            chars
+                .next()
+                .map(|first| first.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use calculator::{Error, Settings, evaluate};
+
+    fn diagnostic(expr: &str) -> Option<String> {
+        render(expr, &evaluate(expr, &Settings::default()).unwrap_err())
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            diagnostic("4 + * 3").unwrap(),
+            "  4 + * 3\n      ^ a value or an operator is missing around here"
+        );
+        assert_eq!(
+            diagnostic("2 * (3 ]").unwrap(),
+            "  2 * (3 ]\n         ^ '(' is closed by ')'"
+        );
+        assert_eq!(
+            diagnostic("(1 + 2) * (3").unwrap(),
+            "  (1 + 2) * (3\n            ^ this bracket is never closed"
+        );
+        assert_eq!(
+            diagnostic("1 + xor 2").unwrap(),
+            "  1 + xor 2\n      ^~~ a value or an operator is missing around here"
+        );
+        assert_eq!(
+            diagnostic("\t1 *").unwrap(),
+            "  \t1 *\n  \t  ^ add the operand this operator is missing"
+        );
+        assert_eq!(
+            render("1 / 0", &Error::DivisionByZero("1 / 0".to_owned())),
+            None
+        );
+    }
+}
@@ -0,0 +1,697 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use num_bigint::BigInt;
+
+use calculator::{DecimalSeparator, Number, to_fraction, to_pattern, to_radix};
+
+///
+/// Whether results carry a sign when they are not negative
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SignDisplay {
+    /// Only negative results carry a sign
+    #[default]
+    Auto,
+    /// Positive results are written with a leading `+`
+    Always,
+}
+
+///
+/// Base results are written in, prefixed as the literals of the same base
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Base {
+    #[value(name = "2")]
+    Binary,
+    #[value(name = "8")]
+    Octal,
+    #[default]
+    #[value(name = "10")]
+    Decimal,
+    #[value(name = "16")]
+    Hexadecimal,
+}
+
+///
+/// Whether the integral digits of results are grouped by thousands
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Grouping {
+    #[default]
+    Off,
+    /// With the separator of the locale, as in `1,234,567` or `1.234.567` after a comma
+    On,
+    /// With a space, as in `1 234 567`
+    Space,
+}
+
+///
+/// Whether results are written with a power of ten, as in `1.2345e6`
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Exponent {
+    /// Digits as they are computed
+    #[default]
+    Plain,
+    /// A single integral digit, as in `1.2345e6`
+    Sci,
+    /// Up to three integral digits and exponents multiple of 3, as in `12.345e3`
+    Eng,
+}
+
+impl Base {
+    fn radix(self) -> u32 {
+        match self {
+            Base::Binary => 2,
+            Base::Octal => 8,
+            Base::Decimal => 10,
+            Base::Hexadecimal => 16,
+        }
+    }
+}
+
+///
+/// Formatting profile applied to the results printed by the REPL
+///
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Format {
+    pub(crate) sign: SignDisplay,
+    pub(crate) base: Base,
+    pub(crate) grouping: Grouping,
+    pub(crate) exponent: Exponent,
+    pub(crate) separator: DecimalSeparator,
+    /// Digits written after the point, rounding half away from zero or padding with zeros
+    pub(crate) places: Option<usize>,
+    /// Significant digits written at most, integral digits being always written
+    pub(crate) precision: Option<usize>,
+    /// Largest denominator of the fractions decimals are written as, as in `1/3`
+    pub(crate) fraction: Option<u64>,
+    /// Symbol written before the digits, after the sign, as in `-$5.00`
+    pub(crate) currency: Option<String>,
+    /// Width of the two's complement bit patterns shown in programmer mode
+    pub(crate) bits: Option<u32>,
+}
+
+impl Format {
+    pub(crate) fn number(&self, n: &Number) -> String {
+        // In programmer mode other bases show the bit pattern, and base 10 is followed by it
+        if let Some(bits) = self.bits
+            && let Some(pattern) = to_pattern(n, self.base.radix(), bits)
+        {
+            return pattern;
+        }
+        let digits = to_radix(n, self.base.radix());
+        // Fractions, grouping, exponents, places, precision and currency only apply to real
+        // numbers written in base 10
+        let digits = match n {
+            _ if self.base != Base::Decimal => digits,
+            Number::Decimal(_)
+                if let Some(fraction) = self.fraction.and_then(|bound| to_fraction(n, bound)) =>
+            {
+                fraction
+            }
+            Number::Int(_) | Number::Big(_) | Number::Decimal(_) => self.style(&digits),
+            _ => digits,
+        };
+        let digits = match self.sign {
+            SignDisplay::Always if *n > Number::Int(0) => format!("+{digits}"),
+            _ => digits,
+        };
+        match self
+            .bits
+            .and_then(|bits| Some((to_pattern(n, 16, bits)?, to_pattern(n, 2, bits)?)))
+        {
+            Some((hex, binary)) => format!("{digits} = {hex} = {binary}"),
+            None => digits,
+        }
+    }
+
+    ///
+    /// Digits of a number rounded to the precision or padded to the places, grouped by thousands or followed
+    /// by an exponent, with the decimal separator and preceded by the currency symbol, as in
+    /// `-$1,234.50`
+    ///
+    fn style(&self, digits: &str) -> String {
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", digits),
+        };
+        let (integral, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let (integral, fraction, mut exponent) = match self.exponent {
+            Exponent::Plain => (integral.to_owned(), fraction.to_owned(), None),
+            Exponent::Sci => shift(integral, fraction, 1),
+            Exponent::Eng => shift(integral, fraction, 3),
+        };
+        // Places are padded with zeros, while the precision only drops digits
+        let places = self.places.or_else(|| {
+            self.precision
+                .map(|digits| significant_places(&integral, &fraction, digits).min(fraction.len()))
+        });
+        let (mut integral, mut fraction) = match places {
+            Some(places) => round(&integral, &fraction, places),
+            None => (integral, fraction),
+        };
+        // Rounding up 9.99 gives 10.0, whose exponent has to grow
+        if let Some(places) = places
+            && let Some(shifted) = exponent
+        {
+            let step = if self.exponent == Exponent::Eng { 3 } else { 1 };
+            let (digits, rest, grown) = shift(&integral, &fraction, step);
+            (integral, fraction) = round(&digits, &rest, places);
+            exponent = grown.map(|grown| grown + shifted);
+        }
+        if self.places.is_none() {
+            fraction.truncate(fraction.trim_end_matches('0').len());
+        }
+        // Rounding may leave nothing but zeros, which carry no sign
+        let sign = if (integral.clone() + &fraction).chars().all(|c| c == '0') {
+            ""
+        } else {
+            sign
+        };
+        let (point, thousands) = match self.separator {
+            DecimalSeparator::Point => (".", ','),
+            DecimalSeparator::Comma => (",", '.'),
+        };
+        match self.grouping {
+            Grouping::Off => {}
+            Grouping::On => integral = group(&integral, thousands),
+            Grouping::Space => integral = group(&integral, ' '),
+        }
+        let currency = self.currency.as_deref().unwrap_or_default();
+        let point = if fraction.is_empty() { "" } else { point };
+        let exponent = exponent.map(|e| format!("e{e}")).unwrap_or_default();
+        format!("{sign}{currency}{integral}{point}{fraction}{exponent}")
+    }
+
+    ///
+    /// Change one option of the profile, as in `:format sign always`
+    ///
+    pub(crate) fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "sign" => self.sign = SignDisplay::from_str(value, true)?,
+            "base" => self.base = Base::from_str(value, true)?,
+            "group" => self.grouping = Grouping::from_str(value, true)?,
+            "notation" => self.exponent = Exponent::from_str(value, true)?,
+            "separator" => self.separator = DecimalSeparator::from_str(value, true)?,
+            "places" => {
+                self.places = match value {
+                    "auto" => None,
+                    places => Some(
+                        places
+                            .parse()
+                            .map_err(|_| format!("Invalid number of places {places}"))?,
+                    ),
+                }
+            }
+            "precision" => {
+                self.precision = match value {
+                    "auto" => None,
+                    digits => Some(
+                        digits
+                            .parse()
+                            .ok()
+                            .filter(|digits| *digits > 0)
+                            .ok_or_else(|| format!("Invalid precision {digits}"))?,
+                    ),
+                }
+            }
+            "fraction" => {
+                self.fraction = match value {
+                    "off" => None,
+                    bound => Some(
+                        bound
+                            .parse()
+                            .ok()
+                            .filter(|bound| *bound > 0)
+                            .ok_or_else(|| format!("Invalid largest denominator {bound}"))?,
+                    ),
+                }
+            }
+            "currency" => {
+                self.currency = match value {
+                    "none" => None,
+                    symbol => Some(symbol.to_owned()),
+                }
+            }
+            _ => return Err(format!("Unknown format option {option}")),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = self
+            .sign
+            .to_possible_value()
+            .expect("Sign displays are never skipped");
+        let base = self
+            .base
+            .to_possible_value()
+            .expect("Bases are never skipped");
+        write!(f, "sign: {}, base: {}", sign.get_name(), base.get_name())?;
+        if self.grouping != Grouping::Off {
+            let grouping = self
+                .grouping
+                .to_possible_value()
+                .expect("Groupings are never skipped");
+            write!(f, ", group: {}", grouping.get_name())?;
+        }
+        if self.exponent != Exponent::Plain {
+            let exponent = self
+                .exponent
+                .to_possible_value()
+                .expect("Exponents are never skipped");
+            write!(f, ", notation: {}", exponent.get_name())?;
+        }
+        if self.separator == DecimalSeparator::Comma {
+            write!(f, ", separator: comma")?;
+        }
+        if let Some(places) = self.places {
+            write!(f, ", places: {places}")?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ", precision: {precision}")?;
+        }
+        if let Some(fraction) = self.fraction {
+            write!(f, ", fraction: {fraction}")?;
+        }
+        if let Some(currency) = &self.currency {
+            write!(f, ", currency: {currency}")?;
+        }
+        match self.bits {
+            Some(bits) => write!(f, ", programmer: {bits} bits"),
+            None => Ok(()),
+        }
+    }
+}
+
+///
+/// Integral and fractional digits with exactly the given number of digits after the point,
+/// rounded half away from zero
+///
+fn round(integral: &str, fraction: &str, places: usize) -> (String, String) {
+    if fraction.len() <= places {
+        return (integral.to_owned(), format!("{fraction:0<places$}"));
+    }
+    let mut digits: Vec<u8> = format!("{integral}{}", &fraction[..places]).into_bytes();
+    if fraction.as_bytes()[places] >= b'5' {
+        // Carry the increment through the nines, a leading one being added when all are
+        let mut position = digits.len();
+        loop {
+            if position == 0 {
+                digits.insert(0, b'1');
+                break;
+            }
+            position -= 1;
+            if digits[position] == b'9' {
+                digits[position] = b'0';
+            } else {
+                digits[position] += 1;
+                break;
+            }
+        }
+    }
+    let digits = String::from_utf8(digits).expect("Digits are ASCII");
+    let (integral, fraction) = digits.split_at(digits.len() - places);
+    let integral = if integral.is_empty() { "0" } else { integral };
+    (integral.to_owned(), fraction.to_owned())
+}
+
+///
+/// Two's complement bit pattern of an integer on the given number of bits, by bytes of
+/// two groups of four as in `1111_1111 1111_0000`, followed by the values it has as a
+/// signed and as an unsigned integer. Otherwise why there is none, for decimals with a
+/// fraction, other numbers and integers beyond the bits
+///
+pub(crate) fn bit_view(n: &Number, bits: u32) -> Result<String, String> {
+    let Some(pattern) = to_pattern(n, 2, bits) else {
+        return Err(match n.to_integer() {
+            Some(_) => format!("which does not fit on {bits} bits"),
+            None => "which is not an integer".to_owned(),
+        });
+    };
+    let digits: String = pattern.trim_start_matches("0b").replace('_', "");
+    let bytes: Vec<String> = digits
+        .as_bytes()
+        .rchunks(8)
+        .rev()
+        .map(|byte| {
+            let byte = String::from_utf8_lossy(byte);
+            let (high, low) = byte.split_at(byte.len().saturating_sub(4));
+            [high, low]
+                .into_iter()
+                .filter(|nibble| !nibble.is_empty())
+                .collect::<Vec<_>>()
+                .join("_")
+        })
+        .collect();
+    let unsigned = BigInt::parse_bytes(digits.as_bytes(), 2).expect("Patterns are binary");
+    let signed = if digits.starts_with('1') {
+        &unsigned - (BigInt::from(1) << bits)
+    } else {
+        unsigned.clone()
+    };
+    Ok(format!(
+        "{} = {signed} signed = {unsigned} unsigned",
+        bytes.join(" ")
+    ))
+}
+
+///
+/// Digits to keep after the point so that the given number of significant digits are
+/// written, or all the integral ones when there are more
+///
+fn significant_places(integral: &str, fraction: &str, digits: usize) -> usize {
+    match integral.trim_start_matches('0').len() {
+        0 => fraction.len() - fraction.trim_start_matches('0').len() + digits,
+        integral => digits.saturating_sub(integral),
+    }
+}
+
+///
+/// Integral and fractional digits of the same number divided by a power of ten whose
+/// exponent is a multiple of the step, leaving between one and step integral digits. The
+/// exponent is None for zero, which is written as it is
+///
+fn shift(integral: &str, fraction: &str, step: i64) -> (String, String, Option<i64>) {
+    let digits = format!("{integral}{fraction}");
+    let Some(first) = digits.find(|c| c != '0') else {
+        return (
+            "0".to_owned(),
+            fraction.trim_end_matches('0').to_owned(),
+            None,
+        );
+    };
+    let exponent = integral.len() as i64 - first as i64 - 1;
+    let aligned = exponent.div_euclid(step) * step;
+    let width = (exponent - aligned + 1) as usize;
+    let significant = digits[first..].trim_end_matches('0');
+    let significant = format!("{significant:0<width$}");
+    let (integral, fraction) = significant.split_at(width);
+    (integral.to_owned(), fraction.to_owned(), Some(aligned))
+}
+
+///
+/// Integral digits with the separator between groups of three, as in `1,234,567`
+///
+fn group(integral: &str, separator: char) -> String {
+    let mut grouped = String::new();
+    for (index, digit) in integral.chars().enumerate() {
+        if index > 0 && (integral.len() - index).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Base, Exponent, Format, Grouping, SignDisplay, bit_view};
+    use calculator::Number;
+
+    #[test]
+    fn test_sign_display() {
+        let mut format = Format::default();
+
+        assert_eq!(format.number(&Number::Int(7)), "7");
+
+        format.set("sign", "always").unwrap();
+        assert_eq!(format.sign, SignDisplay::Always);
+        assert_eq!(format.number(&Number::Int(7)), "+7");
+        assert_eq!(format.number(&Number::Int(0)), "0");
+        assert_eq!(format.number(&Number::Int(-7)), "-7");
+        assert_eq!(format.to_string(), "sign: always, base: 10");
+
+        assert!(format.set("sign", "never").is_err());
+        assert!(format.set("decimals", "2").is_err());
+    }
+    #[test]
+    fn test_base() {
+        let mut format = Format::default();
+
+        format.set("base", "16").unwrap();
+        assert_eq!(format.base, Base::Hexadecimal);
+        assert_eq!(format.number(&Number::Int(255)), "0xFF");
+        assert_eq!(format.number(&Number::Int(-255)), "-0xFF");
+        assert_eq!(format.number(&Number::Int(0)), "0x0");
+
+        format.set("base", "2").unwrap();
+        format.set("sign", "always").unwrap();
+        assert_eq!(format.number(&Number::Int(10)), "+0b1010");
+        format.set("base", "8").unwrap();
+        assert_eq!(format.number(&Number::Int(15)), "+0o17");
+        assert_eq!(format.to_string(), "sign: always, base: 8");
+
+        assert!(format.set("base", "7").is_err());
+    }
+
+    #[test]
+    fn test_programmer_patterns() {
+        let mut format = Format {
+            bits: Some(8),
+            ..Default::default()
+        };
+
+        assert_eq!(format.number(&Number::Int(-56)), "-56 = 0xC8 = 0b1100_1000");
+        assert_eq!(format.number(&Number::Int(5)), "5 = 0x05 = 0b0000_0101");
+        assert_eq!(
+            format.to_string(),
+            "sign: auto, base: 10, programmer: 8 bits"
+        );
+
+        format.set("base", "8").unwrap();
+        assert_eq!(format.number(&Number::Int(-1)), "0o377");
+        format.bits = Some(16);
+        format.set("base", "16").unwrap();
+        assert_eq!(format.number(&Number::Int(-2)), "0xFFFE");
+    }
+
+    #[test]
+    fn test_grouping_places_currency() {
+        let mut format = Format::default();
+        let decimal = |digits: &str| {
+            calculator::evaluate(
+                digits,
+                &calculator::Settings {
+                    decimals: Some(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .result
+        };
+
+        format.set("group", "on").unwrap();
+        assert_eq!(format.grouping, Grouping::On);
+        assert_eq!(format.number(&Number::Int(1234567)), "1,234,567");
+        assert_eq!(format.number(&Number::Int(-123456)), "-123,456");
+        assert_eq!(format.number(&Number::Int(999)), "999");
+        assert_eq!(format.number(&decimal("1234.5")), "1,234.5");
+
+        format.set("places", "2").unwrap();
+        assert_eq!(format.number(&Number::Int(1234)), "1,234.00");
+        assert_eq!(format.number(&decimal("1234.5678")), "1,234.57");
+        assert_eq!(format.number(&decimal("999.995")), "1,000.00");
+        assert_eq!(format.number(&decimal("-0.004")), "0.00");
+
+        format.set("currency", "$").unwrap();
+        format.set("sign", "always").unwrap();
+        assert_eq!(format.number(&decimal("-1234.5")), "-$1,234.50");
+        assert_eq!(format.number(&Number::Int(5)), "+$5.00");
+        assert_eq!(
+            format.to_string(),
+            "sign: always, base: 10, group: on, places: 2, currency: $"
+        );
+
+        // Other bases are left as they are
+        format.set("base", "16").unwrap();
+        assert_eq!(format.number(&Number::Int(4096)), "+0x1000");
+
+        format.set("places", "auto").unwrap();
+        format.set("currency", "none").unwrap();
+        format.set("group", "off").unwrap();
+        format.set("base", "10").unwrap();
+        assert_eq!(format.number(&decimal("1234.5")), "+1234.5");
+        assert!(format.set("places", "-1").is_err());
+
+        format.set("separator", "comma").unwrap();
+        format.set("group", "on").unwrap();
+        assert_eq!(format.number(&decimal("-1234567.25")), "-1.234.567,25");
+        format.set("group", "space").unwrap();
+        format.set("sign", "auto").unwrap();
+        assert_eq!(format.number(&decimal("1234567.25")), "1 234 567,25");
+        assert_eq!(
+            format.to_string(),
+            "sign: auto, base: 10, group: space, separator: comma"
+        );
+        assert!(format.set("group", "maybe").is_err());
+    }
+
+    #[test]
+    fn test_exponent_notation() {
+        let mut format = Format::default();
+        let decimal = |digits: &str| {
+            calculator::evaluate(
+                digits,
+                &calculator::Settings {
+                    decimals: Some(6),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .result
+        };
+
+        format.set("notation", "sci").unwrap();
+        assert_eq!(format.exponent, Exponent::Sci);
+        assert_eq!(format.number(&Number::Int(1234500)), "1.2345e6");
+        assert_eq!(format.number(&Number::Int(-7)), "-7e0");
+        assert_eq!(format.number(&Number::Int(0)), "0");
+        assert_eq!(format.number(&decimal("0.00012")), "1.2e-4");
+        assert_eq!(format.to_string(), "sign: auto, base: 10, notation: sci");
+
+        format.set("notation", "eng").unwrap();
+        assert_eq!(format.number(&Number::Int(1234500)), "1.2345e6");
+        assert_eq!(format.number(&Number::Int(12345)), "12.345e3");
+        assert_eq!(format.number(&Number::Int(10000000)), "10e6");
+        assert_eq!(format.number(&decimal("0.00012")), "120e-6");
+        assert_eq!(format.number(&decimal("-123.45")), "-123.45e0");
+
+        // Places count the digits of the mantissa, whose exponent grows when rounding carries
+        format.set("places", "2").unwrap();
+        assert_eq!(format.number(&Number::Int(12345)), "12.35e3");
+        assert_eq!(format.number(&Number::Int(999999)), "1.00e6");
+        format.set("notation", "sci").unwrap();
+        assert_eq!(format.number(&Number::Int(99960)), "1.00e5");
+        assert_eq!(format.number(&Number::Int(0)), "0.00");
+
+        format.set("notation", "plain").unwrap();
+        assert_eq!(format.number(&Number::Int(12345)), "12345.00");
+        assert!(format.set("notation", "fixed").is_err());
+    }
+
+    #[test]
+    fn test_precision() {
+        let mut format = Format::default();
+        let decimal = |digits: &str| {
+            calculator::evaluate(
+                digits,
+                &calculator::Settings {
+                    decimals: Some(20),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .result
+        };
+
+        format.set("precision", "5").unwrap();
+        assert_eq!(format.number(&decimal("1 / 3")), "0.33333");
+        assert_eq!(format.number(&decimal("-2 / 3")), "-0.66667");
+        assert_eq!(format.number(&decimal("0.000123456789")), "0.00012346");
+        assert_eq!(format.number(&decimal("123456.789")), "123457");
+        assert_eq!(format.number(&decimal("1.5")), "1.5");
+        assert_eq!(format.number(&decimal("0.399999")), "0.4");
+        assert_eq!(format.number(&Number::Int(1234567)), "1234567");
+        assert_eq!(format.to_string(), "sign: auto, base: 10, precision: 5");
+
+        format.set("notation", "sci").unwrap();
+        assert_eq!(format.number(&Number::Int(1234567)), "1.2346e6");
+        assert_eq!(format.number(&decimal("9.999999")), "1e1");
+
+        // Places are written even when they are zeros
+        format.set("places", "3").unwrap();
+        format.set("notation", "plain").unwrap();
+        assert_eq!(format.number(&decimal("1 / 3")), "0.333");
+        assert_eq!(format.number(&decimal("1.5")), "1.500");
+
+        format.set("precision", "auto").unwrap();
+        assert_eq!(format.precision, None);
+        assert!(format.set("precision", "0").is_err());
+        assert!(format.set("precision", "many").is_err());
+    }
+
+    #[test]
+    fn test_fraction() {
+        let mut format = Format::default();
+        let decimal = |digits: &str| {
+            calculator::evaluate(
+                digits,
+                &calculator::Settings {
+                    decimals: Some(4),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .result
+        };
+
+        format.set("fraction", "1000").unwrap();
+        assert_eq!(format.number(&decimal("0.3333")), "1/3");
+        assert_eq!(format.number(&decimal("-1.25")), "-5/4");
+        assert_eq!(format.number(&decimal("2")), "2");
+        assert_eq!(format.number(&Number::Int(7)), "7");
+        format.set("sign", "always").unwrap();
+        format.set("places", "2").unwrap();
+        assert_eq!(format.number(&decimal("0.6667")), "+2/3");
+        assert_eq!(format.number(&decimal("3")), "+3.00");
+        assert_eq!(
+            format.to_string(),
+            "sign: always, base: 10, places: 2, fraction: 1000"
+        );
+
+        format.set("fraction", "off").unwrap();
+        assert_eq!(format.number(&decimal("0.3333")), "+0.33");
+        assert!(format.set("fraction", "0").is_err());
+        assert!(format.set("fraction", "on").is_err());
+    }
+
+    #[test]
+    fn test_bit_view() {
+        assert_eq!(
+            bit_view(&Number::Int(4080), 16).unwrap(),
+            "0000_1111 1111_0000 = 4080 signed = 4080 unsigned"
+        );
+        assert_eq!(
+            bit_view(&Number::Int(-16), 16).unwrap(),
+            "1111_1111 1111_0000 = -16 signed = 65520 unsigned"
+        );
+        assert_eq!(
+            bit_view(&Number::Int(200), 8).unwrap(),
+            "1100_1000 = -56 signed = 200 unsigned"
+        );
+        // Integers beyond the bits would lose their highest ones
+        assert_eq!(
+            bit_view(&Number::Int(258), 8).unwrap_err(),
+            "which does not fit on 8 bits"
+        );
+        assert_eq!(
+            bit_view(&Number::Int(-129), 8).unwrap_err(),
+            "which does not fit on 8 bits"
+        );
+        assert!(bit_view(&Number::Int(-128), 8).is_ok());
+        assert!(
+            bit_view(&Number::Int(-1), 64)
+                .unwrap()
+                .starts_with("1111_1111 1111_1111 1111_1111 1111_1111 1111_1111")
+        );
+
+        let decimal = calculator::evaluate(
+            "1 / 4",
+            &calculator::Settings {
+                decimals: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .result;
+        assert_eq!(
+            bit_view(&decimal, 8).unwrap_err(),
+            "which is not an integer"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use calculator::function_names;
+
+///
+/// REPL commands, as they are written and what they do
+///
+const COMMANDS: &[(&str, &str)] = &[
+    (":help", "operators, functions and commands"),
+    (":vars", "assigned variables with their values"),
+    (":funcs", "defined functions"),
+    (
+        ":define <f(x, ...) = body>",
+        "define a function saved for the next sessions",
+    ),
+    (":define", "functions saved for the next sessions"),
+    (
+        ":clear",
+        "forget the assigned variables and the functions not saved",
+    ),
+    (
+        ":ans list",
+        "results of the answers stack, the most recent first",
+    ),
+    (":ans clear", "empty the answers stack"),
+    (":history", "lines typed in this and previous sessions"),
+    (":history <n>", "run the line numbered n again"),
+    (
+        ":bits",
+        "bit pattern of the last result on the integer width",
+    ),
+    (":consts", "physical constants"),
+    (":format", "current formatting profile of the results"),
+    (":format <option> <value>", "change the formatting profile"),
+    (":base 2|8|10|16", "print results in another base"),
+    (
+        ":notation plain|sci|eng",
+        "print results with a power of ten",
+    ),
+    (
+        ":precision <n>|auto",
+        "print results with at most n significant digits",
+    ),
+    (":mode", "notation expressions are read in"),
+    (":mode rpn|infix", "read expressions in another notation"),
+    (":rpn <expr>", "Reverse Polish Notation of an expression"),
+    (
+        ":infix <rpn>",
+        "expression written from its Reverse Polish Notation",
+    ),
+    (
+        ":ast <expr>",
+        "structure of an expression as an S-expression",
+    ),
+    (
+        ":dot <expr>",
+        "structure of an expression as a Graphviz graph",
+    ),
+    (
+        ":explain <expr>",
+        "expression with brackets around every operation",
+    ),
+    (":tokens <expr>", "tokens an expression is split into"),
+    (
+        ":simplify <expr>",
+        "expression with its constant operations folded",
+    ),
+    (":roots <polynomial>", "roots of a polynomial"),
+    (
+        ":solve <equations>",
+        "solution of linear equations separated by ;",
+    ),
+    (":stats", "summary of the session"),
+];
+
+///
+/// Operators from the loosest to the tightest, with what they compute
+///
+const OPERATORS: &[(&str, &str)] = &[
+    ("c ? a : b", "a when c is not 0, b otherwise"),
+    ("in", "conversion, as in 5 km in m"),
+    ("||", "logical or"),
+    ("&&", "logical and"),
+    ("|", "bitwise or"),
+    ("xor", "bitwise exclusive or"),
+    ("&", "bitwise and"),
+    ("== !=", "equal and not equal, 1 or 0"),
+    ("< > <= >=", "comparisons, 1 or 0"),
+    ("<< >>", "shifts"),
+    ("+ -", "sum and difference"),
+    ("* / %", "product, quotient and remainder"),
+    ("- ~ √ ∛", "sign, bitwise not, square and cube roots"),
+    ("^", "power"),
+    ("|x|", "absolute value"),
+];
+
+///
+/// Operators, built-in functions and commands, printed by `:help`
+///
+pub(crate) fn help() -> String {
+    let column = |entries: &[(&str, &str)]| {
+        entries
+            .iter()
+            .map(|(written, meaning)| format!("  {written:<26}{meaning}\n"))
+            .collect::<String>()
+    };
+    format!(
+        "Operators, from the loosest to the tightest:\n{}Functions:\n  {}\nCommands:\n{}",
+        column(OPERATORS),
+        function_names().join(", "),
+        column(COMMANDS)
+    )
+}
+
+///
+/// Command nearest to a mistyped one, comparing names only, None when none of them is
+/// within two edits. The name of a command which may be run alone is not mistyped, and
+/// has no suggestion
+///
+pub(crate) fn suggestion(line: &str) -> Option<&'static str> {
+    let name = line.split_whitespace().next()?;
+    if COMMANDS.iter().any(|(usage, _)| *usage == name) {
+        return None;
+    }
+    COMMANDS
+        .iter()
+        .map(|(usage, _)| {
+            let command = usage.split_whitespace().next().unwrap_or(usage);
+            (distance(name, command), *usage)
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, usage)| usage)
+}
+
+///
+/// Levenshtein distance, the number of characters to insert, delete or replace to turn one
+/// word into the other
+///
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(x != *y);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{distance, help, suggestion};
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance(":vars", ":vars"), 0);
+        assert_eq!(distance(":var", ":vars"), 1);
+        assert_eq!(distance(":fromat", ":format"), 2);
+        assert_eq!(distance("", ":ast"), 4);
+    }
+
+    #[test]
+    fn test_suggestion() {
+        assert_eq!(suggestion(":hlep"), Some(":help"));
+        assert_eq!(suggestion(":fromat sign always"), Some(":format"));
+        assert_eq!(suggestion(":ans"), Some(":ans list"));
+        assert_eq!(suggestion(":variables"), None);
+        assert_eq!(suggestion(":"), None);
+        assert_eq!(suggestion(":history"), None);
+
+        let help = help();
+        assert!(help.contains("  :vars                     assigned variables"));
+        assert!(help.contains("days_between"));
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::error;
+use rustyline::{DefaultEditor, error::ReadlineError};
+
+///
+/// Lines typed in the REPL, kept in a file across sessions unless disabled
+///
+#[derive(Debug)]
+pub(crate) struct History {
+    path: Option<PathBuf>,
+}
+
+impl History {
+    ///
+    /// History kept in `$XDG_DATA_HOME/calculator/history`, by default
+    /// `~/.local/share/calculator/history`, loading the lines of the previous sessions into
+    /// the editor. Without a file lines are only recalled within the session
+    ///
+    pub(crate) fn open(editor: &mut DefaultEditor, persistent: bool) -> History {
+        let path = persistent
+            .then(|| location(std::env::var_os("XDG_DATA_HOME"), std::env::var_os("HOME")))
+            .flatten();
+        if let Some(path) = &path {
+            match editor.load_history(path) {
+                Ok(()) => {}
+                Err(ReadlineError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => error!("Error loading history {e}"),
+            }
+        }
+        History { path }
+    }
+
+    ///
+    /// Add a line to the history, appending it to the file right away so that it survives
+    /// the session being interrupted
+    ///
+    pub(crate) fn record(&self, editor: &mut DefaultEditor, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        if let Err(e) = editor.add_history_entry(line.trim()) {
+            error!("Error recording history {e}");
+        }
+        if let Some(path) = &self.path
+            && let Err(e) = save(editor, path)
+        {
+            error!("Error saving history {e}");
+        }
+    }
+}
+
+fn save(editor: &mut DefaultEditor, path: &Path) -> Result<(), ReadlineError> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    editor.append_history(path)
+}
+
+///
+/// File of the history, within the data directory or `.local/share` in the home one
+///
+fn location(data: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let data = match data.filter(|data| !data.is_empty()) {
+        Some(data) => PathBuf::from(data),
+        None => PathBuf::from(home.filter(|home| !home.is_empty())?).join(".local/share"),
+    };
+    Some(data.join("calculator").join("history"))
+}
+
+///
+/// Lines of the history numbered from 1, the oldest first
+///
+pub(crate) fn list<'a>(lines: impl Iterator<Item = &'a String>) -> Vec<String> {
+    lines
+        .enumerate()
+        .map(|(index, line)| format!("{:>4}  {line}", index + 1))
+        .collect()
+}
+
+///
+/// Line of the history numbered as in the list
+///
+pub(crate) fn entry<'a>(
+    mut lines: impl Iterator<Item = &'a String>,
+    index: &str,
+) -> Result<String, String> {
+    index
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| lines.nth(index.checked_sub(1)?))
+        .cloned()
+        .ok_or_else(|| format!("No history entry {index}"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{entry, list, location};
+
+    #[test]
+    fn test_location() {
+        assert_eq!(
+            location(None, Some("/home/user".into())),
+            Some(PathBuf::from("/home/user/.local/share/calculator/history"))
+        );
+        assert_eq!(
+            location(Some("/data".into()), Some("/home/user".into())),
+            Some(PathBuf::from("/data/calculator/history"))
+        );
+        assert_eq!(
+            location(Some("".into()), Some("/home/user".into())),
+            Some(PathBuf::from("/home/user/.local/share/calculator/history"))
+        );
+        assert_eq!(location(None, None), None);
+    }
+
+    #[test]
+    fn test_entries() {
+        let lines = ["1 + 2".to_owned(), "x = 4".to_owned(), ":format".to_owned()];
+
+        assert_eq!(
+            list(lines.iter()),
+            vec!["   1  1 + 2", "   2  x = 4", "   3  :format"]
+        );
+        assert_eq!(entry(lines.iter(), "2").unwrap(), "x = 4");
+        for index in ["0", "4", "-1", "two"] {
+            assert_eq!(
+                entry(lines.iter(), index).unwrap_err(),
+                format!("No history entry {index}")
+            );
+        }
+    }
+}
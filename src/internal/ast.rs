@@ -0,0 +1,575 @@
+use super::{
+    Context, Error, Settings, complex,
+    eval::{DecimalLiteral, Operator, Token},
+    function::Function,
+    higher_order::HigherOrderCall,
+    number::Number,
+    physics, rpn, unit,
+};
+
+///
+/// Expression structure, built by the Pratt parser or rebuilt from Reverse Polish Notation.
+/// Expressions are compiled to RPN from it
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq)]
+pub enum Ast {
+    Number(i128),
+    Imaginary(i128),
+    Decimal(DecimalLiteral),
+    Previous,
+    Answer(usize),
+    Variable(String),
+    /// Unit written after a number, which variables never shadow
+    Unit(String),
+    Unary(Operator, Box<Ast>),
+    Binary(Operator, Box<Ast>, Box<Ast>),
+    /// `condition ? then : otherwise`
+    Conditional(Box<Ast>, Box<Ast>, Box<Ast>),
+    Call(Function, Vec<Ast>),
+    UserCall(String, Vec<Ast>),
+    /// Call of a higher-order function, a leaf as its expressions are evaluated by the call
+    HigherOrder(Box<HigherOrderCall>),
+}
+
+impl Ast {
+    ///
+    /// Evaluate the tree recursively, failing like the RPN evaluation does
+    ///
+    pub fn evaluate(&self, settings: &Settings, context: &Context) -> Result<Number, Error> {
+        match self {
+            Ast::Number(n) => Number::from_literal(*n, settings),
+            Ast::Imaginary(n) => Ok(complex::imaginary(*n, settings)),
+            Ast::Decimal(decimal) => Number::from_decimal_literal(decimal, settings),
+            Ast::Previous => context.previous(),
+            Ast::Answer(k) => context.answer(*k),
+            Ast::Variable(name) => match context.constant(name, settings) {
+                Some(_) if settings.exact && settings.decimals.is_none() => {
+                    Err(Error::InexactConstant(name.clone()))
+                }
+                Some(value) => Ok(value),
+                None => match physics::named(name) {
+                    Some(constant) => constant.quantity(settings),
+                    None => context.variable(name),
+                },
+            },
+            Ast::Unit(name) => {
+                unit::named(name).ok_or_else(|| Error::UnknownVariable(name.clone()))
+            }
+            Ast::Unary(operator, operand) => {
+                let v = operand.evaluate(settings, context)?;
+                if settings.exact
+                    && let Some((root, radicand)) = operator.rounded_root(&v)
+                {
+                    return Err(Error::InexactRoot(root, radicand));
+                }
+                operator.execute_unary(v, settings)
+            }
+            Ast::Binary(operator @ (Operator::And | Operator::Or), lhs, rhs) => {
+                // The right operand is only evaluated when the left one does not decide
+                let lhs = lhs.evaluate(settings, context)?;
+                if lhs.is_zero() == (*operator == Operator::And) {
+                    return Number::from_literal(i128::from(*operator == Operator::Or), settings);
+                }
+                operator.execute(lhs, rhs.evaluate(settings, context)?, settings)
+            }
+            Ast::Binary(operator, lhs, rhs) => {
+                let (v1, v2) = (
+                    lhs.evaluate(settings, context)?,
+                    rhs.evaluate(settings, context)?,
+                );
+                if settings.exact
+                    && let Some(conversion) = unit::rounded_conversion(*operator, &v1, &v2)
+                {
+                    return Err(Error::InexactConversion(conversion));
+                }
+                if settings.exact && operator.truncates(&v1, &v2) {
+                    return Err(Error::InexactDivision(v1, v2));
+                }
+                operator.execute(v1, v2, settings)
+            }
+            Ast::Conditional(condition, then, otherwise) => {
+                // Only the branch the condition picks is evaluated
+                if condition.evaluate(settings, context)?.is_zero() {
+                    otherwise.evaluate(settings, context)
+                } else {
+                    then.evaluate(settings, context)
+                }
+            }
+            Ast::Call(function, args) => {
+                function.check_arguments(args.len())?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(settings, context))
+                    .collect::<Result<Vec<Number>, Error>>()?;
+                if let Some(operator) = function.operator()
+                    && settings.exact
+                    && let Some((root, radicand)) = operator.rounded_root(&args[0])
+                {
+                    return Err(Error::InexactRoot(root, radicand));
+                }
+                if function.truncates(&args, settings) {
+                    return Err(Error::InexactCall(function.display(&args)));
+                }
+                function.call(args, settings, context)
+            }
+            Ast::UserCall(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(settings, context))
+                    .collect::<Result<Vec<Number>, Error>>()?;
+                Ok(context.call(name, args, settings)?.result)
+            }
+            Ast::HigherOrder(call) => call.call::<Ast>(settings, context),
+        }
+    }
+
+    ///
+    /// Fold the operations on integer literals and apply identities such as `x*1 = x`,
+    /// `x+0 = x` and `0*x = 0`, from the leaves up. Operations which would fail or round,
+    /// and random draws, are kept as they are
+    ///
+    pub fn simplify(self) -> Ast {
+        let node = match self {
+            Ast::Unary(operator, operand) => Ast::Unary(operator, Box::new(operand.simplify())),
+            Ast::Binary(operator, lhs, rhs) => {
+                Ast::Binary(operator, Box::new(lhs.simplify()), Box::new(rhs.simplify()))
+            }
+            Ast::Conditional(condition, then, otherwise) => Ast::Conditional(
+                Box::new(condition.simplify()),
+                Box::new(then.simplify()),
+                Box::new(otherwise.simplify()),
+            ),
+            Ast::Call(function, args) => {
+                Ast::Call(function, args.into_iter().map(Ast::simplify).collect())
+            }
+            Ast::UserCall(name, args) => {
+                Ast::UserCall(name, args.into_iter().map(Ast::simplify).collect())
+            }
+            leaf => return leaf,
+        };
+        match node.fold() {
+            Some(literal) => literal,
+            None => node.apply_identities(),
+        }
+    }
+
+    ///
+    /// Integer the node stands for when it is a literal, possibly negated
+    ///
+    fn literal(&self) -> Option<i128> {
+        match self {
+            Ast::Number(n) => Some(*n),
+            Ast::Unary(Operator::Neg, operand) => operand.literal()?.checked_neg(),
+            _ => None,
+        }
+    }
+
+    fn from_literal(n: i128) -> Ast {
+        match n.checked_neg() {
+            Some(magnitude) if n < 0 => Ast::Unary(Operator::Neg, Box::new(Ast::Number(magnitude))),
+            _ => Ast::Number(n),
+        }
+    }
+
+    ///
+    /// Literal computed from an operation whose operands are all literals, when it is an
+    /// exact integer
+    ///
+    fn fold(&self) -> Option<Ast> {
+        let children = self.children();
+        if children.is_empty()
+            || self.literal().is_some()
+            || matches!(self, Ast::Call(Function::RandInt, _) | Ast::UserCall(_, _))
+            || !children.iter().all(|child| child.literal().is_some())
+        {
+            return None;
+        }
+        let settings = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        match self.evaluate(&settings, &Context::default()) {
+            Ok(Number::Int(n)) => Some(Ast::from_literal(n)),
+            _ => None,
+        }
+    }
+
+    fn apply_identities(self) -> Ast {
+        let zero = Ast::Number(0);
+        let one = Ast::Number(1);
+        match self {
+            Ast::Binary(Operator::Add, lhs, rhs) if *lhs == zero => *rhs,
+            Ast::Binary(Operator::Add | Operator::Sub, lhs, rhs) if *rhs == zero => *lhs,
+            Ast::Binary(Operator::Sub, lhs, rhs) if *lhs == zero => {
+                Ast::Unary(Operator::Neg, rhs).apply_identities()
+            }
+            Ast::Binary(Operator::Prod, lhs, rhs) if *lhs == zero || *rhs == zero => zero,
+            Ast::Binary(Operator::Prod, lhs, rhs) if *lhs == one => *rhs,
+            Ast::Binary(Operator::Prod | Operator::Div | Operator::Pow, lhs, rhs)
+                if *rhs == one =>
+            {
+                *lhs
+            }
+            Ast::Binary(Operator::Pow, lhs, rhs) if *rhs == zero || *lhs == one => one,
+            Ast::Unary(Operator::Neg, operand) => match *operand {
+                Ast::Unary(Operator::Neg, inner) => *inner,
+                operand => Ast::Unary(Operator::Neg, Box::new(operand)),
+            },
+            Ast::Conditional(condition, then, otherwise) if let Some(n) = condition.literal() => {
+                if n == 0 {
+                    *otherwise
+                } else {
+                    *then
+                }
+            }
+            node => node,
+        }
+    }
+
+    ///
+    /// Reverse Polish Notation generated from the tree, each node following its operands,
+    /// space separated as in `3 4 + 2 *`
+    ///
+    pub fn rpn(&self) -> String {
+        self.rpn_tokens()
+            .iter()
+            .map(rpn::label)
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    ///
+    /// Tokens of the Reverse Polish Notation in evaluation order, which is what expressions
+    /// are compiled to
+    ///
+    pub(super) fn rpn_tokens(&self) -> Vec<Token> {
+        let mut tokens = vec![];
+        self.write_rpn(&mut tokens);
+        tokens
+    }
+
+    fn write_rpn(&self, tokens: &mut Vec<Token>) {
+        for child in self.children() {
+            child.write_rpn(tokens);
+        }
+        tokens.push(match self {
+            Ast::Number(n) => Token::Number(*n),
+            Ast::Imaginary(n) => Token::Imaginary(*n),
+            Ast::Decimal(decimal) => Token::Decimal(decimal.clone()),
+            Ast::Previous => Token::Previous,
+            Ast::Answer(k) => Token::Answer(*k),
+            Ast::Variable(name) => Token::Variable(name.clone()),
+            Ast::Unit(name) => Token::Unit(name.clone()),
+            Ast::Unary(operator, _) | Ast::Binary(operator, _, _) => Token::Operator(*operator),
+            Ast::Conditional(_, _, _) => Token::Operator(Operator::Colon),
+            Ast::Call(function, args) => Token::Call(*function, args.len()),
+            Ast::UserCall(name, args) => Token::UserCall(name.clone(), args.len()),
+            Ast::HigherOrder(call) => Token::HigherOrder(call.clone()),
+        });
+    }
+
+    ///
+    /// Built-in functions called in the tree with their number of arguments, in the order
+    /// they are written
+    ///
+    pub(super) fn calls(&self) -> Vec<(Function, usize)> {
+        let mut calls = vec![];
+        if let Ast::Call(function, args) = self {
+            calls.push((*function, args.len()));
+        }
+        for child in self.children() {
+            calls.extend(child.calls());
+        }
+        calls
+    }
+
+    ///
+    /// Names of the variables the tree refers to, each one once, in the order they are
+    /// written
+    ///
+    pub(super) fn variables(&self) -> Vec<String> {
+        let mut names = vec![];
+        if let Ast::Variable(name) = self {
+            names.push(name.clone());
+        }
+        for child in self.children() {
+            for name in child.variables() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    ///
+    /// Degree of the expression as a polynomial in the given unknowns, None when it is not
+    /// a polynomial, e.g. when an unknown is divided by or passed to a function
+    ///
+    pub(super) fn degree(&self, unknowns: &[String]) -> Option<u32> {
+        let degrees = self
+            .children()
+            .into_iter()
+            .map(|child| child.degree(unknowns))
+            .collect::<Option<Vec<u32>>>()?;
+        match self {
+            Ast::Variable(name) if unknowns.contains(name) => Some(1),
+            _ if degrees.iter().all(|&degree| degree == 0) => Some(0),
+            Ast::Unary(Operator::Neg, _) => Some(degrees[0]),
+            Ast::Binary(Operator::Add | Operator::Sub, _, _) => degrees.into_iter().max(),
+            Ast::Binary(Operator::Prod, _, _) => Some(degrees[0] + degrees[1]),
+            Ast::Binary(Operator::Div, _, _) if degrees[1] == 0 => Some(degrees[0]),
+            Ast::Binary(Operator::Pow, _, exponent) if degrees[1] == 0 => match **exponent {
+                Ast::Number(n) => u32::try_from(n).ok().map(|n| degrees[0] * n),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Ast::Number(n) => n.to_string(),
+            Ast::Imaginary(n) => format!("{n}i"),
+            Ast::Decimal(decimal) => decimal.literal.clone(),
+            Ast::Previous => "_".to_owned(),
+            Ast::Answer(k) => format!("ans({k})"),
+            Ast::Variable(name) | Ast::Unit(name) => name.clone(),
+            Ast::Unary(operator, _) | Ast::Binary(operator, _, _) => operator.into(),
+            Ast::Conditional(_, _, _) => "?:".to_owned(),
+            Ast::Call(function, _) => function.name().to_owned(),
+            Ast::UserCall(name, _) => name.clone(),
+            Ast::HigherOrder(call) => call.to_string(),
+        }
+    }
+
+    fn children(&self) -> Vec<&Ast> {
+        match self {
+            Ast::Number(_)
+            | Ast::Imaginary(_)
+            | Ast::Decimal(_)
+            | Ast::Previous
+            | Ast::Answer(_)
+            | Ast::Variable(_)
+            | Ast::Unit(_)
+            | Ast::HigherOrder(_) => vec![],
+            Ast::Unary(_, operand) => vec![operand],
+            Ast::Binary(_, lhs, rhs) => vec![lhs, rhs],
+            Ast::Conditional(condition, then, otherwise) => vec![condition, then, otherwise],
+            Ast::Call(_, args) | Ast::UserCall(_, args) => args.iter().collect(),
+        }
+    }
+
+    ///
+    /// Render the expression as a box-drawing tree, one node per line
+    ///
+    /// +
+    /// ├── 4
+    /// └── 2
+    ///
+    pub fn tree(&self) -> String {
+        let mut out = format!("{}\n", self.label());
+        self.write_children(&mut out, "");
+        out
+    }
+
+    fn write_children(&self, out: &mut String, prefix: &str) {
+        let children = self.children();
+        for (index, child) in children.iter().enumerate() {
+            let last = index == children.len() - 1;
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            out.push_str(&format!("{prefix}{branch}{}\n", child.label()));
+            child.write_children(out, &format!("{prefix}{indent}"));
+        }
+    }
+
+    ///
+    /// Render the expression as an S-expression, each operation written before its
+    /// operands as in `(+ 3 (* 7 2))`
+    ///
+    pub fn sexpr(&self) -> String {
+        let children = self.children();
+        if children.is_empty() {
+            return self.label();
+        }
+        let operands: Vec<String> = children.iter().map(|child| child.sexpr()).collect();
+        format!("({} {})", self.label(), operands.join(" "))
+    }
+
+    ///
+    /// Render the tree as a Graphviz DOT graph, each node pointing to its operands from
+    /// left to right, to be drawn with `dot -Tpng`
+    ///
+    pub fn dot(&self) -> String {
+        let mut out = "digraph ast {\n".to_owned();
+        self.write_dot(&mut out, &mut 0);
+        out.push_str("}\n");
+        out
+    }
+
+    ///
+    /// Write the node and its subtree, numbering nodes in pre-order, and return the id of
+    /// the node
+    ///
+    fn write_dot(&self, out: &mut String, next: &mut usize) -> usize {
+        let id = *next;
+        *next += 1;
+        let label = self.label().replace('\\', "\\\\").replace('"', "\\\"");
+        out.push_str(&format!("    n{id} [label=\"{label}\"];\n"));
+        for child in self.children() {
+            let child = child.write_dot(out, next);
+            out.push_str(&format!("    n{id} -> n{child};\n"));
+        }
+        id
+    }
+
+    ///
+    /// Write the expression back with brackets around every nested operation, making the
+    /// grouping applied by precedence and associativity explicit
+    ///
+    pub fn grouped(&self) -> String {
+        match self {
+            Ast::Number(n) => n.to_string(),
+            Ast::Imaginary(n) => format!("{n}i"),
+            Ast::Decimal(decimal) => decimal.literal.clone(),
+            Ast::Previous => "_".to_owned(),
+            Ast::Answer(k) => format!("ans({k})"),
+            Ast::Variable(name) | Ast::Unit(name) => name.clone(),
+            Ast::Unary(Operator::Neg, operand) => format!("-{}", operand.grouped_operand()),
+            Ast::Unary(Operator::Not, operand) => format!("~{}", operand.grouped_operand()),
+            Ast::Unary(Operator::Abs, operand) => format!("|{}|", operand.grouped()),
+            Ast::Unary(root @ (Operator::Sqrt | Operator::Cbrt), operand) => {
+                format!("{}{}", String::from(root), operand.grouped_operand())
+            }
+            Ast::Unary(operator, _) => unreachable!("Hit unknown unary operator {operator:?}"),
+            Ast::Binary(operator, lhs, rhs) => format!(
+                "{}{}{}",
+                lhs.grouped_operand(),
+                String::from(operator),
+                rhs.grouped_operand()
+            ),
+            Ast::Conditional(condition, then, otherwise) => format!(
+                "{}?{}:{}",
+                condition.grouped_operand(),
+                then.grouped_operand(),
+                otherwise.grouped_operand()
+            ),
+            // Vectors are written back as literals, which need a comma
+            Ast::Call(Function::Vector, args) if args.len() > 1 => {
+                format!("[{}]", grouped_args(args))
+            }
+            Ast::Call(function, args) => format!("{}({})", function.name(), grouped_args(args)),
+            Ast::UserCall(name, args) => format!("{name}({})", grouped_args(args)),
+            Ast::HigherOrder(call) => call.to_string().split_whitespace().collect(),
+        }
+    }
+
+    ///
+    /// Write the expression back with brackets around every operation, the outermost one
+    /// included, as in `((3+(7*2))-1)`
+    ///
+    pub fn parenthesized(&self) -> String {
+        self.grouped_operand()
+    }
+
+    fn grouped_operand(&self) -> String {
+        match self {
+            Ast::Number(_)
+            | Ast::Imaginary(_)
+            | Ast::Decimal(_)
+            | Ast::Previous
+            | Ast::Answer(_)
+            | Ast::Variable(_)
+            | Ast::Unit(_)
+            | Ast::Unary(Operator::Abs, _)
+            | Ast::Call(_, _)
+            | Ast::UserCall(_, _)
+            | Ast::HigherOrder(_) => self.grouped(),
+            _ => format!("({})", self.grouped()),
+        }
+    }
+
+    ///
+    /// Write the expression back with only the brackets needed for it to be parsed into
+    /// the same tree, as in `(3 + 4) * 2`
+    ///
+    pub fn infix(&self) -> String {
+        match self {
+            Ast::Unary(Operator::Abs, operand) => format!("|{}|", operand.infix()),
+            Ast::Unary(operator, operand) => {
+                let symbol = match operator {
+                    Operator::Neg => "-".to_owned(),
+                    operator => String::from(operator),
+                };
+                format!("{symbol}{}", operand.infix_operand(operator.precedence()))
+            }
+            Ast::Binary(operator, lhs, rhs) => {
+                let precedence = operator.precedence();
+                // The operand on the side the operator does not associate to is bracketed
+                // when it binds as loosely as the operator itself
+                let (left, right) = if operator.is_right_associative() {
+                    (precedence + 1, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                format!(
+                    "{} {} {}",
+                    lhs.infix_operand(left),
+                    String::from(operator),
+                    rhs.infix_operand(right)
+                )
+            }
+            Ast::Conditional(condition, then, otherwise) => {
+                let precedence = Operator::Colon.precedence();
+                format!(
+                    "{} ? {} : {}",
+                    condition.infix_operand(precedence + 1),
+                    then.infix(),
+                    otherwise.infix_operand(precedence)
+                )
+            }
+            Ast::Call(Function::Vector, args) if args.len() > 1 => {
+                format!("[{}]", infix_args(args))
+            }
+            Ast::Call(function, args) => format!("{}({})", function.name(), infix_args(args)),
+            Ast::UserCall(name, args) => format!("{name}({})", infix_args(args)),
+            leaf => leaf.label(),
+        }
+    }
+
+    ///
+    /// Write the node as the operand of an operation, bracketed when it binds looser than
+    /// the given precedence
+    ///
+    fn infix_operand(&self, precedence: u8) -> String {
+        let binding = match self {
+            Ast::Unary(Operator::Abs, _) => None,
+            Ast::Unary(operator, _) | Ast::Binary(operator, _, _) => Some(operator.precedence()),
+            Ast::Conditional(_, _, _) => Some(Operator::Colon.precedence()),
+            _ => None,
+        };
+        match binding {
+            Some(binding) if binding < precedence => format!("({})", self.infix()),
+            _ => self.infix(),
+        }
+    }
+}
+
+fn grouped_args(args: &[Ast]) -> String {
+    args.iter()
+        .map(Ast::grouped)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn infix_args(args: &[Ast]) -> String {
+    args.iter()
+        .map(Ast::infix)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::{
+    Error, Settings,
+    decimal::Decimal,
+    eval::{MAX_PROMOTED_BITS, Operator},
+    number::Number,
+};
+
+///
+/// Complex number with integer parts re + im·i, a Gaussian integer. Parts are arbitrary
+/// precision integers, whatever the integer type
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Complex {
+    pub(super) re: BigInt,
+    pub(super) im: BigInt,
+}
+
+impl Complex {
+    pub(super) fn i() -> Self {
+        Complex {
+            re: BigInt::zero(),
+            im: BigInt::one(),
+        }
+    }
+
+    pub(super) fn is_zero(&self) -> bool {
+        self.re.is_zero() && self.im.is_zero()
+    }
+
+    ///
+    /// Square of the modulus, re² + im²
+    ///
+    pub(super) fn norm(&self) -> BigInt {
+        &self.re * &self.re + &self.im * &self.im
+    }
+
+    fn pow(&self, mut exp: u32) -> Complex {
+        let (mut base, mut result) = (
+            self.clone(),
+            Complex {
+                re: BigInt::one(),
+                im: BigInt::zero(),
+            },
+        );
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    fn mul(&self, other: &Complex) -> Complex {
+        Complex {
+            re: &self.re * &other.re - &self.im * &other.im,
+            im: &self.re * &other.im + &self.im * &other.re,
+        }
+    }
+}
+
+///
+/// Written as `a+bi`, leaving out a real part of 0 and an imaginary coefficient of 1
+///
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let im = if self.im.is_one() {
+            "i".to_owned()
+        } else if self.im == -BigInt::one() {
+            "-i".to_owned()
+        } else {
+            format!("{}i", self.im)
+        };
+        match (self.re.is_zero(), self.im.is_negative()) {
+            (true, _) => write!(f, "{im}"),
+            (false, true) => write!(f, "{}{im}", self.re),
+            (false, false) => write!(f, "{}+{im}", self.re),
+        }
+    }
+}
+
+///
+/// Number of the given parts, which is a real one when the imaginary part is 0
+///
+fn number(re: BigInt, im: BigInt, settings: &Settings) -> Number {
+    if !im.is_zero() {
+        return Number::Complex(Box::new(Complex { re, im }));
+    }
+    match settings.decimals {
+        Some(scale) => Number::Decimal(Decimal::from_int(re, scale)),
+        None => Number::from_big(re, settings.int_type()),
+    }
+}
+
+///
+/// Imaginary literal `ni`
+///
+pub(super) fn imaginary(n: i128, settings: &Settings) -> Number {
+    number(BigInt::zero(), BigInt::from(n), settings)
+}
+
+///
+/// Value as a complex number, None for a decimal with a fraction
+///
+fn complex(n: &Number) -> Option<Complex> {
+    match n {
+        Number::Complex(z) => Some(z.as_ref().clone()),
+        n => n.to_integer().map(|re| Complex {
+            re,
+            im: BigInt::zero(),
+        }),
+    }
+}
+
+///
+/// Apply a binary operator to operands of which at least one is complex. Division rounds
+/// both parts toward zero
+///
+pub(super) fn execute(
+    operator: Operator,
+    v1: Number,
+    v2: Number,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    let computation = || format!("{v1} {} {v2}", String::from(&operator));
+    let (Some(a), Some(b)) = (complex(&v1), complex(&v2)) else {
+        return Err(Error::ComplexOperand(computation()));
+    };
+    let z = match operator {
+        Operator::Add => Complex {
+            re: a.re + b.re,
+            im: a.im + b.im,
+        },
+        Operator::Sub => Complex {
+            re: a.re - b.re,
+            im: a.im - b.im,
+        },
+        Operator::Prod => a.mul(&b),
+        Operator::Div => {
+            let norm = b.norm();
+            Complex {
+                re: (&a.re * &b.re + &a.im * &b.im) / &norm,
+                im: (&a.im * &b.re - &a.re * &b.im) / norm,
+            }
+        }
+        Operator::Pow if !b.im.is_zero() => return Err(Error::ComplexOperand(computation())),
+        Operator::Pow if b.re.is_negative() => return Err(Error::NegativeExponent(v2)),
+        Operator::Pow => {
+            let bits = a.re.bits().max(a.im.bits()) + 1;
+            let exp =
+                b.re.to_u32()
+                    .filter(|exp| bits * u64::from(*exp) <= MAX_PROMOTED_BITS)
+                    .ok_or_else(|| Error::Overflow(computation()))?;
+            a.pow(exp)
+        }
+        Operator::And => {
+            return Number::from_literal((!a.is_zero() && !b.is_zero()).into(), settings);
+        }
+        Operator::Or => {
+            return Number::from_literal((!a.is_zero() || !b.is_zero()).into(), settings);
+        }
+        _ => return Err(Error::ComplexOperand(computation())),
+    };
+    Ok(number(z.re, z.im, settings))
+}
+
+pub(super) fn execute_unary(
+    operator: Operator,
+    z: &Complex,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    match operator {
+        Operator::Neg => Ok(number(-&z.re, -&z.im, settings)),
+        // The modulus, rounded toward zero as roots are
+        Operator::Abs => Ok(number(z.norm().sqrt(), BigInt::zero(), settings)),
+        _ => Err(Error::ComplexOperand(format!(
+            "{}{z}",
+            String::from(&operator)
+        ))),
+    }
+}
+
+///
+/// Whether dividing by a complex number, or dividing one, loses a remainder
+///
+pub(super) fn truncates(v1: &Number, v2: &Number) -> bool {
+    let (Some(a), Some(b)) = (complex(v1), complex(v2)) else {
+        return false;
+    };
+    let norm = b.norm();
+    !norm.is_zero()
+        && [&a.re * &b.re + &a.im * &b.im, &a.im * &b.re - &a.re * &b.im]
+            .iter()
+            .any(|part| !(part % &norm).is_zero())
+}
@@ -0,0 +1,248 @@
+use std::{cmp::Ordering, fmt};
+
+use num_traits::{ToPrimitive, Zero};
+
+use super::{
+    Error, Settings,
+    eval::Operator,
+    function::Function,
+    number::Number,
+    unit::{self, Quantity, Unit},
+};
+
+/// Years a date can be written in, so that it always has four digits
+const YEARS: std::ops::RangeInclusive<i64> = 1..=9999;
+
+///
+/// Day of the proleptic Gregorian calendar, written `2024-03-01`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    /// Days since 1970-01-01
+    days: i64,
+}
+
+impl Date {
+    ///
+    /// Date of the given year, month and day, None when there is no such day
+    ///
+    pub(super) fn new(year: i64, month: i64, day: i64) -> Option<Date> {
+        let valid = YEARS.contains(&year)
+            && (1..=12).contains(&month)
+            && (1..=days_in_month(year, month)).contains(&day);
+        valid.then(|| Date {
+            days: days_from_civil(year, month, day),
+        })
+    }
+
+    ///
+    /// Date the given number of days later, None when it leaves the years dates are
+    /// written in
+    ///
+    fn plus(self, days: i64) -> Option<Date> {
+        let date = Date {
+            days: self.days.checked_add(days)?,
+        };
+        YEARS.contains(&date.civil().0).then_some(date)
+    }
+
+    ///
+    /// Year, month and day of the date
+    ///
+    fn civil(self) -> (i64, i64, i64) {
+        // Days counted from 0000-03-01, so that leap days end the 400 years eras
+        let days = self.days + 719_468;
+        let (era, day_of_era) = (days.div_euclid(146_097), days.rem_euclid(146_097));
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.civil();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+///
+/// Days since 1970-01-01 of a valid date
+///
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years start in March, so that the leap day is the last one of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, year_of_era) = (year.div_euclid(400), year.rem_euclid(400));
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+///
+/// Whole number of days of a duration, a quantity of time
+///
+fn whole_days(duration: &Number, call: impl Fn() -> String) -> Result<i64, Error> {
+    let Number::Quantity(q) = duration else {
+        return Err(Error::DateOperand(call()));
+    };
+    let (numerator, denominator) = unit::days(q).ok_or_else(|| Error::IncompatibleUnits(call()))?;
+    if !(&numerator % &denominator).is_zero() {
+        return Err(Error::FractionalDays(q.to_string()));
+    }
+    (numerator / denominator)
+        .to_i64()
+        .ok_or_else(|| Error::Overflow(call()))
+}
+
+///
+/// Apply a binary operator to operands of which at least one is a date. Durations are
+/// added to or subtracted from dates, and the difference of two dates is a duration in
+/// days
+///
+pub(super) fn execute(
+    operator: Operator,
+    v1: Number,
+    v2: Number,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    let call = || format!("{v1} {} {v2}", String::from(&operator));
+    match (operator, &v1, &v2) {
+        (Operator::Sub, Number::Date(a), Number::Date(b)) => {
+            Ok(Number::Quantity(Box::new(Quantity {
+                value: Number::from_literal((a.days - b.days).into(), settings)?,
+                unit: Unit::new(&[("day", 1)]),
+            })))
+        }
+        (Operator::Add | Operator::Sub, Number::Date(date), duration)
+        | (Operator::Add, duration, Number::Date(date)) => {
+            let days = whole_days(duration, call)?;
+            let days = if operator == Operator::Sub {
+                -days
+            } else {
+                days
+            };
+            date.plus(days)
+                .map(Number::Date)
+                .ok_or_else(|| Error::Overflow(call()))
+        }
+        _ => Err(Error::DateOperand(call())),
+    }
+}
+
+///
+/// Compare two dates, None when either operand is not one
+///
+pub(super) fn compare(v1: &Number, v2: &Number) -> Option<Ordering> {
+    match (v1, v2) {
+        (Number::Date(a), Number::Date(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+///
+/// Call a function building or taking dates
+///
+pub(super) fn call(
+    function: Function,
+    args: &[Number],
+    settings: &Settings,
+) -> Result<Number, Error> {
+    match (function, args) {
+        (Function::Date, [year, month, day]) => {
+            let [year, month, day] = [year, month, day].map(|n| {
+                n.to_integer()
+                    .and_then(|n| n.to_i64())
+                    .ok_or_else(|| Error::FractionalOperand(function.display(args)))
+            });
+            let (year, month, day) = (year?, month?, day?);
+            Date::new(year, month, day)
+                .map(Number::Date)
+                .ok_or_else(|| Error::InvalidDate(format!("{year:04}-{month:02}-{day:02}")))
+        }
+        (Function::DaysBetween, [Number::Date(from), Number::Date(to)]) => {
+            Number::from_literal((to.days - from.days).into(), settings)
+        }
+        _ => Err(Error::DateOperand(function.display(args))),
+    }
+}
+
+///
+/// Year, month and day of a date literal, four digits, two and two separated by `-` as in
+/// `2024-03-01`, at the start of the characters
+///
+pub(super) fn literal(chars: impl Iterator<Item = char>) -> Option<(i128, i128, i128)> {
+    let chars: Vec<char> = chars.take(11).collect();
+    let shape = chars.len() >= 10
+        && chars[..10]
+            .iter()
+            .enumerate()
+            .all(|(index, c)| match index {
+                4 | 7 => *c == '-',
+                _ => c.is_ascii_digit(),
+            })
+        && !chars
+            .get(10)
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.'));
+    if !shape {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| {
+        chars[range]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .expect("Date fields are made of digits")
+    };
+    Some((number(0..4), number(5..7), number(8..10)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Date;
+
+    #[test]
+    fn test_civil_days() {
+        for (year, month, day, days) in [
+            (1970, 1, 1, 0),
+            (2000, 2, 29, 11_016),
+            (2024, 3, 1, 19_783),
+            (1969, 12, 31, -1),
+            (1, 1, 1, -719_162),
+            (9999, 12, 31, 2_932_896),
+        ] {
+            let date = Date::new(year, month, day).unwrap();
+            assert_eq!(date.days, days, "{date}");
+            assert_eq!(date.civil(), (year, month, day));
+        }
+        assert_eq!(Date::new(2023, 2, 29), None);
+        assert_eq!(Date::new(1900, 2, 29), None);
+        assert!(Date::new(2000, 2, 29).is_some());
+        assert_eq!(Date::new(2024, 4, 31), None);
+        assert_eq!(Date::new(2024, 13, 1), None);
+        assert_eq!(Date::new(0, 1, 1), None);
+        assert_eq!(Date::new(9999, 12, 31).unwrap().plus(1), None);
+    }
+}
@@ -0,0 +1,760 @@
+use std::{cmp::Ordering, fmt};
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use super::{
+    Context, Error, IntWidth, Overflow, Rounding, Settings,
+    eval::{MAX_PROMOTED_BITS, Operator},
+    function::{Function, MAX_EXPONENT},
+    number::Number,
+    unit,
+};
+
+/// Digits computed beyond the scale by the series, covering their rounding errors
+const GUARD_DIGITS: u32 = 10;
+
+///
+/// Number with a fixed count of fractional digits, its value being units / 10^scale
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decimal {
+    pub(super) units: BigInt,
+    pub(super) scale: u32,
+}
+
+impl Decimal {
+    pub(super) fn from_int(n: BigInt, scale: u32) -> Self {
+        Self {
+            units: n * pow10(scale),
+            scale,
+        }
+    }
+
+    ///
+    /// Digits having the given number of them after the point, rounded to the scale
+    ///
+    pub(super) fn from_digits(
+        digits: BigInt,
+        fraction: u32,
+        scale: u32,
+        rounding: Rounding,
+    ) -> Self {
+        let units = match fraction.checked_sub(scale) {
+            // Beyond the digits and one more, what is dropped is below a tenth of a unit,
+            // which spares building a huge power of ten, as for `1e-99999999`
+            Some(dropped) if u64::from(dropped) > digits.bits() + 1 => {
+                let below_half = (!digits.is_zero()).then_some(Ordering::Less);
+                let negative = digits.is_negative();
+                signed(
+                    round_magnitude(BigUint::zero(), below_half, negative, rounding),
+                    negative,
+                )
+            }
+            Some(dropped) => divide(&digits, &pow10(dropped), rounding),
+            None => digits * pow10(scale - fraction),
+        };
+        Self { units, scale }
+    }
+
+    pub(super) fn is_zero(&self) -> bool {
+        self.units.is_zero()
+    }
+
+    ///
+    /// Integer value, None when any fractional digit is not 0
+    ///
+    pub(super) fn integral(&self) -> Option<BigInt> {
+        let one = pow10(self.scale);
+        (&self.units % &one).is_zero().then(|| &self.units / one)
+    }
+
+    ///
+    /// Integer part, the fraction being dropped toward zero
+    ///
+    pub(super) fn trunc(&self) -> BigInt {
+        &self.units / pow10(self.scale)
+    }
+
+    ///
+    /// Numerator and positive denominator of the fraction nearest to the decimal among those
+    /// whose denominator is at most the bound, which is positive. The convergents of the
+    /// continued fraction of the decimal are followed while their denominators fit, the last
+    /// one being compared with the largest semiconvergent that fits too
+    ///
+    pub(super) fn approximate(&self, max_denominator: &BigInt) -> (BigInt, BigInt) {
+        let (numerator, denominator) = (
+            BigInt::from(self.units.magnitude().clone()),
+            pow10(self.scale),
+        );
+        let (mut n, mut d) = (numerator.clone(), denominator.clone());
+        let (mut p0, mut q0, mut p1, mut q1) = (
+            BigInt::zero(),
+            BigInt::from(1),
+            BigInt::from(1),
+            BigInt::zero(),
+        );
+        let (p, q) = loop {
+            let a = &n / &d;
+            let q2 = &q0 + &a * &q1;
+            if &q2 > max_denominator {
+                let k = (max_denominator - &q0) / &q1;
+                let (p2, q2) = (&p0 + &k * &p1, &q0 + &k * &q1);
+                // Distances to the decimal, both scaled by the product of the denominators
+                let distance = |p: &BigInt, q: &BigInt, other: &BigInt| {
+                    (p * &denominator - &numerator * q).magnitude() * other.magnitude()
+                };
+                break if distance(&p1, &q1, &q2) <= distance(&p2, &q2, &q1) {
+                    (p1, q1)
+                } else {
+                    (p2, q2)
+                };
+            }
+            (p0, q0, p1, q1) = (p1.clone(), q1, p0 + &a * &p1, q2);
+            (n, d) = (d.clone(), n - a * d);
+            if d.is_zero() {
+                break (p1, q1);
+            }
+        };
+        (signed(p.magnitude().clone(), self.units.is_negative()), q)
+    }
+
+    pub(super) fn compare(&self, other: &Decimal) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        let lhs = &self.units * pow10(scale - self.scale);
+        let rhs = &other.units * pow10(scale - other.scale);
+        lhs.cmp(&rhs)
+    }
+}
+
+///
+/// Fractional digits are written up to the last one which is not 0, integers without point
+///
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = self.scale as usize;
+        let digits = format!(
+            "{:0>width$}",
+            self.units.magnitude().to_string(),
+            width = scale + 1
+        );
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        let fraction = fraction.trim_end_matches('0');
+        let sign = if self.units.is_negative() { "-" } else { "" };
+        if fraction.is_empty() {
+            write!(f, "{sign}{integer}")
+        } else {
+            write!(f, "{sign}{integer}.{fraction}")
+        }
+    }
+}
+
+fn pow10(k: u32) -> BigInt {
+    BigInt::from(10).pow(k)
+}
+
+///
+/// Quotient of n by d, which is not 0, rounded as requested
+///
+pub(super) fn divide(n: &BigInt, d: &BigInt, rounding: Rounding) -> BigInt {
+    let (quotient, remainder) = (n.magnitude() / d.magnitude(), n.magnitude() % d.magnitude());
+    let negative = n.is_negative() != d.is_negative();
+    let half = (!remainder.is_zero()).then(|| (remainder * 2u32).cmp(d.magnitude()));
+    signed(
+        round_magnitude(quotient, half, negative, rounding),
+        negative,
+    )
+}
+
+///
+/// Round the magnitude of a value lying between floor and floor + 1, given how its
+/// fraction compares to one half, None when there is no fraction at all
+///
+fn round_magnitude(
+    floor: BigUint,
+    half: Option<Ordering>,
+    negative: bool,
+    rounding: Rounding,
+) -> BigUint {
+    let Some(half) = half else {
+        return floor;
+    };
+    let up = match rounding {
+        Rounding::HalfEven => {
+            half == Ordering::Greater || (half == Ordering::Equal && floor.bit(0))
+        }
+        Rounding::HalfUp => half != Ordering::Less,
+        Rounding::Down => false,
+        Rounding::Up => true,
+        Rounding::Floor => negative,
+        Rounding::Ceiling => !negative,
+    };
+    if up { floor + 1u32 } else { floor }
+}
+
+fn signed(magnitude: BigUint, negative: bool) -> BigInt {
+    let sign = if negative { Sign::Minus } else { Sign::Plus };
+    BigInt::from_biguint(sign, magnitude)
+}
+
+///
+/// Value as a decimal of the given scale, integers being converted exactly
+///
+fn decimal(n: &Number, settings: &Settings, scale: u32) -> Decimal {
+    match n {
+        Number::Decimal(d) if d.scale == scale => d.clone(),
+        Number::Decimal(d) => {
+            Decimal::from_digits(d.units.clone(), d.scale, scale, settings.rounding)
+        }
+        n => Decimal::from_int(n.to_big(), scale),
+    }
+}
+
+///
+/// Settings computing the operations defined on integers only, which never lose digits
+///
+fn integer_settings(settings: &Settings) -> Settings {
+    Settings {
+        overflow: Overflow::Promote,
+        int_width: IntWidth::W128,
+        unsigned: false,
+        decimals: None,
+        programmer: false,
+        ..*settings
+    }
+}
+
+fn integer(n: &Number, settings: &Settings) -> Option<Number> {
+    n.to_integer()
+        .map(|n| Number::from_big(n, integer_settings(settings).int_type()))
+}
+
+pub(super) fn execute(
+    operator: Operator,
+    v1: Number,
+    v2: Number,
+    settings: &Settings,
+    scale: u32,
+) -> Result<Number, Error> {
+    let rounding = settings.rounding;
+    let (a, b) = (decimal(&v1, settings, scale), decimal(&v2, settings, scale));
+    let one = pow10(scale);
+    let units = match operator {
+        Operator::Add => a.units + b.units,
+        Operator::Sub => a.units - b.units,
+        Operator::Prod => divide(&(a.units * b.units), &one, rounding),
+        Operator::Div => divide(&(a.units * &one), &b.units, rounding),
+        Operator::Mod => a.units % b.units,
+        Operator::Pow => power(&a, &b, rounding).ok_or_else(|| {
+            let computation = format!("{v1} ^ {v2}");
+            match b.integral() {
+                None => Error::FractionalOperand(computation),
+                Some(_) if a.is_zero() => Error::MathDomain(computation),
+                Some(_) => Error::Overflow(computation),
+            }
+        })?,
+        Operator::And => BigInt::from(!a.is_zero() && !b.is_zero()) * one,
+        Operator::Or => BigInt::from(!a.is_zero() || !b.is_zero()) * one,
+        _ => {
+            let (Some(n1), Some(n2)) = (integer(&v1, settings), integer(&v2, settings)) else {
+                return Err(Error::FractionalOperand(format!(
+                    "{v1} {} {v2}",
+                    String::from(&operator)
+                )));
+            };
+            let n = operator.execute(n1, n2, &integer_settings(settings))?;
+            return Ok(Number::Decimal(Decimal::from_int(n.to_big(), scale)));
+        }
+    };
+    Ok(Number::Decimal(Decimal { units, scale }))
+}
+
+///
+/// Units of a power to an integer exponent, which may be negative. None when the exponent
+/// is not an integer, the base is 0 with a negative exponent, or the power is too large
+///
+fn power(base: &Decimal, exponent: &Decimal, rounding: Rounding) -> Option<BigInt> {
+    let exponent = exponent.integral()?;
+    let k = exponent
+        .magnitude()
+        .to_u32()
+        .filter(|k| base.units.bits() * u64::from(*k) <= MAX_PROMOTED_BITS)?;
+    let one = pow10(base.scale);
+    Some(match k {
+        0 => one,
+        _ if exponent.is_negative() && base.is_zero() => return None,
+        _ if exponent.is_negative() => divide(&one.pow(k + 1), &base.units.pow(k), rounding),
+        _ => divide(&base.units.pow(k), &one.pow(k - 1), rounding),
+    })
+}
+
+pub(super) fn execute_unary(
+    operator: Operator,
+    v: Number,
+    settings: &Settings,
+    scale: u32,
+) -> Result<Number, Error> {
+    let d = decimal(&v, settings, scale);
+    let units = match operator {
+        Operator::Neg => -d.units,
+        Operator::Abs => d.units.abs(),
+        Operator::Sqrt if d.units.is_negative() => return Err(Error::NegativeRoot(v)),
+        Operator::Sqrt | Operator::Cbrt => root(operator, &d, settings.rounding),
+        _ => {
+            let Some(n) = integer(&v, settings) else {
+                return Err(Error::FractionalOperand(format!(
+                    "{}{v}",
+                    String::from(&operator)
+                )));
+            };
+            let n = operator.execute_unary(n, &integer_settings(settings))?;
+            return Ok(Number::Decimal(Decimal::from_int(n.to_big(), scale)));
+        }
+    };
+    Ok(Number::Decimal(Decimal { units, scale }))
+}
+
+///
+/// Units of a square or cube root. The root of an integer is never halfway between two
+/// integers, comparing with the cube or square of the halfway value tells how to round
+///
+fn root(operator: Operator, d: &Decimal, rounding: Rounding) -> BigInt {
+    let negative = d.units.is_negative();
+    let (n, degree) = match operator {
+        Operator::Sqrt => (d.units.magnitude() * pow10(d.scale).magnitude(), 2),
+        _ => (d.units.magnitude() * pow10(2 * d.scale).magnitude(), 3),
+    };
+    let floor = n.nth_root(degree);
+    let half =
+        (floor.pow(degree) != n).then(|| (&n << degree).cmp(&(&floor * 2u32 + 1u32).pow(degree)));
+    signed(round_magnitude(floor, half, negative, rounding), negative)
+}
+
+///
+/// Call a function defined on integers only, when all the arguments are integers
+///
+pub(super) fn integral_call(
+    function: Function,
+    args: Vec<Number>,
+    settings: &Settings,
+    context: &Context,
+    scale: u32,
+) -> Result<Number, Error> {
+    let Some(integers) = args
+        .iter()
+        .map(|arg| integer(arg, settings))
+        .collect::<Option<Vec<Number>>>()
+    else {
+        return Err(Error::FractionalOperand(function.display(&args)));
+    };
+    let n = function.call(integers, &integer_settings(settings), context)?;
+    Ok(Number::Decimal(Decimal::from_int(n.to_big(), scale)))
+}
+
+///
+/// Call a logarithm, the exponential or the arctangent, computed with guard digits and
+/// rounded to the scale. Results which are integers, as `log10(1000)`, are computed exactly
+///
+pub(super) fn transcendental_call(
+    function: Function,
+    args: Vec<Number>,
+    settings: &Settings,
+    context: &Context,
+    scale: u32,
+) -> Result<Number, Error> {
+    if let Some(integers) = args
+        .iter()
+        .map(|arg| integer(arg, settings))
+        .collect::<Option<Vec<Number>>>()
+        && !function.is_irrational(&integers)
+    {
+        return integral_call(function, args, settings, context, scale);
+    }
+    let values = args
+        .iter()
+        .map(|arg| decimal(arg, settings, scale))
+        .collect::<Vec<Decimal>>();
+    let domain = || Error::MathDomain(function.display(&args));
+    let (units, digits) = match (function, values.as_slice()) {
+        (Function::Exp, [x]) => {
+            let integral = x.trunc();
+            if integral > BigInt::from(MAX_EXPONENT) {
+                return Err(Error::Overflow(function.display(&args)));
+            }
+            // e^x has less than x / 2 integer digits, which the working digits cover too
+            let digits = scale + GUARD_DIGITS + integral.to_u32().unwrap_or(0) / 2;
+            match integral < -BigInt::from(MAX_EXPONENT) {
+                true => (BigInt::zero(), digits),
+                false => (exp(&fixed(x, digits), digits), digits),
+            }
+        }
+        (Function::Ln, [x]) => {
+            let digits = scale + GUARD_DIGITS;
+            (logarithm(x, None, digits).ok_or_else(domain)?, digits)
+        }
+        (Function::Log10 | Function::Log2, [x]) => {
+            let base = Decimal::from_int(
+                BigInt::from(if function == Function::Log10 { 10 } else { 2 }),
+                0,
+            );
+            let digits = scale + GUARD_DIGITS;
+            (
+                logarithm(x, Some(&base), digits).ok_or_else(domain)?,
+                digits,
+            )
+        }
+        (Function::Log, [base, x]) => {
+            let digits = scale + GUARD_DIGITS;
+            (logarithm(x, Some(base), digits).ok_or_else(domain)?, digits)
+        }
+        (Function::Atan, [x]) => {
+            let digits = scale + GUARD_DIGITS;
+            (atan(&fixed(x, digits), &pow10(digits)), digits)
+        }
+        _ => unreachable!("Hit {function:?} as a logarithm, the exponential or the arctangent"),
+    };
+    Ok(Number::Decimal(Decimal {
+        units: divide(&units, &pow10(digits - scale), settings.rounding),
+        scale,
+    }))
+}
+
+///
+/// Sine, cosine or tangent of an angle, radians being reduced to a remainder within π/4 of
+/// a multiple of π/2 whose quadrant turns the series of the remainder into the result.
+/// Angles in degrees take their multiples of 90° away exactly, so that `sin(180 deg)` is 0
+/// and `tan(90 deg)` is undefined, those multiples giving integers
+///
+pub(super) fn trigonometric_call(
+    function: Function,
+    args: &[Number],
+    settings: &Settings,
+    scale: u32,
+) -> Result<Number, Error> {
+    let [angle] = args else {
+        unreachable!("Argument count was checked")
+    };
+    let (quadrant, remainder, digits) = match angle {
+        Number::Quantity(q) => {
+            let (numerator, denominator) =
+                unit::degrees(q).ok_or_else(|| Error::UnitOperand(function.display(args)))?;
+            let right = &denominator * 90;
+            let quadrant = divide(&numerator, &right, Rounding::HalfEven);
+            let rest = numerator - &quadrant * right;
+            let digits = scale + GUARD_DIGITS;
+            let one = pow10(digits);
+            (quadrant, rest * pi(&one) / (denominator * 180), digits)
+        }
+        angle => {
+            let integral = decimal(angle, settings, 0)
+                .units
+                .magnitude()
+                .to_string()
+                .len();
+            let integral = u32::try_from(integral)
+                .ok()
+                .filter(|digits| *digits <= MAX_EXPONENT)
+                .ok_or_else(|| Error::Overflow(function.display(args)))?;
+            // The multiple of π/2 taken away needs as many more digits as the angle has
+            let digits = scale + GUARD_DIGITS + integral;
+            let x = decimal(angle, settings, digits).units;
+            let half_pi = pi(&pow10(digits)) / 2;
+            let quadrant = divide(&x, &half_pi, Rounding::HalfEven);
+            let rest = x - &quadrant * half_pi;
+            (quadrant, rest, digits)
+        }
+    };
+    let one = pow10(digits);
+    let (sin, cos) = match remainder.is_zero() {
+        true => (BigInt::zero(), one.clone()),
+        false => sine_cosine(&remainder, &one),
+    };
+    let (sin, cos) = match ((quadrant % 4u32 + 4u32) % 4u32).to_u8() {
+        Some(0) => (sin, cos),
+        Some(1) => (cos, -sin),
+        Some(2) => (-sin, -cos),
+        _ => (-cos, sin),
+    };
+    let units = match function {
+        Function::Sin => sin,
+        Function::Cos => cos,
+        Function::Tan if cos.is_zero() => return Err(Error::MathDomain(function.display(args))),
+        Function::Tan => sin * &one / cos,
+        _ => unreachable!("Hit {function:?} as a trigonometric function"),
+    };
+    // Multiples of a right angle have integer results
+    if remainder.is_zero() {
+        return Ok(Number::from_big(&units / one, settings.int_type()));
+    }
+    Ok(Number::Decimal(Decimal {
+        units: divide(&units, &pow10(digits - scale), settings.rounding),
+        scale,
+    }))
+}
+
+///
+/// Sine and cosine of a value within π/4 of 0 in fixed point, as the sums of the odd and
+/// even terms of ±r^n / n!
+///
+fn sine_cosine(r: &BigInt, one: &BigInt) -> (BigInt, BigInt) {
+    let (mut term, mut sin, mut cos, mut n) = (one.clone(), BigInt::zero(), BigInt::zero(), 0u32);
+    while !term.is_zero() {
+        match n % 4 {
+            0 => cos += &term,
+            1 => sin += &term,
+            2 => cos -= &term,
+            _ => sin -= &term,
+        }
+        n += 1;
+        term = term * r / one / n;
+    }
+    (sin, cos)
+}
+
+///
+/// π in fixed point, which is 16·atan(1/5) - 4·atan(1/239)
+///
+fn pi(one: &BigInt) -> BigInt {
+    (inverse_atan(5, one) * 4 - inverse_atan(239, one)) * 4
+}
+
+///
+/// atan(1/n) in fixed point, as the sum of ±1 / ((2k+1)·n^(2k+1))
+///
+fn inverse_atan(n: u32, one: &BigInt) -> BigInt {
+    let (mut power, mut sum, mut k) = (one / n, BigInt::zero(), 1u32);
+    while !power.is_zero() {
+        match k % 4 {
+            1 => sum += &power / k,
+            _ => sum -= &power / k,
+        }
+        power /= n * n;
+        k += 2;
+    }
+    sum
+}
+
+///
+/// atan(x) in fixed point. Beyond 1 it is π/2 - atan(1/x), and below it x is halved as an
+/// angle, atan(x) being 2·atan(x / (1 + √(1 + x²))), until the sum of ±x^(2k+1) / (2k+1)
+/// converges quickly
+///
+fn atan(x: &BigInt, one: &BigInt) -> BigInt {
+    if x.is_negative() {
+        return -atan(&-x, one);
+    }
+    if x > one {
+        return pi(one) / 2 - atan(&(one * one / x), one);
+    }
+    let (mut x, mut halvings) = (x.clone(), 0);
+    while &x * 10 > *one {
+        let root = (one * one + &x * &x).sqrt();
+        x = &x * one / (one + root);
+        halvings += 1;
+    }
+    let square = &x * &x / one;
+    let (mut power, mut sum, mut k) = (x, BigInt::zero(), 1u32);
+    while !power.is_zero() {
+        match k % 4 {
+            1 => sum += &power / k,
+            _ => sum -= &power / k,
+        }
+        power = power * &square / one;
+        k += 2;
+    }
+    sum << halvings
+}
+
+///
+/// Units of a decimal given as many fractional digits, which is at least its scale
+///
+fn fixed(d: &Decimal, digits: u32) -> BigInt {
+    &d.units * pow10(digits - d.scale)
+}
+
+///
+/// Logarithm of a decimal in the given base, natural when there is none, in fixed point.
+/// None when the decimal or the base is not positive, or the base is 1
+///
+fn logarithm(x: &Decimal, base: Option<&Decimal>, digits: u32) -> Option<BigInt> {
+    if !x.units.is_positive() {
+        return None;
+    }
+    let ln_x = ln(&fixed(x, digits), digits);
+    let Some(base) = base else {
+        return Some(ln_x);
+    };
+    if !base.units.is_positive() {
+        return None;
+    }
+    let ln_base = ln(&fixed(base, digits), digits);
+    (!ln_base.is_zero()).then(|| ln_x * pow10(digits) / ln_base)
+}
+
+///
+/// Natural logarithm of a positive value in fixed point. The value is m·2^k with m between
+/// 1 and 2, whose logarithm is 2·atanh((m - 1) / (m + 1))
+///
+fn ln(x: &BigInt, digits: u32) -> BigInt {
+    let one = pow10(digits);
+    let mut k = i64::try_from(x.bits()).expect("Values have few bits")
+        - i64::try_from(one.bits()).expect("Values have few bits");
+    let m = loop {
+        let m = match k.unsigned_abs() {
+            shift if k >= 0 => x >> shift,
+            shift => x << shift,
+        };
+        if m < one {
+            k -= 1;
+        } else if m >= &one * 2 {
+            k += 1;
+        } else {
+            break m;
+        }
+    };
+    let z = (&m - &one) * &one / (&m + &one);
+    double_atanh(z, &one) + ln2(&one) * k
+}
+
+///
+/// ln 2, which is 2·atanh(1/3), in fixed point
+///
+fn ln2(one: &BigInt) -> BigInt {
+    double_atanh(one / 3, one)
+}
+
+///
+/// 2·atanh(z) in fixed point, as the sum of 2·z^(2n+1) / (2n+1), for z well below 1
+///
+fn double_atanh(z: BigInt, one: &BigInt) -> BigInt {
+    let z2 = &z * &z / one;
+    let (mut power, mut sum, mut n) = (z, BigInt::zero(), 1u32);
+    while !power.is_zero() {
+        sum += &power / n;
+        power = power * &z2 / one;
+        n += 2;
+    }
+    sum * 2
+}
+
+///
+/// e^x in fixed point. With x = k·ln 2 + r, e^x is 2^k·e^r, where r lies between -ln 2 / 2
+/// and ln 2 / 2 and e^r is the sum of r^n / n!
+///
+fn exp(x: &BigInt, digits: u32) -> BigInt {
+    let one = pow10(digits);
+    let ln2 = ln2(&one);
+    let k = divide(x, &ln2, Rounding::HalfEven);
+    let r = x - &k * &ln2;
+    let (mut term, mut sum, mut n) = (one.clone(), BigInt::zero(), 0u32);
+    while !term.is_zero() {
+        sum += &term;
+        n += 1;
+        term = term * &r / &one / n;
+    }
+    let shift = k.magnitude().to_u64().expect("Exponents are bounded");
+    if k.is_negative() {
+        sum >> shift
+    } else {
+        sum << shift
+    }
+}
+
+///
+/// Value of a built-in constant rounded to the scale, its digits being known up to the
+/// 62nd after the point
+///
+pub(super) fn constant(name: &str, settings: &Settings, scale: u32) -> Option<Number> {
+    let digits = match name {
+        "pi" => "314159265358979323846264338327950288419716939937510582097494459",
+        "e" => "271828182845904523536028747135266249775724709369995957496696762",
+        "tau" => "628318530717958647692528676655900576839433879875021164194988918",
+        "phi" => "161803398874989484820458683436563811772030917980576286213544862",
+        _ => return None,
+    };
+    let digits = digits
+        .parse::<BigInt>()
+        .expect("Constants are made of digits");
+    Some(Number::Decimal(Decimal::from_digits(
+        digits,
+        62,
+        scale,
+        settings.rounding,
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use num_bigint::BigInt;
+
+    use super::{Decimal, divide};
+    use crate::internal::Rounding;
+
+    #[test]
+    fn test_divide_rounding() {
+        for (rounding, results) in [
+            (Rounding::HalfEven, [2, 2, 3, -2, -2]),
+            (Rounding::HalfUp, [2, 3, 3, -3, -2]),
+            (Rounding::Down, [2, 2, 2, -2, -2]),
+            (Rounding::Up, [3, 3, 3, -3, -3]),
+            (Rounding::Floor, [2, 2, 2, -3, -3]),
+            (Rounding::Ceiling, [3, 3, 3, -2, -2]),
+        ] {
+            // 2.25, 2.5, 2.75, -2.5 and -2.25
+            for ((n, d), result) in [(9, 4), (5, 2), (11, 4), (-5, 2), (9, -4)]
+                .into_iter()
+                .zip(results)
+            {
+                assert_eq!(
+                    divide(&BigInt::from(n), &BigInt::from(d), rounding),
+                    BigInt::from(result),
+                    "{n} / {d} {rounding:?}"
+                );
+            }
+        }
+        assert_eq!(
+            divide(&BigInt::from(7), &BigInt::from(2), Rounding::HalfEven),
+            BigInt::from(4)
+        );
+        assert_eq!(
+            divide(&BigInt::from(6), &BigInt::from(3), Rounding::Up),
+            BigInt::from(2)
+        );
+    }
+
+    #[test]
+    fn test_tiny_digits() {
+        // Dropping a hundred million digits rounds without computing 10^100000000
+        for (rounding, digits, units) in [
+            (Rounding::HalfEven, 1, 0),
+            (Rounding::Up, 1, 1),
+            (Rounding::Floor, -1, -1),
+            (Rounding::Ceiling, -1, 0),
+            (Rounding::Up, 0, 0),
+        ] {
+            assert_eq!(
+                Decimal::from_digits(BigInt::from(digits), 100_000_000, 20, rounding).units,
+                BigInt::from(units),
+                "{digits} {rounding:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decimal_display() {
+        let decimal = |units: i64, scale| Decimal {
+            units: BigInt::from(units),
+            scale,
+        };
+
+        assert_eq!(decimal(300, 3).to_string(), "0.3");
+        assert_eq!(decimal(-5, 3).to_string(), "-0.005");
+        assert_eq!(decimal(12000, 3).to_string(), "12");
+        assert_eq!(decimal(0, 2).to_string(), "0");
+        assert_eq!(decimal(42, 0).to_string(), "42");
+        assert_eq!(decimal(15, 1).compare(&decimal(150, 2)), Ordering::Equal);
+        assert_eq!(decimal(-15, 1).compare(&decimal(-149, 2)), Ordering::Less);
+    }
+}
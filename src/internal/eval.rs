@@ -1,15 +1,63 @@
-use std::iter::Peekable;
+use std::{cmp::Ordering, iter::Peekable, num::IntErrorKind};
 
-use super::Error;
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
 
+use super::{
+    DecimalSeparator, Error, IntType, Overflow, Settings, complex, date, decimal,
+    function::{Function, MAX_EXPONENT},
+    higher_order::{HigherOrder, HigherOrderCall},
+    matrix,
+    number::{Kind, Number},
+    unit,
+};
+
+/// Largest size, in bits, a promoted power is allowed to reach
+pub(super) const MAX_PROMOTED_BITS: u64 = 1 << 20;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
-pub(super) enum Operator {
+pub enum Operator {
     LeftBracket,
     RightBracket,
+    Abs,
+    Sqrt,
+    Cbrt,
+    Neg,
+    Pow,
     Prod,
     Div,
+    Mod,
     Sub,
     Add,
+    /// Bitwise not `~`, the complement within the integer type
+    Not,
+    Shl,
+    Shr,
+    BitAnd,
+    /// Bitwise exclusive or, written `xor` as `^` is the power
+    Xor,
+    BitOr,
+    /// Comparisons, 1 when they hold and 0 otherwise
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    /// Logical and, 1 when both operands are not 0 and 0 otherwise. The right operand is
+    /// only evaluated when the left one is not 0
+    And,
+    /// Logical or, 1 when any operand is not 0 and 0 otherwise. The right operand is only
+    /// evaluated when the left one is 0
+    Or,
+    /// Conversion of a quantity to the unit on its right, as in `2.5 h in min`
+    In,
+    /// `?` of a conditional, followed by its first branch and `:`
+    Question,
+    /// `:` of a conditional, which becomes the conditional itself in RPN: the first branch
+    /// when the condition is not 0, the second one otherwise. Only that branch is evaluated
+    Colon,
 }
 
 impl From<&Operator> for String {
@@ -18,160 +66,1504 @@ impl From<&Operator> for String {
             Operator::LeftBracket | Operator::RightBracket => {
                 unreachable!("Hit brackets in RPN stringify")
             }
+            Operator::Abs => "abs".to_owned(),
+            Operator::Sqrt => "√".to_owned(),
+            Operator::Cbrt => "∛".to_owned(),
+            Operator::Neg => "neg".to_owned(),
+            Operator::Pow => "^".to_owned(),
             Operator::Prod => "*".to_owned(),
             Operator::Div => "/".to_owned(),
+            Operator::Mod => "%".to_owned(),
             Operator::Add => "+".to_owned(),
             Operator::Sub => "-".to_owned(),
+            Operator::Not => "~".to_owned(),
+            Operator::Shl => "<<".to_owned(),
+            Operator::Shr => ">>".to_owned(),
+            Operator::BitAnd => "&".to_owned(),
+            Operator::Xor => "xor".to_owned(),
+            Operator::BitOr => "|".to_owned(),
+            Operator::Lt => "<".to_owned(),
+            Operator::Gt => ">".to_owned(),
+            Operator::Le => "<=".to_owned(),
+            Operator::Ge => ">=".to_owned(),
+            Operator::Eq => "==".to_owned(),
+            Operator::Ne => "!=".to_owned(),
+            Operator::And => "&&".to_owned(),
+            Operator::Or => "||".to_owned(),
+            Operator::In => "in".to_owned(),
+            Operator::Question => "?".to_owned(),
+            Operator::Colon => "?:".to_owned(),
+        }
+    }
+}
+
+///
+/// Literal with a fractional part, as in `0.1` or `2.5e-3`
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecimalLiteral {
+    pub(super) digits: BigInt,
+    /// Number of digits after the point
+    pub(super) fraction: u32,
+    /// Literal as written, for messages
+    pub(super) literal: String,
+}
+
+///
+/// Written as a string, as it is in the expression, so that no reader loses digits
+///
+#[cfg(feature = "serde")]
+impl serde::Serialize for DecimalLiteral {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.literal)
+    }
+}
+
+///
+/// Read back from the literal, which gives its digits again
+///
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DecimalLiteral {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let literal = String::deserialize(deserializer)?;
+        match parse_expr(&literal)
+            .map_err(serde::de::Error::custom)?
+            .as_slice()
+        {
+            [Token::Decimal(decimal)] => Ok(decimal.clone()),
+            _ => Err(serde::de::Error::custom(format!(
+                "{literal} is not a decimal literal"
+            ))),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub(super) enum Token {
-    Number(i64),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token {
+    Number(i128),
+    /// Imaginary literal `ni`, as in `4i`
+    Imaginary(i128),
+    /// Literal with a fraction, only valid in decimal mode
+    Decimal(DecimalLiteral),
+    /// `_`, the result of the previous expression
+    Previous,
+    /// `ans(k)`, the k-th most recent result
+    Answer(usize),
+    /// Name of a variable assigned in an earlier line
+    Variable(String),
+    /// Unit written after a number, as in `5 km`, which variables never shadow
+    Unit(String),
     Operator(Operator),
+    /// Name of a function, always followed by the left bracket of its arguments
+    Function(Function),
+    /// Separator of function arguments
+    Comma,
+    /// Function applied to the given number of arguments, only found in RPN
+    Call(Function, usize),
+    /// Name of a function defined in the REPL, always followed by the left bracket of its
+    /// arguments
+    UserFunction(String),
+    /// Function defined in the REPL applied to the given number of arguments, only found
+    /// in RPN
+    UserCall(String, usize),
+    /// Call of a function evaluating its expression for values of a variable, as in
+    /// `solve(x^2 - 2, x, 1)`, read with all its arguments
+    HigherOrder(Box<HigherOrderCall>),
 }
 
 impl From<&Token> for String {
     fn from(value: &Token) -> Self {
         match value {
             Token::Number(n) => (*n).to_string(),
+            Token::Imaginary(n) => format!("{n}i"),
+            Token::Decimal(decimal) => decimal.literal.clone(),
+            Token::Previous => "_".to_owned(),
+            Token::Answer(k) => format!("ans({k})"),
+            Token::Variable(name) | Token::Unit(name) => name.clone(),
             Token::Operator(operator) => operator.into(),
+            Token::Function(function) => function.name().to_owned(),
+            Token::Comma => ",".to_owned(),
+            Token::Call(function, count) => format!("{}/{count}", function.name()),
+            Token::UserFunction(name) => name.clone(),
+            Token::UserCall(name, count) => format!("{name}/{count}"),
+            Token::HigherOrder(call) => call.to_string(),
         }
     }
 }
 
+///
+/// Write the two's complement bit pattern of an integer on the given number of bits, in
+/// base 2, 8 or 16, padded with zeros and grouped by four digits as in `0b0010_1100`.
+/// None for decimals with a fraction, complex numbers and integers beyond the bits, as
+/// signed or as unsigned integers, whose pattern would lose its high bits
+///
+pub fn to_pattern(n: &Number, radix: u32, bits: u32) -> Option<String> {
+    let (prefix, digit_bits) = match radix {
+        2 => ("0b", 1),
+        8 => ("0o", 3),
+        16 => ("0x", 4),
+        _ => return None,
+    };
+    let n = n.to_integer()?;
+    let limit: BigInt = BigInt::from(1) << bits;
+    if n >= limit || n < -(&limit >> 1u32) {
+        return None;
+    }
+    let pattern = (n & (limit - 1u32)).to_str_radix(radix).to_uppercase();
+    let width = usize::try_from(bits.div_ceil(digit_bits)).expect("Widths are small");
+    let padded: Vec<char> = format!("{pattern:0>width$}").chars().collect();
+    let groups: Vec<String> = padded
+        .rchunks(4)
+        .rev()
+        .map(|group| group.iter().collect())
+        .collect();
+    Some(format!("{prefix}{}", groups.join("_")))
+}
+
+///
+/// Write a decimal with a fraction as the nearest fraction whose denominator is at most the
+/// bound, as in `1/3` for `0.3333`, or as an integer when that is the nearest. None for
+/// other numbers
+///
+pub fn to_fraction(n: &Number, max_denominator: u64) -> Option<String> {
+    let Number::Decimal(d) = n else {
+        return None;
+    };
+    if d.integral().is_some() || max_denominator == 0 {
+        return None;
+    }
+    match d.approximate(&max_denominator.into()) {
+        (numerator, denominator) if denominator == BigInt::from(1) => Some(numerator.to_string()),
+        (numerator, denominator) => Some(format!("{numerator}/{denominator}")),
+    }
+}
+
+///
+/// Write a number in base 2, 8, 10 or 16, with the prefix the parser reads back, as in
+/// `-0xFF`
+///
+pub fn to_radix(n: &Number, radix: u32) -> String {
+    // Fractions, complex numbers, dates, durations in `hms` and angles in `dms` are only
+    // written in base 10
+    match n {
+        Number::Complex(z) => return z.to_string(),
+        Number::Date(date) => return date.to_string(),
+        Number::Quantity(q) if q.is_clock() || q.is_dms() => return q.to_string(),
+        Number::Quantity(q) => return format!("{} {}", to_radix(&q.value, radix), q.unit),
+        Number::Matrix(m) => return m.display_with(|n| to_radix(n, radix)),
+        _ => {}
+    }
+    if let Number::Decimal(d) = n
+        && (radix == 10 || d.integral().is_none())
+    {
+        return d.to_string();
+    }
+    let n = n.to_big();
+    let prefix = match radix {
+        2 => "0b",
+        8 => "0o",
+        16 => "0x",
+        _ => "",
+    };
+    let sign = if n.is_negative() { "-" } else { "" };
+    let digits = n.magnitude().to_str_radix(radix).to_uppercase();
+    format!("{sign}{prefix}{digits}")
+}
+
+///
+/// Number of bits of a shift, which cannot be negative
+///
+fn shift_amount(v: i128) -> Result<u32, Error> {
+    if v < 0 {
+        return Err(Error::NegativeShift(Number::Int(v)));
+    }
+    Ok(u32::try_from(v).unwrap_or(u32::MAX))
+}
+
 impl Operator {
-    pub(super) fn execute(self, v1: i64, v2: i64) -> i64 {
+    ///
+    /// Apply the operator with the configured integer type, handling overflow as requested
+    /// by the settings
+    ///
+    pub(super) fn execute(
+        self,
+        v1: Number,
+        v2: Number,
+        settings: &Settings,
+    ) -> Result<Number, Error> {
+        if matches!(self, Operator::Div | Operator::Mod) && v2.is_zero() {
+            return Err(Error::DivisionByZero(format!(
+                "{v1} {} {v2}",
+                String::from(&self)
+            )));
+        }
+        // Conversions only make sense for quantities, whatever the kind of their values
+        if self == Operator::In {
+            return unit::execute(self, v1, v2, settings);
+        }
+        if self.is_comparison() {
+            return self.compare(v1, v2, settings);
+        }
+        let int_type = settings.int_type();
+        match Number::promote(&[&v1, &v2], settings) {
+            Kind::Matrix => matrix::execute(self, v1, v2, settings),
+            Kind::Date => date::execute(self, v1, v2, settings),
+            Kind::Quantity => unit::execute(self, v1, v2, settings),
+            Kind::Complex => complex::execute(self, v1, v2, settings),
+            Kind::Decimal => decimal::execute(self, v1, v2, settings, settings.scale()),
+            Kind::Big => self.execute_big(&v1.to_big(), &v2.to_big(), int_type),
+            Kind::Int => {
+                let (Number::Int(a), Number::Int(b)) = (&v1, &v2) else {
+                    unreachable!("Both operands are integers")
+                };
+                if let Some(n) = self.execute_int(*a, *b)?.filter(|n| int_type.contains(*n)) {
+                    return Ok(Number::Int(n));
+                }
+                match settings.overflow {
+                    Overflow::Checked => Err(Error::Overflow(format!(
+                        "{v1} {} {v2}",
+                        String::from(&self)
+                    ))),
+                    Overflow::Wrap => Ok(Number::Int(int_type.wrap(self.execute_wrapping(*a, *b)))),
+                    Overflow::Saturate => {
+                        Ok(Number::Int(int_type.clamp(self.execute_saturating(*a, *b))))
+                    }
+                    Overflow::Promote => self.execute_big(&v1.to_big(), &v2.to_big(), int_type),
+                }
+            }
+        }
+    }
+
+    ///
+    /// Apply the operator in 128 bits, None means that the result does not fit
+    ///
+    fn execute_int(self, v1: i128, v2: i128) -> Result<Option<i128>, Error> {
+        Ok(match self {
+            Operator::LeftBracket | Operator::RightBracket => {
+                unreachable!("Hit brackets in operation execution")
+            }
+            Operator::Abs | Operator::Sqrt | Operator::Cbrt | Operator::Neg | Operator::Not => {
+                unreachable!("Hit unary operator in binary operation execution")
+            }
+            Operator::Question | Operator::Colon => {
+                unreachable!("Hit conditional in binary operation execution")
+            }
+            Operator::In => unreachable!("Hit conversion in integer operation execution"),
+            Operator::Lt
+            | Operator::Gt
+            | Operator::Le
+            | Operator::Ge
+            | Operator::Eq
+            | Operator::Ne => unreachable!("Hit comparison in integer operation execution"),
+            Operator::Shl => {
+                let shift = shift_amount(v2)?;
+                // Shifting back has to restore the operand, so that no bit was lost
+                let shifted = (shift < 128).then(|| v1 << shift);
+                match shifted {
+                    Some(n) if n >> shift == v1 => Some(n),
+                    _ if v1 == 0 => Some(0),
+                    _ => None,
+                }
+            }
+            Operator::Shr => Some(v1 >> shift_amount(v2)?.min(127)),
+            Operator::BitAnd => Some(v1 & v2),
+            Operator::Xor => Some(v1 ^ v2),
+            Operator::BitOr => Some(v1 | v2),
+            Operator::And => Some(i128::from(v1 != 0 && v2 != 0)),
+            Operator::Or => Some(i128::from(v1 != 0 || v2 != 0)),
+            Operator::Pow => match u32::try_from(v2) {
+                Ok(exp) => v1.checked_pow(exp),
+                Err(_) if v2 < 0 => return Err(Error::NegativeExponent(Number::Int(v2))),
+                // Only trivial bases survive such a large exponent
+                Err(_) => match v1 {
+                    0 | 1 => Some(v1),
+                    -1 => Some(if v2 % 2 == 0 { 1 } else { -1 }),
+                    _ => None,
+                },
+            },
+            Operator::Prod => v1.checked_mul(v2),
+            Operator::Div => Some(v1 / v2),
+            // The remainder always fits, MIN % -1 is 0
+            Operator::Mod => Some(v1.wrapping_rem(v2)),
+            Operator::Add => v1.checked_add(v2),
+            Operator::Sub => v1.checked_sub(v2),
+        })
+    }
+
+    ///
+    /// Apply the operator modulo 2^128, narrower integer types wrap the result once more
+    ///
+    fn execute_wrapping(self, v1: i128, v2: i128) -> i128 {
+        match self {
+            Operator::Pow => {
+                // Exponentiation by squaring, the exponent may not fit in u32
+                let (mut base, mut exp, mut result) = (v1, v2, 1i128);
+                while exp > 0 {
+                    if exp & 1 == 1 {
+                        result = result.wrapping_mul(base);
+                    }
+                    base = base.wrapping_mul(base);
+                    exp >>= 1;
+                }
+                result
+            }
+            Operator::Prod => v1.wrapping_mul(v2),
+            Operator::Div => v1 / v2,
+            Operator::Mod => v1.wrapping_rem(v2),
+            Operator::Add => v1.wrapping_add(v2),
+            Operator::Sub => v1.wrapping_sub(v2),
+            // Negative shifts were rejected by the checked execution
+            Operator::Shl if v2 < 128 => v1 << v2,
+            Operator::Shl => 0,
+            _ => unreachable!("Hit non binary operator in wrapping execution"),
+        }
+    }
+
+    ///
+    /// Apply the operator clamping the result to the 128 bits range, narrower integer types
+    /// clamp the result once more
+    ///
+    fn execute_saturating(self, v1: i128, v2: i128) -> i128 {
         match self {
+            // Any exponent beyond u32 saturates, only its parity matters for the sign
+            Operator::Pow => v1.saturating_pow(u32::try_from(v2).unwrap_or(if v2 % 2 == 0 {
+                u32::MAX - 1
+            } else {
+                u32::MAX
+            })),
+            Operator::Prod => v1.saturating_mul(v2),
+            Operator::Div => v1 / v2,
+            Operator::Mod => v1.wrapping_rem(v2),
+            Operator::Add => v1.saturating_add(v2),
+            Operator::Sub => v1.saturating_sub(v2),
+            // Only reached when bits are lost, which saturates toward the sign of the operand
+            Operator::Shl if v1 < 0 => i128::MIN,
+            Operator::Shl => i128::MAX,
+            _ => unreachable!("Hit non binary operator in saturating execution"),
+        }
+    }
+
+    fn execute_big(self, v1: &BigInt, v2: &BigInt, int_type: IntType) -> Result<Number, Error> {
+        let result = match self {
             Operator::LeftBracket | Operator::RightBracket => {
                 unreachable!("Hit brackets in operation execution")
             }
+            Operator::Abs | Operator::Sqrt | Operator::Cbrt | Operator::Neg | Operator::Not => {
+                unreachable!("Hit unary operator in binary operation execution")
+            }
+            Operator::Question | Operator::Colon => {
+                unreachable!("Hit conditional in binary operation execution")
+            }
+            Operator::In => unreachable!("Hit conversion in integer operation execution"),
+            Operator::Lt
+            | Operator::Gt
+            | Operator::Le
+            | Operator::Ge
+            | Operator::Eq
+            | Operator::Ne => unreachable!("Hit comparison in integer operation execution"),
+            Operator::Shl | Operator::Shr if v2.is_negative() => {
+                return Err(Error::NegativeShift(Number::from_big(v2.clone(), int_type)));
+            }
+            Operator::Shl => {
+                let shift = v2
+                    .to_u64()
+                    .filter(|shift| v1.bits() + shift <= MAX_PROMOTED_BITS)
+                    .ok_or_else(|| Error::Overflow(format!("{v1} << {v2}")))?;
+                v1 << shift
+            }
+            Operator::Shr => match v2.to_u64() {
+                Some(shift) => v1 >> shift,
+                // Any larger shift leaves only the sign
+                None if v1.is_negative() => BigInt::from(-1),
+                None => BigInt::zero(),
+            },
+            Operator::BitAnd => v1 & v2,
+            Operator::Xor => v1 ^ v2,
+            Operator::BitOr => v1 | v2,
+            Operator::And => BigInt::from(!v1.is_zero() && !v2.is_zero()),
+            Operator::Or => BigInt::from(!v1.is_zero() || !v2.is_zero()),
+            Operator::Pow => {
+                if v2.is_negative() {
+                    return Err(Error::NegativeExponent(Number::from_big(
+                        v2.clone(),
+                        int_type,
+                    )));
+                }
+                let exp = v2
+                    .to_u32()
+                    .filter(|exp| v1.bits() * u64::from(*exp) <= MAX_PROMOTED_BITS)
+                    .ok_or_else(|| Error::Overflow(format!("{v1} ^ {v2}")))?;
+                v1.pow(exp)
+            }
             Operator::Prod => v1 * v2,
             Operator::Div => v1 / v2,
+            Operator::Mod => v1 % v2,
             Operator::Add => v1 + v2,
             Operator::Sub => v1 - v2,
+        };
+        Ok(Number::from_big(result, int_type))
+    }
+
+    pub(super) fn execute_unary(self, v: Number, settings: &Settings) -> Result<Number, Error> {
+        match (Number::promote(&[&v], settings), &v) {
+            (Kind::Matrix, Number::Matrix(m)) => {
+                return matrix::execute_unary(self, m, settings);
+            }
+            (Kind::Date, Number::Date(_)) => {
+                return Err(Error::DateOperand(format!("{}({v})", String::from(&self))));
+            }
+            (Kind::Quantity, Number::Quantity(q)) => {
+                return unit::execute_unary(self, q, settings);
+            }
+            (Kind::Complex, Number::Complex(z)) => {
+                return complex::execute_unary(self, z, settings);
+            }
+            (Kind::Decimal, _) => {
+                return decimal::execute_unary(self, v, settings, settings.scale());
+            }
+            _ => {}
+        }
+        let int_type = settings.int_type();
+        if matches!(self, Operator::Sqrt | Operator::Cbrt) {
+            return self.execute_root(v, int_type);
+        }
+        if self == Operator::Not {
+            return Ok(match v {
+                // The complement of an unsigned value flips the bits of its width only
+                Number::Int(n) if int_type.unsigned => Number::Int(int_type.max() - n),
+                Number::Int(n) => Number::Int(!n),
+                n => Number::from_big(!n.to_big(), int_type),
+            });
+        }
+        let n = match v {
+            Number::Int(n) => n,
+            n => {
+                return Ok(Number::from_big(
+                    self.execute_unary_big(n.to_big()),
+                    int_type,
+                ));
+            }
+        };
+        let checked = match self {
+            Operator::Neg => n.checked_neg(),
+            Operator::Abs => n.checked_abs(),
+            _ => unreachable!("Hit binary operator in unary operation execution"),
+        };
+        match (checked.filter(|n| int_type.contains(*n)), settings.overflow) {
+            (Some(n), _) => Ok(Number::Int(n)),
+            (None, Overflow::Checked) => Err(Error::Overflow(match self {
+                Operator::Abs => format!("|{n}|"),
+                _ => format!("-{n}"),
+            })),
+            (None, Overflow::Wrap) => Ok(Number::Int(int_type.wrap(match self {
+                Operator::Abs => n.wrapping_abs(),
+                _ => n.wrapping_neg(),
+            }))),
+            (None, Overflow::Saturate) => Ok(Number::Int(int_type.clamp(match self {
+                Operator::Abs => n.saturating_abs(),
+                _ => n.saturating_neg(),
+            }))),
+            (None, Overflow::Promote) => Ok(Number::from_big(
+                self.execute_unary_big(BigInt::from(n)),
+                int_type,
+            )),
+        }
+    }
+
+    ///
+    /// Integer root, rounded toward zero. Roots never grow beyond their operand
+    ///
+    fn execute_root(self, v: Number, int_type: IntType) -> Result<Number, Error> {
+        let n = v.to_big();
+        match self {
+            Operator::Sqrt if n.is_negative() => Err(Error::NegativeRoot(v)),
+            Operator::Sqrt => Ok(Number::from_big(n.sqrt(), int_type)),
+            Operator::Cbrt => Ok(Number::from_big(n.cbrt(), int_type)),
+            _ => unreachable!("Hit non root operator in root execution"),
+        }
+    }
+
+    fn execute_unary_big(self, n: BigInt) -> BigInt {
+        match self {
+            Operator::Neg => -n,
+            Operator::Abs => n.abs(),
+            _ => unreachable!("Hit binary operator in unary operation execution"),
+        }
+    }
+
+    ///
+    /// Compare two numbers of any kind. Those which are not ordered compare through their
+    /// difference, so that quantities in different units and complex numbers can still be
+    /// told equal, and quantities of the same dimension ordered
+    ///
+    fn compare(self, v1: Number, v2: Number, settings: &Settings) -> Result<Number, Error> {
+        let ordering = match v1.partial_cmp(&v2) {
+            Some(ordering) => Some(ordering),
+            None => match Operator::Sub.execute(v1.clone(), v2.clone(), settings)? {
+                Number::Quantity(q) => q.value.partial_cmp(&Number::Int(0)),
+                difference if difference.is_zero() => Some(Ordering::Equal),
+                _ => None,
+            },
+        };
+        let holds = match (self, ordering) {
+            (Operator::Eq, ordering) => ordering == Some(Ordering::Equal),
+            (Operator::Ne, ordering) => ordering != Some(Ordering::Equal),
+            (Operator::Lt, Some(ordering)) => ordering.is_lt(),
+            (Operator::Gt, Some(ordering)) => ordering.is_gt(),
+            (Operator::Le, Some(ordering)) => ordering.is_le(),
+            (Operator::Ge, Some(ordering)) => ordering.is_ge(),
+            (_, None) => {
+                let comparison = format!("{v1} {} {v2}", String::from(&self));
+                return Err(match Number::promote(&[&v1, &v2], settings) {
+                    Kind::Matrix => Error::MatrixOperand(comparison),
+                    Kind::Date => Error::DateOperand(comparison),
+                    Kind::Quantity => Error::UnitOperand(comparison),
+                    _ => Error::ComplexOperand(comparison),
+                });
+            }
+            _ => unreachable!("Hit non comparison operator in comparison"),
+        };
+        Number::from_literal(i128::from(holds), settings)
+    }
+
+    pub(super) fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Operator::Lt | Operator::Gt | Operator::Le | Operator::Ge | Operator::Eq | Operator::Ne
+        )
+    }
+
+    pub(super) fn is_unary(self) -> bool {
+        matches!(
+            self,
+            Operator::Neg | Operator::Not | Operator::Abs | Operator::Sqrt | Operator::Cbrt
+        )
+    }
+
+    ///
+    /// Binding strength of the operator, higher values are applied first. Comparisons,
+    /// bitwise and logical operators bind looser than arithmetic ones, and conditionals
+    /// looser than anything else, in the same order as in C. A conversion applies to
+    /// everything on its left but a conditional
+    ///
+    pub(super) fn precedence(self) -> u8 {
+        match self {
+            Operator::LeftBracket | Operator::RightBracket => 0,
+            Operator::Question | Operator::Colon => 1,
+            Operator::In => 2,
+            Operator::Or => 3,
+            Operator::And => 4,
+            Operator::BitOr => 5,
+            Operator::Xor => 6,
+            Operator::BitAnd => 7,
+            Operator::Eq | Operator::Ne => 8,
+            Operator::Lt | Operator::Gt | Operator::Le | Operator::Ge => 9,
+            Operator::Shl | Operator::Shr => 10,
+            Operator::Add | Operator::Sub => 11,
+            Operator::Prod | Operator::Div | Operator::Mod => 12,
+            Operator::Neg | Operator::Not | Operator::Sqrt | Operator::Cbrt => 13,
+            Operator::Pow => 14,
+            // Always followed by its bracketed operand, applied as soon as it closes
+            Operator::Abs => 15,
+        }
+    }
+
+    pub(super) fn is_right_associative(self) -> bool {
+        matches!(self, Operator::Pow | Operator::Question | Operator::Colon)
+    }
+
+    ///
+    /// Number of operands the operator is applied to
+    ///
+    pub(super) fn arity(self) -> usize {
+        match self {
+            _ if self.is_unary() => 1,
+            Operator::Colon => 3,
+            _ => 2,
         }
     }
+
+    ///
+    /// Root of the operand, written as its symbol and radicand, when it is not an integer
+    /// and gets rounded. The modulus of a complex number is the square root of its norm.
+    /// Decimals are rounded to their precision without notice, the roots of vectors and
+    /// matrices are those of their entries
+    ///
+    pub(super) fn rounded_root(self, v: &Number) -> Option<(String, Number)> {
+        let (operator, n) = match (self, v) {
+            (_, Number::Matrix(m)) => return m.entries().find_map(|n| self.rounded_root(n)),
+            (_, Number::Quantity(q)) => return self.rounded_root(&q.value),
+            (_, Number::Date(_)) => return None,
+            (_, Number::Decimal(_)) => return None,
+            (Operator::Abs, Number::Complex(z)) => (Operator::Sqrt, z.norm()),
+            (_, Number::Complex(_)) => return None,
+            (operator, v) => (operator, v.to_big()),
+        };
+        let rounded = match operator {
+            Operator::Sqrt => !n.is_negative() && n.sqrt().pow(2) != n,
+            Operator::Cbrt => n.cbrt().pow(3) != n,
+            _ => false,
+        };
+        let radicand = match v {
+            Number::Complex(_) => n.to_i128().map_or(Number::Big(n), Number::Int),
+            v => v.clone(),
+        };
+        rounded.then(|| (String::from(&operator), radicand))
+    }
+
+    ///
+    /// Whether applying the operator to the given operands loses a remainder
+    ///
+    pub(super) fn truncates(self, v1: &Number, v2: &Number) -> bool {
+        self == Operator::Div
+            && !v2.is_zero()
+            && match (v1, v2) {
+                (Number::Int(a), Number::Int(b)) => a.checked_rem(*b).is_some_and(|r| r != 0),
+                (Number::Matrix(_), _) | (_, Number::Matrix(_)) => matrix::truncates(self, v1, v2),
+                (Number::Date(_), _) | (_, Number::Date(_)) => false,
+                (Number::Quantity(_), _) | (_, Number::Quantity(_)) => {
+                    unit::truncates(self, v1, v2)
+                }
+                (Number::Complex(_), _) | (_, Number::Complex(_)) => complex::truncates(v1, v2),
+                (Number::Decimal(_), _) | (_, Number::Decimal(_)) => false,
+                _ => !(v1.to_big() % v2.to_big()).is_zero(),
+            }
+    }
+}
+
+///
+/// Drop the comment ending the expression, if any, introduced by `#` or `//`
+///
+pub fn strip_comment(s: &str) -> &str {
+    let end = [s.find('#'), s.find("//")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(s.len());
+    &s[..end]
+}
+
+///
+/// Read numbers written with the decimal separator of the locale as the parser does: with
+/// a comma, a comma between two digits is the decimal point and a dot followed by three
+/// digits separates thousands, as in `1.234,5` read as `1_234.5`. Every character keeps its
+/// position, so that errors point at the expression as written
+///
+pub fn normalize_separators(s: &str, separator: DecimalSeparator) -> String {
+    if separator == DecimalSeparator::Point {
+        return s.to_owned();
+    }
+    let chars: Vec<char> = s.chars().collect();
+    let digit = |index: Option<usize>| {
+        index
+            .and_then(|index| chars.get(index))
+            .is_some_and(char::is_ascii_digit)
+    };
+    chars
+        .iter()
+        .enumerate()
+        .map(|(index, &c)| {
+            let between = digit(index.checked_sub(1)) && digit(Some(index + 1));
+            match c {
+                ',' if between => '.',
+                '.' if between
+                    && (2..=3).all(|k| digit(Some(index + k)))
+                    && !digit(Some(index + 4)) =>
+                {
+                    '_'
+                }
+                c => c,
+            }
+        })
+        .collect()
 }
 
 ///
 /// Parse mathematic expression to Token list
 ///
-pub(super) fn parse_expr(s: &str) -> Result<Vec<Token>, Error> {
+pub fn parse_expr(s: &str) -> Result<Vec<Token>, Error> {
+    Ok(tokenize(s)?
+        .into_iter()
+        .map(|spanned| spanned.token)
+        .collect())
+}
+
+///
+/// Characters of the expression a token was read from, counted from 0 with the end
+/// excluded
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spanned {
+    pub token: Token,
+    pub span: Span,
+}
+
+///
+/// Parse mathematic expression to Token list, each token with its span. The bar opening an
+/// absolute value gives two tokens with the same span, as does the square bracket opening a
+/// vector
+///
+pub fn tokenize(s: &str) -> Result<Vec<Spanned>, Error> {
+    let s = strip_comment(s);
+    if s.trim().is_empty() {
+        return Err(Error::EmptyExpression);
+    }
+
     let mut result = Vec::new();
+    let mut spans = Vec::new();
     let mut it = s.chars().peekable();
-    // If we have multiple consecutive signs pop last value and replace it following this logic:
-    // - * - = +
-    // + * + = +
-    // - * + = -
-    // + * - = -
-    let mut last_token = &None::<Token>;
+    let length = s.chars().count();
+    let mut groups: Vec<Group> = vec![];
+    // Signs following another operator (or nothing at all) are unary, so `4 - -3`, `--5`
+    // and `4 + - 3` are read as applying the sign to the next operand. Binary operators and
+    // right brackets in the same position are syntax errors, whatever whitespace sits between
     while let Some(&c) = it.peek() {
+        let start = length - it.clone().count();
         match c {
+            // A date literal reads as a call of `date`, as in `date(2024, 3, 1)`
+            '0'..='9' if let Some((year, month, day)) = date::literal(it.clone()) => {
+                result.extend([
+                    Token::Function(Function::Date),
+                    Token::Operator(Operator::LeftBracket),
+                    Token::Number(year),
+                    Token::Comma,
+                    Token::Number(month),
+                    Token::Comma,
+                    Token::Number(day),
+                    Token::Operator(Operator::RightBracket),
+                ]);
+                it.nth(9);
+            }
+            // A clock literal is a number of seconds, as if bracketed with `hms`: `1:45:30`
+            // is read as `(6330 * hms)`
+            '0'..='9' if let Some((seconds, length)) = unit::clock_literal(it.clone()) => {
+                result.extend([
+                    Token::Operator(Operator::LeftBracket),
+                    Token::Number(seconds),
+                    Token::Operator(Operator::Prod),
+                    Token::Unit(unit::CLOCK.to_owned()),
+                    Token::Operator(Operator::RightBracket),
+                ]);
+                it.nth(length - 1);
+            }
+            // Likewise an angle is a number of arcseconds bracketed with `dms`: `45°30'15"` is
+            // read as `(163815 * dms)`
+            '0'..='9' if let Some((arcseconds, length)) = unit::dms_literal(it.clone()) => {
+                result.extend([
+                    Token::Operator(Operator::LeftBracket),
+                    Token::Number(arcseconds),
+                    Token::Operator(Operator::Prod),
+                    Token::Unit(unit::DMS.to_owned()),
+                    Token::Operator(Operator::RightBracket),
+                ]);
+                it.nth(length - 1);
+            }
             '0'..='9' => {
                 it.next();
-                let n = get_number(c, &mut it)?;
-                result.push(Token::Number(n));
-                last_token = &None;
+                let token = match get_number(c, &mut it)? {
+                    // An `i` right after an integer makes it imaginary, unless it starts a name
+                    Token::Number(n)
+                        if it.peek() == Some(&'i')
+                            && !it
+                                .clone()
+                                .nth(1)
+                                .is_some_and(|c| c.is_alphanumeric() || c == '_') =>
+                    {
+                        it.next();
+                        Token::Imaginary(n)
+                    }
+                    token => token,
+                };
+                result.push(token);
             }
-            '(' => {
-                result.push(Token::Operator(Operator::LeftBracket));
-                last_token = &Some(Token::Operator(Operator::LeftBracket));
+            '_' => {
+                if it.clone().nth(1).is_some_and(|c| c.is_ascii_digit()) {
+                    return Err(Error::MisplacedSeparator(
+                        it.clone()
+                            .take_while(|c| c.is_ascii_digit() || *c == '_')
+                            .collect(),
+                    ));
+                }
+                if !starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                result.push(Token::Previous);
                 it.next();
             }
-            ')' => {
-                if let Some(last_operator_token) = last_token {
-                    if last_operator_token == &Token::Operator(Operator::Add)
-                        || last_operator_token == &Token::Operator(Operator::Div)
-                        || last_operator_token == &Token::Operator(Operator::Prod)
-                        || last_operator_token == &Token::Operator(Operator::Sub)
+            c if c.is_alphabetic() => {
+                let starts_operand = starts_operand(&result);
+                match get_identifier(&mut it, start)? {
+                    Token::Variable(name) if name == "xor" && !starts_operand => {
+                        result.push(Token::Operator(Operator::Xor))
+                    }
+                    // A unit right after a number multiplies it, as if bracketed with its
+                    // integer exponent: `5 km` is read as `(5 * km)` and `9 m^2` as
+                    // `(9 * m^2)`, whatever variable has its name. So does a variable
+                    // written against the number, its coefficient, as in `3x^2` read as
+                    // `(3 * x^2)`, unless an `e` makes it look like an exponent without
+                    // digits
+                    Token::Variable(name)
+                        if !starts_operand
+                            && matches!(
+                                result.last(),
+                                Some(Token::Number(_) | Token::Decimal(_))
+                            )
+                            && (unit::is_unit(&name)
+                                || (spans.last().is_some_and(|span: &Span| span.end == start)
+                                    && is_variable_name(&name)
+                                    && !name.starts_with(['e', 'E']))) =>
                     {
-                        return Err(Error::InvalidSyntax);
+                        let literal = spans.len() - 1;
+                        result.insert(literal, Token::Operator(Operator::LeftBracket));
+                        spans.insert(literal, spans[literal]);
+                        let operand = match unit::is_unit(&name) {
+                            true => Token::Unit(name),
+                            false => Token::Variable(name),
+                        };
+                        result.extend([Token::Operator(Operator::Prod), operand]);
+                        if it.next_if_eq(&'^').is_some() {
+                            result.push(Token::Operator(Operator::Pow));
+                            if it.next_if_eq(&'-').is_some() {
+                                result.push(Token::Operator(Operator::Neg));
+                            }
+                            let exponent = get_number(
+                                it.next_if(char::is_ascii_digit)
+                                    .ok_or(Error::InvalidSyntax {
+                                        found: "^".to_owned(),
+                                        position: length - it.clone().count() - 1,
+                                    })?,
+                                &mut it,
+                            )?;
+                            result.push(exponent);
+                        }
+                        result.push(Token::Operator(Operator::RightBracket));
+                    }
+                    // Where an operator is expected `in` converts, elsewhere it is the inch
+                    Token::Variable(name) if name == "in" && !starts_operand => {
+                        result.push(Token::Operator(Operator::In))
                     }
+                    token if (token == Token::Variable("xor".to_owned())) || !starts_operand => {
+                        return Err(Error::InvalidSyntax {
+                            found: String::from(&token),
+                            position: start,
+                        });
+                    }
+                    token => result.push(token),
+                }
+            }
+            '(' | '[' | '{' => {
+                // Square brackets holding a comma make a vector, as if calling `vec`
+                if c == '[' && starts_operand(&result) && holds_comma(it.clone().skip(1)) {
+                    result.push(Token::Function(Function::Vector));
+                }
+                groups.push(Group {
+                    opener: c,
+                    position: length - it.clone().count(),
+                    call: matches!(
+                        result.last(),
+                        Some(Token::Function(_) | Token::UserFunction(_))
+                    ),
+                });
+                result.push(Token::Operator(Operator::LeftBracket));
+                it.next();
+            }
+            ')' | ']' | '}' => {
+                // A call may have no argument, as `rand()`
+                let no_argument = c == ')'
+                    && result.last() == Some(&Token::Operator(Operator::LeftBracket))
+                    && groups.last().is_some_and(|group| group.call);
+                if starts_operand(&result) && !no_argument {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
                 }
+                close_group(&mut groups, c, length - it.clone().count())?;
                 result.push(Token::Operator(Operator::RightBracket));
-                last_token = &Some(Token::Operator(Operator::RightBracket));
+                it.next();
+            }
+            '+' if starts_operand(&result) => {
+                // A unary plus sign does not change the operand
                 it.next();
             }
             '+' => {
-                if let Some(last_operator_token) = last_token {
-                    let _ = result.pop();
-                    if last_operator_token == &Token::Operator(Operator::Sub) {
-                        result.push(Token::Operator(Operator::Sub));
-                        last_token = &Some(Token::Operator(Operator::Sub));
-                    } else {
-                        result.push(Token::Operator(Operator::Add));
-                        last_token = &Some(Token::Operator(Operator::Add));
-                    }
-                } else {
-                    result.push(Token::Operator(Operator::Add));
-                    last_token = &Some(Token::Operator(Operator::Add));
+                result.push(Token::Operator(Operator::Add));
+                it.next();
+            }
+            // The minus sign `−` pasted from documents is the same as the hyphen
+            '-' | '−' if starts_operand(&result) => {
+                result.push(Token::Operator(Operator::Neg));
+                it.next();
+            }
+            '-' | '−' => {
+                result.push(Token::Operator(Operator::Sub));
+                it.next();
+            }
+            '/' | '÷' => {
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
                 }
+                result.push(Token::Operator(Operator::Div));
                 it.next();
             }
-            '-' => {
-                if let Some(last_operator_token) = last_token {
-                    let _ = result.pop();
-                    if last_operator_token == &Token::Operator(Operator::Sub) {
-                        result.push(Token::Operator(Operator::Add));
-                        last_token = &Some(Token::Operator(Operator::Add));
-                    } else {
-                        result.push(Token::Operator(Operator::Add));
-                        last_token = &Some(Token::Operator(Operator::Add));
-                    }
-                } else {
-                    result.push(Token::Operator(Operator::Sub));
-                    last_token = &Some(Token::Operator(Operator::Sub));
+            '%' => {
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
                 }
+                result.push(Token::Operator(Operator::Mod));
                 it.next();
             }
-            '/' => {
-                if let Some(last_operator_token) = last_token {
-                    if last_operator_token == &Token::Operator(Operator::Div)
-                        || last_operator_token == &Token::Operator(Operator::Prod)
-                        || last_operator_token == &Token::Operator(Operator::Add)
-                        || last_operator_token == &Token::Operator(Operator::Sub)
-                    {
-                        return Err(Error::InvalidSyntax);
-                    }
+            '*' | '×' => {
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
                 }
-                result.push(Token::Operator(Operator::Div));
-                last_token = &Some(Token::Operator(Operator::Div));
+                result.push(Token::Operator(Operator::Prod));
                 it.next();
             }
-            '*' => {
-                if let Some(last_operator_token) = last_token {
-                    if last_operator_token == &Token::Operator(Operator::Div)
-                        || last_operator_token == &Token::Operator(Operator::Prod)
-                        || last_operator_token == &Token::Operator(Operator::Add)
-                        || last_operator_token == &Token::Operator(Operator::Sub)
-                    {
-                        return Err(Error::InvalidSyntax);
+            '^' => {
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                result.push(Token::Operator(Operator::Pow));
+                it.next();
+            }
+            '&' => {
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                it.next();
+                let and = match it.next_if_eq(&'&') {
+                    Some(_) => Operator::And,
+                    None => Operator::BitAnd,
+                };
+                result.push(Token::Operator(and));
+            }
+            '<' | '>' | '=' | '!' => {
+                it.next();
+                let operator = match (c, it.next_if(|next| *next == c || *next == '=')) {
+                    ('<', None) => Operator::Lt,
+                    ('>', None) => Operator::Gt,
+                    ('<', Some('<')) => Operator::Shl,
+                    ('>', Some('>')) => Operator::Shr,
+                    ('<', Some(_)) => Operator::Le,
+                    ('>', Some(_)) => Operator::Ge,
+                    ('=', Some('=')) => Operator::Eq,
+                    ('!', Some('=')) => Operator::Ne,
+                    _ => {
+                        return Err(Error::InvalidExpression {
+                            message: format!("Unknown operator {c}"),
+                            position: start,
+                        });
                     }
+                };
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: String::from(&operator),
+                        position: start,
+                    });
                 }
-                result.push(Token::Operator(Operator::Prod));
-                last_token = &Some(Token::Operator(Operator::Prod));
+                result.push(Token::Operator(operator));
+            }
+            '~' => {
+                if !starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                result.push(Token::Operator(Operator::Not));
+                it.next();
+            }
+            // A bar where an operand starts opens an absolute value, otherwise it closes the
+            // innermost group when that is an absolute value, and is a bitwise or when not
+            '|' if starts_operand(&result) => {
+                groups.push(Group {
+                    opener: c,
+                    position: length - it.clone().count(),
+                    call: false,
+                });
+                result.push(Token::Operator(Operator::Abs));
+                result.push(Token::Operator(Operator::LeftBracket));
+                it.next();
+            }
+            '|' if groups.last().is_some_and(|group| group.opener == '|') => {
+                close_group(&mut groups, c, length - it.clone().count())?;
+                result.push(Token::Operator(Operator::RightBracket));
+                it.next();
+            }
+            '|' => {
+                it.next();
+                let or = match it.next_if_eq(&'|') {
+                    Some(_) => Operator::Or,
+                    None => Operator::BitOr,
+                };
+                result.push(Token::Operator(or));
+            }
+            // The condition and the first branch of a conditional are told apart like a group,
+            // so that every `:` closes the innermost `?` within the same brackets
+            '?' => {
+                if starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                groups.push(Group {
+                    opener: c,
+                    position: length - it.clone().count(),
+                    call: false,
+                });
+                result.push(Token::Operator(Operator::Question));
+                it.next();
+            }
+            ':' => {
+                if starts_operand(&result) || groups.last().is_none_or(|group| group.opener != '?')
+                {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                groups.pop();
+                result.push(Token::Operator(Operator::Colon));
+                it.next();
+            }
+            ',' => {
+                if starts_operand(&result) || !groups.last().is_some_and(|group| group.call) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                result.push(Token::Comma);
+                it.next();
+            }
+            '√' | '∛' => {
+                if !starts_operand(&result) {
+                    return Err(Error::InvalidSyntax {
+                        found: c.to_string(),
+                        position: start,
+                    });
+                }
+                let root = if c == '√' {
+                    Operator::Sqrt
+                } else {
+                    Operator::Cbrt
+                };
+                result.push(Token::Operator(root));
                 it.next();
             }
-            ' ' => {
-                last_token = &None;
+            c if c.is_whitespace() => {
                 it.next();
             }
             _ => {
-                return Err(Error::InvalidExpression(format!("Unknown character {c}")));
+                return Err(Error::InvalidExpression {
+                    message: format!("Unknown character {c}"),
+                    position: start,
+                });
             }
         }
+        let end = length - it.clone().count();
+        spans.resize(result.len(), Span { start, end });
+    }
+
+    // Whatever is left awaiting an operand is the last non-whitespace character
+    if starts_operand(&result)
+        && let Some((position, operator)) = s.trim_end().chars().enumerate().last()
+    {
+        return Err(Error::TrailingOperator { operator, position });
+    }
+    // Absolute value bars and conditionals left open
+    if let Some(group) = groups
+        .iter()
+        .find(|group| matches!(group.opener, '|' | '?'))
+    {
+        return Err(Error::InvalidSyntax {
+            found: group.opener.to_string(),
+            position: group.position,
+        });
+    }
+
+    Ok(result
+        .into_iter()
+        .zip(spans)
+        .map(|(token, span)| Spanned { token, span })
+        .collect())
+}
+
+///
+/// Group opened and not closed yet while parsing
+///
+struct Group {
+    opener: char,
+    position: usize,
+    /// Whether the group holds the arguments of a function call
+    call: bool,
+}
+
+///
+/// Whether the characters following an opening bracket hold a comma before its closing
+/// bracket, commas within nested brackets aside
+///
+fn holds_comma(chars: impl Iterator<Item = char>) -> bool {
+    let mut depth = 0;
+    for c in chars {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => return false,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+///
+/// Close the innermost open group, which has to be of the same kind as the closer. Closers
+/// without any open group are left to the parser
+///
+fn close_group(groups: &mut Vec<Group>, closer: char, position: usize) -> Result<(), Error> {
+    let Some(&Group {
+        opener,
+        position: opened,
+        ..
+    }) = groups.last()
+    else {
+        return Ok(());
+    };
+    if opener == '?' {
+        // A conditional missing its `:` within the brackets
+        return Err(Error::InvalidSyntax {
+            found: closer.to_string(),
+            position,
+        });
+    }
+    let expected = match opener {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        _ => '|',
+    };
+    if closer != expected {
+        return Err(Error::MismatchedBracket {
+            opener,
+            opened,
+            closer,
+            position,
+        });
     }
+    groups.pop();
+    Ok(())
+}
+
+///
+/// Whether the next token begins an operand, i.e. there is no value on its left to operate on.
+/// Whitespace never produces tokens, so spaced signs are classified like adjacent ones
+///
+fn starts_operand(tokens: &[Token]) -> bool {
+    match tokens.last() {
+        None => true,
+        Some(
+            Token::Number(_)
+            | Token::Imaginary(_)
+            | Token::Decimal(_)
+            | Token::Previous
+            | Token::Answer(_)
+            | Token::Variable(_)
+            | Token::Unit(_)
+            | Token::HigherOrder(_),
+        ) => false,
+        Some(Token::Operator(operator)) => operator != &Operator::RightBracket,
+        Some(
+            Token::Function(_)
+            | Token::UserFunction(_)
+            | Token::Comma
+            | Token::Call(_, _)
+            | Token::UserCall(_, _),
+        ) => true,
+    }
+}
 
-    Ok(result)
+///
+/// Whether the name can be given to a variable: a letter followed by letters, digits or
+/// underscores, other than the names of built-in functions, `ans` and the keywords
+///
+pub(super) fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(char::is_alphabetic)
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && Function::from_name(name).is_none()
+        && HigherOrder::from_name(name).is_none()
+        && !["ans", "xor", "where", "in"].contains(&name)
 }
 
-fn get_number<T: Iterator<Item = char>>(c: char, iter: &mut Peekable<T>) -> Result<i64, Error> {
-    let mut number = c.to_string().parse::<i64>().map_err(Error::NumberParse)?;
-    while let Some(Ok(digit)) = iter.peek().map(|c| c.to_string().parse::<i64>()) {
-        number = number * 10 + digit;
+///
+/// Read an identifier: a built-in or user function, whose arguments are left to the
+/// caller, a higher-order call with its arguments, `ans(k)` or a variable, `ans` included,
+/// starting at the given position
+///
+fn get_identifier<T: Iterator<Item = char> + Clone>(
+    iter: &mut Peekable<T>,
+    position: usize,
+) -> Result<Token, Error> {
+    let mut name = String::new();
+    while let Some(&c) = iter.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+        name.push(c);
         iter.next();
     }
-    Ok(number)
+    // A physical constant, as in `const.c`
+    if name == "const" && iter.peek() == Some(&'.') {
+        let mut ahead = iter.clone();
+        ahead.next();
+        if ahead.peek().is_some_and(|c| c.is_alphabetic()) {
+            *iter = ahead;
+            name.push('.');
+            while let Some(c) = iter.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+            return Ok(Token::Variable(name));
+        }
+    }
+    if let Some(function) = HigherOrder::from_name(&name) {
+        let opened = position + name.chars().count();
+        let mut ahead = iter.clone();
+        if ahead.next_if_eq(&'(').is_none() {
+            return Err(Error::InvalidExpression {
+                message: format!("Function {name} takes its arguments in brackets"),
+                position,
+            });
+        }
+        let args = get_arguments(&mut ahead).ok_or(Error::UnmatchedParen {
+            bracket: '(',
+            position: opened,
+        })?;
+        // A sum of values, as in `sum(3, 5, 9)`, is a call of the built-in function
+        if let Some(plain) = Function::from_name(&name)
+            && !function.iterates(&args)
+        {
+            return Ok(Token::Function(plain));
+        }
+        *iter = ahead;
+        let call = HigherOrderCall::new(function, args)?;
+        return Ok(Token::HigherOrder(Box::new(call)));
+    }
+    if let Some(function) = Function::from_name(&name) {
+        // The minute shares its name with the function
+        if iter.peek() != Some(&'(') && unit::is_unit(&name) {
+            return Ok(Token::Variable(name));
+        }
+        if iter.peek() != Some(&'(') {
+            return Err(Error::InvalidExpression {
+                message: format!("Function {name} takes its arguments in brackets"),
+                position,
+            });
+        }
+        return Ok(Token::Function(function));
+    }
+    if name != "ans" && iter.peek() == Some(&'(') {
+        return Ok(Token::UserFunction(name));
+    }
+    // A bare `ans` is the variable always bound to the previous result
+    if name != "ans" || iter.next_if_eq(&'(').is_none() {
+        return Ok(Token::Variable(name));
+    }
+    let mut index = String::new();
+    while let Some(digit) = iter.next_if(|c| c.is_ascii_digit()) {
+        index.push(digit);
+    }
+    match (index.parse::<usize>(), iter.next_if_eq(&')')) {
+        (Ok(k), Some(_)) => Ok(Token::Answer(k)),
+        _ => Err(Error::InvalidExpression {
+            message: "ans takes the index of a result, as in ans(2)".to_owned(),
+            position,
+        }),
+    }
+}
+
+///
+/// Read the arguments of a call as they are written, up to the bracket closing the one
+/// already read, split on the commas outside of any nested bracket. None when the call is
+/// not closed
+///
+fn get_arguments<T: Iterator<Item = char>>(iter: &mut Peekable<T>) -> Option<Vec<String>> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut depth = 0;
+    for c in iter.by_ref() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => {
+                args.push(arg.trim().to_owned());
+                return Some(args);
+            }
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(arg.trim().to_owned());
+                arg.clear();
+                continue;
+            }
+            _ => {}
+        }
+        arg.push(c);
+    }
+    None
+}
+
+///
+/// Read a decimal literal, or a hexadecimal, octal or binary one after its `0x`, `0o` or
+/// `0b` prefix
+///
+fn get_number<T: Iterator<Item = char> + Clone>(
+    c: char,
+    iter: &mut Peekable<T>,
+) -> Result<Token, Error> {
+    let radix = match (c, iter.peek()) {
+        ('0', Some('x' | 'X')) => 16,
+        ('0', Some('o' | 'O')) => 8,
+        ('0', Some('b' | 'B')) => 2,
+        _ => return get_decimal(c, iter),
+    };
+    let mut literal = c.to_string();
+    literal.extend(iter.next());
+    let mut digits = String::new();
+    // Any letter following a prefix is taken as a digit, to report those out of the radix
+    take_digits(iter, &mut literal, &mut digits, char::is_ascii_alphanumeric)?;
+    i128::from_str_radix(&digits, radix)
+        .map(Token::Number)
+        .map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => Error::Overflow(format!("literal {literal}")),
+            _ => Error::NumberParse(e),
+        })
+}
+
+///
+/// Read a decimal literal, which may have a fraction and an exponent as in `1.5e3`
+///
+fn get_decimal<T: Iterator<Item = char> + Clone>(
+    c: char,
+    iter: &mut Peekable<T>,
+) -> Result<Token, Error> {
+    let mut literal = c.to_string();
+    let mut digits = c.to_string();
+    // Power of ten the digits are multiplied by
+    let mut scale = 0i64;
+    take_digits(iter, &mut literal, &mut digits, char::is_ascii_digit)?;
+    if iter.peek() == Some(&'.') && followed_by_digit(iter.clone().skip(1)) {
+        literal.extend(iter.next());
+        let integer = digits.len();
+        take_digits(iter, &mut literal, &mut digits, char::is_ascii_digit)?;
+        scale -= i64::try_from(digits.len() - integer).expect("Literals are short");
+    }
+    // An exponent needs its digits, otherwise the letter starts an identifier
+    if matches!(iter.peek(), Some('e' | 'E')) {
+        let mut ahead = iter.clone().skip(1).peekable();
+        ahead.next_if(|c| matches!(c, '+' | '-'));
+        if followed_by_digit(ahead) {
+            let mut exponent = String::new();
+            literal.extend(iter.next());
+            if let Some(sign) = iter.next_if(|c| matches!(c, '+' | '-')) {
+                literal.push(sign);
+                exponent.push(sign);
+            }
+            while let Some(digit) = iter.next_if(char::is_ascii_digit) {
+                literal.push(digit);
+                exponent.push(digit);
+            }
+            scale = exponent
+                .parse::<i64>()
+                .ok()
+                .and_then(|exponent| exponent.checked_add(scale))
+                .ok_or_else(|| Error::Overflow(format!("literal {literal}")))?;
+        }
+    }
+    scaled_literal(&digits, scale, &literal)
+}
+
+///
+/// Read the digits following one already read, skipping the `_` separators, which have to
+/// sit between two digits as in `1_000_000`
+///
+fn take_digits<T: Iterator<Item = char> + Clone>(
+    iter: &mut Peekable<T>,
+    literal: &mut String,
+    digits: &mut String,
+    is_digit: fn(&char) -> bool,
+) -> Result<(), Error> {
+    loop {
+        if let Some(digit) = iter.next_if(is_digit) {
+            literal.push(digit);
+            digits.push(digit);
+        } else if iter.peek() == Some(&'_') {
+            let between = literal.ends_with(|c| is_digit(&c))
+                && iter.clone().nth(1).is_some_and(|c| is_digit(&c));
+            literal.extend(iter.next());
+            if !between {
+                return Err(Error::MisplacedSeparator(literal.clone()));
+            }
+        } else {
+            return Ok(());
+        }
+    }
+}
+
+fn followed_by_digit(mut iter: impl Iterator<Item = char>) -> bool {
+    iter.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+///
+/// Value of the digits times 10^scale. A literal written with a fraction or an exponent
+/// is a decimal one, even when it is an integer as `5.0` or `1e3`
+///
+fn scaled_literal(digits: &str, scale: i64, literal: &str) -> Result<Token, Error> {
+    let overflow = || Error::Overflow(format!("literal {literal}"));
+    if literal.contains(['.', 'e', 'E']) {
+        let digits: BigInt = digits.parse().map_err(|_| overflow())?;
+        let (digits, fraction) = match u32::try_from(scale) {
+            Ok(scale) if scale <= MAX_EXPONENT || digits.is_zero() => {
+                (digits * BigInt::from(10).pow(scale.min(MAX_EXPONENT)), 0)
+            }
+            Ok(_) => return Err(overflow()),
+            Err(_) => (digits, u32::try_from(-scale).map_err(|_| overflow())?),
+        };
+        return Ok(Token::Decimal(DecimalLiteral {
+            digits,
+            fraction,
+            literal: literal.to_owned(),
+        }));
+    }
+    digits
+        .parse::<i128>()
+        .map(Token::Number)
+        .map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => overflow(),
+            _ => Error::NumberParse(e),
+        })
 }
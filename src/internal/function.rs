@@ -0,0 +1,797 @@
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::{
+    Context, Error, HigherOrder, Settings, date,
+    decimal::{self, Decimal},
+    eval::Operator,
+    matrix::{self, Matrix},
+    number::Number,
+    unit,
+};
+
+/// Largest argument of `exp`, result of `ln` and number of digits rounded away, computed
+/// before giving up as an overflow
+pub(super) const MAX_EXPONENT: u32 = 1 << 12;
+
+///
+/// Built-in functions, called with their arguments in brackets as in `max(3, 7)`
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Function {
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+    Ln,
+    Log10,
+    Log2,
+    /// Logarithm in any base, the base coming first as in `log(3, 81)`
+    Log,
+    Exp,
+    /// Trigonometric functions of an angle in radians, or of a quantity in degrees, as in
+    /// `sin(90 deg)`
+    Sin,
+    Cos,
+    Tan,
+    /// Arctangent in radians, between -π/2 and π/2
+    Atan,
+    /// Greatest common divisor of all the arguments, never negative
+    Gcd,
+    /// Least common multiple of all the arguments, never negative
+    Lcm,
+    /// Quotient and remainder of a division as the vector `[q, r]`, the quotient rounded
+    /// toward zero like `/` and the remainder the one of `%`
+    DivMod,
+    /// Rounding functions, the identity on integers unless a negative number of digits
+    /// is given, as in `round(1234, -2)`
+    Floor,
+    Ceil,
+    Round,
+    Trunc,
+    /// Random decimal from 0 included to 1 excluded, of the digits of the decimals
+    Rand,
+    /// Random integer between both arguments, included
+    RandInt,
+    /// Vector of the arguments, or matrix when they are vectors, also written `[1, 2]`
+    Vector,
+    /// Dot product of two vectors of the same length
+    Dot,
+    Transpose,
+    /// Determinant of a square matrix
+    Det,
+    /// Inverse of a square matrix, with the decimals of the integer mode when integers have
+    /// an inverse with fractions
+    Inv,
+    /// Statistics of all the arguments, with the decimals of the integer mode when those of
+    /// integers have a fraction. The variance and the standard deviation are those of a
+    /// sample, dividing by one less than the number of values
+    Sum,
+    Mean,
+    Median,
+    Var,
+    Stdev,
+    /// Quantity converted to the unit of the second argument, as in `convert(100 mph, km/h)`
+    Convert,
+    /// Date of a year, a month and a day, also written `2024-03-01`
+    Date,
+    /// Number of days from the first date to the second one
+    DaysBetween,
+}
+
+/// Every built-in function, in the order they are declared
+const FUNCTIONS: &[Function] = &[
+    Function::Sqrt,
+    Function::Abs,
+    Function::Min,
+    Function::Max,
+    Function::Ln,
+    Function::Log10,
+    Function::Log2,
+    Function::Log,
+    Function::Exp,
+    Function::Sin,
+    Function::Cos,
+    Function::Tan,
+    Function::Atan,
+    Function::Gcd,
+    Function::Lcm,
+    Function::DivMod,
+    Function::Floor,
+    Function::Ceil,
+    Function::Round,
+    Function::Trunc,
+    Function::Rand,
+    Function::RandInt,
+    Function::Vector,
+    Function::Dot,
+    Function::Transpose,
+    Function::Det,
+    Function::Inv,
+    Function::Sum,
+    Function::Mean,
+    Function::Median,
+    Function::Var,
+    Function::Stdev,
+    Function::Convert,
+    Function::Date,
+    Function::DaysBetween,
+];
+
+///
+/// Names of the built-in functions, those taking an expression included, in alphabetical
+/// order
+///
+pub fn function_names() -> Vec<&'static str> {
+    let higher_order = [
+        HigherOrder::Solve,
+        HigherOrder::Integrate,
+        HigherOrder::Sum,
+        HigherOrder::Prod,
+    ];
+    let mut names: Vec<&'static str> = FUNCTIONS
+        .iter()
+        .map(|function| function.name())
+        .chain(higher_order.iter().map(|function| function.name()))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+impl Function {
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sqrt" => Some(Function::Sqrt),
+            "abs" => Some(Function::Abs),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "ln" => Some(Function::Ln),
+            "log10" => Some(Function::Log10),
+            "log2" => Some(Function::Log2),
+            "log" => Some(Function::Log),
+            "exp" => Some(Function::Exp),
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "tan" => Some(Function::Tan),
+            "atan" => Some(Function::Atan),
+            "gcd" => Some(Function::Gcd),
+            "lcm" => Some(Function::Lcm),
+            "divmod" => Some(Function::DivMod),
+            "floor" => Some(Function::Floor),
+            "ceil" => Some(Function::Ceil),
+            "round" => Some(Function::Round),
+            "trunc" => Some(Function::Trunc),
+            "rand" => Some(Function::Rand),
+            "randint" => Some(Function::RandInt),
+            "vec" => Some(Function::Vector),
+            "dot" => Some(Function::Dot),
+            "transpose" => Some(Function::Transpose),
+            "det" => Some(Function::Det),
+            "inv" => Some(Function::Inv),
+            "sum" => Some(Function::Sum),
+            "mean" => Some(Function::Mean),
+            "median" => Some(Function::Median),
+            "var" => Some(Function::Var),
+            "stdev" => Some(Function::Stdev),
+            "convert" => Some(Function::Convert),
+            "date" => Some(Function::Date),
+            "days_between" => Some(Function::DaysBetween),
+            _ => None,
+        }
+    }
+
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Function::Sqrt => "sqrt",
+            Function::Abs => "abs",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Ln => "ln",
+            Function::Log10 => "log10",
+            Function::Log2 => "log2",
+            Function::Log => "log",
+            Function::Exp => "exp",
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Atan => "atan",
+            Function::Gcd => "gcd",
+            Function::Lcm => "lcm",
+            Function::DivMod => "divmod",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Round => "round",
+            Function::Trunc => "trunc",
+            Function::Rand => "rand",
+            Function::RandInt => "randint",
+            Function::Vector => "vec",
+            Function::Dot => "dot",
+            Function::Transpose => "transpose",
+            Function::Det => "det",
+            Function::Inv => "inv",
+            Function::Sum => "sum",
+            Function::Mean => "mean",
+            Function::Median => "median",
+            Function::Var => "var",
+            Function::Stdev => "stdev",
+            Function::Convert => "convert",
+            Function::Date => "date",
+            Function::DaysBetween => "days_between",
+        }
+    }
+
+    ///
+    /// Operator computing the function, for functions of a single argument that also have
+    /// an operator syntax
+    ///
+    pub(super) fn operator(self) -> Option<Operator> {
+        match self {
+            Function::Sqrt => Some(Operator::Sqrt),
+            Function::Abs => Some(Operator::Abs),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Whether the function is computed on decimals as they are, the others only accepting
+    /// integers in decimal mode
+    ///
+    fn takes_decimals(self) -> bool {
+        matches!(
+            self,
+            Function::Sqrt
+                | Function::Abs
+                | Function::Min
+                | Function::Max
+                | Function::Floor
+                | Function::Ceil
+                | Function::Round
+                | Function::Trunc
+                | Function::DivMod
+                | Function::Rand
+                | Function::Sum
+                | Function::Mean
+                | Function::Median
+                | Function::Var
+                | Function::Stdev
+                | Function::Convert
+                | Function::Date
+                | Function::DaysBetween
+        ) || self.of_matrices()
+    }
+
+    ///
+    /// Whether the function is a logarithm, the exponential or the arctangent, which
+    /// decimals compute with series
+    ///
+    fn is_transcendental(self) -> bool {
+        matches!(
+            self,
+            Function::Ln
+                | Function::Log10
+                | Function::Log2
+                | Function::Log
+                | Function::Exp
+                | Function::Atan
+        )
+    }
+
+    ///
+    /// Whether the function is a sine, a cosine or a tangent, which also take angles
+    ///
+    pub(super) fn is_trigonometric(self) -> bool {
+        matches!(self, Function::Sin | Function::Cos | Function::Tan)
+    }
+
+    ///
+    /// Whether the function builds or takes vectors and matrices
+    ///
+    fn of_matrices(self) -> bool {
+        matches!(
+            self,
+            Function::Vector | Function::Dot | Function::Transpose | Function::Det | Function::Inv
+        )
+    }
+
+    pub(super) fn check_arguments(self, count: usize) -> Result<(), Error> {
+        let accepted = match self {
+            Function::Min
+            | Function::Max
+            | Function::Gcd
+            | Function::Lcm
+            | Function::Vector
+            | Function::Sum
+            | Function::Mean
+            | Function::Median => count >= 1,
+            Function::Var | Function::Stdev => count >= 2,
+            Function::Log
+            | Function::DivMod
+            | Function::RandInt
+            | Function::Dot
+            | Function::Convert
+            | Function::DaysBetween => count == 2,
+            Function::Date => count == 3,
+            Function::Rand => count == 0,
+            Function::Floor | Function::Ceil | Function::Round | Function::Trunc => {
+                count == 1 || count == 2
+            }
+            _ => count == 1,
+        };
+        if accepted {
+            Ok(())
+        } else {
+            Err(Error::ArgumentCount {
+                function: self.name().to_owned(),
+                count,
+            })
+        }
+    }
+
+    ///
+    /// Write the call back with the values of its arguments, as in `log(3, 10)`
+    ///
+    pub(super) fn display(self, args: &[Number]) -> String {
+        let args = args.iter().map(Number::to_string).collect::<Vec<String>>();
+        format!("{}({})", self.name(), args.join(", "))
+    }
+
+    ///
+    /// Whether the exact integer mode meets a call whose result is not made of integers: a
+    /// statistic, an inverse matrix, a conversion, a logarithm, an exponential or a
+    /// trigonometric function. Roots are checked through their operator
+    ///
+    pub(super) fn truncates(self, args: &[Number], settings: &Settings) -> bool {
+        match (self, args) {
+            (Function::Inv, _) => settings.exact && matrix::inverse_truncates(args, settings),
+            (Function::Convert, _) => settings.exact && unit::convert_truncates(args),
+            (Function::Mean | Function::Median | Function::Var | Function::Stdev, _) => {
+                settings.exact && self.statistic_has_fraction(args, settings)
+            }
+            // Logarithms, the exponential and trigonometric functions get a fraction, which
+            // the exact integer mode refuses
+            _ => settings.exact && !computes_decimals(args, settings) && self.is_irrational(args),
+        }
+    }
+
+    ///
+    /// Whether the logarithm, exponential or arctangent of integer arguments is not an
+    /// integer, or the trigonometric function of an angle other than a multiple of 90°
+    ///
+    pub(super) fn is_irrational(self, args: &[Number]) -> bool {
+        match (self, args) {
+            (Function::Sin | Function::Cos | Function::Tan, [Number::Quantity(q)]) => {
+                unit::degrees(q).is_some_and(|(n, d)| !(n % (d * 90u32)).is_zero())
+            }
+            (Function::Sin | Function::Cos | Function::Tan, [v]) => !v.is_zero(),
+            // e^k is irrational for any k other than 0
+            (Function::Ln, [v]) => v.to_big() > BigInt::one(),
+            (Function::Exp | Function::Atan, [v]) => !v.is_zero(),
+            (Function::Log10 | Function::Log2 | Function::Log, _) => {
+                let (base, v) = self.logarithm_operands(args);
+                if !v.is_positive() || base <= BigInt::one() {
+                    return false;
+                }
+                base.pow(integer_log(&base, &v)) != v
+            }
+            _ => false,
+        }
+    }
+
+    pub(super) fn call(
+        self,
+        mut args: Vec<Number>,
+        settings: &Settings,
+        context: &Context,
+    ) -> Result<Number, Error> {
+        self.check_arguments(args.len())?;
+        if self.of_matrices() {
+            return matrix::call(self, args, settings);
+        }
+        if self == Function::Convert {
+            return unit::convert(&args, settings);
+        }
+        if matches!(self, Function::Date | Function::DaysBetween) {
+            return date::call(self, &args, settings);
+        }
+        if args.iter().any(|arg| matches!(arg, Number::Date(_))) {
+            return Err(Error::DateOperand(self.display(&args)));
+        }
+        // Sums and functions with an operator keep the units, the others take numbers only
+        if args.iter().any(|arg| matches!(arg, Number::Quantity(_))) {
+            return match self.operator() {
+                Some(operator) => operator.execute_unary(args.swap_remove(0), settings),
+                None if self == Function::Sum => sum(args, settings),
+                None if self.is_trigonometric() => {
+                    decimal::trigonometric_call(self, &args, settings, settings.scale())
+                }
+                None => Err(Error::UnitOperand(self.display(&args))),
+            };
+        }
+        // Functions with an operator apply to each entry, the others take numbers only
+        if args.iter().any(|arg| matches!(arg, Number::Matrix(_))) {
+            return match self.operator() {
+                Some(operator) => operator.execute_unary(args.swap_remove(0), settings),
+                None => Err(Error::MatrixOperand(self.display(&args))),
+            };
+        }
+        // The modulus is the only function of a complex number
+        if self != Function::Abs && args.iter().any(|arg| matches!(arg, Number::Complex(_))) {
+            return Err(Error::ComplexOperand(self.display(&args)));
+        }
+        if self.is_trigonometric() {
+            return decimal::trigonometric_call(self, &args, settings, settings.scale());
+        }
+        let decimals = computes_decimals(&args, settings);
+        // Out of the exact mode, integers whose logarithm or exponential has a fraction
+        // compute it with the decimals of the integer mode
+        let irrational = !settings.exact && self.is_irrational(&args);
+        if self.is_transcendental() && (decimals || irrational) {
+            return decimal::transcendental_call(self, args, settings, context, settings.scale());
+        }
+        if decimals && !self.takes_decimals() {
+            return decimal::integral_call(self, args, settings, context, settings.scale());
+        }
+        if let Some(operator) = self.operator() {
+            let v = args.pop().expect("Argument count was checked");
+            return operator.execute_unary(v, settings);
+        }
+        let int_type = settings.int_type();
+        match self {
+            Function::Min => Ok(args
+                .into_iter()
+                .reduce(|a, b| if b < a { b } else { a })
+                .expect("Argument count was checked")),
+            Function::Max => Ok(args
+                .into_iter()
+                .reduce(|a, b| if b > a { b } else { a })
+                .expect("Argument count was checked")),
+            Function::Ln => {
+                let v = args[0].to_big();
+                if !v.is_positive() {
+                    return Err(Error::MathDomain(self.display(&args)));
+                }
+                let ln = natural_log(&v).ok_or_else(|| Error::Overflow(self.display(&args)))?;
+                Ok(Number::from_big(ln.into(), int_type))
+            }
+            Function::Log10 | Function::Log2 | Function::Log => {
+                let (base, v) = self.logarithm_operands(&args);
+                if !v.is_positive() || base <= BigInt::one() {
+                    return Err(Error::MathDomain(self.display(&args)));
+                }
+                Ok(Number::from_big(integer_log(&base, &v).into(), int_type))
+            }
+            Function::Exp => {
+                let v = args[0].to_big();
+                if v.is_negative() {
+                    // e^-k lies between 0 and 1
+                    return Ok(Number::Int(0));
+                }
+                match v.to_u32().filter(|k| *k <= MAX_EXPONENT) {
+                    Some(k) => Number::fit(exp_floor(k), settings, || self.display(&args)),
+                    None => Err(Error::Overflow(self.display(&args))),
+                }
+            }
+            // atan(0) is the only arctangent of an integer without a fraction
+            Function::Atan => Ok(Number::Int(0)),
+            Function::Gcd | Function::Lcm => {
+                let result = args
+                    .iter()
+                    .map(Number::to_big)
+                    .reduce(|a, b| match self {
+                        Function::Gcd => gcd(a, b),
+                        _ => lcm(a, b),
+                    })
+                    .expect("Argument count was checked");
+                Number::fit(result.abs(), settings, || self.display(&args))
+            }
+            Function::DivMod => {
+                if args[1].is_zero() {
+                    return Err(Error::DivisionByZero(self.display(&args)));
+                }
+                // Taking the remainder away leaves a multiple of the divisor, which divides
+                // exactly
+                let [a, b] = <[Number; 2]>::try_from(args).expect("Argument count was checked");
+                let remainder = Operator::Mod.execute(a.clone(), b.clone(), settings)?;
+                let multiple = Operator::Sub.execute(a, remainder.clone(), settings)?;
+                let quotient = Operator::Div.execute(multiple, b, settings)?;
+                Ok(Number::Matrix(Box::new(Matrix::vector(vec![
+                    quotient, remainder,
+                ]))))
+            }
+            Function::Floor | Function::Ceil | Function::Round | Function::Trunc => {
+                // Integers are decimals without fractional digits
+                let (units, scale) = match &args[0] {
+                    Number::Decimal(d) => (d.units.clone(), d.scale),
+                    n => (n.to_big(), 0),
+                };
+                let digits = match args.get(1).map(Number::to_integer) {
+                    Some(Some(digits)) => digits,
+                    Some(None) => return Err(Error::FractionalOperand(self.display(&args))),
+                    None => BigInt::zero(),
+                };
+                // Digits dropped from the units, nothing to round when there are none
+                let dropped = BigInt::from(scale) - digits;
+                if !dropped.is_positive() {
+                    return Ok(args.swap_remove(0));
+                }
+                let step = match dropped.to_u32().filter(|k| *k <= MAX_EXPONENT) {
+                    Some(k) => BigInt::from(10).pow(k),
+                    None => return Err(Error::Overflow(self.display(&args))),
+                };
+                let rounded = Decimal {
+                    units: self.round_to(units, &step),
+                    scale,
+                };
+                // The integer mode comes back to integers once no fractional digit is left
+                match rounded.integral() {
+                    Some(n) if settings.decimals.is_none() => {
+                        Number::fit(n, settings, || self.display(&args))
+                    }
+                    _ => Ok(Number::Decimal(rounded)),
+                }
+            }
+            Function::Rand => Ok(Number::Decimal(Decimal {
+                units: context.random_digits(settings.scale()),
+                scale: settings.scale(),
+            })),
+            Function::RandInt => match (&args[0], &args[1]) {
+                (Number::Int(low), Number::Int(high)) if low <= high => {
+                    Ok(Number::Int(context.random(*low..=*high)))
+                }
+                (Number::Int(_), Number::Int(_)) => Err(Error::MathDomain(self.display(&args))),
+                // Random numbers are drawn in 128 bits
+                _ => Err(Error::Overflow(self.display(&args))),
+            },
+            Function::Sum => sum(args, settings),
+            Function::Mean | Function::Median | Function::Var | Function::Stdev => {
+                // Statistics of integers with a fraction get the decimals of the integer mode
+                let decimals = Settings {
+                    decimals: Some(settings.scale()),
+                    ..*settings
+                };
+                let settings = match self.statistic_has_fraction(&args, settings) {
+                    true => &decimals,
+                    false => settings,
+                };
+                let (numerator, denominator) = self.statistic_parts(&args, settings)?;
+                let result = Operator::Div.execute(numerator, denominator, settings)?;
+                match self {
+                    Function::Stdev => Operator::Sqrt.execute_unary(result, settings),
+                    _ => Ok(result),
+                }
+            }
+            Function::Sqrt | Function::Abs => unreachable!("Hit operator function {self:?}"),
+            Function::Vector
+            | Function::Dot
+            | Function::Transpose
+            | Function::Det
+            | Function::Inv => unreachable!("Hit matrix function {self:?}"),
+            Function::Convert => unreachable!("Hit unit conversion {self:?}"),
+            Function::Sin | Function::Cos | Function::Tan => {
+                unreachable!("Hit trigonometric function {self:?}")
+            }
+            Function::Date | Function::DaysBetween => unreachable!("Hit date function {self:?}"),
+        }
+    }
+
+    ///
+    /// Whether the mean, median, variance or standard deviation of integers has a fraction
+    ///
+    fn statistic_has_fraction(self, args: &[Number], settings: &Settings) -> bool {
+        let Ok((numerator, denominator)) = self.statistic_parts(args, settings) else {
+            return false;
+        };
+        Operator::Div.truncates(&numerator, &denominator)
+            || (self == Function::Stdev
+                && Operator::Div
+                    .execute(numerator, denominator, settings)
+                    .is_ok_and(|variance| Operator::Sqrt.rounded_root(&variance).is_some()))
+    }
+
+    ///
+    /// Numerator and denominator of the mean, median or variance of the arguments, the
+    /// standard deviation being the root of the variance, so that integers are divided
+    /// once at the end
+    ///
+    fn statistic_parts(
+        self,
+        args: &[Number],
+        settings: &Settings,
+    ) -> Result<(Number, Number), Error> {
+        let count = Number::from_literal(args.len() as i128, settings)?;
+        match self {
+            Function::Mean => Ok((sum(args.iter().cloned(), settings)?, count)),
+            Function::Median => {
+                let mut sorted = args.to_vec();
+                // Complex numbers and matrices were refused, the others are ordered
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                let middle = sorted.len() / 2;
+                if sorted.len() % 2 == 1 {
+                    return Ok((
+                        sorted.swap_remove(middle),
+                        Number::from_literal(1, settings)?,
+                    ));
+                }
+                Ok((
+                    Operator::Add.execute(
+                        sorted[middle - 1].clone(),
+                        sorted[middle].clone(),
+                        settings,
+                    )?,
+                    Number::from_literal(2, settings)?,
+                ))
+            }
+            // n Σx² - (Σx)² over n (n - 1), which has no fraction before the division
+            _ => {
+                let squares = args
+                    .iter()
+                    .map(|v| Operator::Prod.execute(v.clone(), v.clone(), settings))
+                    .collect::<Result<Vec<Number>, Error>>()?;
+                let total = sum(args.iter().cloned(), settings)?;
+                let numerator = Operator::Sub.execute(
+                    Operator::Prod.execute(count.clone(), sum(squares, settings)?, settings)?,
+                    Operator::Prod.execute(total.clone(), total, settings)?,
+                    settings,
+                )?;
+                let less = Operator::Sub.execute(
+                    count.clone(),
+                    Number::from_literal(1, settings)?,
+                    settings,
+                )?;
+                Ok((numerator, Operator::Prod.execute(count, less, settings)?))
+            }
+        }
+    }
+
+    ///
+    /// Round to a multiple of the scale, halves going away from zero
+    ///
+    fn round_to(self, v: BigInt, scale: &BigInt) -> BigInt {
+        let (quotient, remainder) = (&v / scale, &v % scale);
+        let step = match self {
+            Function::Floor if remainder.is_negative() => -1,
+            Function::Ceil if remainder.is_positive() => 1,
+            Function::Round if remainder.abs() * 2 >= *scale => {
+                remainder.signum().to_i32().unwrap_or(0)
+            }
+            _ => 0,
+        };
+        (quotient + step) * scale
+    }
+
+    fn logarithm_operands(self, args: &[Number]) -> (BigInt, BigInt) {
+        match (self, args) {
+            (Function::Log10, [v]) => (BigInt::from(10), v.to_big()),
+            (Function::Log2, [v]) => (BigInt::from(2), v.to_big()),
+            (Function::Log, [base, v]) => (base.to_big(), v.to_big()),
+            _ => unreachable!("Hit {self:?} without its logarithm operands"),
+        }
+    }
+}
+
+///
+/// Whether a call computes with decimals, in decimal mode or, in integer mode, once an
+/// argument is one
+///
+fn computes_decimals(args: &[Number], settings: &Settings) -> bool {
+    settings.decimals.is_some() || args.iter().any(|arg| matches!(arg, Number::Decimal(_)))
+}
+
+///
+/// Largest k such that base^k <= v, for a positive v and a base greater than one
+///
+fn integer_log(base: &BigInt, v: &BigInt) -> u32 {
+    let mut k = 0;
+    let mut power = base.clone();
+    while &power <= v {
+        power *= base;
+        k += 1;
+    }
+    k
+}
+
+///
+/// Sum of the values, 0 when there are none
+///
+fn sum(values: impl IntoIterator<Item = Number>, settings: &Settings) -> Result<Number, Error> {
+    let mut values = values.into_iter();
+    // Starting from the first value keeps its unit, if any
+    let Some(first) = values.next() else {
+        return Number::from_literal(0, settings);
+    };
+    values.try_fold(first, |sum, v| Operator::Add.execute(sum, v, settings))
+}
+
+///
+/// Greatest common divisor by Euclid's algorithm, its sign is the one of the last remainder
+///
+fn gcd(mut a: BigInt, mut b: BigInt) -> BigInt {
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+///
+/// Least common multiple, 0 when any of the numbers is 0
+///
+fn lcm(a: BigInt, b: BigInt) -> BigInt {
+    if a.is_zero() || b.is_zero() {
+        return BigInt::zero();
+    }
+    let divisor = gcd(a.clone(), b.clone());
+    (a / divisor * b).abs()
+}
+
+///
+/// Largest k such that e^k <= v for a positive v, None when k goes beyond the exponents
+/// computed by `exp_floor`
+///
+fn natural_log(v: &BigInt) -> Option<u32> {
+    // Starting from a lower bound, as 0.693 < ln 2 and v >= 2^(bits - 1). Since e^k is not
+    // an integer for k > 0, e^k <= v exactly when its integer part is below v
+    let mut k = u32::try_from((v.bits() - 1) * 693 / 1000).ok()?;
+    while k < MAX_EXPONENT && &exp_floor(k + 1) < v {
+        k += 1;
+    }
+    (k < MAX_EXPONENT).then_some(k)
+}
+
+///
+/// Integer part of e^k, computed in fixed point with enough fraction bits to cover the
+/// rounding errors of the series and the powers
+///
+fn exp_floor(k: u32) -> BigInt {
+    // e^k takes less than 1.5 bits per unit of k, plus guard bits for the rounding errors
+    let fraction = 2 * u64::from(k) + 64;
+    let one = BigInt::one() << fraction;
+
+    // e as the sum of 1/n!
+    let mut e = BigInt::zero();
+    let mut term = one.clone();
+    let mut n = 0u32;
+    while !term.is_zero() {
+        e += &term;
+        n += 1;
+        term /= n;
+    }
+
+    let (mut result, mut base, mut exp) = (one, e, k);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = (result * &base) >> fraction;
+        }
+        base = (&base * &base) >> fraction;
+        exp >>= 1;
+    }
+    result >> fraction
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigInt;
+
+    use super::{exp_floor, natural_log};
+
+    #[test]
+    fn test_exp_floor() {
+        assert_eq!(exp_floor(0), BigInt::from(1));
+        assert_eq!(exp_floor(1), BigInt::from(2));
+        assert_eq!(exp_floor(10), BigInt::from(22026));
+        assert_eq!(exp_floor(40), BigInt::from(235385266837019985u64));
+    }
+
+    #[test]
+    fn test_natural_log() {
+        assert_eq!(natural_log(&BigInt::from(1)), Some(0));
+        assert_eq!(natural_log(&BigInt::from(2)), Some(0));
+        assert_eq!(natural_log(&BigInt::from(3)), Some(1));
+        assert_eq!(natural_log(&BigInt::from(22027)), Some(10));
+        assert_eq!(natural_log(&BigInt::from(22026)), Some(9));
+        assert_eq!(natural_log(&BigInt::from(u128::MAX)), Some(88));
+    }
+}
@@ -0,0 +1,440 @@
+use num_bigint::BigInt;
+use std::fmt;
+
+use super::{
+    Context, Error, Rounding, Settings,
+    ast::Ast,
+    eval::{Operator, is_variable_name},
+    number::Number,
+    parse_ast,
+    polynomial::{GUARD_DIGITS, decimal_result},
+    rpn::Rpn,
+};
+
+/// Steps of a root search before giving up on it
+const MAX_ITERATIONS: usize = 200;
+/// Halvings of an interval of integration before giving up on it
+const MAX_DEPTH: u32 = 40;
+/// Evaluations of an integrand before giving up on the integral
+const MAX_EVALUATIONS: usize = 100_000;
+/// Terms of a sum or a product, beyond which the range is refused
+const MAX_TERMS: u32 = 1_000_000;
+
+///
+/// Built-in functions taking an expression and the variable it is evaluated for, rather
+/// than values
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum HigherOrder {
+    /// `solve(expr, x, guess)`, a root of the expression found from the guess
+    Solve,
+    /// `integrate(expr, x, a, b)`, the definite integral of the expression from a to b
+    Integrate,
+    /// `sum(i, a, b, expr)`, the sum of the expression for the integers i from a to b
+    Sum,
+    /// `prod(i, a, b, expr)`, the product of the expression for the integers i from a to b
+    Prod,
+}
+
+impl HigherOrder {
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "solve" => Some(HigherOrder::Solve),
+            "integrate" => Some(HigherOrder::Integrate),
+            "sum" => Some(HigherOrder::Sum),
+            "prod" => Some(HigherOrder::Prod),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HigherOrder::Solve => "solve",
+            HigherOrder::Integrate => "integrate",
+            HigherOrder::Sum => "sum",
+            HigherOrder::Prod => "prod",
+        }
+    }
+
+    ///
+    /// Number of arguments, the expression and its variable included
+    ///
+    fn arity(self) -> usize {
+        match self {
+            HigherOrder::Solve => 3,
+            HigherOrder::Integrate | HigherOrder::Sum | HigherOrder::Prod => 4,
+        }
+    }
+
+    ///
+    /// Whether the arguments, as written, make a call of the function: as many as it takes,
+    /// with a variable name where its variable goes
+    ///
+    pub(super) fn iterates(self, args: &[String]) -> bool {
+        args.len() == self.arity() && is_variable_name(&args[self.positions().1])
+    }
+
+    ///
+    /// Positions of the expression and of its variable among the arguments
+    ///
+    fn positions(self) -> (usize, usize) {
+        match self {
+            HigherOrder::Solve | HigherOrder::Integrate => (0, 1),
+            HigherOrder::Sum | HigherOrder::Prod => (3, 0),
+        }
+    }
+}
+
+///
+/// Call of a higher-order function, its expressions kept as they are written so that they
+/// are evaluated for each value of the variable
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Clone)]
+pub struct HigherOrderCall {
+    pub(super) function: HigherOrder,
+    /// Expression evaluated with the variable bound to the values the function picks
+    pub(super) body: String,
+    pub(super) variable: String,
+    /// Remaining arguments, evaluated once in the context of the call
+    pub(super) args: Vec<String>,
+}
+
+impl HigherOrderCall {
+    ///
+    /// Call written with the given arguments, checking they make expressions and a
+    /// variable name
+    ///
+    pub(super) fn new(function: HigherOrder, mut args: Vec<String>) -> Result<Self, Error> {
+        if args.len() != function.arity() {
+            return Err(Error::ArgumentCount {
+                function: function.name().to_owned(),
+                count: args.len(),
+            });
+        }
+        let (body_at, variable_at) = function.positions();
+        // The later argument goes first, which leaves the position of the other unchanged
+        let (body, variable) = if body_at > variable_at {
+            let body = args.remove(body_at);
+            (body, args.remove(variable_at))
+        } else {
+            let variable = args.remove(variable_at);
+            (args.remove(body_at), variable)
+        };
+        if !is_variable_name(&variable) {
+            return Err(Error::InvalidAssignment(variable));
+        }
+        for expr in std::iter::once(&body).chain(&args) {
+            Rpn::new(expr)?;
+        }
+        Ok(Self {
+            function,
+            body,
+            variable,
+            args,
+        })
+    }
+
+    ///
+    /// Run the call, its expressions compiled once by the given evaluator
+    ///
+    pub(super) fn call<E: Evaluator>(
+        &self,
+        settings: &Settings,
+        context: &Context,
+    ) -> Result<Number, Error> {
+        let body = E::compile(&self.body)?;
+        let args = self
+            .args
+            .iter()
+            .map(|arg| E::compile(arg)?.run(settings, context))
+            .collect::<Result<Vec<Number>, Error>>()?;
+        let mut scope = context.clone();
+        let mut f = |x: &Number, settings: &Settings| {
+            scope.assign(&self.variable, x.clone());
+            body.run(settings, &scope)
+        };
+        let result = match self.function {
+            // Integers search the root with the decimals of the integer mode
+            HigherOrder::Solve if settings.decimals.is_none() => {
+                let work = Settings {
+                    decimals: Some(settings.scale() + GUARD_DIGITS),
+                    rounding: Rounding::HalfEven,
+                    ..*settings
+                };
+                solve(&mut |x| f(x, &work), &args[0], &work)?
+                    .map(|root| decimal_result(root, settings, &self.to_string()))
+                    .transpose()?
+            }
+            HigherOrder::Solve => solve(&mut |x| f(x, settings), &args[0], settings)?,
+            HigherOrder::Integrate => {
+                let digits = settings.scale();
+                let work = Settings {
+                    decimals: Some(digits + GUARD_DIGITS),
+                    rounding: Rounding::HalfEven,
+                    ..Default::default()
+                };
+                let tolerance = Operator::Pow.execute(
+                    Number::from_literal(10, &work)?,
+                    Number::from_literal(-i128::from(digits), &work)?,
+                    &work,
+                )?;
+                let mut quadrature = Quadrature {
+                    f: |x: &Number| f(x, &work),
+                    settings: work,
+                    evaluations: 0,
+                };
+                quadrature
+                    .integrate(&args[0], &args[1], tolerance)?
+                    .map(|integral| decimal_result(integral, settings, &self.to_string()))
+                    .transpose()?
+            }
+            HigherOrder::Sum | HigherOrder::Prod => {
+                Some(self.accumulate(&mut |x| f(x, settings), &args[0], &args[1], settings)?)
+            }
+        };
+        result.ok_or_else(|| Error::NoConvergence(self.to_string()))
+    }
+
+    ///
+    /// Sum or product of f over the integers from a to b, which is 0 or 1 when the range
+    /// is empty
+    ///
+    fn accumulate(
+        &self,
+        f: &mut impl FnMut(&Number) -> Result<Number, Error>,
+        a: &Number,
+        b: &Number,
+        settings: &Settings,
+    ) -> Result<Number, Error> {
+        let (operator, identity) = match self.function {
+            HigherOrder::Prod => (Operator::Prod, 1),
+            _ => (Operator::Add, 0),
+        };
+        let (Some(first), Some(last)) = (a.to_integer(), b.to_integer()) else {
+            return Err(Error::FractionalOperand(self.to_string()));
+        };
+        let terms = (last - first + BigInt::from(1)).max(BigInt::ZERO);
+        if terms > BigInt::from(MAX_TERMS) {
+            return Err(Error::TooManyTerms(self.to_string()));
+        }
+        let one = Number::from_literal(1, settings)?;
+        let mut result = Number::from_literal(identity, settings)?;
+        let mut index = a.clone();
+        for _ in 0..u32::try_from(terms).expect("Terms were bounded") {
+            result = operator.execute(result, f(&index)?, settings)?;
+            index = Operator::Add.execute(index, one.clone(), settings)?;
+        }
+        Ok(result)
+    }
+}
+
+impl fmt::Display for HigherOrderCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut args: Vec<&str> = self.args.iter().map(String::as_str).collect();
+        let (body_at, variable_at) = self.function.positions();
+        if body_at > variable_at {
+            args.insert(variable_at, &self.variable);
+            args.insert(body_at, &self.body);
+        } else {
+            args.insert(body_at, &self.body);
+            args.insert(variable_at, &self.variable);
+        }
+        write!(f, "{}({})", self.function.name(), args.join(", "))
+    }
+}
+
+///
+/// Way of evaluating the expressions of a higher-order call, the same one the call is
+/// evaluated with
+///
+pub(super) trait Evaluator: Sized {
+    fn compile(expr: &str) -> Result<Self, Error>;
+
+    fn run(&self, settings: &Settings, context: &Context) -> Result<Number, Error>;
+}
+
+impl Evaluator for Rpn {
+    fn compile(expr: &str) -> Result<Self, Error> {
+        Rpn::new(expr)
+    }
+
+    fn run(&self, settings: &Settings, context: &Context) -> Result<Number, Error> {
+        Ok(self.compute(settings, context)?.result)
+    }
+}
+
+impl Evaluator for Ast {
+    fn compile(expr: &str) -> Result<Self, Error> {
+        parse_ast(expr)
+    }
+
+    fn run(&self, settings: &Settings, context: &Context) -> Result<Number, Error> {
+        self.evaluate(settings, context)
+    }
+}
+
+///
+/// Root of f found with the secant method, starting from the guess and the guess plus 1.
+/// With decimals the search stops once two steps agree to the precision, with integers
+/// only an exact root is accepted. None when the search does not settle
+///
+fn solve(
+    f: &mut impl FnMut(&Number) -> Result<Number, Error>,
+    guess: &Number,
+    settings: &Settings,
+) -> Result<Option<Number>, Error> {
+    let mut x0 = guess.clone();
+    let mut f0 = f(&x0)?;
+    if f0.is_zero() {
+        return Ok(Some(x0));
+    }
+    let mut x1 = Operator::Add.execute(x0.clone(), Number::from_literal(1, settings)?, settings)?;
+    for _ in 0..MAX_ITERATIONS {
+        let f1 = f(&x1)?;
+        if f1.is_zero() || (settings.decimals.is_some() && x1 == x0) {
+            return Ok(Some(x1));
+        }
+        if x1 == x0 {
+            return Ok(None);
+        }
+        // Dividing the differences first keeps the digits of the small values near the root
+        let slope = Operator::Div.execute(
+            Operator::Sub.execute(f1.clone(), f0, settings)?,
+            Operator::Sub.execute(x1.clone(), x0, settings)?,
+            settings,
+        )?;
+        if slope.is_zero() {
+            return Ok(None);
+        }
+        let step = Operator::Div.execute(f1.clone(), slope, settings)?;
+        let x2 = Operator::Sub.execute(x1.clone(), step, settings)?;
+        (x0, f0, x1) = (x1, f1, x2);
+    }
+    Ok(None)
+}
+
+///
+/// Adaptive Simpson's rule, splitting intervals in halves until the estimate on the halves
+/// agrees with the one on the whole interval
+///
+struct Quadrature<F> {
+    f: F,
+    settings: Settings,
+    evaluations: usize,
+}
+
+impl<F: FnMut(&Number) -> Result<Number, Error>> Quadrature<F> {
+    ///
+    /// Integral from a to b within the tolerance, None when the integrand needs more
+    /// halvings or evaluations than allowed
+    ///
+    fn integrate(
+        &mut self,
+        a: &Number,
+        b: &Number,
+        tolerance: Number,
+    ) -> Result<Option<Number>, Error> {
+        let (a, b) = (self.real(a)?, self.real(b)?);
+        let m = self.middle(&a, &b)?;
+        let (fa, fm, fb) = (self.eval(&a)?, self.eval(&m)?, self.eval(&b)?);
+        let whole = self.simpson(&a, &b, [&fa, &fm, &fb])?;
+        self.adaptive([&a, &m, &b], [&fa, &fm, &fb], whole, tolerance, MAX_DEPTH)
+    }
+
+    fn adaptive(
+        &mut self,
+        [a, m, b]: [&Number; 3],
+        [fa, fm, fb]: [&Number; 3],
+        whole: Number,
+        tolerance: Number,
+        depth: u32,
+    ) -> Result<Option<Number>, Error> {
+        let (left_middle, right_middle) = (self.middle(a, m)?, self.middle(m, b)?);
+        let (f_left, f_right) = (self.eval(&left_middle)?, self.eval(&right_middle)?);
+        let left = self.simpson(a, m, [fa, &f_left, fm])?;
+        let right = self.simpson(m, b, [fm, &f_right, fb])?;
+        let halves = self.op(Operator::Add, &left, &right)?;
+        let delta = self.op(Operator::Sub, &halves, &whole)?;
+        let error = Operator::Abs.execute_unary(delta.clone(), &self.settings)?;
+        let fifteen = Number::from_literal(15, &self.settings)?;
+        if error <= self.op(Operator::Prod, &fifteen, &tolerance)? {
+            // Richardson extrapolation of the two estimates
+            let correction = self.op(Operator::Div, &delta, &fifteen)?;
+            return Ok(Some(self.op(Operator::Add, &halves, &correction)?));
+        }
+        if depth == 0 || self.evaluations > MAX_EVALUATIONS {
+            return Ok(None);
+        }
+        let half_tolerance = self.op(
+            Operator::Div,
+            &tolerance,
+            &Number::from_literal(2, &self.settings)?,
+        )?;
+        let Some(left) = self.adaptive(
+            [a, &left_middle, m],
+            [fa, &f_left, fm],
+            left,
+            half_tolerance.clone(),
+            depth - 1,
+        )?
+        else {
+            return Ok(None);
+        };
+        let Some(right) = self.adaptive(
+            [m, &right_middle, b],
+            [fm, &f_right, fb],
+            right,
+            half_tolerance,
+            depth - 1,
+        )?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(self.op(Operator::Add, &left, &right)?))
+    }
+
+    ///
+    /// Simpson's estimate (b - a) / 6 * (f(a) + 4 f(m) + f(b)) from the values at the ends
+    /// and the middle of the interval
+    ///
+    fn simpson(&self, a: &Number, b: &Number, [fa, fm, fb]: [&Number; 3]) -> Result<Number, Error> {
+        let four = Number::from_literal(4, &self.settings)?;
+        let sum = self.op(
+            Operator::Add,
+            &self.op(Operator::Add, fa, &self.op(Operator::Prod, &four, fm)?)?,
+            fb,
+        )?;
+        let width = self.op(Operator::Sub, b, a)?;
+        let sixth = self.op(
+            Operator::Div,
+            &width,
+            &Number::from_literal(6, &self.settings)?,
+        )?;
+        self.op(Operator::Prod, &sixth, &sum)
+    }
+
+    fn middle(&self, a: &Number, b: &Number) -> Result<Number, Error> {
+        let sum = self.op(Operator::Add, a, b)?;
+        self.op(
+            Operator::Div,
+            &sum,
+            &Number::from_literal(2, &self.settings)?,
+        )
+    }
+
+    fn real(&self, n: &Number) -> Result<Number, Error> {
+        self.op(Operator::Add, n, &Number::from_literal(0, &self.settings)?)
+    }
+
+    fn eval(&mut self, x: &Number) -> Result<Number, Error> {
+        self.evaluations += 1;
+        let y = (self.f)(x)?;
+        self.real(&y)
+    }
+
+    fn op(&self, operator: Operator, a: &Number, b: &Number) -> Result<Number, Error> {
+        operator.execute(a.clone(), b.clone(), &self.settings)
+    }
+}
@@ -0,0 +1,280 @@
+use super::{
+    Context, Error, Settings, Warning, decimal,
+    eval::{Operator, strip_comment},
+    number::Number,
+    physics,
+    rpn::Rpn,
+};
+
+///
+/// Values of the unknowns of a system of linear equations
+///
+#[derive(Debug, PartialEq)]
+pub struct Solution {
+    /// Unknowns with their values, in the order they are first written
+    pub values: Vec<(String, Number)>,
+    pub warnings: Vec<Warning>,
+}
+
+///
+/// Solve a system of linear equations separated by `;`, as in `2x + 3y = 7; x - y = 1`,
+/// by Gaussian elimination. Every variable of the equations is an unknown, the built-in
+/// and physical constants, `i` and `ans` aside, and there have to be as many equations as unknowns.
+/// With integers, solutions are divided last and rounded toward zero like divisions
+///
+pub fn solve_linear(
+    system: &str,
+    settings: &Settings,
+    context: &Context,
+) -> Result<Solution, Error> {
+    let mut equations = vec![];
+    let mut unknowns: Vec<String> = vec![];
+    for equation in strip_comment(system).split(';').map(str::trim) {
+        if equation.is_empty() {
+            continue;
+        }
+        let program = difference(equation)?;
+        for name in program.ast()?.variables() {
+            if decimal::constant(&name, settings, 0).is_none()
+                && physics::named(&name).is_none()
+                && !["i", "ans"].contains(&name.as_str())
+            {
+                unknowns.extend((!unknowns.contains(&name)).then_some(name));
+            }
+        }
+        equations.push((equation, program));
+    }
+    if equations.is_empty() {
+        return Err(Error::EmptyExpression);
+    }
+    if equations.len() != unknowns.len() {
+        return Err(Error::NoSingleSolution(system.trim().to_owned()));
+    }
+    let mut matrix = vec![];
+    for (equation, program) in &equations {
+        if program
+            .ast()?
+            .degree(&unknowns)
+            .is_none_or(|degree| degree > 1)
+        {
+            return Err(Error::InvalidEquation((*equation).to_owned()));
+        }
+        matrix.push(coefficients(program, &unknowns, settings, context)?);
+    }
+    let mut warnings = vec![];
+    let values = eliminate(matrix, settings)?
+        .ok_or_else(|| Error::NoSingleSolution(system.trim().to_owned()))?
+        .into_iter()
+        .map(|(numerator, denominator)| {
+            if Operator::Div.truncates(&numerator, &denominator) {
+                if settings.exact {
+                    return Err(Error::InexactDivision(numerator, denominator));
+                }
+                warnings.push(Warning::TruncatedDivision(
+                    numerator.clone(),
+                    denominator.clone(),
+                ));
+            }
+            Operator::Div.execute(numerator, denominator, settings)
+        })
+        .collect::<Result<Vec<Number>, Error>>()?;
+    Ok(Solution {
+        values: unknowns.into_iter().zip(values).collect(),
+        warnings,
+    })
+}
+
+///
+/// Expression of the difference between the two sides of an equation, which is 0 for its
+/// solutions
+///
+fn difference(equation: &str) -> Result<Rpn, Error> {
+    let invalid = || Error::InvalidEquation(equation.to_owned());
+    let (lhs, rhs) = equation.split_once('=').ok_or_else(invalid)?;
+    if lhs.trim().is_empty() || rhs.trim().is_empty() || rhs.contains('=') {
+        return Err(invalid());
+    }
+    Rpn::new(&format!("({lhs}) - ({rhs})"))
+}
+
+///
+/// Row of the augmented matrix of a linear equation: the coefficient of each unknown,
+/// found setting it to 1 and the others to 0, then the constant moved to the right side
+///
+fn coefficients(
+    difference: &Rpn,
+    unknowns: &[String],
+    settings: &Settings,
+    context: &Context,
+) -> Result<Vec<Number>, Error> {
+    let zero = Number::from_literal(0, settings)?;
+    let one = Number::from_literal(1, settings)?;
+    let mut scope = context.clone();
+    let mut at = |unit: Option<usize>| {
+        for (index, unknown) in unknowns.iter().enumerate() {
+            let value = if unit == Some(index) { &one } else { &zero };
+            scope.assign(unknown, value.clone());
+        }
+        Ok::<Number, Error>(difference.compute(settings, &scope)?.result)
+    };
+    let constant = at(None)?;
+    let mut row = vec![];
+    for index in 0..unknowns.len() {
+        row.push(Operator::Sub.execute(at(Some(index))?, constant.clone(), settings)?);
+    }
+    row.push(Operator::Neg.execute_unary(constant, settings)?);
+    Ok(row)
+}
+
+///
+/// Fraction-free Gauss-Jordan elimination of an augmented matrix, which leaves each
+/// unknown as a numerator over the same denominator, the determinant, so that integers
+/// are divided only once. None when the determinant is 0
+///
+fn eliminate(
+    mut matrix: Vec<Vec<Number>>,
+    settings: &Settings,
+) -> Result<Option<Vec<(Number, Number)>>, Error> {
+    let size = matrix.len();
+    let mut previous = Number::from_literal(1, settings)?;
+    for k in 0..size {
+        let Some(pivot) = (k..size).find(|&row| !matrix[row][k].is_zero()) else {
+            return Ok(None);
+        };
+        matrix.swap(k, pivot);
+        for row in (0..size).filter(|&row| row != k) {
+            for column in (0..=size).filter(|&column| column != k) {
+                let kept = Operator::Prod.execute(
+                    matrix[k][k].clone(),
+                    matrix[row][column].clone(),
+                    settings,
+                )?;
+                let removed = Operator::Prod.execute(
+                    matrix[row][k].clone(),
+                    matrix[k][column].clone(),
+                    settings,
+                )?;
+                // The division is exact for integers, the entries being minors of the matrix
+                matrix[row][column] = Operator::Div.execute(
+                    Operator::Sub.execute(kept, removed, settings)?,
+                    previous.clone(),
+                    settings,
+                )?;
+            }
+            matrix[row][k] = Number::from_literal(0, settings)?;
+        }
+        previous = matrix[k][k].clone();
+    }
+    Ok(Some(
+        matrix
+            .into_iter()
+            .enumerate()
+            .map(|(k, mut row)| {
+                (
+                    row.pop().expect("Rows end with a constant"),
+                    row.swap_remove(k),
+                )
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Context, Error, Number, Settings, Solution, Warning, solve_linear};
+
+    fn values(solution: Solution) -> Vec<(String, String)> {
+        solution
+            .values
+            .into_iter()
+            .map(|(name, value)| (name, value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_solve_linear() {
+        let settings = Settings::default();
+        let context = Context::default();
+        for (system, solution) in [
+            ("2*x + 3*y = 7; x - y = 1", vec![("x", "2"), ("y", "1")]),
+            // Numbers written against a variable are its coefficient
+            ("2x + 3y = 7; x - y = 1", vec![("x", "2"), ("y", "1")]),
+            ("0.5a + 2b = 17; 4a = b", vec![("a", "2"), ("b", "8")]),
+            ("3 * a = 12", vec![("a", "4")]),
+            (
+                "x + y + z = 6; 2*y + 5*z = -4; 2*x + 5*y - z = 27",
+                vec![("x", "5"), ("y", "3"), ("z", "-2")],
+            ),
+            // The first pivot is 0, the rows are swapped
+            ("x - x + y = 2; x + y = 5", vec![("x", "3"), ("y", "2")]),
+            (
+                "(x + 1) * 2 = y + 5; x - y = -tau / 2 # constant",
+                vec![
+                    ("x", "6.14159265358979323846"),
+                    ("y", "9.28318530717958647692"),
+                ],
+            ),
+        ] {
+            let solution: Vec<(String, String)> = solution
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                .collect();
+            assert_eq!(
+                values(solve_linear(system, &settings, &context).unwrap()),
+                solution,
+                "{system}"
+            );
+        }
+
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            values(solve_linear("x + y = 1; x - y = 0", &decimals, &context).unwrap()),
+            [
+                ("x".to_owned(), "0.5".to_owned()),
+                ("y".to_owned(), "0.5".to_owned())
+            ]
+        );
+        let truncated = solve_linear("x + y = 1; x - y = 0", &settings, &context).unwrap();
+        assert_eq!(
+            truncated.warnings,
+            [
+                Warning::TruncatedDivision(Number::Int(-1), Number::Int(-2)),
+                Warning::TruncatedDivision(Number::Int(-1), Number::Int(-2))
+            ]
+        );
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            solve_linear("x + y = 1; x - y = 0", &exact, &context).unwrap_err(),
+            Error::InexactDivision(Number::Int(-1), Number::Int(-2))
+        );
+
+        for (system, error) in [
+            ("", Error::EmptyExpression),
+            ("x + y = 1", Error::NoSingleSolution("x + y = 1".to_owned())),
+            (
+                "x + y = 1; 2*x + 2*y = 3",
+                Error::NoSingleSolution("x + y = 1; 2*x + 2*y = 3".to_owned()),
+            ),
+            (
+                "x * y = 1; x = 2",
+                Error::InvalidEquation("x * y = 1".to_owned()),
+            ),
+            ("x^2 = 4", Error::InvalidEquation("x^2 = 4".to_owned())),
+            ("2 / x = 1", Error::InvalidEquation("2 / x = 1".to_owned())),
+            ("x + 1", Error::InvalidEquation("x + 1".to_owned())),
+            ("x = 1 = 1", Error::InvalidEquation("x = 1 = 1".to_owned())),
+        ] {
+            assert_eq!(
+                solve_linear(system, &settings, &context).unwrap_err(),
+                error,
+                "{system}"
+            );
+        }
+    }
+}
@@ -0,0 +1,452 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_traits::Signed;
+
+use super::{Error, Settings, eval::Operator, function::Function, number::Number};
+
+/// Entries of a matrix row by row
+type Rows<T = Number> = Vec<Vec<T>>;
+
+///
+/// Vector or matrix of numbers, the entries being integers, decimals or complex numbers
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    /// Entries row by row, a vector being a single row
+    pub(super) rows: Rows,
+    /// Whether the entries make a vector, written `[1, 2]` rather than `[[1, 2]]`
+    pub(super) vector: bool,
+}
+
+impl Matrix {
+    pub(super) fn vector(entries: Vec<Number>) -> Self {
+        Self {
+            rows: vec![entries],
+            vector: true,
+        }
+    }
+
+    fn from_rows(rows: Rows) -> Self {
+        Self {
+            rows,
+            vector: false,
+        }
+    }
+
+    ///
+    /// Number of rows and columns, a vector being a single row
+    ///
+    fn shape(&self) -> (usize, usize) {
+        (self.rows.len(), self.rows[0].len())
+    }
+
+    pub(super) fn entries(&self) -> impl Iterator<Item = &Number> {
+        self.rows.iter().flatten()
+    }
+
+    ///
+    /// Matrix of the same shape with the function applied to each entry
+    ///
+    fn map(&self, mut f: impl FnMut(&Number) -> Result<Number, Error>) -> Result<Self, Error> {
+        Ok(Self {
+            rows: self
+                .rows
+                .iter()
+                .map(|row| row.iter().map(&mut f).collect())
+                .collect::<Result<_, _>>()?,
+            vector: self.vector,
+        })
+    }
+
+    ///
+    /// Write the entries with the given function, as in `[1, 2]` or `[[1, 2], [3, 4]]`
+    ///
+    pub(super) fn display_with(&self, entry: impl Fn(&Number) -> String) -> String {
+        let rows: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let entries: Vec<String> = row.iter().map(&entry).collect();
+                format!("[{}]", entries.join(", "))
+            })
+            .collect();
+        if self.vector {
+            rows.concat()
+        } else {
+            format!("[{}]", rows.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with(Number::to_string))
+    }
+}
+
+///
+/// Vector of the arguments of `[a, b]`, or matrix when the arguments are vectors of the
+/// same length, each one becoming a row
+///
+fn from_entries(args: Vec<Number>) -> Result<Number, Error> {
+    let mismatch = || Error::DimensionMismatch(Function::Vector.display(&args));
+    if !args.iter().any(|arg| matches!(arg, Number::Matrix(_))) {
+        return Ok(Number::Matrix(Box::new(Matrix::vector(args))));
+    }
+    let mut rows = vec![];
+    for arg in &args {
+        match arg {
+            Number::Matrix(m) if m.vector => rows.push(m.rows[0].clone()),
+            _ => return Err(mismatch()),
+        }
+    }
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Err(mismatch());
+    }
+    Ok(Number::Matrix(Box::new(Matrix::from_rows(rows))))
+}
+
+///
+/// Apply a binary operator to operands of which at least one is a vector or a matrix.
+/// Operations are computed entry by entry, a number going with every entry of the other
+/// operand, except for products involving a matrix, which are matrix products, and powers
+/// of matrices, which are repeated products
+///
+pub(super) fn execute(
+    operator: Operator,
+    v1: Number,
+    v2: Number,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    let mismatch = || Error::DimensionMismatch(format!("{v1} {} {v2}", String::from(&operator)));
+    let result = match (&v1, &v2) {
+        (Number::Matrix(a), Number::Matrix(b))
+            if operator == Operator::Prod && !(a.vector && b.vector) =>
+        {
+            product(a, b, settings)?.ok_or_else(mismatch)?
+        }
+        // Matrices are only raised to integer powers
+        (Number::Matrix(a), Number::Matrix(_)) | (_, Number::Matrix(a))
+            if operator == Operator::Pow && !a.vector =>
+        {
+            return Err(Error::MatrixOperand(format!("{v1} ^ {v2}")));
+        }
+        (Number::Matrix(a), n) if operator == Operator::Pow && !a.vector => {
+            if !is_square(a) {
+                return Err(mismatch());
+            }
+            power(a, n, settings)?
+        }
+        (Number::Matrix(a), Number::Matrix(b)) => {
+            if a.vector != b.vector || a.shape() != b.shape() {
+                return Err(mismatch());
+            }
+            let mut entries = b.entries();
+            a.map(|x| {
+                let y = entries.next().expect("Shapes are the same");
+                operator.execute(x.clone(), y.clone(), settings)
+            })?
+        }
+        (Number::Matrix(a), n) => a.map(|x| operator.execute(x.clone(), n.clone(), settings))?,
+        (n, Number::Matrix(b)) => b.map(|y| operator.execute(n.clone(), y.clone(), settings))?,
+        _ => unreachable!("Hit numbers in matrix operation execution"),
+    };
+    Ok(Number::Matrix(Box::new(result)))
+}
+
+pub(super) fn execute_unary(
+    operator: Operator,
+    m: &Matrix,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    Ok(Number::Matrix(Box::new(
+        m.map(|x| operator.execute_unary(x.clone(), settings))?,
+    )))
+}
+
+///
+/// Whether an operation computed entry by entry divides any pair of entries with a
+/// remainder
+///
+pub(super) fn truncates(operator: Operator, v1: &Number, v2: &Number) -> bool {
+    match (v1, v2) {
+        (Number::Matrix(a), Number::Matrix(b)) => {
+            a.shape() == b.shape()
+                && a.entries()
+                    .zip(b.entries())
+                    .any(|(x, y)| operator.truncates(x, y))
+        }
+        (Number::Matrix(a), n) => a.entries().any(|x| operator.truncates(x, n)),
+        (n, Number::Matrix(b)) => b.entries().any(|y| operator.truncates(n, y)),
+        _ => false,
+    }
+}
+
+///
+/// Product of two matrices, a vector on the left being a row and one on the right a column,
+/// which gives a vector again. None when the columns on the left are not as many as the
+/// rows on the right
+///
+fn product(a: &Matrix, b: &Matrix, settings: &Settings) -> Result<Option<Matrix>, Error> {
+    let columns_of_b = match b.vector {
+        true => transposed(b),
+        false => b.clone(),
+    };
+    let ((rows, inner), (inner_b, columns)) = (a.shape(), columns_of_b.shape());
+    if inner != inner_b {
+        return Ok(None);
+    }
+    let mut result = vec![];
+    for i in 0..rows {
+        let mut row = vec![];
+        for j in 0..columns {
+            let mut sum = Number::from_literal(0, settings)?;
+            for k in 0..inner {
+                let term = Operator::Prod.execute(
+                    a.rows[i][k].clone(),
+                    columns_of_b.rows[k][j].clone(),
+                    settings,
+                )?;
+                sum = Operator::Add.execute(sum, term, settings)?;
+            }
+            row.push(sum);
+        }
+        result.push(row);
+    }
+    Ok(Some(if a.vector {
+        Matrix::vector(result.swap_remove(0))
+    } else if b.vector {
+        Matrix::vector(result.into_iter().flatten().collect())
+    } else {
+        Matrix::from_rows(result)
+    }))
+}
+
+///
+/// Square matrix to an integer power. Negative exponents raise the inverse, which only
+/// exists with decimals as for numbers
+///
+fn power(m: &Matrix, exponent: &Number, settings: &Settings) -> Result<Matrix, Error> {
+    let Some(k) = exponent.to_integer() else {
+        return Err(Error::FractionalOperand(format!("{m} ^ {exponent}")));
+    };
+    let mut base = match k.is_negative() {
+        true if settings.decimals.is_none() => {
+            return Err(Error::NegativeExponent(exponent.clone()));
+        }
+        true => inverse(m, settings)?,
+        false => m.clone(),
+    };
+    let mut k = k.abs();
+    let mut result = identity(m.rows.len(), settings)?;
+    let two = BigInt::from(2);
+    while k.is_positive() {
+        if (&k % &two).is_positive() {
+            result = product(&result, &base, settings)?.expect("Matrices are square");
+        }
+        base = product(&base, &base, settings)?.expect("Matrices are square");
+        k /= &two;
+    }
+    Ok(result)
+}
+
+fn identity(size: usize, settings: &Settings) -> Result<Matrix, Error> {
+    let (zero, one) = (
+        Number::from_literal(0, settings)?,
+        Number::from_literal(1, settings)?,
+    );
+    Ok(Matrix::from_rows(
+        (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| if i == j { one.clone() } else { zero.clone() })
+                    .collect()
+            })
+            .collect(),
+    ))
+}
+
+///
+/// Rows turned into columns, a vector becoming a column
+///
+fn transposed(m: &Matrix) -> Matrix {
+    let (rows, columns) = m.shape();
+    Matrix::from_rows(
+        (0..columns)
+            .map(|j| (0..rows).map(|i| m.rows[i][j].clone()).collect())
+            .collect(),
+    )
+}
+
+///
+/// Call a function building or taking vectors and matrices
+///
+pub(super) fn call(
+    function: Function,
+    args: Vec<Number>,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    let mismatch = || Error::DimensionMismatch(function.display(&args));
+    let result = match (function, args.as_slice()) {
+        (Function::Vector, _) => return from_entries(args),
+        (Function::Dot, [Number::Matrix(a), Number::Matrix(b)]) if a.vector && b.vector => {
+            if a.shape() != b.shape() {
+                return Err(mismatch());
+            }
+            let mut sum = Number::from_literal(0, settings)?;
+            for (x, y) in a.entries().zip(b.entries()) {
+                let term = Operator::Prod.execute(x.clone(), y.clone(), settings)?;
+                sum = Operator::Add.execute(sum, term, settings)?;
+            }
+            return Ok(sum);
+        }
+        (Function::Transpose, [Number::Matrix(m)]) => transposed(m),
+        (Function::Det, [Number::Matrix(m)]) if is_square(m) => {
+            return determinant(m, settings);
+        }
+        (Function::Inv, [Number::Matrix(m)]) if is_square(m) => inverse(m, settings)?,
+        _ => return Err(mismatch()),
+    };
+    Ok(Number::Matrix(Box::new(result)))
+}
+
+///
+/// Whether the inverse of the argument of `inv` has entries with a fraction
+///
+pub(super) fn inverse_truncates(args: &[Number], settings: &Settings) -> bool {
+    let [Number::Matrix(m)] = args else {
+        return false;
+    };
+    is_square(m)
+        && inverse_parts(m, settings)
+            .ok()
+            .flatten()
+            .is_some_and(|parts| {
+                parts
+                    .iter()
+                    .flatten()
+                    .any(|(numerator, denominator)| Operator::Div.truncates(numerator, denominator))
+            })
+}
+
+fn is_square(m: &Matrix) -> bool {
+    let (rows, columns) = m.shape();
+    !m.vector && rows == columns
+}
+
+fn determinant(m: &Matrix, settings: &Settings) -> Result<Number, Error> {
+    let Some((rows, swaps)) = eliminate(m.rows.clone(), settings)? else {
+        return Number::from_literal(0, settings);
+    };
+    let last = rows.len() - 1;
+    let determinant = rows[last][last].clone();
+    if swaps % 2 == 1 {
+        return Operator::Neg.execute_unary(determinant, settings);
+    }
+    Ok(determinant)
+}
+
+fn inverse(m: &Matrix, settings: &Settings) -> Result<Matrix, Error> {
+    let parts = inverse_parts(m, settings)?.ok_or_else(|| {
+        Error::MathDomain(Function::Inv.display(&[Number::Matrix(Box::new(m.clone()))]))
+    })?;
+    // An inverse of integers with fractions gets the decimals of the integer mode
+    let decimals = Settings {
+        decimals: Some(settings.scale()),
+        ..*settings
+    };
+    let settings = match parts
+        .iter()
+        .flatten()
+        .any(|(numerator, denominator)| Operator::Div.truncates(numerator, denominator))
+    {
+        true => &decimals,
+        false => settings,
+    };
+    Ok(Matrix::from_rows(
+        parts
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(numerator, denominator)| {
+                        Operator::Div.execute(numerator, denominator, settings)
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+///
+/// Entries of the inverse as numerators over the determinant, found eliminating the
+/// matrix followed by the identity. None when the matrix is singular
+///
+fn inverse_parts(m: &Matrix, settings: &Settings) -> Result<Option<Rows<(Number, Number)>>, Error> {
+    let size = m.rows.len();
+    let identity = identity(size, settings)?;
+    let augmented = m
+        .rows
+        .iter()
+        .zip(identity.rows)
+        .map(|(row, unit)| row.iter().cloned().chain(unit).collect())
+        .collect();
+    let Some((rows, _)) = eliminate(augmented, settings)? else {
+        return Ok(None);
+    };
+    Ok(Some(
+        rows.into_iter()
+            .enumerate()
+            .map(|(k, mut row)| {
+                let inverse = row.split_off(size);
+                inverse
+                    .into_iter()
+                    .map(|numerator| (numerator, row[k].clone()))
+                    .collect()
+            })
+            .collect(),
+    ))
+}
+
+///
+/// Fraction-free Gauss-Jordan elimination of the square matrix the rows start with, which
+/// leaves the determinant on the diagonal so that integers are divided exactly, along with
+/// the number of rows swapped. None when the determinant is 0
+///
+fn eliminate(mut rows: Rows, settings: &Settings) -> Result<Option<(Rows, usize)>, Error> {
+    let (size, columns) = (rows.len(), rows[0].len());
+    let mut previous = Number::from_literal(1, settings)?;
+    let mut swaps = 0;
+    for k in 0..size {
+        let Some(pivot) = (k..size).find(|&row| !rows[row][k].is_zero()) else {
+            return Ok(None);
+        };
+        if pivot != k {
+            rows.swap(k, pivot);
+            swaps += 1;
+        }
+        for row in (0..size).filter(|&row| row != k) {
+            for column in (0..columns).filter(|&column| column != k) {
+                let kept = Operator::Prod.execute(
+                    rows[k][k].clone(),
+                    rows[row][column].clone(),
+                    settings,
+                )?;
+                let removed = Operator::Prod.execute(
+                    rows[row][k].clone(),
+                    rows[k][column].clone(),
+                    settings,
+                )?;
+                // The division is exact for integers, the entries being minors of the matrix
+                rows[row][column] = Operator::Div.execute(
+                    Operator::Sub.execute(kept, removed, settings)?,
+                    previous.clone(),
+                    settings,
+                )?;
+            }
+            rows[row][k] = Number::from_literal(0, settings)?;
+        }
+        previous = rows[k][k].clone();
+    }
+    Ok(Some((rows, swaps)))
+}
@@ -1,46 +1,1210 @@
-use std::num::ParseIntError;
+use std::{
+    cell::RefCell, collections::HashMap, fmt, num::ParseIntError, ops::RangeInclusive, rc::Rc,
+    time::Duration,
+};
 
-use shunting_yard::ShuntingYard;
+pub use ast::Ast;
+use complex::Complex;
+pub use date::Date;
+use eval::is_variable_name;
+pub use eval::{
+    DecimalLiteral, Operator, Span, Spanned, Token, normalize_separators, parse_expr,
+    strip_comment, to_fraction, to_pattern, to_radix, tokenize,
+};
+pub use function::{Function, function_names};
+pub use higher_order::{HigherOrder, HigherOrderCall};
+pub use linear::{Solution, solve_linear};
+pub use matrix::Matrix;
+use num_bigint::BigInt;
+pub use number::Number;
+pub use physics::{PhysicalConstant, physical_constants};
+pub use plot::{Plot, plot};
+pub use polynomial::{Root, Roots, roots};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rpn::Rpn;
+pub use table::{Table, table};
 use thiserror::Error;
+pub use unit::{Quantity, Unit};
 
+mod ast;
+mod complex;
+mod date;
+mod decimal;
 mod eval;
-mod shunting_yard;
+mod function;
+mod higher_order;
+mod linear;
+mod matrix;
+mod number;
+mod physics;
+mod plot;
+mod polynomial;
+mod pratt;
+mod rpn;
+mod table;
+mod unit;
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Error, Debug)]
-pub(crate) enum Error {
-    #[error("Expression has invalid syntax")]
-    InvalidSyntax,
-    #[error("Invalid expression {0}")]
-    InvalidExpression(String),
+pub enum Error {
+    #[error("Expression is empty")]
+    EmptyExpression,
+    #[error("Unexpected '{found}' at position {position}")]
+    InvalidSyntax { found: String, position: usize },
+    #[error("Expression ends with '{operator}' at position {position}")]
+    TrailingOperator { operator: char, position: usize },
+    #[error(
+        "Bracket '{closer}' at position {position} does not match '{opener}' at position {opened}"
+    )]
+    MismatchedBracket {
+        opener: char,
+        opened: usize,
+        closer: char,
+        position: usize,
+    },
+    #[error("Bracket '{bracket}' at position {position} is not matched")]
+    UnmatchedParen { bracket: char, position: usize },
+    #[error("Invalid expression at position {position}: {message}")]
+    InvalidExpression { message: String, position: usize },
     #[error("Caller should have passed a digit")]
     NumberParse(ParseIntError),
+    #[error("Digit separator _ is not between two digits in literal {0}")]
+    MisplacedSeparator(String),
+    #[error("Literal {0} is not an integer")]
+    FractionalLiteral(String),
     #[error("Invalid RPN {0} for expression")]
     InvalidRpn(String),
+    #[error("Division by zero computing {0}")]
+    DivisionByZero(String),
+    #[error("Division {0} / {1} is not exact")]
+    InexactDivision(Number, Number),
+    #[error("Integer overflow computing {0}")]
+    Overflow(String),
+    #[error("Negative exponent {0} is not supported for integers")]
+    NegativeExponent(Number),
+    #[error("Negative shift {0} is not supported")]
+    NegativeShift(Number),
+    #[error("Root {0}{1} is not exact")]
+    InexactRoot(String, Number),
+    #[error("Square root of negative number {0} is not supported for integers")]
+    NegativeRoot(Number),
+    #[error("There is no previous result to refer to")]
+    NoPreviousResult,
+    #[error("Function {0} is not exact")]
+    InexactCall(String),
+    #[error("Constant {0} is not an integer")]
+    InexactConstant(String),
+    #[error("{0} is not defined")]
+    MathDomain(String),
+    #[error("Operation {0} is only defined for integers")]
+    FractionalOperand(String),
+    #[error("Operation {0} is not supported for complex numbers")]
+    ComplexOperand(String),
+    #[error("Function {function} does not take {count} arguments")]
+    ArgumentCount { function: String, count: usize },
+    #[error("Variable {0} is not defined")]
+    UnknownVariable(String),
+    #[error("Cannot assign to {0}, which is not a variable name")]
+    InvalidAssignment(String),
+    #[error("Function {name} taking {count} arguments is not defined")]
+    UnknownFunction { name: String, count: usize },
+    #[error("Function {0} cannot call itself")]
+    RecursiveFunction(String),
+    #[error("There is no result ans({0}) in the answers stack")]
+    NoAnswer(usize),
+    #[error("Search {0} did not converge")]
+    NoConvergence(String),
+    #[error("{0} is not a linear equation")]
+    InvalidEquation(String),
+    #[error("System {0} has no single solution")]
+    NoSingleSolution(String),
+    #[error("{expr} is not a polynomial in {variable}")]
+    InvalidPolynomial { expr: String, variable: String },
+    #[error("Range of {0} has too many terms")]
+    TooManyTerms(String),
+    #[error("Dimensions do not match computing {0}")]
+    DimensionMismatch(String),
+    #[error("Operation {0} is not supported for vectors and matrices")]
+    MatrixOperand(String),
+    #[error("Units do not match computing {0}")]
+    IncompatibleUnits(String),
+    #[error("Operation {0} is not supported for quantities with units")]
+    UnitOperand(String),
+    #[error("Conversion {0} is not exact")]
+    InexactConversion(String),
+    #[error("Date {0} does not exist")]
+    InvalidDate(String),
+    #[error("Operation {0} is not supported for dates")]
+    DateOperand(String),
+    #[error("Duration {0} is not a whole number of days")]
+    FractionalDays(String),
 }
 
-pub(super) fn estimate_expression(expr: &str) -> Result<i64, Error> {
-    let mut shunting_yard = ShuntingYard::new(expr)?;
-    shunting_yard.to_rpn().compute()
+impl Error {
+    ///
+    /// Short name of the kind of error, independent of the values involved
+    ///
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::EmptyExpression => "empty expression",
+            Error::InvalidSyntax { .. } => "invalid syntax",
+            Error::TrailingOperator { .. } => "trailing operator",
+            Error::MismatchedBracket { .. } => "mismatched bracket",
+            Error::UnmatchedParen { .. } => "unmatched bracket",
+            Error::InvalidExpression { .. } => "invalid expression",
+            Error::NumberParse(_) => "number parse",
+            Error::FractionalLiteral(_) => "fractional literal",
+            Error::MisplacedSeparator(_) => "misplaced separator",
+            Error::InvalidRpn(_) => "invalid rpn",
+            Error::DivisionByZero(_) => "division by zero",
+            Error::InexactDivision(_, _) => "inexact division",
+            Error::Overflow(_) => "overflow",
+            Error::NegativeExponent(_) => "negative exponent",
+            Error::NegativeShift(_) => "negative shift",
+            Error::InexactRoot(_, _) => "inexact root",
+            Error::NegativeRoot(_) => "negative root",
+            Error::NoPreviousResult => "no previous result",
+            Error::NoAnswer(_) => "no answer",
+            Error::UnknownFunction { .. } => "unknown function",
+            Error::RecursiveFunction(_) => "recursive function",
+            Error::UnknownVariable(_) => "unknown variable",
+            Error::InvalidAssignment(_) => "invalid assignment",
+            Error::ArgumentCount { .. } => "argument count",
+            Error::InexactCall(_) => "inexact call",
+            Error::InexactConstant(_) => "inexact constant",
+            Error::MathDomain(_) => "math domain",
+            Error::FractionalOperand(_) => "fractional operand",
+            Error::ComplexOperand(_) => "complex operand",
+            Error::NoConvergence(_) => "no convergence",
+            Error::InvalidEquation(_) => "invalid equation",
+            Error::NoSingleSolution(_) => "no single solution",
+            Error::InvalidPolynomial { .. } => "invalid polynomial",
+            Error::TooManyTerms(_) => "too many terms",
+            Error::DimensionMismatch(_) => "dimension mismatch",
+            Error::MatrixOperand(_) => "matrix operand",
+            Error::IncompatibleUnits(_) => "incompatible units",
+            Error::UnitOperand(_) => "unit operand",
+            Error::InexactConversion(_) => "inexact conversion",
+            Error::InvalidDate(_) => "invalid date",
+            Error::DateOperand(_) => "date operand",
+            Error::FractionalDays(_) => "fractional days",
+        }
+    }
+
+    ///
+    /// Characters of the expression the error was found at, for the errors raised while
+    /// parsing
+    ///
+    pub fn span(&self) -> Option<Span> {
+        let (start, len) = match self {
+            Error::InvalidSyntax { found, position } => (*position, found.chars().count()),
+            Error::TrailingOperator { position, .. }
+            | Error::MismatchedBracket { position, .. }
+            | Error::UnmatchedParen { position, .. }
+            | Error::InvalidExpression { position, .. } => (*position, 1),
+            _ => return None,
+        };
+        Some(Span {
+            start,
+            end: start + len.max(1),
+        })
+    }
+}
+
+///
+/// Problem found in an expression without evaluating it, located in the expression when
+/// it can be
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        Diagnostic {
+            kind: error.kind(),
+            message: error.to_string(),
+            span: error.span(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Warning {
+    TruncatedDivision(Number, Number),
+    TruncatedRoot(String, Number),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::TruncatedDivision(v1, v2) => {
+                write!(f, "Division {v1} / {v2} was rounded toward zero")
+            }
+            Warning::TruncatedRoot(root, v) => {
+                write!(f, "Root {root}{v} was rounded toward zero")
+            }
+        }
+    }
+}
+
+///
+/// What to do when an integer operation does not fit in the integer width
+///
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Fail with an overflow error
+    #[default]
+    Checked,
+    /// Continue the computation with arbitrary precision integers
+    Promote,
+    /// Wrap around like fixed-width machine arithmetic
+    Wrap,
+    /// Clamp the result to the closest representable value
+    Saturate,
+}
+
+///
+/// Character written between the integral and the fractional digits of a number
+///
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalSeparator {
+    /// `1234.5`, as in English
+    #[default]
+    Point,
+    /// `1234,5`, as in most European languages
+    Comma,
+}
+
+///
+/// How decimal results are rounded to the precision
+///
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// To the nearest value, halves going to the even neighbour
+    #[default]
+    HalfEven,
+    /// To the nearest value, halves going away from zero
+    HalfUp,
+    /// Toward zero
+    Down,
+    /// Away from zero
+    Up,
+    /// Toward negative infinity
+    Floor,
+    /// Toward positive infinity
+    Ceiling,
+}
+
+///
+/// Number of bits of the integers handled by the evaluation
+///
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IntWidth {
+    #[cfg_attr(feature = "cli", value(name = "8"))]
+    W8,
+    #[cfg_attr(feature = "cli", value(name = "16"))]
+    W16,
+    #[cfg_attr(feature = "cli", value(name = "32"))]
+    W32,
+    #[default]
+    #[cfg_attr(feature = "cli", value(name = "64"))]
+    W64,
+    #[cfg_attr(feature = "cli", value(name = "128"))]
+    W128,
+}
+
+impl IntWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            IntWidth::W8 => 8,
+            IntWidth::W16 => 16,
+            IntWidth::W32 => 32,
+            IntWidth::W64 => 64,
+            IntWidth::W128 => 128,
+        }
+    }
+}
+
+///
+/// Integer type the evaluation works with, unsigned types are limited to 64 bits
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntType {
+    width: IntWidth,
+    unsigned: bool,
+}
+
+impl IntType {
+    fn min(self) -> i128 {
+        match (self.width, self.unsigned) {
+            (_, true) => 0,
+            (IntWidth::W128, false) => i128::MIN,
+            (width, false) => -(1 << (width.bits() - 1)),
+        }
+    }
+
+    fn max(self) -> i128 {
+        match (self.width, self.unsigned) {
+            (IntWidth::W128, _) => i128::MAX,
+            (width, true) => (1 << width.bits()) - 1,
+            (width, false) => (1 << (width.bits() - 1)) - 1,
+        }
+    }
+
+    fn contains(self, n: i128) -> bool {
+        (self.min()..=self.max()).contains(&n)
+    }
+
+    ///
+    /// Reduce a value modulo 2^width into the range of the type
+    ///
+    fn wrap(self, n: i128) -> i128 {
+        if self.width == IntWidth::W128 {
+            return n;
+        }
+        let bits = self.width.bits();
+        let low = n & ((1 << bits) - 1);
+        if !self.unsigned && low > self.max() {
+            low - (1 << bits)
+        } else {
+            low
+        }
+    }
+
+    ///
+    /// Whether the value is a bit pattern of the width, read as an unsigned integer
+    ///
+    fn holds_pattern(self, n: i128) -> bool {
+        self.width == IntWidth::W128 || (0..1 << self.width.bits()).contains(&n)
+    }
+
+    fn clamp(self, n: i128) -> i128 {
+        n.clamp(self.min(), self.max())
+    }
+}
+
+///
+/// Options driving how an expression is evaluated
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Settings {
+    /// Reject divisions that would truncate instead of warning about them
+    pub exact: bool,
+    pub overflow: Overflow,
+    pub int_width: IntWidth,
+    /// Work with unsigned integers, where negative results overflow
+    pub unsigned: bool,
+    /// Record a snapshot of the value stack after each RPN token
+    pub trace: bool,
+    /// Compute with decimals of this many fractional digits instead of integers
+    pub decimals: Option<u32>,
+    pub rounding: Rounding,
+    /// Read literals up to the unsigned maximum of the width as two's complement bit
+    /// patterns, so that `0xFF` is -1 on 8 signed bits
+    pub programmer: bool,
+}
+
+impl Settings {
+    fn int_type(&self) -> IntType {
+        IntType {
+            width: self.int_width,
+            unsigned: self.unsigned,
+        }
+    }
+
+    ///
+    /// Fractional digits decimals are computed with, those of the decimal mode or the ones
+    /// the integer mode falls back to for values which are not integers
+    ///
+    pub(super) fn scale(&self) -> u32 {
+        self.decimals.unwrap_or(DEFAULT_DECIMALS)
+    }
+}
+
+///
+/// Format used to print the structure of an expression
+///
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstFormat {
+    /// Indented box-drawing tree
+    Tree,
+    /// S-expression on a single line, as in `(+ 3 (* 7 2))`
+    Sexpr,
+    /// Graphviz DOT graph
+    Dot,
+    /// JSON document with the tokens, with their spans, and the tree built from them
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+///
+/// Size metrics of an evaluated expression
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of tokens produced by the parser
+    pub tokens: usize,
+    /// Depth of the expression tree, a lone number has depth 1
+    pub depth: usize,
+    /// Number of operators applied
+    pub operations: usize,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tokens: {}, depth: {}, operations: {}",
+            self.tokens, self.depth, self.operations
+        )
+    }
+}
+
+///
+/// Result of an expression together with the warnings raised computing it
+///
+#[derive(Debug)]
+pub struct Evaluation {
+    pub result: Number,
+    pub warnings: Vec<Warning>,
+    pub stats: Stats,
+    /// Reverse Polish Notation the result was computed from
+    pub rpn: String,
+    /// Evaluation steps, only recorded when tracing is enabled
+    pub trace: Vec<TraceStep>,
+}
+
+///
+/// Value stack right after applying an RPN token
+///
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TraceStep {
+    pub token: String,
+    pub stack: Vec<Number>,
+    /// Time since the evaluation started
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "elapsed_ns", serialize_with = "serialize_nanos")
+    )]
+    pub elapsed: Duration,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_nanos<S: serde::Serializer>(
+    elapsed: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(elapsed.as_nanos())
+}
+
+/// Number of results kept in the answers stack unless configured otherwise
+pub const DEFAULT_ANSWERS: usize = 10;
+
+/// Fractional digits of the decimals the integer mode computes with once a value is not an
+/// integer, as `pi` is
+const DEFAULT_DECIMALS: u32 = 20;
+
+///
+/// Values an expression can refer to besides its literals
+///
+#[derive(Debug, Clone)]
+pub struct Context {
+    /// Results of the last expressions evaluated successfully, the most recent last
+    answers: Vec<Number>,
+    capacity: usize,
+    /// Values assigned with `name = expression`
+    variables: HashMap<String, Number>,
+    /// Functions defined with `name(parameters) = body`, by name and number of parameters
+    functions: HashMap<(String, usize), UserFunction>,
+    /// Generator of the random functions, shared with the scopes of user function calls
+    rng: Rc<RefCell<StdRng>>,
+}
+
+///
+/// Function defined in the REPL, its body kept in Reverse Polish Notation
+///
+#[derive(Debug, Clone)]
+struct UserFunction {
+    params: Vec<String>,
+    body: Rpn,
+    /// Body as it was written
+    source: String,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new(DEFAULT_ANSWERS)
+    }
+}
+
+impl Context {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            answers: vec![],
+            capacity,
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            rng: Rc::new(RefCell::new(StdRng::from_os_rng())),
+        }
+    }
+
+    ///
+    /// Restart the random numbers from a seed, so that they repeat from one run to another
+    ///
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rc::new(RefCell::new(StdRng::seed_from_u64(seed)));
+    }
+
+    ///
+    /// Copy of the context drawing its own random numbers, the same ones this context is
+    /// about to draw
+    ///
+    pub fn snapshot(&self) -> Self {
+        Self {
+            rng: Rc::new(RefCell::new(self.rng.borrow().clone())),
+            ..self.clone()
+        }
+    }
+
+    fn random(&self, range: RangeInclusive<i128>) -> i128 {
+        self.rng.borrow_mut().random_range(range)
+    }
+
+    ///
+    /// Random integer below 10^digits, all of them equally likely
+    ///
+    fn random_digits(&self, digits: u32) -> BigInt {
+        // Drawn by blocks of 19 digits, which fit in a u64
+        let mut rng = self.rng.borrow_mut();
+        let mut units = BigInt::ZERO;
+        let mut left = digits;
+        while left > 0 {
+            let block = 10u64.pow(left.min(19));
+            units = units * block + rng.random_range(0..block);
+            left = left.saturating_sub(19);
+        }
+        units
+    }
+
+    ///
+    /// Record a result, forgetting the oldest one when the answers stack is full
+    ///
+    pub fn push(&mut self, result: Number) {
+        self.answers.push(result);
+        if self.answers.len() > self.capacity {
+            self.answers.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.answers.clear();
+    }
+
+    ///
+    /// Kept results, the most recent first
+    ///
+    pub fn answers(&self) -> impl Iterator<Item = &Number> {
+        self.answers.iter().rev()
+    }
+
+    fn previous(&self) -> Result<Number, Error> {
+        self.answers.last().cloned().ok_or(Error::NoPreviousResult)
+    }
+
+    ///
+    /// The k-th most recent result, ans(1) being the previous one
+    ///
+    fn answer(&self, k: usize) -> Result<Number, Error> {
+        k.checked_sub(1)
+            .and_then(|back| self.answers.iter().rev().nth(back))
+            .cloned()
+            .ok_or(Error::NoAnswer(k))
+    }
+
+    ///
+    /// Assigned variables with their values, by name
+    ///
+    pub fn variables(&self) -> Vec<(&str, &Number)> {
+        let mut variables: Vec<_> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        variables.sort_unstable_by_key(|(name, _)| *name);
+        variables
+    }
+
+    ///
+    /// Defined functions written as they were defined, as in `f(x) = x^2 + 1`, by name and
+    /// number of parameters
+    ///
+    pub fn functions(&self) -> Vec<String> {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_unstable_by_key(|((name, count), _)| (name, *count));
+        functions
+            .into_iter()
+            .map(|((name, _), function)| {
+                format!(
+                    "{name}({}) = {}",
+                    function.params.join(", "),
+                    function.source
+                )
+            })
+            .collect()
+    }
+
+    ///
+    /// Forget the assigned variables and the defined functions, results being kept
+    ///
+    pub fn reset(&mut self) {
+        self.variables.clear();
+        self.functions.clear();
+    }
+
+    pub fn assign(&mut self, name: &str, value: Number) {
+        self.variables.insert(name.to_owned(), value);
+    }
+
+    ///
+    /// Scope where the bindings of a `where` clause are assigned in turn, each one seeing
+    /// the ones before it, leaving this context as it is
+    ///
+    pub fn bind(&self, bindings: &[Binding], settings: &Settings) -> Result<Context, Error> {
+        let mut scope = self.clone();
+        for (name, expr) in bindings {
+            let value = CompiledExpression::new(expr)?
+                .eval(settings, &scope)?
+                .result;
+            scope.assign(name, value);
+        }
+        Ok(scope)
+    }
+
+    ///
+    /// Define a function, replacing any function with the same name and number of
+    /// parameters. Calls are resolved when the function runs, but no function may end up
+    /// calling itself
+    ///
+    pub fn define(&mut self, name: &str, params: &[&str], body: &str) -> Result<(), Error> {
+        let source = body.trim().to_owned();
+        let body = Rpn::new(body)?;
+        let key = (name.to_owned(), params.len());
+        if self.reaches(&body, &key) {
+            return Err(Error::RecursiveFunction(name.to_owned()));
+        }
+        let params = params.iter().map(|param| (*param).to_owned()).collect();
+        self.functions.insert(
+            key,
+            UserFunction {
+                params,
+                body,
+                source,
+            },
+        );
+        Ok(())
+    }
+
+    ///
+    /// Whether running the body may call the given function, directly or through the
+    /// functions defined so far
+    ///
+    fn reaches(&self, body: &Rpn, function: &(String, usize)) -> bool {
+        body.calls().into_iter().any(|callee| {
+            &callee == function
+                || self
+                    .functions
+                    .get(&callee)
+                    .is_some_and(|callee| self.reaches(&callee.body, function))
+        })
+    }
+
+    ///
+    /// Run a user function, its parameters bound to the arguments on top of the variables
+    ///
+    fn call(
+        &self,
+        name: &str,
+        args: Vec<Number>,
+        settings: &Settings,
+    ) -> Result<Evaluation, Error> {
+        let function = self
+            .functions
+            .get(&(name.to_owned(), args.len()))
+            .ok_or_else(|| Error::UnknownFunction {
+                name: name.to_owned(),
+                count: args.len(),
+            })?;
+        let mut scope = self.clone();
+        for (param, arg) in function.params.iter().zip(args) {
+            scope.assign(param, arg);
+        }
+        function.body.compute(settings, &scope)
+    }
+
+    ///
+    /// Value of a built-in constant rounded to the scale, unless a variable of the same name
+    /// shadows it
+    ///
+    fn constant(&self, name: &str, settings: &Settings) -> Option<Number> {
+        decimal::constant(name, settings, settings.scale())
+            .filter(|_| !self.variables.contains_key(name))
+    }
+
+    ///
+    /// Value of a variable, `ans` being bound to the previous result
+    ///
+    fn variable(&self, name: &str) -> Result<Number, Error> {
+        if name == "ans" {
+            return self.previous();
+        }
+        match self.variables.get(name) {
+            Some(value) => Ok(value.clone()),
+            // The imaginary unit, unless a variable shadows it
+            None if name == "i" => Ok(Number::Complex(Box::new(Complex::i()))),
+            // Units, unless variables shadow them as well
+            None => unit::named(name).ok_or_else(|| Error::UnknownVariable(name.to_owned())),
+        }
+    }
+}
+
+///
+/// Input line of the REPL
+///
+#[derive(Debug, PartialEq)]
+pub enum Statement<'a> {
+    Expression(&'a str),
+    /// `name = expression`
+    Assignment(&'a str, &'a str),
+    /// `name(parameters) = body`
+    Definition {
+        name: &'a str,
+        params: Vec<&'a str>,
+        body: &'a str,
+    },
+}
+
+///
+/// Tell expressions from assignments and function definitions, according to what stands
+/// on the left of `=`
+///
+pub fn parse_statement(line: &str) -> Result<Statement<'_>, Error> {
+    // An `=` within the comment or the `where` clause does not make an assignment
+    let code = strip_comment(line);
+    let Some(sign) = assignment_sign(&code[..where_keyword(code).unwrap_or(code.len())]) else {
+        return Ok(Statement::Expression(line));
+    };
+    let (lhs, expr) = (&line[..sign], &line[sign + 1..]);
+    let lhs = lhs.trim();
+    let invalid = || Error::InvalidAssignment(lhs.to_owned());
+    let Some((name, params)) = lhs.split_once('(') else {
+        if !is_variable_name(lhs) {
+            return Err(invalid());
+        }
+        return Ok(Statement::Assignment(lhs, expr.trim()));
+    };
+    let params = params
+        .strip_suffix(')')
+        .ok_or_else(invalid)?
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<&str>>();
+    let unique = params
+        .iter()
+        .enumerate()
+        .all(|(index, param)| !params[..index].contains(param));
+    if !is_variable_name(name.trim()) || !unique || !params.iter().all(|p| is_variable_name(p)) {
+        return Err(invalid());
+    }
+    Ok(Statement::Definition {
+        name: name.trim(),
+        params,
+        body: expr.trim(),
+    })
+}
+
+///
+/// Position of the `=` making an assignment, rather than part of a comparison as `==`,
+/// `!=`, `<=` or `>=` are
+///
+fn assignment_sign(code: &str) -> Option<usize> {
+    let bytes = code.as_bytes();
+    code.match_indices('=')
+        .map(|(index, _)| index)
+        .find(|index| {
+            bytes.get(index + 1) != Some(&b'=')
+                && !index
+                    .checked_sub(1)
+                    .is_some_and(|before| matches!(bytes[before], b'=' | b'!' | b'<' | b'>'))
+        })
+}
+
+///
+/// Statements of a line separated by `;`, in the order they are written. A `;` within
+/// brackets separates nothing, and the comment ending the line goes with the last statement
+///
+pub fn split_statements(line: &str) -> Vec<&str> {
+    let code = strip_comment(line);
+    let mut statements = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in code.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ';' if depth <= 0 => {
+                statements.push(&line[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&line[start..]);
+    statements
+}
+
+///
+/// Binding of a `where` clause, as the name of the variable and the expression of its value
+///
+pub type Binding<'a> = (&'a str, &'a str);
+
+///
+/// Split an expression from the bindings of its `where` clause, as in
+/// `x^2 + y where x = 3, y = x + 1`, which are kept in the order they are written. A
+/// clause without expression, binding or binding name is empty, and `where` followed by `=`
+/// is taken as an assignment to the keyword
+///
+pub fn split_bindings(expr: &str) -> Result<(&str, Vec<Binding<'_>>), Error> {
+    let code = strip_comment(expr);
+    let Some(start) = where_keyword(code) else {
+        return Ok((expr, vec![]));
+    };
+    if code[start + "where".len()..].trim_start().starts_with('=') {
+        return Err(Error::InvalidAssignment("where".to_owned()));
+    }
+    if code[..start].trim().is_empty() {
+        return Err(Error::EmptyExpression);
+    }
+    let mut bindings = vec![];
+    let mut depth = 0;
+    let mut binding_start = start + "where".len();
+    for (index, c) in code.char_indices().filter(|(index, _)| *index >= start) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                bindings.push(binding(&code[binding_start..index])?);
+                binding_start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    bindings.push(binding(&code[binding_start..])?);
+    Ok((code[..start].trim_end(), bindings))
+}
+
+///
+/// Position of the `where` keyword opening the bindings of an expression, if any
+///
+fn where_keyword(expr: &str) -> Option<usize> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    expr.match_indices("where")
+        .map(|(index, _)| index)
+        .find(|index| {
+            !expr[..*index].ends_with(is_name)
+                && !expr[index + "where".len()..].starts_with(is_name)
+        })
+}
+
+fn binding(binding: &str) -> Result<Binding<'_>, Error> {
+    if binding.trim().is_empty() || binding.trim_start().starts_with('=') {
+        return Err(Error::EmptyExpression);
+    }
+    let invalid = || Error::InvalidAssignment(binding.trim().to_owned());
+    let (name, expr) = binding.split_once('=').ok_or_else(invalid)?;
+    if !is_variable_name(name.trim()) {
+        return Err(Error::InvalidAssignment(name.trim().to_owned()));
+    }
+    Ok((name.trim(), expr.trim()))
+}
+
+pub fn estimate_expression(expr: &str, settings: &Settings) -> Result<Evaluation, Error> {
+    estimate_expression_in(expr, settings, &Context::default())
+}
+
+///
+/// Evaluate an expression which may refer to the values of the given context
+///
+pub fn estimate_expression_in(
+    expr: &str,
+    settings: &Settings,
+    context: &Context,
+) -> Result<Evaluation, Error> {
+    let (expr, bindings) = split_bindings(expr)?;
+    let compiled = CompiledExpression::new(expr)?;
+    if bindings.is_empty() {
+        return compiled.eval(settings, context);
+    }
+    compiled.eval(settings, &context.bind(&bindings, settings)?)
+}
+
+///
+/// Evaluate an expression written in Reverse Polish Notation, as in `3 4 + 2 *`, which may
+/// refer to the values of the given context
+///
+pub fn estimate_rpn_in(
+    rpn: &str,
+    settings: &Settings,
+    context: &Context,
+) -> Result<Evaluation, Error> {
+    CompiledExpression::from_rpn(rpn)?.eval(settings, context)
+}
+
+///
+/// Expression converted to Reverse Polish Notation once, which can be evaluated any number
+/// of times, e.g. with different values assigned to its variables in the context. With the
+/// serde feature it can be stored, e.g. as JSON, and read back without parsing it again
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(into = "SerializedExpression", try_from = "SerializedExpression")
+)]
+#[derive(Debug, Clone)]
+pub struct CompiledExpression {
+    program: Rpn,
+}
+
+///
+/// Stored form of a compiled expression: its RPN tokens, in evaluation order
+///
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedExpression {
+    rpn: Vec<Token>,
+}
+
+#[cfg(feature = "serde")]
+impl From<CompiledExpression> for SerializedExpression {
+    fn from(value: CompiledExpression) -> Self {
+        Self {
+            rpn: value.program.rpn_tokens(),
+        }
+    }
+}
+
+///
+/// Stored RPN is checked as typed RPN is, before any evaluation
+///
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedExpression> for CompiledExpression {
+    type Error = Error;
+
+    fn try_from(value: SerializedExpression) -> Result<Self, Self::Error> {
+        let program = Rpn::from_rpn_tokens(value.rpn);
+        program.ast()?;
+        Ok(Self { program })
+    }
+}
+
+impl CompiledExpression {
+    pub fn new(expr: &str) -> Result<Self, Error> {
+        Ok(Self {
+            program: Rpn::new(expr)?,
+        })
+    }
+
+    ///
+    /// Expression read straight from its Reverse Polish Notation, space separated as in
+    /// `3 4 + 2 *`, without going through the parser
+    ///
+    pub fn from_rpn(rpn: &str) -> Result<Self, Error> {
+        let program = Rpn::from_rpn(rpn)?;
+        // Operators missing operands, or operands left over, are rejected before evaluating
+        program.ast()?;
+        Ok(Self { program })
+    }
+
+    pub fn eval(&self, settings: &Settings, context: &Context) -> Result<Evaluation, Error> {
+        self.program.compute(settings, context)
+    }
+
+    ///
+    /// Reverse Polish Notation of the expression, space separated
+    ///
+    pub fn rpn(&self) -> String {
+        self.program.rpn()
+    }
+}
+
+///
+/// Reverse Polish Notation the expression is evaluated from, space separated as in
+/// `3 4 + 2 *`
+///
+pub fn to_rpn_string(expr: &str) -> Result<String, Error> {
+    Ok(CompiledExpression::new(expr)?.rpn())
+}
+
+///
+/// Infix expression of a Reverse Polish Notation written as `to_rpn_string` writes it,
+/// with only the brackets it needs, as in `(3 + 4) * 2` for `3 4 + 2 *`
+///
+pub fn rpn_to_infix(rpn: &str) -> Result<String, Error> {
+    Ok(Rpn::from_rpn(rpn)?.ast()?.infix())
+}
+
+///
+/// Check that an expression parses and that its functions are called with as many
+/// arguments as they take, without evaluating it. Variables, answers and user functions
+/// are not checked, as they depend on the context the expression is evaluated in
+///
+pub fn validate(expr: &str) -> Result<(), Vec<Diagnostic>> {
+    let parsed = eval::tokenize(expr).and_then(|tokens| Ok((tokens, parse_ast(expr)?)));
+    let (tokens, ast) = parsed.map_err(|e| vec![Diagnostic::from(e)])?;
+    // Calls are found in the tree in the same order as their function names in the tokens
+    let names = tokens
+        .iter()
+        .filter(|spanned| matches!(spanned.token, Token::Function(_)))
+        .map(|spanned| spanned.span);
+    let diagnostics: Vec<Diagnostic> = ast
+        .calls()
+        .into_iter()
+        .zip(names)
+        .filter_map(|((function, count), span)| {
+            let error = function.check_arguments(count).err()?;
+            Some(Diagnostic {
+                span: Some(span),
+                ..error.into()
+            })
+        })
+        .collect();
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+///
+/// Build the structure of an expression with the Pratt parser
+///
+pub fn parse_ast(expr: &str) -> Result<Ast, Error> {
+    pratt::parse(expr, &eval::tokenize(expr)?)
+}
+
+///
+/// Render the structure of an expression, as the calculator groups it, in the given format
+///
+pub fn dump_ast(expr: &str, format: AstFormat) -> Result<String, Error> {
+    let ast = parse_ast(expr)?;
+    Ok(match format {
+        AstFormat::Tree => ast.tree(),
+        AstFormat::Sexpr => format!("{}\n", ast.sexpr()),
+        AstFormat::Dot => ast.dot(),
+        #[cfg(feature = "serde")]
+        AstFormat::Json => {
+            let json = serde_json::json!({ "tokens": eval::tokenize(expr)?, "ast": ast });
+            format!("{json}\n")
+        }
+    })
+}
+
+///
+/// Spell out the grouping the calculator applies to an expression, None when the
+/// expression already reads that way
+///
+pub fn explain_grouping(expr: &str) -> Result<Option<String>, Error> {
+    let grouped = parse_ast(expr)?.grouped();
+    let written = strip_comment(expr).split_whitespace().collect::<String>();
+    Ok((grouped != written).then_some(grouped))
+}
+
+///
+/// Echo the expression with brackets around every operation, showing how it is parsed, as
+/// in `((3+(7*2))-1)` for `3+7*2-1`
+///
+pub fn parenthesize(expr: &str) -> Result<String, Error> {
+    Ok(parse_ast(expr)?.parenthesized())
+}
+
+///
+/// Simplified form of the expression, its operations on integer literals folded and
+/// identities such as `x*1 = x` applied, written with only the brackets it needs
+///
+pub fn simplify(expr: &str) -> Result<String, Error> {
+    Ok(parse_ast(expr)?.simplify().infix())
+}
+
+///
+/// Evaluate the expression again by walking its tree rather than its Reverse Polish
+/// Notation, returning the reference outcome when it disagrees with the RPN one. Two
+/// failures agree whatever their errors. The RPN is generated from the same tree, so only
+/// the evaluations are cross-checked, not the parsing
+///
+pub fn verify_expression(
+    expr: &str,
+    settings: &Settings,
+    context: &Context,
+    outcome: &Result<Evaluation, Error>,
+) -> Option<Result<Number, Error>> {
+    let reference = split_bindings(expr).and_then(|(expr, bindings)| {
+        let ast = parse_ast(expr)?;
+        if bindings.is_empty() {
+            return ast.evaluate(settings, context);
+        }
+        ast.evaluate(settings, &context.bind(&bindings, settings)?)
+    });
+    match (outcome, &reference) {
+        (Ok(evaluation), Ok(n)) if &evaluation.result == n => None,
+        (Err(_), Err(_)) => None,
+        _ => Some(reference),
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::estimate_expression;
+    use num_bigint::BigInt;
+
+    use super::{
+        AstFormat, CompiledExpression, Context, DEFAULT_DECIMALS, DecimalSeparator, Diagnostic,
+        Error, IntWidth, Number, Overflow, Rounding, Settings, Span, Statement, Warning,
+        complex::Complex, decimal::Decimal, dump_ast, estimate_expression, estimate_expression_in,
+        estimate_rpn_in, explain_grouping, function_names, normalize_separators, number::Kind,
+        parenthesize, parse_ast, parse_statement, physical_constants, rpn_to_infix, simplify,
+        split_bindings, split_statements, to_fraction, to_radix, to_rpn_string, tokenize, validate,
+        verify_expression,
+    };
+
+    fn unexpected(found: &str, position: usize) -> Error {
+        Error::InvalidSyntax {
+            found: found.to_owned(),
+            position,
+        }
+    }
 
     const EASY_EXPR: &str = "4+2";
-    const EASY_RESULT: i64 = 6;
+    const EASY_RESULT: i128 = 6;
     const MEDIUM_EXPR: &str = "3    * 6 - 7  + 2";
-    const MEDIUM_RESULT: i64 = 13;
+    const MEDIUM_RESULT: i128 = 13;
     const HARD_EXPR: &str = "(3+4) +  7 *2 -1-9";
-    const HARD_RESULT: i64 = 11;
+    const HARD_RESULT: i128 = 11;
     const HARDER_EXPR: &str = "(8 -1 +3)  *6 -((3+7)*2  )";
-    const HARDER_RESULT: i64 = 40;
+    const HARDER_RESULT: i128 = 40;
 
     #[test]
     fn test_easy_computation() {
-        match estimate_expression(EASY_EXPR) {
-            Ok(res) => {
+        match estimate_expression(EASY_EXPR, &Settings::default()) {
+            Ok(evaluation) => {
+                let res = evaluation.result;
                 println!("Result of expression {} is {res}", EASY_EXPR.trim());
                 assert_eq!(res, EASY_RESULT)
             }
@@ -50,8 +1214,9 @@ mod test {
 
     #[test]
     fn test_medium_computation() {
-        match estimate_expression(MEDIUM_EXPR) {
-            Ok(res) => {
+        match estimate_expression(MEDIUM_EXPR, &Settings::default()) {
+            Ok(evaluation) => {
+                let res = evaluation.result;
                 println!("Result of expression {} is {res}", MEDIUM_EXPR.trim());
                 assert_eq!(res, MEDIUM_RESULT)
             }
@@ -59,15 +1224,11 @@ mod test {
         }
     }
 
-    // TODO -> This case fails since RPN generated is:
-    // 3 4 + 7 2 * 1 9 - - +
-    // Meanwhile the correct one should be:
-    // 3 4 + 7 2 * 1 - 9 - +
-    // Using lower or equal in Token::Operator branch seems to solve the problem. Investigate...
     #[test]
     fn test_hard_computation() {
-        match estimate_expression(HARD_EXPR) {
-            Ok(res) => {
+        match estimate_expression(HARD_EXPR, &Settings::default()) {
+            Ok(evaluation) => {
+                let res = evaluation.result;
                 println!("Result of expression {} is {res}", HARD_EXPR.trim());
                 assert_eq!(res, HARD_RESULT)
             }
@@ -77,12 +1238,3500 @@ mod test {
 
     #[test]
     fn test_harder_computation() {
-        match estimate_expression(HARDER_EXPR) {
-            Ok(res) => {
+        match estimate_expression(HARDER_EXPR, &Settings::default()) {
+            Ok(evaluation) => {
+                let res = evaluation.result;
                 println!("Result of expression {} is {res}", HARDER_EXPR.trim());
                 assert_eq!(res, HARDER_RESULT)
             }
             Err(e) => panic!("Expected result {HARDER_RESULT}, received error {e}"),
         }
     }
+
+    #[test]
+    fn test_truncated_division_warning() {
+        let evaluation = estimate_expression("7/2", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, 3);
+        assert_eq!(
+            evaluation.warnings,
+            vec![Warning::TruncatedDivision(Number::Int(7), Number::Int(2))]
+        );
+    }
+
+    #[test]
+    fn test_exact_division_has_no_warning() {
+        let evaluation = estimate_expression(
+            "8/2",
+            &Settings {
+                exact: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(evaluation.result, 4);
+        assert!(evaluation.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_division_exact_mode() {
+        assert_eq!(
+            estimate_expression(
+                "1 + 7/2",
+                &Settings {
+                    exact: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap_err(),
+            Error::InexactDivision(Number::Int(7), Number::Int(2))
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        for (expr, computation, settings) in [
+            ("10 / 0", "10 / 0", Settings::default()),
+            ("7 % (3 - 3)", "7 % 0", Settings::default()),
+            (
+                "1 / 0",
+                "1 / 0",
+                Settings {
+                    overflow: Overflow::Wrap,
+                    ..Default::default()
+                },
+            ),
+            ("(1+i) / 0", "1+i / 0", Settings::default()),
+            (
+                "1.5 / 0",
+                "1.5 / 0",
+                Settings {
+                    decimals: Some(2),
+                    ..Default::default()
+                },
+            ),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                Error::DivisionByZero(computation.to_owned()),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_leading_negative_number() {
+        let evaluation = estimate_expression("-5", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, -5);
+    }
+
+    #[test]
+    fn test_leading_negative_bracket() {
+        let evaluation = estimate_expression("-(2+3)", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, -5);
+    }
+
+    #[test]
+    fn test_leading_spaced_negative_product() {
+        let evaluation = estimate_expression("- 5 * 2", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, -10);
+    }
+
+    #[test]
+    fn test_leading_signed_numbers() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("-5 + 3", &settings).unwrap().result, -2);
+        assert_eq!(estimate_expression("+5 - 3", &settings).unwrap().result, 2);
+        assert_eq!(
+            estimate_expression("2 * (-3 + 1)", &settings)
+                .unwrap()
+                .result,
+            -4
+        );
+    }
+
+    #[test]
+    fn test_nested_unary_signs() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("--5", &settings).unwrap().result, 5);
+        assert_eq!(estimate_expression("-+-3", &settings).unwrap().result, 3);
+        assert_eq!(estimate_expression("4 - -3", &settings).unwrap().result, 7);
+        assert_eq!(estimate_expression("2 * -3", &settings).unwrap().result, -6);
+    }
+
+    #[test]
+    fn test_spaced_unary_signs() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("4 + - 3", &settings).unwrap().result, 1);
+        assert_eq!(estimate_expression("4 - - 3", &settings).unwrap().result, 7);
+        assert_eq!(estimate_expression("- - 5", &settings).unwrap().result, 5);
+    }
+
+    #[test]
+    fn test_sign_after_bracket_is_binary() {
+        let evaluation = estimate_expression("(1+2)-3", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, 0);
+    }
+
+    #[test]
+    fn test_negated_operands() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+        context.push(Number::Int(3));
+        context.push(Number::Int(4));
+
+        assert_eq!(
+            estimate_expression_in("-_", &settings, &context)
+                .unwrap()
+                .result,
+            -4
+        );
+        assert_eq!(
+            estimate_expression_in("2 - -ans(2) * 2", &settings, &context)
+                .unwrap()
+                .result,
+            8
+        );
+        assert_eq!(
+            estimate_expression_in("-(ans(2) + _) % 4", &settings, &context)
+                .unwrap()
+                .result,
+            -3
+        );
+        assert_eq!(
+            explain_grouping("-ans(1)^2 * 3").unwrap(),
+            Some("(-(ans(1)^2))*3".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_empty_expression() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("", &settings).unwrap_err(),
+            Error::EmptyExpression
+        );
+        assert_eq!(
+            estimate_expression(" \t ", &settings).unwrap_err(),
+            Error::EmptyExpression
+        );
+    }
+
+    #[test]
+    fn test_tab_separated_expression() {
+        let evaluation = estimate_expression("4\t+\t2", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, 6);
+    }
+
+    #[test]
+    fn test_power() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("2^10", &settings).unwrap().result, 1024);
+        assert_eq!(estimate_expression("2^3^2", &settings).unwrap().result, 512);
+        assert_eq!(estimate_expression("-2^2", &settings).unwrap().result, -4);
+        assert_eq!(estimate_expression("(-2)^3", &settings).unwrap().result, -8);
+        assert_eq!(
+            estimate_expression("3 * 2^2 + 1", &settings)
+                .unwrap()
+                .result,
+            13
+        );
+    }
+
+    #[test]
+    fn test_negative_exponent() {
+        assert_eq!(
+            estimate_expression("2^-1", &Settings::default()).unwrap_err(),
+            Error::NegativeExponent(Number::Int(-1))
+        );
+    }
+
+    #[test]
+    fn test_left_associative_operators() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("8/2*2", &settings).unwrap().result, 8);
+        assert_eq!(estimate_expression("8-2+2", &settings).unwrap().result, 8);
+        assert_eq!(
+            estimate_expression("100/10/5", &settings).unwrap().result,
+            2
+        );
+    }
+
+    #[test]
+    fn test_overflow_checked() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("3037000500 * 3037000500", &settings).unwrap_err(),
+            Error::Overflow("3037000500 * 3037000500".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("2^63", &settings).unwrap_err(),
+            Error::Overflow("2 ^ 63".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("99999999999999999999", &settings).unwrap_err(),
+            Error::Overflow("literal 99999999999999999999".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_overflow_promote() {
+        let settings = Settings {
+            overflow: Overflow::Promote,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("2^64 * 3", &settings).unwrap().result,
+            Number::Big(BigInt::from(3u128 << 64))
+        );
+        // Values are brought back to 64 bits once they fit again
+        assert_eq!(
+            estimate_expression("2^100 / 2^90", &settings)
+                .unwrap()
+                .result,
+            1024
+        );
+    }
+
+    #[test]
+    fn test_overflow_wrap() {
+        let settings = Settings {
+            overflow: Overflow::Wrap,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("9223372036854775807 + 1", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MIN)
+        );
+        assert_eq!(estimate_expression("2^64", &settings).unwrap().result, 0);
+        assert_eq!(
+            estimate_expression("3^40", &settings).unwrap().result,
+            i128::from(3i64.wrapping_pow(40))
+        );
+        assert_eq!(
+            estimate_expression("-(-9223372036854775807 - 1)", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MIN)
+        );
+    }
+
+    #[test]
+    fn test_overflow_saturate() {
+        let settings = Settings {
+            overflow: Overflow::Saturate,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("9223372036854775807 + 1", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MAX)
+        );
+        assert_eq!(
+            estimate_expression("(-3)^41", &settings).unwrap().result,
+            i128::from(i64::MIN)
+        );
+        assert_eq!(
+            estimate_expression("2^9999999999", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MAX)
+        );
+        assert_eq!(
+            estimate_expression("-(-9223372036854775807 - 1)", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_int_width_128() {
+        let settings = Settings {
+            int_width: IntWidth::W128,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("9223372036854775807 * 4", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MAX) * 4
+        );
+        assert_eq!(
+            estimate_expression("99999999999999999999 + 1", &settings)
+                .unwrap()
+                .result,
+            100000000000000000000i128
+        );
+        assert_eq!(
+            estimate_expression("2^127", &settings).unwrap_err(),
+            Error::Overflow("2 ^ 127".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_int_width_128_overflow_modes() {
+        let wrap = Settings {
+            int_width: IntWidth::W128,
+            overflow: Overflow::Wrap,
+            ..Default::default()
+        };
+        let saturate = Settings {
+            int_width: IntWidth::W128,
+            overflow: Overflow::Saturate,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("2^127", &wrap).unwrap().result,
+            i128::MIN
+        );
+        assert_eq!(estimate_expression("2^128", &wrap).unwrap().result, 0);
+        assert_eq!(
+            estimate_expression("2^127", &saturate).unwrap().result,
+            i128::MAX
+        );
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let settings = Settings {
+            unsigned: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("18446744073709551615 - 5", &settings)
+                .unwrap()
+                .result,
+            i128::from(u64::MAX - 5)
+        );
+        assert_eq!(
+            estimate_expression("3 - 5", &settings).unwrap_err(),
+            Error::Overflow("3 - 5".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("-1", &settings).unwrap_err(),
+            Error::Overflow("-1".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_unsigned_overflow_modes() {
+        let wrap = Settings {
+            unsigned: true,
+            overflow: Overflow::Wrap,
+            ..Default::default()
+        };
+        let saturate = Settings {
+            unsigned: true,
+            overflow: Overflow::Saturate,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("3 - 5", &wrap).unwrap().result,
+            i128::from(u64::MAX - 1)
+        );
+        assert_eq!(
+            estimate_expression("18446744073709551615 + 1", &wrap)
+                .unwrap()
+                .result,
+            0
+        );
+        assert_eq!(estimate_expression("3 - 5", &saturate).unwrap().result, 0);
+        assert_eq!(
+            estimate_expression("2^64", &saturate).unwrap().result,
+            i128::from(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_dump_ast_tree() {
+        assert_eq!(
+            dump_ast("4 + 18/(9-3)", AstFormat::Tree)
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>(),
+            vec![
+                "+",
+                "├── 4",
+                "└── /",
+                "    ├── 18",
+                "    └── -",
+                "        ├── 9",
+                "        └── 3",
+            ]
+        );
+        assert_eq!(dump_ast("-7", AstFormat::Tree).unwrap(), "neg\n└── 7\n");
+        assert_eq!(dump_ast("", AstFormat::Tree), Err(Error::EmptyExpression));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_dump_ast_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&dump_ast("max(2.5, -x)", AstFormat::Json).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "tokens": [
+                    { "token": { "Function": "Max" }, "span": { "start": 0, "end": 3 } },
+                    { "token": { "Operator": "LeftBracket" }, "span": { "start": 3, "end": 4 } },
+                    { "token": { "Decimal": "2.5" }, "span": { "start": 4, "end": 7 } },
+                    { "token": "Comma", "span": { "start": 7, "end": 8 } },
+                    { "token": { "Operator": "Neg" }, "span": { "start": 9, "end": 10 } },
+                    { "token": { "Variable": "x" }, "span": { "start": 10, "end": 11 } },
+                    { "token": { "Operator": "RightBracket" }, "span": { "start": 11, "end": 12 } },
+                ],
+                "ast": {
+                    "Call": ["Max", [{ "Decimal": "2.5" }, { "Unary": ["Neg", { "Variable": "x" }] }]]
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_dump_ast_dot() {
+        assert_eq!(
+            dump_ast("3 + 7 * 2", AstFormat::Dot).unwrap(),
+            "digraph ast {\n    \
+                 n0 [label=\"+\"];\n    \
+                 n1 [label=\"3\"];\n    \
+                 n0 -> n1;\n    \
+                 n2 [label=\"*\"];\n    \
+                 n3 [label=\"7\"];\n    \
+                 n2 -> n3;\n    \
+                 n4 [label=\"2\"];\n    \
+                 n2 -> n4;\n    \
+                 n0 -> n2;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_dump_ast_sexpr() {
+        for (expr, sexpr) in [
+            ("3 + 7 * 2", "(+ 3 (* 7 2))"),
+            ("2 ^ 3 ^ 2", "(^ 2 (^ 3 2))"),
+            ("10 - 4 - 3", "(- (- 10 4) 3)"),
+            ("-max(1, x) ? 2 : |y|", "(?: (neg (max 1 x)) 2 (abs y))"),
+            ("7", "7"),
+        ] {
+            assert_eq!(
+                dump_ast(expr, AstFormat::Sexpr).unwrap(),
+                format!("{sexpr}\n"),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain_grouping() {
+        assert_eq!(
+            explain_grouping("6/2*(1+2)").unwrap(),
+            Some("(6/2)*(1+2)".to_owned())
+        );
+        assert_eq!(
+            explain_grouping("2^3^2 - -2^2").unwrap(),
+            Some("(2^(3^2))-(-(2^2))".to_owned())
+        );
+        assert_eq!(explain_grouping("(1 + 2) * 3").unwrap(), None);
+        assert_eq!(explain_grouping("42").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parenthesize() {
+        for (expr, parenthesized) in [
+            ("3+7*2-1", "((3+(7*2))-1)"),
+            ("2^3^2 - -2^2", "((2^(3^2))-(-(2^2)))"),
+            ("(1 + 2) * 3", "((1+2)*3)"),
+            ("max(1, 2 * x) ? |-3| : 4", "(max(1,2*x)?|-3|:4)"),
+            ("42", "42"),
+        ] {
+            assert_eq!(parenthesize(expr).unwrap(), parenthesized, "{expr}");
+        }
+        assert!(parenthesize("(1 + 2").is_err());
+    }
+
+    #[test]
+    fn test_simplify() {
+        for (expr, simplified) in [
+            ("2 * 3 + x", "6 + x"),
+            ("x * 1 + 0", "x"),
+            ("0 * (y + 4) + 1 * x ^ 1", "x"),
+            ("(x - 0) / 1 - 2 * 3", "x - 6"),
+            ("0 - (2 - 5) * x", "-(-3 * x)"),
+            ("--x + y ^ (3 - 3)", "x + 1"),
+            ("1 - 3 ? x : y", "x"),
+            ("max(2, 7) * min(x, 1 + 1)", "7 * min(x, 2)"),
+            ("7 / 2 + sqrt(2)", "7 / 2 + sqrt(2)"),
+            ("randint(1, 6) + 0", "randint(1, 6)"),
+            ("1 / 0 * 1", "1 / 0"),
+            ("2.5 * 1 + pi", "2.5 + pi"),
+        ] {
+            assert_eq!(simplify(expr).unwrap(), simplified, "{expr}");
+        }
+        assert!(simplify("2 *").is_err());
+    }
+
+    #[test]
+    fn test_where_bindings() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+        context.assign("x", Number::Int(10));
+
+        assert_eq!(
+            split_bindings("x^2 + y where x = 3, y = max(x, 1) # where z = 1").unwrap(),
+            ("x^2 + y", vec![("x", "3"), ("y", "max(x, 1)")])
+        );
+        assert_eq!(split_bindings("x + 1").unwrap(), ("x + 1", vec![]));
+        assert_eq!(
+            parse_statement("z = x * y where y = 2").unwrap(),
+            Statement::Assignment("z", "x * y where y = 2")
+        );
+        assert_eq!(
+            parse_statement("x where x = 3").unwrap(),
+            Statement::Expression("x where x = 3")
+        );
+
+        for (expr, result) in [
+            ("x^2 + 1 where x = 3", 10),
+            ("x + y where y = x * 2, x = 1", 21),
+            ("x + y where x = 1, y = x * 2", 3),
+            ("x + 1", 11),
+        ] {
+            let outcome = estimate_expression_in(expr, &settings, &context);
+            assert!(verify_expression(expr, &settings, &context, &outcome).is_none());
+            assert_eq!(outcome.unwrap().result, Number::Int(result), "{expr}");
+        }
+        for (expr, error) in [
+            ("x where", Error::EmptyExpression),
+            ("x where y = 1,", Error::EmptyExpression),
+            ("where x = 1", Error::EmptyExpression),
+            ("where = 1", Error::InvalidAssignment("where".to_owned())),
+            ("x where = 1", Error::InvalidAssignment("where".to_owned())),
+            ("x where y = 1, = 2", Error::EmptyExpression),
+            ("x where 3 = 4", Error::InvalidAssignment("3".to_owned())),
+            ("x where y", Error::InvalidAssignment("y".to_owned())),
+            ("x where y = ", Error::EmptyExpression),
+            ("x + y where x = 1", Error::UnknownVariable("y".to_owned())),
+        ] {
+            assert_eq!(
+                estimate_expression_in(expr, &settings, &context).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let settings = Settings {
+            decimals: Some(10),
+            ..Default::default()
+        };
+        let mut context = Context::default();
+        context.assign("x", Number::Int(10));
+        context.assign("a", Number::Int(9));
+
+        for (expr, result) in [
+            ("solve(x^2 - 2, x, 1)", "1.4142135624"),
+            ("solve(x^2 - 2, x, -1)", "-1.4142135624"),
+            ("solve(x^3 - 2 * x - 5, x, 2)", "2.0945514815"),
+            ("solve(y^2 - a, y, 1) + x", "13"),
+            ("solve(t - k, t, 0) where k = 0.5", "0.5"),
+            ("2 * solve((x - 1) * (x - 3), x, [2.5])", "6"),
+        ] {
+            let outcome = estimate_expression_in(expr, &settings, &context);
+            assert!(verify_expression(expr, &settings, &context, &outcome).is_none());
+            assert_eq!(outcome.unwrap().result.to_string(), result, "{expr}");
+        }
+        // Integers search with the decimals of the integer mode, integer roots staying
+        // integers
+        for (expr, result) in [
+            ("solve(x^2 - 9, x, 1)", "3"),
+            ("solve(2 * x + 8, x, 0)", "-4"),
+            ("solve(x^2 - 2, x, 1)", "1.4142135623730950488"),
+            ("solve(x^3 - 2 * x - 5, x, 2)", "2.09455148154232659148"),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &Settings::default())
+                    .unwrap()
+                    .result
+                    .to_string(),
+                result,
+                "{expr}"
+            );
+        }
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("solve(x^2 - 9, x, 1)", &exact)
+                .unwrap()
+                .result,
+            3
+        );
+        assert_eq!(
+            estimate_expression("solve(x^2 - 2, x, 1)", &exact).unwrap_err(),
+            Error::InexactCall("solve(x^2 - 2, x, 1)".to_owned())
+        );
+        assert_eq!(
+            estimate_rpn_in("solve(x^2 - 2, x, 1) 2 *", &settings, &context)
+                .unwrap()
+                .result
+                .to_string(),
+            "2.8284271248"
+        );
+        assert_eq!(
+            rpn_to_infix("solve(x^2 - 2, x, 1) 2 *").unwrap(),
+            "solve(x^2 - 2, x, 1) * 2"
+        );
+        assert_eq!(
+            to_rpn_string("1 + solve(x - 2,x,0)").unwrap(),
+            "1 solve(x - 2, x, 0) +"
+        );
+
+        for (expr, error) in [
+            (
+                "solve(x^2 + 1, x, 1)",
+                Error::NoConvergence("solve(x^2 + 1, x, 1)".to_owned()),
+            ),
+            ("solve(x, 2, 1)", Error::InvalidAssignment("2".to_owned())),
+            (
+                "solve(x, x)",
+                Error::ArgumentCount {
+                    function: "solve".to_owned(),
+                    count: 2,
+                },
+            ),
+            (
+                "solve(x - 1, x, 0",
+                Error::UnmatchedParen {
+                    bracket: '(',
+                    position: 5,
+                },
+            ),
+            (
+                "solve(x +, x, 0)",
+                Error::TrailingOperator {
+                    operator: '+',
+                    position: 2,
+                },
+            ),
+            ("solve(y - 1, x, 0)", Error::UnknownVariable("y".to_owned())),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &Settings::default()).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_integrate() {
+        let settings = Settings {
+            decimals: Some(8),
+            ..Default::default()
+        };
+        let mut context = Context::default();
+        context.assign("k", Number::Int(3));
+
+        for (expr, result) in [
+            ("integrate(x^2, x, 0, 3)", "9"),
+            ("integrate(k * t, t, 0, 2)", "6"),
+            ("integrate(x^2, x, 3, 0)", "-9"),
+            ("integrate(x, x, 1, 1)", "0"),
+            ("integrate(1 / x, x, 1, 2)", "0.69314718"),
+            ("integrate(sqrt(1 - x^2), x, -1, 1)", "1.57079633"),
+            ("integrate(x^3 - 2 * x, x, -1, 2)", "0.75"),
+            ("integrate(integrate(x * y, y, 0, 1), x, 0, 2)", "1"),
+        ] {
+            let outcome = estimate_expression_in(expr, &settings, &context);
+            assert!(verify_expression(expr, &settings, &context, &outcome).is_none());
+            assert_eq!(outcome.unwrap().result.to_string(), result, "{expr}");
+        }
+
+        // Integers keep exact integrals, others get the decimals of the integer mode
+        let integers = Settings::default();
+        let exact = estimate_expression("integrate(3 * x^2, x, 0, 2)", &integers).unwrap();
+        assert_eq!(exact.result, Number::Int(8));
+        assert!(exact.warnings.is_empty());
+        for (expr, result) in [
+            ("integrate(x, x, 0, 3)", "4.5"),
+            ("integrate(x^2, x, 0, 1)", "0.33333333333333333333"),
+        ] {
+            let evaluation = estimate_expression(expr, &integers).unwrap();
+            assert_eq!(evaluation.result.to_string(), result, "{expr}");
+            assert!(evaluation.warnings.is_empty(), "{expr}");
+        }
+        let settings = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("integrate(x, x, 0, 3)", &settings).unwrap_err(),
+            Error::InexactCall("integrate(x, x, 0, 3)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("integrate(1 / x, x, 0, 1)", &integers).unwrap_err(),
+            Error::DivisionByZero("1 / 0".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("integrate(x, x, 0)", &integers).unwrap_err(),
+            Error::ArgumentCount {
+                function: "integrate".to_owned(),
+                count: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_sum() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+        context.assign("n", Number::Int(4));
+
+        for (expr, result) in [
+            ("sum(i, 1, 100, i^2)", "338350"),
+            ("prod(i, 1, 10, i)", "3628800"),
+            ("sum(k, 1, n, 2 * k - 1)", "16"),
+            ("sum(k, -2, 2, k^3)", "0"),
+            ("sum(k, 1, 0, k) + prod(k, 5, 1, k)", "1"),
+            ("sum(k, 1, 3, sum(j, 1, k, j))", "10"),
+            ("prod(i, 1, 20, i)", "2432902008176640000"),
+            ("sum(k, 1, 3, k * i)", "6i"),
+        ] {
+            let outcome = estimate_expression_in(expr, &settings, &context);
+            assert!(verify_expression(expr, &settings, &context, &outcome).is_none());
+            assert_eq!(outcome.unwrap().result.to_string(), result, "{expr}");
+        }
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("sum(k, 1, 4, 1 / k)", &decimals)
+                .unwrap()
+                .result
+                .to_string(),
+            "2.0833"
+        );
+        assert_eq!(
+            to_rpn_string("2 * sum(k,1,3,k)").unwrap(),
+            "2 sum(k, 1, 3, k) *"
+        );
+
+        for (expr, error) in [
+            (
+                "sum(k, 1, 2.5, k)",
+                Error::FractionalOperand("sum(k, 1, 2.5, k)".to_owned()),
+            ),
+            (
+                "sum(k, 1, 10^7, k)",
+                Error::TooManyTerms("sum(k, 1, 10^7, k)".to_owned()),
+            ),
+            ("prod(1, 1, 3, k)", Error::InvalidAssignment("1".to_owned())),
+            (
+                "prod(k, 1, k)",
+                Error::ArgumentCount {
+                    function: "prod".to_owned(),
+                    count: 3,
+                },
+            ),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("sum(k, 1, 5 / 2, k)", &decimals).unwrap_err(),
+            Error::FractionalOperand("sum(k, 1, 5 / 2, k)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_statistics() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        let statistic = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, integer, decimal) in [
+            ("mean(3, 5, 9, 12)", "7.25", "7.25"),
+            ("mean(-7)", "-7", "-7"),
+            ("median(9, 1, 5)", "5", "5"),
+            ("median(4, 1, 3, 2)", "2.5", "2.5"),
+            (
+                "var(2, 4, 4, 4, 5, 5, 7, 9)",
+                "4.57142857142857142857",
+                "4.5714",
+            ),
+            (
+                "stdev(2, 4, 4, 4, 5, 5, 7, 9)",
+                "2.13808993529939507748",
+                "2.1381",
+            ),
+            ("stdev(1, 3)", "1.4142135623730950488", "1.4142"),
+            ("sum(1, 2, 3, 4, 5)", "15", "15"),
+            ("sum(2)", "2", "2"),
+        ] {
+            assert_eq!(statistic(expr, &settings), integer, "{expr}");
+            assert_eq!(statistic(expr, &decimals), decimal, "{expr}");
+        }
+        // A variable name first in four arguments makes an iterated sum
+        assert_eq!(statistic("sum(k, 1, 4, k)", &settings), "10");
+        assert_eq!(statistic("sum(1, 1, 4, 1)", &settings), "7");
+        assert_eq!(
+            to_rpn_string("sum(1, 2) + mean(3, 4, 5)").unwrap(),
+            "1 2 sum/2 3 4 5 mean/3 +"
+        );
+
+        // Integers keep integer statistics, the others get the decimals of the integer mode
+        let evaluation = estimate_expression("mean(2, 4)", &settings).unwrap();
+        assert_eq!(evaluation.result, Number::Int(3));
+        assert!(evaluation.warnings.is_empty());
+        assert!(
+            estimate_expression("mean(1, 2)", &settings)
+                .unwrap()
+                .warnings
+                .is_empty()
+        );
+        assert_eq!(
+            estimate_expression(
+                "stdev(1, 3)",
+                &Settings {
+                    exact: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap_err(),
+            Error::InexactCall("stdev(1, 3)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("var(5)", &settings).unwrap_err(),
+            Error::ArgumentCount {
+                function: "var".to_owned(),
+                count: 1,
+            }
+        );
+        assert_eq!(
+            estimate_expression("median(1, i)", &settings).unwrap_err(),
+            Error::ComplexOperand("median(1, i)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_verify_expression() {
+        let settings = Settings::default();
+        for expr in [
+            HARD_EXPR,
+            HARDER_EXPR,
+            "2^3^2 - -7 / 2",
+            "9223372036854775807 + 1",
+            "0 ? 1/0 : 5",
+            "0 && 1/0 || 1",
+        ] {
+            let outcome = estimate_expression(expr, &settings);
+
+            assert!(
+                verify_expression(expr, &settings, &Context::default(), &outcome).is_none(),
+                "{expr}"
+            );
+        }
+
+        let wrong = estimate_expression("1 + 1", &settings);
+        assert_eq!(
+            verify_expression("1 + 2", &settings, &Context::default(), &wrong),
+            Some(Ok(Number::Int(3)))
+        );
+    }
+
+    #[test]
+    fn test_absolute_value_bars() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("|3 - 5| + 3", &settings)
+                .unwrap()
+                .result,
+            5
+        );
+        assert_eq!(estimate_expression("-|-4|", &settings).unwrap().result, -4);
+        assert_eq!(
+            estimate_expression("||2-5| - 10|", &settings)
+                .unwrap()
+                .result,
+            7
+        );
+        assert_eq!(
+            estimate_expression("2*|1-4|^2", &settings).unwrap().result,
+            18
+        );
+        assert_eq!(
+            explain_grouping("2*|1-4|^2").unwrap(),
+            Some("2*(|1-4|^2)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("|-9223372036854775807 - 1|", &settings).unwrap_err(),
+            Error::Overflow("|-9223372036854775808|".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_absolute_value_bars_invalid() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("|1 - 2", &settings).unwrap_err(),
+            unexpected("|", 0)
+        );
+        assert_eq!(
+            estimate_expression("||", &settings).unwrap_err(),
+            Error::TrailingOperator {
+                operator: '|',
+                position: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_roots() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("√16", &settings).unwrap().result, 4);
+        assert_eq!(
+            estimate_expression("∛27 + √2^4", &settings).unwrap().result,
+            7
+        );
+        assert_eq!(estimate_expression("∛-27", &settings).unwrap().result, -3);
+        assert_eq!(
+            estimate_expression("-√(3*3)", &settings).unwrap().result,
+            -3
+        );
+        assert_eq!(
+            estimate_expression("2√4", &settings).unwrap_err(),
+            unexpected("√", 1)
+        );
+        assert_eq!(
+            estimate_expression("√-4", &settings).unwrap_err(),
+            Error::NegativeRoot(Number::Int(-4))
+        );
+    }
+
+    #[test]
+    fn test_inexact_roots() {
+        let evaluation = estimate_expression("√17", &Settings::default()).unwrap();
+
+        assert_eq!(evaluation.result, 4);
+        assert_eq!(
+            evaluation.warnings,
+            vec![Warning::TruncatedRoot("√".to_owned(), Number::Int(17))]
+        );
+        assert_eq!(
+            estimate_expression(
+                "∛-30",
+                &Settings {
+                    exact: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap_err(),
+            Error::InexactRoot("∛".to_owned(), Number::Int(-30))
+        );
+    }
+
+    #[test]
+    fn test_square_and_curly_brackets() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("2*[3 + (4-1)]", &settings)
+                .unwrap()
+                .result,
+            12
+        );
+        assert_eq!(
+            estimate_expression("{2 * [1 + |2 - 5|]} - 1", &settings)
+                .unwrap()
+                .result,
+            7
+        );
+        assert_eq!(
+            estimate_expression("2*[3 + (4-1])", &settings).unwrap_err(),
+            Error::MismatchedBracket {
+                opener: '(',
+                opened: 7,
+                closer: ']',
+                position: 11
+            }
+        );
+        assert_eq!(
+            estimate_expression("{1 + |2}|", &settings).unwrap_err(),
+            Error::MismatchedBracket {
+                opener: '|',
+                opened: 5,
+                closer: '}',
+                position: 7
+            }
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("40 * 12  # weekly pay", &settings)
+                .unwrap()
+                .result,
+            480
+        );
+        assert_eq!(
+            estimate_expression("8 / 2 // halved", &settings)
+                .unwrap()
+                .result,
+            4
+        );
+        assert_eq!(
+            estimate_expression("3 + # missing operand", &settings).unwrap_err(),
+            Error::TrailingOperator {
+                operator: '+',
+                position: 2
+            }
+        );
+        assert_eq!(
+            estimate_expression("# only a comment", &settings).unwrap_err(),
+            Error::EmptyExpression
+        );
+    }
+
+    #[test]
+    fn test_decimal_separators() {
+        for (expr, normalized) in [
+            ("1,5 + 2", "1.5 + 2"),
+            ("1.234,5", "1_234.5"),
+            ("1.234.567", "1_234_567"),
+            // Commas followed by a space separate arguments, short dots stay decimal points
+            ("max(1,5, 2)", "max(1.5, 2)"),
+            ("max(1, 5)", "max(1, 5)"),
+            ("2.5 * 3", "2.5 * 3"),
+            ("1.2345", "1.2345"),
+        ] {
+            assert_eq!(
+                normalize_separators(expr, DecimalSeparator::Comma),
+                normalized,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            normalize_separators("max(1,5)", DecimalSeparator::Point),
+            "max(1,5)"
+        );
+
+        let decimals = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression(
+                &normalize_separators("1.234,5 * 2", DecimalSeparator::Comma),
+                &decimals
+            )
+            .unwrap()
+            .result
+            .to_string(),
+            "2469"
+        );
+    }
+
+    #[test]
+    fn test_fractions() {
+        let decimals = |decimals| Settings {
+            decimals: Some(decimals),
+            ..Default::default()
+        };
+        let fraction = |expr: &str, bound| {
+            let n = estimate_expression(expr, &decimals(20)).unwrap().result;
+            to_fraction(&n, bound)
+        };
+
+        assert_eq!(fraction("0.3333", 1000).unwrap(), "1/3");
+        assert_eq!(fraction("0.3333", 100_000).unwrap(), "3333/10000");
+        assert_eq!(fraction("-0.75", 1000).unwrap(), "-3/4");
+        assert_eq!(fraction("22 / 7", 1000).unwrap(), "22/7");
+        assert_eq!(fraction("pi", 10).unwrap(), "22/7");
+        assert_eq!(fraction("pi", 1000).unwrap(), "355/113");
+        assert_eq!(fraction("1.999", 10).unwrap(), "2");
+        assert_eq!(fraction("0.001", 10).unwrap(), "0");
+        // Semiconvergents are nearer than the last convergent which fits
+        assert_eq!(fraction("0.2777", 5).unwrap(), "1/4");
+        assert_eq!(fraction("0.35", 5).unwrap(), "1/3");
+        assert_eq!(fraction("1.5", 1).unwrap(), "1");
+
+        // Integers are written as they are
+        assert_eq!(fraction("3", 1000), None);
+        assert_eq!(to_fraction(&Number::Int(3), 1000), None);
+        assert_eq!(fraction("0.5", 0), None);
+        let decimal = estimate_expression("1 / 3", &decimals(4)).unwrap().result;
+        assert_eq!(to_fraction(&decimal, 1000).unwrap(), "1/3");
+    }
+
+    #[test]
+    fn test_previous_result() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+        context.push(Number::Int(120));
+
+        assert_eq!(
+            estimate_expression_in("_ - 30", &settings, &context)
+                .unwrap()
+                .result,
+            90
+        );
+        assert_eq!(
+            estimate_expression_in("(_ + _) / -_", &settings, &context)
+                .unwrap()
+                .result,
+            -2
+        );
+        assert_eq!(
+            estimate_expression_in("2 _", &settings, &context).unwrap_err(),
+            unexpected("_", 2)
+        );
+        assert_eq!(
+            estimate_expression("_ + 1", &settings).unwrap_err(),
+            Error::NoPreviousResult
+        );
+        assert!(
+            verify_expression(
+                "_ * 2",
+                &settings,
+                &context,
+                &Ok(estimate_expression_in("_ * 2", &settings, &context).unwrap())
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_answers_stack() {
+        let settings = Settings::default();
+        let mut context = Context::new(3);
+        for n in [1, 2, 3, 4] {
+            context.push(Number::Int(n));
+        }
+
+        assert_eq!(
+            estimate_expression_in("ans(1) * 100 + ans(2) * 10 + ans(3)", &settings, &context)
+                .unwrap()
+                .result,
+            432
+        );
+        assert_eq!(
+            estimate_expression_in("ans(4)", &settings, &context).unwrap_err(),
+            Error::NoAnswer(4)
+        );
+        assert_eq!(
+            estimate_expression_in("ans(0)", &settings, &context).unwrap_err(),
+            Error::NoAnswer(0)
+        );
+        assert_eq!(
+            estimate_expression_in("ans * 2 + ans(2)", &settings, &context)
+                .unwrap()
+                .result,
+            11
+        );
+        assert!(matches!(
+            estimate_expression_in("ans(", &settings, &context),
+            Err(Error::InvalidExpression { .. })
+        ));
+        assert!(matches!(
+            estimate_expression_in("foo(1)", &settings, &context),
+            Err(Error::UnknownFunction { .. })
+        ));
+
+        context.clear();
+        assert_eq!(
+            estimate_expression_in("_", &settings, &context).unwrap_err(),
+            Error::NoPreviousResult
+        );
+        // Whether it was written `_` or `ans`, the message names neither
+        assert_eq!(
+            estimate_expression_in("ans", &settings, &context)
+                .unwrap_err()
+                .to_string(),
+            "There is no previous result to refer to"
+        );
+    }
+
+    #[test]
+    fn test_bare_ans() {
+        let settings = Settings::default();
+        let mut context = Context::new(3);
+        context.push(Number::Int(5));
+        context.push(Number::Int(7));
+
+        // `ans` is the previous result wherever a value goes
+        for (expr, result) in [
+            ("ans", 7),
+            ("ans - ans(1) + _", 7),
+            ("max(ans, ans(2) * 2)", 10),
+            ("2 * ans", 14),
+            ("x * ans where x = 3", 21),
+            ("ans > 6 ? ans : 0", 7),
+        ] {
+            assert_eq!(
+                estimate_expression_in(expr, &settings, &context)
+                    .unwrap()
+                    .result,
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_rpn_in("ans 2 *", &settings, &context)
+                .unwrap()
+                .result,
+            14
+        );
+
+        // A compiled expression and a defined function read it when they are evaluated
+        let expression = CompiledExpression::new("ans + 1").unwrap();
+        context.push(Number::Int(10));
+        assert_eq!(expression.eval(&settings, &context).unwrap().result, 11);
+        context.define("scaled", &["k"], "k * ans").unwrap();
+        assert_eq!(
+            estimate_expression_in("scaled(3)", &settings, &context)
+                .unwrap()
+                .result,
+            30
+        );
+    }
+
+    #[test]
+    fn test_modulo() {
+        let settings = Settings::default();
+
+        assert_eq!(estimate_expression("17 % 5", &settings).unwrap().result, 2);
+        assert_eq!(
+            estimate_expression("2 + 17 % 5 * 3", &settings)
+                .unwrap()
+                .result,
+            8
+        );
+        assert_eq!(
+            estimate_expression("100 % 7 % 4", &settings)
+                .unwrap()
+                .result,
+            2
+        );
+        // The remainder takes the sign of the dividend, like the division truncates toward zero
+        assert_eq!(
+            estimate_expression("-17 % 5", &settings).unwrap().result,
+            -2
+        );
+        assert_eq!(estimate_expression("17 % -5", &settings).unwrap().result, 2);
+        assert_eq!(
+            estimate_expression("-17 % -5", &settings).unwrap().result,
+            -2
+        );
+        assert_eq!(
+            estimate_expression("-9223372036854775807 - 1 % -1", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MIN) + 1
+        );
+        assert_eq!(
+            estimate_expression("(-9223372036854775807 - 1) % -1", &settings)
+                .unwrap()
+                .result,
+            0
+        );
+        assert_eq!(
+            estimate_expression(
+                "(170141183460469231731687303715884105727 + 1) % 10",
+                &Settings {
+                    overflow: Overflow::Promote,
+                    int_width: IntWidth::W128,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .result,
+            8
+        );
+        assert_eq!(
+            estimate_expression("5 % % 2", &settings).unwrap_err(),
+            unexpected("%", 4)
+        );
+    }
+
+    #[test]
+    fn test_function_calls() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("sqrt(16) + max(3, 7)", &settings)
+                .unwrap()
+                .result,
+            11
+        );
+        assert_eq!(
+            estimate_expression("min(4, max(1, 2), -3) * 2", &settings)
+                .unwrap()
+                .result,
+            -6
+        );
+        assert_eq!(
+            estimate_expression("abs(-5) + max(1)", &settings)
+                .unwrap()
+                .result,
+            6
+        );
+        assert_eq!(
+            estimate_expression("sqrt(17)", &settings).unwrap().warnings,
+            vec![Warning::TruncatedRoot("√".to_owned(), Number::Int(17))]
+        );
+        assert_eq!(
+            estimate_expression(
+                "sqrt(17)",
+                &Settings {
+                    exact: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap_err(),
+            Error::InexactRoot("√".to_owned(), Number::Int(17))
+        );
+        assert_eq!(
+            estimate_expression("sqrt(1, 2)", &settings).unwrap_err(),
+            Error::ArgumentCount {
+                function: "sqrt".to_owned(),
+                count: 2
+            }
+        );
+        assert_eq!(
+            estimate_expression("3, 4", &settings).unwrap_err(),
+            unexpected(",", 1)
+        );
+        assert_eq!(
+            estimate_expression("max(3,, 4)", &settings).unwrap_err(),
+            unexpected(",", 6)
+        );
+        assert!(matches!(
+            estimate_expression("max 3", &settings).unwrap_err(),
+            Error::InvalidExpression { .. }
+        ));
+    }
+
+    #[test]
+    fn test_logarithms_and_exponential() {
+        let settings = Settings::default();
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            estimate_expression("log10(1000)", &exact).unwrap().result,
+            3
+        );
+        assert_eq!(
+            estimate_expression("log2(1024)", &exact).unwrap().result,
+            10
+        );
+        assert_eq!(estimate_expression("log(3, 81)", &exact).unwrap().result, 4);
+        assert_eq!(
+            estimate_expression("ln(1) + exp(0)", &exact)
+                .unwrap()
+                .result,
+            1
+        );
+
+        // Results with a fraction are decimals, as are fractional arguments
+        for (expr, result) in [
+            ("ln(10)", "2.30258509299404568402"),
+            ("ln(2.5)", "0.91629073187415506518"),
+            ("exp(-3)", "0.04978706836786394298"),
+            ("exp(0.5)", "1.64872127070012814685"),
+            ("log2(0.25)", "-2"),
+            ("log(2.5, 10)", "2.51294159473206005886"),
+            ("log10(999) + ln(10) + exp(2)", "12.69120668015067821994"),
+        ] {
+            let evaluation = estimate_expression(expr, &settings).unwrap();
+            assert_eq!(evaluation.result.to_string(), result, "{expr}");
+            assert!(evaluation.warnings.is_empty(), "{expr}");
+        }
+        assert_eq!(
+            estimate_expression("ln(10)", &exact).unwrap_err(),
+            Error::InexactCall("ln(10)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("log(3, 10)", &exact).unwrap_err(),
+            Error::InexactCall("log(3, 10)".to_owned())
+        );
+
+        assert_eq!(
+            estimate_expression("ln(-1)", &settings).unwrap_err(),
+            Error::MathDomain("ln(-1)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("log2(0)", &settings).unwrap_err(),
+            Error::MathDomain("log2(0)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("log(1, 5)", &settings).unwrap_err(),
+            Error::MathDomain("log(1, 5)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("log(81)", &settings).unwrap_err(),
+            Error::ArgumentCount {
+                function: "log".to_owned(),
+                count: 1
+            }
+        );
+
+        assert_eq!(
+            estimate_expression("exp(50)", &settings)
+                .unwrap()
+                .result
+                .to_string(),
+            "5184705528587072464087.45332293348538482747"
+        );
+        assert_eq!(
+            estimate_expression("ln(exp(200))", &settings)
+                .unwrap()
+                .result
+                .to_string(),
+            "200"
+        );
+        assert_eq!(
+            estimate_expression("exp(5000)", &settings).unwrap_err(),
+            Error::Overflow("exp(5000)".to_owned())
+        );
+
+        // With decimals the functions are computed to the configured precision
+        let decimals = Settings {
+            decimals: Some(30),
+            ..Default::default()
+        };
+        for (expr, result) in [
+            ("ln(2)", "0.693147180559945309417232121458"),
+            ("ln(0.5)", "-0.693147180559945309417232121458"),
+            ("ln(10)", "2.302585092994045684017991454684"),
+            ("exp(1)", "2.718281828459045235360287471353"),
+            ("exp(-1)", "0.367879441171442321595523770161"),
+            ("log(2, 10)", "3.321928094887362347870319429489"),
+            ("log10(2)", "0.301029995663981195213738894724"),
+            ("log10(1000)", "3"),
+            ("log(0.5, 8)", "-3"),
+        ] {
+            let evaluation = estimate_expression(expr, &decimals).unwrap();
+            assert_eq!(evaluation.result.to_string(), result, "{expr}");
+            assert!(evaluation.warnings.is_empty(), "{expr}");
+        }
+        assert_eq!(
+            estimate_expression("ln(-1)", &decimals).unwrap_err(),
+            Error::MathDomain("ln(-1)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_trigonometric_functions() {
+        let settings = Settings::default();
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+
+        // Plain numbers are radians, which `rad` marks, and quantities are angles
+        for (expr, result) in [
+            ("sin(0)", "0"),
+            ("cos(0)", "1"),
+            ("sin(0.5rad)", "0.47942553860420300027"),
+            ("cos(1e10)", "0.87311962267685600118"),
+            ("tan(-1.5 radians)", "-14.10141994717171938765"),
+            ("cos(pi)", "-1"),
+            ("sin(90deg)", "1"),
+            ("cos(180 deg)", "-1"),
+            ("sin(-270 degrees)", "1"),
+            ("sin(30°)", "0.5"),
+            ("sin(20 deg)", "0.34202014332566873304"),
+            ("tan(45 deg) + cos(5400 arcmin)", "1"),
+        ] {
+            let evaluation = estimate_expression(expr, &settings).unwrap();
+            assert_eq!(evaluation.result.to_string(), result, "{expr}");
+            assert!(evaluation.warnings.is_empty(), "{expr}");
+        }
+        assert_eq!(
+            estimate_expression("tan(90deg)", &settings).unwrap_err(),
+            Error::MathDomain("tan(90 deg)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("sin(3 m)", &settings).unwrap_err(),
+            Error::UnitOperand("sin(3 m)".to_owned())
+        );
+
+        // Multiples of a right angle are the only exact angles
+        assert_eq!(
+            estimate_expression("sin(90deg) + cos(0)", &exact)
+                .unwrap()
+                .result,
+            2
+        );
+        for expr in ["sin(1)", "tan(45 deg)"] {
+            assert_eq!(
+                estimate_expression(expr, &exact).unwrap_err(),
+                Error::InexactCall(expr.to_owned()),
+            );
+        }
+
+        let decimals = Settings {
+            decimals: Some(30),
+            ..Default::default()
+        };
+        for (expr, result) in [
+            ("sin(1)", "0.84147098480789650665250232163"),
+            ("cos(1)", "0.540302305868139717400936607443"),
+            ("tan(1)", "1.557407724654902230506974807458"),
+            ("atan(3)", "1.249045772398254425829917077281"),
+            ("atan(-0.5)", "-0.463647609000806116214256231461"),
+        ] {
+            let evaluation = estimate_expression(expr, &decimals).unwrap();
+            assert_eq!(evaluation.result.to_string(), result, "{expr}");
+        }
+
+        // The arctangent comes back in radians
+        assert_eq!(
+            estimate_expression("4 * atan(1)", &settings)
+                .unwrap()
+                .result
+                .to_string(),
+            "3.14159265358979323848"
+        );
+        assert_eq!(estimate_expression("atan(0)", &exact).unwrap().result, 0);
+        assert_eq!(
+            estimate_expression("atan(2)", &exact).unwrap_err(),
+            Error::InexactCall("atan(2)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(split_statements("x = 3; x * 2"), ["x = 3", " x * 2"]);
+        assert_eq!(split_statements("1 + 2"), ["1 + 2"]);
+        assert_eq!(split_statements(""), [""]);
+        // Empty statements are kept, for the caller to skip
+        assert_eq!(split_statements(";1;;2;"), ["", "1", "", "2", ""]);
+        // Within brackets `;` is part of the statement
+        assert_eq!(
+            split_statements("max(1; 2); [3; 4]; 5"),
+            ["max(1; 2)", " [3; 4]", " 5"]
+        );
+        assert_eq!(split_statements("(1; 2"), ["(1; 2"]);
+        // The comment goes with the last statement, a `;` in it separating nothing
+        assert_eq!(
+            split_statements("1; 2 # three; four"),
+            ["1", " 2 # three; four"]
+        );
+        assert_eq!(split_statements("1 // 2; 3"), ["1 // 2; 3"]);
+        assert_eq!(split_statements("2 × 3; 6 ÷ 2"), ["2 × 3", " 6 ÷ 2"]);
+    }
+
+    #[test]
+    fn test_variables() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+
+        assert_eq!(
+            parse_statement("x = 3 * 7").unwrap(),
+            Statement::Assignment("x", "3 * 7")
+        );
+        assert_eq!(
+            parse_statement("x + 2").unwrap(),
+            Statement::Expression("x + 2")
+        );
+        assert_eq!(
+            parse_statement("x + 2 # not = an assignment").unwrap(),
+            Statement::Expression("x + 2 # not = an assignment")
+        );
+        assert_eq!(
+            parse_statement("3 = 4").unwrap_err(),
+            Error::InvalidAssignment("3".to_owned())
+        );
+        assert_eq!(
+            parse_statement("sqrt = 4").unwrap_err(),
+            Error::InvalidAssignment("sqrt".to_owned())
+        );
+        assert_eq!(
+            parse_statement("ans = 4").unwrap_err(),
+            Error::InvalidAssignment("ans".to_owned())
+        );
+
+        assert_eq!(
+            estimate_expression_in("x + 2", &settings, &context).unwrap_err(),
+            Error::UnknownVariable("x".to_owned())
+        );
+        context.assign("x", Number::Int(21));
+        context.assign("rate_2", Number::Int(3));
+        assert_eq!(
+            estimate_expression_in("x + 2", &settings, &context)
+                .unwrap()
+                .result,
+            23
+        );
+        assert_eq!(
+            estimate_expression_in("-x * rate_2 + max(x, 30)", &settings, &context)
+                .unwrap()
+                .result,
+            -33
+        );
+        assert_eq!(
+            estimate_expression_in("x rate_2", &settings, &context).unwrap_err(),
+            unexpected("rate_2", 2)
+        );
+        context.assign("x", Number::Int(1));
+        assert_eq!(
+            estimate_expression_in("x", &settings, &context)
+                .unwrap()
+                .result,
+            1
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+
+        // The integer mode computes with the decimals of constants
+        for (expr, result) in [
+            ("2 * pi * 5", "31.4159265358979323846"),
+            ("e + tau + phi", "10.61950112438852656049"),
+            ("floor(tau)", "6"),
+            ("round(pi, 2)", "3.14"),
+            ("pi > 3", "1"),
+        ] {
+            let evaluation = estimate_expression_in(expr, &settings, &context).unwrap();
+            assert_eq!(evaluation.result.to_string(), result, "{expr}");
+            assert!(evaluation.warnings.is_empty(), "{expr}");
+        }
+        assert_eq!(
+            estimate_expression_in(
+                "tau",
+                &Settings {
+                    exact: true,
+                    ..Default::default()
+                },
+                &context
+            )
+            .unwrap_err(),
+            Error::InexactConstant("tau".to_owned())
+        );
+
+        context.assign("e", Number::Int(10));
+        let evaluation = estimate_expression_in("e * 2", &settings, &context).unwrap();
+        assert_eq!(evaluation.result, 20);
+        assert!(evaluation.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+
+        assert_eq!(
+            parse_statement("f(x) = x^2 + 1").unwrap(),
+            Statement::Definition {
+                name: "f",
+                params: vec!["x"],
+                body: "x^2 + 1"
+            }
+        );
+        for invalid in [
+            "f(x, x) = x",
+            "f(1) = 2",
+            "f(x = x",
+            "sqrt(x) = x",
+            "f() = 1",
+        ] {
+            assert!(
+                matches!(parse_statement(invalid), Err(Error::InvalidAssignment(_))),
+                "{invalid}"
+            );
+        }
+
+        context.define("f", &["x"], "x^2 + 1").unwrap();
+        context.define("f", &["x", "y"], "x * y").unwrap();
+        context
+            .define("g", &["a", "b"], "f(a) - f(b, 3) + x")
+            .unwrap();
+        context.assign("x", Number::Int(100));
+        assert_eq!(
+            estimate_expression_in("f(3) + f(2, 5)", &settings, &context)
+                .unwrap()
+                .result,
+            20
+        );
+        // Parameters shadow the variables of the same name
+        assert_eq!(
+            estimate_expression_in("g(2, 1) + x", &settings, &context)
+                .unwrap()
+                .result,
+            5 - 3 + 100 + 100
+        );
+        assert_eq!(
+            estimate_expression_in("f(1, 2, 3)", &settings, &context).unwrap_err(),
+            Error::UnknownFunction {
+                name: "f".to_owned(),
+                count: 3
+            }
+        );
+
+        context.define("h", &["x"], "sqrt(x)").unwrap();
+        assert_eq!(
+            estimate_expression_in("h(17)", &settings, &context)
+                .unwrap()
+                .warnings,
+            vec![Warning::TruncatedRoot("√".to_owned(), Number::Int(17))]
+        );
+
+        assert_eq!(
+            context.define("k", &["x"], "k(x - 1)").unwrap_err(),
+            Error::RecursiveFunction("k".to_owned())
+        );
+        assert_eq!(
+            context.define("f", &["x"], "g(x, 1)").unwrap_err(),
+            Error::RecursiveFunction("f".to_owned())
+        );
+        assert_eq!(
+            context.define("k", &["x"], "x +").unwrap_err(),
+            Error::TrailingOperator {
+                operator: '+',
+                position: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_environment() {
+        let mut context = Context::default();
+        context.assign("y", Number::Int(2));
+        context.assign("x", Number::Int(-1));
+        context.define("g", &["a", "b"], " a * b ").unwrap();
+        context.define("f", &["x", "y"], "x + y").unwrap();
+        context.define("f", &["x"], "x^2 + 1").unwrap();
+        context.push(Number::Int(7));
+
+        assert_eq!(
+            context.variables(),
+            vec![("x", &Number::Int(-1)), ("y", &Number::Int(2))]
+        );
+        assert_eq!(
+            context.functions(),
+            vec!["f(x) = x^2 + 1", "f(x, y) = x + y", "g(a, b) = a * b"]
+        );
+
+        context.reset();
+        assert!(context.variables().is_empty());
+        assert!(context.functions().is_empty());
+        assert_eq!(context.answers().next(), Some(&Number::Int(7)));
+
+        let names = function_names();
+        assert!(names.is_sorted());
+        for name in ["sqrt", "days_between", "integrate", "prod", "sum"] {
+            assert!(names.contains(&name), "{name}");
+        }
+        assert_eq!(names.iter().filter(|name| **name == "sum").count(), 1);
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("0xFF + 0b1010 - 0o17", &settings)
+                .unwrap()
+                .result,
+            250
+        );
+        assert_eq!(
+            estimate_expression("0Xff * 0B1 + 0O7 + 010", &settings)
+                .unwrap()
+                .result,
+            255 + 7 + 10
+        );
+        assert_eq!(
+            estimate_expression("0x7FFFFFFFFFFFFFFF", &settings)
+                .unwrap()
+                .result,
+            i128::from(i64::MAX)
+        );
+        assert_eq!(
+            estimate_expression("0x8000000000000000", &settings).unwrap_err(),
+            Error::Overflow("literal 9223372036854775808".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("0x1000000000000000000000000000000000", &settings).unwrap_err(),
+            Error::Overflow("literal 0x1000000000000000000000000000000000".to_owned())
+        );
+        for invalid in ["0b102", "0o8", "0xG", "0x"] {
+            assert!(
+                matches!(
+                    estimate_expression(invalid, &settings),
+                    Err(Error::NumberParse(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("(1 << 4) | 0x0F", &settings)
+                .unwrap()
+                .result,
+            31
+        );
+        // Same precedence levels as in C: shifts, and, xor then or
+        assert_eq!(
+            estimate_expression("1 | 6 & 3 xor 1 << 1 + 1", &settings)
+                .unwrap()
+                .result,
+            1 | (6 & 3) ^ (1 << (1 + 1))
+        );
+        assert_eq!(estimate_expression("1 | 2", &settings).unwrap().result, 3);
+        assert_eq!(
+            estimate_expression("~5 & 0xFF", &settings).unwrap().result,
+            250
+        );
+        assert_eq!(
+            estimate_expression("-17 >> 2", &settings).unwrap().result,
+            -5
+        );
+        assert_eq!(
+            estimate_expression("-17 >> 200", &settings).unwrap().result,
+            -1
+        );
+        // A bar inside an absolute value closes it, unless brackets hold the or
+        assert_eq!(
+            estimate_expression("|(-8 | 1)|", &settings).unwrap().result,
+            7
+        );
+
+        assert_eq!(
+            estimate_expression("1 << 63", &settings).unwrap_err(),
+            Error::Overflow("1 << 63".to_owned())
+        );
+        assert_eq!(
+            estimate_expression(
+                "1 << 63",
+                &Settings {
+                    overflow: Overflow::Wrap,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .result,
+            i128::from(i64::MIN)
+        );
+        assert_eq!(
+            estimate_expression(
+                "-3 << 200",
+                &Settings {
+                    overflow: Overflow::Saturate,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .result,
+            i128::from(i64::MIN)
+        );
+        assert_eq!(
+            estimate_expression(
+                "1 << 100",
+                &Settings {
+                    overflow: Overflow::Promote,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .result
+            .to_string(),
+            (1i128 << 100).to_string()
+        );
+        assert_eq!(
+            estimate_expression(
+                "~0",
+                &Settings {
+                    unsigned: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .result,
+            i128::from(u64::MAX)
+        );
+        assert_eq!(
+            estimate_expression("1 << -1", &settings).unwrap_err(),
+            Error::NegativeShift(Number::Int(-1))
+        );
+        assert!(matches!(
+            estimate_expression("1 = 2", &settings),
+            Err(Error::InvalidExpression { .. })
+        ));
+        assert_eq!(
+            estimate_expression("xor 1", &settings).unwrap_err(),
+            unexpected("xor", 0)
+        );
+    }
+
+    #[test]
+    fn test_scientific_literals() {
+        let settings = Settings::default();
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+
+        // A fraction or an exponent makes a decimal, which the exact integer mode takes
+        // when it is an integer
+        for (expr, result) in [
+            ("1.5e3", 1500),
+            ("2E4 + 1", 20001),
+            ("120e-1", 12),
+            ("2.50E+2", 250),
+            ("3.0", 3),
+            ("0e999999", 0),
+            ("-1e18", -1_000_000_000_000_000_000),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings)
+                    .unwrap()
+                    .result
+                    .to_string(),
+                result.to_string(),
+                "{expr}"
+            );
+            assert_eq!(
+                estimate_expression(expr, &exact).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("5.0 / 2", &settings)
+                .unwrap()
+                .result
+                .to_string(),
+            "2.5"
+        );
+        // Fractions are decimals, which the exact integer mode rejects
+        for (expr, result) in [("2E-4", "0.0002"), ("0.5", "0.5"), ("1.25e1", "12.5")] {
+            assert_eq!(
+                estimate_expression(expr, &settings)
+                    .unwrap()
+                    .result
+                    .to_string(),
+                result,
+                "{expr}"
+            );
+            assert_eq!(
+                estimate_expression(expr, &exact).unwrap_err(),
+                Error::FractionalLiteral(expr.to_owned())
+            );
+        }
+        assert_eq!(
+            estimate_expression("1e19", &settings)
+                .unwrap()
+                .result
+                .to_string(),
+            "10000000000000000000"
+        );
+        assert_eq!(
+            estimate_expression("1e19", &exact).unwrap_err(),
+            Error::Overflow("literal 10000000000000000000".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("1e99999999999999999999", &settings).unwrap_err(),
+            Error::Overflow("literal 1e99999999999999999999".to_owned())
+        );
+        // Without digits the letter is not an exponent
+        assert_eq!(
+            estimate_expression("2e", &settings).unwrap_err(),
+            unexpected("e", 1)
+        );
+        assert!(matches!(
+            estimate_expression("1.", &settings),
+            Err(Error::InvalidExpression { .. })
+        ));
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let settings = Settings::default();
+
+        for (expr, result) in [
+            ("1_000_000", 1_000_000),
+            ("0xFF_FF + 0b1010_1010", 0xFFFF + 0b1010_1010),
+            ("1_500.000_5e4", 15_000_005),
+            ("2 * 1_0", 20),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings)
+                    .unwrap()
+                    .result
+                    .to_string(),
+                result.to_string(),
+                "{expr}"
+            );
+        }
+        for (expr, literal) in [
+            ("_100", "_100"),
+            ("1__0", "1_"),
+            ("100_", "100_"),
+            ("3 + 100_ * 2", "100_"),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                Error::MisplacedSeparator(literal.to_owned()),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        let settings = Settings::default();
+
+        assert_eq!(
+            estimate_expression("6 × 7 ÷ 2 − −3", &settings)
+                .unwrap()
+                .result,
+            24
+        );
+        assert_eq!(estimate_expression("√16 × 2", &settings).unwrap().result, 8);
+        assert_eq!(
+            estimate_expression("6 ×", &settings).unwrap_err(),
+            Error::TrailingOperator {
+                operator: '×',
+                position: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let settings = Settings::default();
+
+        for (expr, result) in [
+            ("2 && 3", 1),
+            ("2 && 0", 0),
+            ("0 || -4", 1),
+            ("0 || 0", 0),
+            // && binds tighter than ||, both looser than the bitwise operators
+            ("1 || 0 && 0", 1),
+            ("1 & 2 && 3", 0),
+            ("4 | 0 || 0", 1),
+            ("||-2| - 3| || 0", 1),
+            // The right operand is not evaluated once the left one decides
+            ("0 && 1/0", 0),
+            ("1 || 1/0", 1),
+            ("2 || ans", 1),
+            ("x > 0 && x < 10 where x = 5", 1),
+            ("x > 0 && x < 10 where x = 0", 0),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("1 && && 2", &settings).unwrap_err(),
+            unexpected("&", 5)
+        );
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let settings = Settings::default();
+
+        for (expr, result) in [
+            ("1 ? 2 : 3", 2),
+            ("0 ? 2 : 3", 3),
+            ("5 - 5 ? 2 : 3 + 4", 7),
+            // Right associative, as in C
+            ("0 ? 1 : 0 ? 2 : 3", 3),
+            ("1 ? 0 ? 2 : 3 : 4", 3),
+            ("(0 ? 1 : 2) * 10", 20),
+            ("max(1 ? 4 : 5, 0 ? 6 : 2)", 4),
+            // Only the branch the condition picks is evaluated
+            ("0 ? 1/0 : 5", 5),
+            ("1 ? 5 : 1/0", 5),
+            ("1 ? 0 ? 1/0 : 2 : 1/0", 2),
+            ("(0 ? ans : 3) * (1 ? 4 : ans)", 12),
+            ("(1 ? 0 : 3) ? 1/0 : 6", 6),
+            ("(1 ? 0 : 3) && 1/0 ? 1/0 : 6", 6),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        for (expr, found, position) in [
+            ("1 ? 2", "?", 2),
+            ("1 : 2", ":", 2),
+            ("(1 ? 2) : 3", ")", 6),
+            ("1 ? (2 : 3)", ":", 7),
+            ("1 ? : 3", ":", 4),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                unexpected(found, position),
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("1 ? 1/0 : 5", &settings).unwrap_err(),
+            Error::DivisionByZero("1 / 0".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let settings = Settings::default();
+
+        for (expr, result) in [
+            ("1 < 2", 1),
+            ("2 < 2", 0),
+            ("2 <= 2", 1),
+            ("3 > 2", 1),
+            ("2 >= 3", 0),
+            ("2 == 2", 1),
+            ("2 != 2", 0),
+            // Tighter than the bitwise operators, looser than shifts, as in C
+            ("1 << 2 > 3", 1),
+            ("1 + 1 == 2 & 3", 1),
+            ("1 < 2 == 1", 1),
+            ("5 km > 300 m", 1),
+            ("1 km == 1000 m", 1),
+            ("2 + i == i + 2", 1),
+            ("[1, 2] != [1, 3]", 1),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        for (expr, error) in [
+            ("i < 1", Error::ComplexOperand("i < 1".to_owned())),
+            (
+                "1 m < 1 s",
+                Error::IncompatibleUnits("1 m - 1 s".to_owned()),
+            ),
+            ("1 < < 2", unexpected("<", 4)),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+
+        let settings = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        for (expr, result) in [
+            ("x > 100 ? x * 0.9 : x where x = 120", "108"),
+            ("x > 100 ? x * 0.9 : x where x = 80", "80"),
+            ("0.1 + 0.2 == 0.3", "1"),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings)
+                    .unwrap()
+                    .result
+                    .to_string(),
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            parse_statement("x == 2").unwrap(),
+            Statement::Expression("x == 2")
+        );
+        assert_eq!(
+            parse_statement("y = x >= 2").unwrap(),
+            Statement::Assignment("y", "x >= 2")
+        );
+    }
+
+    #[test]
+    fn test_gcd_and_lcm() {
+        let settings = Settings::default();
+
+        for (expr, result) in [
+            ("gcd(12, 18)", 6),
+            ("gcd(-12, 18)", 6),
+            ("gcd(0, -5)", 5),
+            ("gcd(0, 0)", 0),
+            ("gcd(12, 18, 8)", 2),
+            ("lcm(4, 6)", 12),
+            ("lcm(-4, 6)", 12),
+            ("lcm(4, 0)", 0),
+            ("lcm(2, 3, 4)", 12),
+            ("gcd(7)", 7),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("gcd(-9223372036854775807 - 1, 0)", &settings).unwrap_err(),
+            Error::Overflow("gcd(-9223372036854775808, 0)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("lcm(2^62, 3)", &settings).unwrap_err(),
+            Error::Overflow("lcm(4611686018427387904, 3)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_divmod() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        let divmod = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+
+        // The quotient and the remainder are those of `/` and `%`
+        for (expr, result) in [
+            ("divmod(17, 5)", "[3, 2]"),
+            ("divmod(-17, 5)", "[-3, -2]"),
+            ("divmod(17, -5)", "[-3, 2]"),
+            ("divmod(15, 5)", "[3, 0]"),
+            ("divmod(17, 5) * 5", "[15, 10]"),
+            ("divmod(7.5, 2)", "[3, 1.5]"),
+        ] {
+            assert_eq!(divmod(expr, &settings), result, "{expr}");
+        }
+        assert_eq!(divmod("divmod(7.5, 2)", &decimals), "[3, 1.5]");
+        assert_eq!(divmod("divmod(10, 0.3)", &decimals), "[33, 0.1]");
+        assert!(
+            estimate_expression("divmod(17, 5)", &settings)
+                .unwrap()
+                .warnings
+                .is_empty()
+        );
+
+        for (expr, error) in [
+            (
+                "divmod(17, 0)",
+                Error::DivisionByZero("divmod(17, 0)".to_owned()),
+            ),
+            (
+                "divmod(17)",
+                Error::ArgumentCount {
+                    function: "divmod".to_owned(),
+                    count: 1,
+                },
+            ),
+            (
+                "divmod([1, 2], 2)",
+                Error::MatrixOperand("divmod([1, 2], 2)".to_owned()),
+            ),
+            (
+                "divmod(-9223372036854775807 - 1, -1)",
+                Error::Overflow("-9223372036854775808 / -1".to_owned()),
+            ),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rounding_functions() {
+        let settings = Settings::default();
+
+        for (expr, result) in [
+            ("floor(7)", 7),
+            ("round(-7, 3)", -7),
+            ("trunc(1299, -2)", 1200),
+            ("trunc(-1299, -2)", -1200),
+            ("floor(1201, -2)", 1200),
+            ("floor(-1201, -2)", -1300),
+            ("ceil(1201, -2)", 1300),
+            ("ceil(-1299, -2)", -1200),
+            ("ceil(1200, -2)", 1200),
+            ("round(1249, -2)", 1200),
+            ("round(1250, -2)", 1300),
+            ("round(-1250, -2)", -1300),
+            ("round(49, -2)", 0),
+            ("round(5, -1000)", 0),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("round(1, 2, 3)", &settings).unwrap_err(),
+            Error::ArgumentCount {
+                function: "round".to_owned(),
+                count: 3
+            }
+        );
+        assert_eq!(
+            estimate_expression("ceil(1, -19)", &settings).unwrap_err(),
+            Error::Overflow("ceil(1, -19)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_random_integers() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+
+        for _ in 0..100 {
+            let roll = estimate_expression_in("randint(1, 6)", &settings, &context)
+                .unwrap()
+                .result;
+            assert!((Number::Int(1)..=Number::Int(6)).contains(&roll), "{roll}");
+        }
+        assert_eq!(
+            estimate_expression_in("randint(-3, -3)", &settings, &context)
+                .unwrap()
+                .result,
+            -3
+        );
+        assert_eq!(
+            estimate_expression_in("randint(6, 1)", &settings, &context).unwrap_err(),
+            Error::MathDomain("randint(6, 1)".to_owned())
+        );
+
+        // The same seed draws the same numbers, and so does a snapshot
+        let draw = |context: &Context| {
+            estimate_expression_in("randint(0, 10^9)", &settings, context)
+                .unwrap()
+                .result
+        };
+        context.seed(42);
+        let first = draw(&context);
+        let snapshot = context.snapshot();
+        let second = draw(&context);
+        context.seed(42);
+        assert_eq!(draw(&context), first);
+        assert_eq!(draw(&context), second);
+        assert_eq!(draw(&snapshot), second);
+    }
+
+    #[test]
+    fn test_random_decimals() {
+        let mut context = Context::default();
+        context.seed(7);
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        let draw = |expr: &str, settings: &Settings, context: &Context| {
+            estimate_expression_in(expr, settings, context)
+                .unwrap()
+                .result
+        };
+
+        for _ in 0..100 {
+            let Number::Decimal(value) = draw("rand()", &decimals, &context) else {
+                panic!("rand() is a decimal");
+            };
+            assert_eq!(value.scale, 4);
+            assert!((BigInt::ZERO..BigInt::from(10_000)).contains(&value.units));
+        }
+        // The integer mode draws the digits of its decimals
+        let Number::Decimal(value) = draw("rand()", &Settings::default(), &context) else {
+            panic!("rand() is a decimal");
+        };
+        assert_eq!(value.scale, DEFAULT_DECIMALS);
+        assert!(value.units < BigInt::from(10).pow(DEFAULT_DECIMALS));
+
+        // The same seed draws the same numbers
+        context.seed(42);
+        let first = draw("floor(rand() * 6) + 1", &decimals, &context);
+        let second = draw("rand()", &decimals, &context);
+        context.seed(42);
+        assert_eq!(draw("floor(rand() * 6) + 1", &decimals, &context), first);
+        assert_eq!(draw("rand()", &decimals, &context), second);
+
+        assert_eq!(to_rpn_string("rand() * 6").unwrap(), "rand/0 6 *");
+        for (expr, function, count) in [("rand(1)", "rand", 1), ("max()", "max", 0)] {
+            assert_eq!(
+                estimate_expression(expr, &decimals).unwrap_err(),
+                Error::ArgumentCount {
+                    function: function.to_owned(),
+                    count
+                }
+            );
+        }
+        // Only calls may have no argument
+        assert_eq!(
+            estimate_expression("2 * ()", &decimals).unwrap_err(),
+            unexpected(")", 5)
+        );
+    }
+
+    #[test]
+    fn test_decimal_mode() {
+        let settings = Settings {
+            decimals: Some(10),
+            ..Default::default()
+        };
+        let decimal = |expr| {
+            estimate_expression(expr, &settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+
+        for (expr, result) in [
+            ("0.1 + 0.2", "0.3"),
+            ("1 / 3", "0.3333333333"),
+            ("2 / 3", "0.6666666667"),
+            ("-2 / 3", "-0.6666666667"),
+            ("1.5 * 1.5", "2.25"),
+            ("7.5 % 2", "1.5"),
+            ("2 ^ -2", "0.25"),
+            ("1.1 ^ 2", "1.21"),
+            ("√2", "1.4142135624"),
+            ("∛-2", "-1.2599210499"),
+            ("2.5e-3 * 4", "0.01"),
+            ("0.000000000049", "0"),
+            ("0.000000000051", "0.0000000001"),
+            ("pi", "3.1415926536"),
+            ("round(2.345, 2)", "2.35"),
+            ("floor(-2.5)", "-3"),
+            ("trunc(-2.5)", "-2"),
+            ("max(0.5, 1 / 3)", "0.5"),
+            ("6 & 3", "2"),
+            ("gcd(12, 18.0)", "6"),
+            ("0.5 && 0 ? 1 : 0.25", "0.25"),
+        ] {
+            assert_eq!(decimal(expr), result, "{expr}");
+        }
+
+        assert_eq!(
+            estimate_expression("1.5 & 1", &settings).unwrap_err(),
+            Error::FractionalOperand("1.5 & 1".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("2 ^ 0.5", &settings).unwrap_err(),
+            Error::FractionalOperand("2 ^ 0.5".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("gcd(1.5, 3)", &settings).unwrap_err(),
+            Error::FractionalOperand("gcd(1.5, 3)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("√-2", &settings).unwrap_err(),
+            Error::NegativeRoot(estimate_expression("-2", &settings).unwrap().result)
+        );
+        // Decimals are arbitrary precision, whatever the integer width
+        assert_eq!(decimal("2^70 / 4"), "295147905179352825856");
+    }
+
+    #[test]
+    fn test_decimal_rounding_modes() {
+        for (rounding, results) in [
+            (Rounding::HalfEven, ["0.2", "0.4", "-0.2"]),
+            (Rounding::HalfUp, ["0.3", "0.5", "-0.3"]),
+            (Rounding::Down, ["0.2", "0.4", "-0.2"]),
+            (Rounding::Up, ["0.3", "0.5", "-0.3"]),
+            (Rounding::Floor, ["0.2", "0.4", "-0.3"]),
+            (Rounding::Ceiling, ["0.3", "0.5", "-0.2"]),
+        ] {
+            let settings = Settings {
+                decimals: Some(1),
+                rounding,
+                ..Default::default()
+            };
+            for (expr, result) in ["1 / 4", "9 / 20", "-1 / 4"].into_iter().zip(results) {
+                assert_eq!(
+                    estimate_expression(expr, &settings)
+                        .unwrap()
+                        .result
+                        .to_string(),
+                    result,
+                    "{expr} {rounding:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_complex_numbers() {
+        let settings = Settings::default();
+        let complex = |expr: &str| {
+            estimate_expression(expr, &settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, result) in [
+            ("(3+4i)*(1-2i)", "11-2i"),
+            ("i * i", "-1"),
+            ("(1+i)^3", "-2+2i"),
+            ("(5+5i) / (1+2i)", "3-i"),
+            ("-i", "-i"),
+            ("2 - 3i", "2-3i"),
+            ("4i - 4i", "0"),
+            ("abs(3+4i)", "5"),
+            ("|-5+12i|", "13"),
+            ("i ? 1 : 2", "1"),
+        ] {
+            assert_eq!(complex(expr), result, "{expr}");
+        }
+
+        let evaluation = estimate_expression("(1+i) / 2", &settings).unwrap();
+        assert_eq!(evaluation.result, Number::Int(0));
+        assert_eq!(evaluation.warnings.len(), 1);
+        let evaluation = estimate_expression("|1+i|", &settings).unwrap();
+        assert_eq!(
+            evaluation.warnings,
+            vec![Warning::TruncatedRoot("√".to_owned(), Number::Int(2))]
+        );
+
+        assert_eq!(
+            estimate_expression("2 ^ i", &settings).unwrap_err(),
+            Error::ComplexOperand("2 ^ i".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("max(i, 1)", &settings).unwrap_err(),
+            Error::ComplexOperand("max(i, 1)".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("(1+i) % 2", &settings).unwrap_err(),
+            Error::ComplexOperand("1+i % 2".to_owned())
+        );
+
+        // A variable named i shadows the imaginary unit
+        let mut context = Context::new(10);
+        context.assign("i", Number::Int(3));
+        assert_eq!(
+            estimate_expression_in("i * 2", &settings, &context)
+                .unwrap()
+                .result,
+            Number::Int(6)
+        );
+    }
+
+    #[test]
+    fn test_matrices() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        let matrix = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, result) in [
+            ("[1, 2, 3] + [4, 5, 6]", "[5, 7, 9]"),
+            ("2 * [1, -2]", "[2, -4]"),
+            (
+                "[[1, 2], [3, 4]] * [[5, 6], [7, 8]]",
+                "[[19, 22], [43, 50]]",
+            ),
+            ("[1, 2] * [[1, 2], [3, 4]]", "[7, 10]"),
+            ("[[1, 2], [3, 4]] * [1, 1]", "[3, 7]"),
+            ("[1, 2] * [3, 4]", "[3, 8]"),
+            ("[[1, 1], [1, 0]]^10", "[[89, 55], [55, 34]]"),
+            ("[[1, 2], [3, 4]]^0", "[[1, 0], [0, 1]]"),
+            ("dot([1, 2, 3], [4, 5, 6])", "32"),
+            (
+                "transpose([[1, 2, 3], [4, 5, 6]])",
+                "[[1, 4], [2, 5], [3, 6]]",
+            ),
+            ("transpose([1, 2])", "[[1], [2]]"),
+            ("det([[1, 2], [3, 4]])", "-2"),
+            ("det([[0, 1, 2], [1, 0, 3], [4, -3, 8]])", "-2"),
+            ("det([[1, 2], [2, 4]])", "0"),
+            ("inv([[2, 1], [1, 1]])", "[[1, -1], [-1, 2]]"),
+            ("vec(5)", "[5]"),
+            ("vec([1, 2])", "[[1, 2]]"),
+            ("sqrt([4, 9])", "[2, 3]"),
+            ("-[1, 2]", "[-1, -2]"),
+            // Brackets without a comma only group
+            ("[2] * 3", "6"),
+        ] {
+            assert_eq!(matrix(expr, &settings), result, "{expr}");
+        }
+        assert_eq!(
+            matrix("inv([[1, 2], [3, 4]])", &decimals),
+            "[[-2, 1], [1.5, -0.5]]"
+        );
+        assert_eq!(
+            matrix("[[4, 7], [2, 6]]^-1 * [[4, 7], [2, 6]]", &decimals),
+            "[[1, 0], [0, 1]]"
+        );
+
+        // Integers get the decimals of the integer mode rather than a wrong inverse
+        let evaluation = estimate_expression("inv([[1, 2], [3, 4]])", &settings).unwrap();
+        assert_eq!(evaluation.result.to_string(), "[[-2, 1], [1.5, -0.5]]");
+        assert!(evaluation.warnings.is_empty());
+        assert_eq!(
+            matrix("inv([[1, 2], [3, 4]]) * [[1, 2], [3, 4]]", &settings),
+            "[[1, 0], [0, 1]]"
+        );
+        assert_eq!(
+            estimate_expression(
+                "inv([[1, 2], [3, 4]])",
+                &Settings {
+                    exact: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap_err(),
+            Error::InexactCall("inv([[1, 2], [3, 4]])".to_owned())
+        );
+        let evaluation = estimate_expression("[3, 4] / 2", &settings).unwrap();
+        assert_eq!(evaluation.result.to_string(), "[1, 2]");
+        assert_eq!(evaluation.warnings.len(), 1);
+
+        for (expr, error) in [
+            (
+                "[1, 2] + [1, 2, 3]",
+                Error::DimensionMismatch("[1, 2] + [1, 2, 3]".to_owned()),
+            ),
+            (
+                "[[1, 2], [3, 4]] * [1, 2, 3]",
+                Error::DimensionMismatch("[[1, 2], [3, 4]] * [1, 2, 3]".to_owned()),
+            ),
+            (
+                "[[1, 2], [3]]",
+                Error::DimensionMismatch("vec([1, 2], 3)".to_owned()),
+            ),
+            (
+                "det([[1, 2, 3], [4, 5, 6]])",
+                Error::DimensionMismatch("det([[1, 2, 3], [4, 5, 6]])".to_owned()),
+            ),
+            (
+                "dot([1, 2], [1, 2, 3])",
+                Error::DimensionMismatch("dot([1, 2], [1, 2, 3])".to_owned()),
+            ),
+            (
+                "inv([[1, 2], [2, 4]])",
+                Error::MathDomain("inv([[1, 2], [2, 4]])".to_owned()),
+            ),
+            (
+                "max([1, 2], 3)",
+                Error::MatrixOperand("max([1, 2], 3)".to_owned()),
+            ),
+            (
+                "2 ^ [[1, 0], [0, 1]]",
+                Error::MatrixOperand("2 ^ [[1, 0], [0, 1]]".to_owned()),
+            ),
+            (
+                "[[1, 2], [3, 4]]^-1",
+                Error::NegativeExponent(Number::Int(-1)),
+            ),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+
+        assert_eq!(
+            parse_ast("[1, 2*3] + vec(4)").unwrap().infix(),
+            "[1, 2 * 3] + vec(4)"
+        );
+        assert_eq!(
+            to_radix(
+                &estimate_expression("[10, 255]", &settings).unwrap().result,
+                16
+            ),
+            "[0xA, 0xFF]"
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        let quantity = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, result) in [
+            ("5 km + 300 m", "5.3 km"),
+            ("convert(100 mph, km/h)", "160.9344 km/h"),
+            ("3 min + 30 s", "3.5 min"),
+            ("10 m / 4 s", "2.5 m/s"),
+            ("2 km * 3 km", "6 km^2"),
+            ("sqrt(16 m^2)", "4 m"),
+            ("9.81 m/s^2", "9.81 m/s^2"),
+            ("2 kg * 9.81 m/s^2", "19.62 kg*m/s^2"),
+            ("1 kg / (2 m * 4 s^2)", "0.125 kg/(m*s^2)"),
+            ("5 m^-1", "5 m^-1"),
+            ("-2 kg", "-2 kg"),
+            ("convert(1 kWh, J)", "3600000 J"),
+            ("convert(1 day, h)", "24 h"),
+            ("sum(1 km, 300 m)", "1.3 km"),
+            // Units of no dimension leave a number
+            ("6 km / 2 m", "3000"),
+            ("2 h * 1 Hz", "7200"),
+        ] {
+            assert_eq!(quantity(expr, &decimals), result, "{expr}");
+        }
+        // Integers get the fraction of a conversion
+        for (expr, result) in [
+            ("5 km + 300 m", "5.3 km"),
+            ("1 km + 300 m", "1.3 km"),
+            ("300 m + 1 km", "1300 m"),
+            ("convert(2 h, min)", "120 min"),
+            ("convert(100 mph, km/h)", "160.9344 km/h"),
+            ("convert(1 ft, cm)", "30.48 cm"),
+        ] {
+            assert_eq!(quantity(expr, &settings), result, "{expr}");
+        }
+        let evaluation = estimate_expression("5 km + 300 m", &settings).unwrap();
+        assert!(evaluation.warnings.is_empty());
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("5 km + 300 m", &exact).unwrap_err(),
+            Error::InexactConversion("300 m to km".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("convert(1 mi, km)", &exact).unwrap_err(),
+            Error::InexactCall("convert(1 mi, 1 km)".to_owned())
+        );
+
+        // Variables shadow units, except after a number, and a function name stays a unit
+        // without brackets
+        let mut context = Context::new(10);
+        context.assign("m", Number::Int(3));
+        context.assign("km", Number::Int(5));
+        assert_eq!(
+            estimate_expression_in("m * 2", &settings, &context)
+                .unwrap()
+                .result,
+            Number::Int(6)
+        );
+        for (expr, result) in [("5 m", "5 m"), ("2 km", "2 km"), ("2km + 5 m", "2.005 km")] {
+            let evaluation = estimate_expression_in(expr, &settings, &context);
+            assert_eq!(evaluation.unwrap().result.to_string(), result, "{expr}");
+        }
+        assert_eq!(quantity("2 min * min(1, 2)", &settings), "2 min");
+        assert_eq!(to_rpn_string("5 km + 300 m").unwrap(), "5 km * 300 m * +");
+
+        for (expr, error) in [
+            (
+                "5 km + 3 s",
+                Error::IncompatibleUnits("5 km + 3 s".to_owned()),
+            ),
+            ("5 km + 3", Error::IncompatibleUnits("5 km + 3".to_owned())),
+            (
+                "convert(3 kg, km)",
+                Error::IncompatibleUnits("convert(3 kg, 1 km)".to_owned()),
+            ),
+            ("2 ^ (3 s)", Error::UnitOperand("2 ^ 3 s".to_owned())),
+            ("ln(3 s)", Error::UnitOperand("ln(3 s)".to_owned())),
+            ("sqrt(4 m)", Error::UnitOperand("√(4 m)".to_owned())),
+            ("5 km << 1", Error::UnitOperand("5 km << 1".to_owned())),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_dates() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        let date = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, result) in [
+            ("2024-03-01 + 45 days", "2024-04-15"),
+            ("days_between(2024-01-01, 2024-12-25)", "359"),
+            ("2024-12-25 - 2024-01-01", "359 day"),
+            ("2024-03-01 - 2 weeks", "2024-02-16"),
+            ("1 day + 2024-02-28", "2024-02-29"),
+            ("2023-02-28 + 1 day", "2023-03-01"),
+            ("2024-01-01 + 48 h", "2024-01-03"),
+            ("date(2000, 2, 29) + 366 days", "2001-03-01"),
+            ("days_between(2024-12-25, 2024-01-01)", "-359"),
+            // Without the dashes being glued to the digits it is a subtraction
+            ("2024 - 03 - 01", "2020"),
+        ] {
+            assert_eq!(date(expr, &settings), result, "{expr}");
+        }
+        assert_eq!(date("2024-03-01 + 45 days", &decimals), "2024-04-15");
+        assert_eq!(
+            date("days_between(2024-01-01, 2024-01-31)", &decimals),
+            "30"
+        );
+        assert_eq!(
+            to_rpn_string("2024-03-01 + 45 days").unwrap(),
+            "2024 3 1 date/3 45 days * +"
+        );
+
+        for (expr, error) in [
+            ("2024-02-30", Error::InvalidDate("2024-02-30".to_owned())),
+            (
+                "date(2024, 13, 1)",
+                Error::InvalidDate("2024-13-01".to_owned()),
+            ),
+            (
+                "2024-01-01 + 36 h",
+                Error::FractionalDays("36 h".to_owned()),
+            ),
+            (
+                "2024-01-01 + 3",
+                Error::DateOperand("2024-01-01 + 3".to_owned()),
+            ),
+            (
+                "2024-01-01 + 3 km",
+                Error::IncompatibleUnits("2024-01-01 + 3 km".to_owned()),
+            ),
+            (
+                "2024-01-01 + 2024-01-02",
+                Error::DateOperand("2024-01-01 + 2024-01-02".to_owned()),
+            ),
+            (
+                "sqrt(2024-01-01)",
+                Error::DateOperand("sqrt(2024-01-01)".to_owned()),
+            ),
+            (
+                "-2024-01-01",
+                Error::DateOperand("neg(2024-01-01)".to_owned()),
+            ),
+            (
+                "days_between(2024-01-01, 3)",
+                Error::DateOperand("days_between(2024-01-01, 3)".to_owned()),
+            ),
+            (
+                "9999-12-31 + 1 day",
+                Error::Overflow("9999-12-31 + 1 day".to_owned()),
+            ),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_durations() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(3),
+            ..Default::default()
+        };
+        let duration = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, result) in [
+            ("1:45:30 + 0:20:45", "2:06:15"),
+            ("1:45:30 + 15 min", "2:00:30"),
+            ("0:20:00 - 1:00:00", "-0:40:00"),
+            ("1:00:00 * 3", "3:00:00"),
+            ("100:00:00", "100:00:00"),
+            ("6375 s in hms", "1:46:15"),
+            ("1:30:00 in min", "90 min"),
+            ("1:30:00 / 0:45:00", "2"),
+            ("2024-01-01 + 48:00:00", "2024-01-03"),
+            ("30 minutes + 2 hours in seconds", "9000 s"),
+            // Where an operand is expected `in` is still the inch
+            ("5 in in cm", "12.7 cm"),
+            // Long names and plurals, and fractions without decimal mode
+            ("2.5h in minutes", "150 min"),
+            ("1 hour + 30 minutes", "1.5 h"),
+            ("1.5 hours in hms", "1:30:00"),
+            ("3 feet in centimeters", "91.44 cm"),
+            ("2 pounds in grams", "907.18474 g"),
+        ] {
+            assert_eq!(duration(expr, &settings), result, "{expr}");
+        }
+        assert_eq!(duration("2.5h in minutes", &decimals), "150 min");
+        assert_eq!(duration("2.5 h in hms", &decimals), "2:30:00");
+        assert_eq!(duration("1:00:00 / 7", &decimals), "0:08:34.286");
+        assert_eq!(duration("100 mph in km/h", &decimals), "160.934 km/h");
+        assert_eq!(
+            to_rpn_string("1:45:30 in min").unwrap(),
+            "6330 hms * min in"
+        );
+        assert_eq!(
+            rpn_to_infix("6330 hms * min in").unwrap(),
+            "6330 * hms in min"
+        );
+
+        let evaluation = estimate_expression("90 min in hours", &settings).unwrap();
+        assert_eq!(evaluation.result.to_string(), "1.5 h");
+        assert!(evaluation.warnings.is_empty());
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("90 min in h", &exact).unwrap_err(),
+            Error::InexactConversion("90 min to h".to_owned())
+        );
+        for (expr, error) in [
+            (
+                "5 km in s",
+                Error::IncompatibleUnits("5 km in 1 s".to_owned()),
+            ),
+            ("1:75:00", unexpected(":", 1)),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_angles() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        let angle = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        for (expr, result) in [
+            ("45°30'15\"", "45°30'15\""),
+            ("45d30m15s", "45°30'15\""),
+            ("45°30'", "45°30'0\""),
+            ("45d", "45°0'0\""),
+            ("45°30'15\" + 10°29'45\"", "56°0'0\""),
+            ("-45°30'", "-45°30'0\""),
+            ("1°30' * 2", "3°0'0\""),
+            ("10°0'0\" / 4°0'0\"", "2"),
+            ("90 deg in dms", "90°0'0\""),
+            ("1°30' in arcmin", "90 arcmin"),
+        ] {
+            assert_eq!(angle(expr, &settings), result, "{expr}");
+        }
+        assert_eq!(angle("45°30'15\" in deg", &decimals), "45.5042 deg");
+        assert_eq!(angle("45.5 deg in dms", &decimals), "45°30'0\"");
+        assert_eq!(angle("10°0'0\" / 3", &decimals), "3°20'0\"");
+        assert_eq!(angle("1°0'1\" / 4", &decimals), "0°15'0.25\"");
+        assert_eq!(
+            to_rpn_string("45°30'15\" in deg").unwrap(),
+            "163815 dms * deg in"
+        );
+
+        for (expr, error) in [
+            (
+                "45°30'15\" + 5 m",
+                Error::IncompatibleUnits("45°30'15\" + 5 m".to_owned()),
+            ),
+            (
+                "45°30'15\" + 1",
+                Error::IncompatibleUnits("45°30'15\" + 1".to_owned()),
+            ),
+            (
+                "45°75'",
+                Error::InvalidExpression {
+                    message: "Unknown character °".to_owned(),
+                    position: 2,
+                },
+            ),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap_err(),
+                error,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_physical_constants() {
+        let settings = Settings::default();
+        let decimals = Settings {
+            decimals: Some(4),
+            ..Default::default()
+        };
+        let quantity = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings)
+                .unwrap()
+                .result
+                .to_string()
+        };
+        assert_eq!(quantity("const.c", &settings), "299792458 m/s");
+        assert_eq!(
+            quantity("2 kg * const.c^2", &settings),
+            "179751035747363528 kg*m^2/s^2"
+        );
+        assert_eq!(quantity("const.g", &decimals), "9.8066 m/s^2");
+        assert_eq!(quantity("const.R * 2 K", &decimals), "16.629 J/mol");
+        assert_eq!(
+            quantity("convert(const.atm, kPa)", &decimals),
+            "101.325 kPa"
+        );
+        assert_eq!(
+            parse_ast("const.G * 1e11")
+                .unwrap()
+                .evaluate(
+                    &Settings {
+                        decimals: Some(20),
+                        ..Default::default()
+                    },
+                    &Context::new(10)
+                )
+                .unwrap()
+                .to_string(),
+            "6.6743 m^3/(kg*s^2)"
+        );
+        assert_eq!(to_rpn_string("const.Na * 2").unwrap(), "const.Na 2 *");
+
+        // Constants which are not integers have the decimals of the integer mode
+        let evaluation = estimate_expression("const.g", &settings).unwrap();
+        assert_eq!(evaluation.result.to_string(), "9.80665 m/s^2");
+        assert!(evaluation.warnings.is_empty());
+        assert_eq!(
+            quantity("const.G", &settings),
+            "0.000000000066743 m^3/(kg*s^2)"
+        );
+        assert_eq!(
+            quantity("const.Na", &settings),
+            "602214076000000000000000 mol^-1"
+        );
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("const.h", &exact).unwrap_err(),
+            Error::InexactConstant("const.h".to_owned())
+        );
+        assert!(estimate_expression("const.c", &exact).is_ok());
+        assert_eq!(
+            estimate_expression("const.x", &settings).unwrap_err(),
+            Error::UnknownVariable("const.x".to_owned())
+        );
+        assert!(physical_constants().iter().any(|c| c.name == "Na"));
+    }
+
+    #[test]
+    fn test_narrow_widths() {
+        let settings = |int_width, overflow| Settings {
+            int_width,
+            overflow,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_expression("100 + 28", &settings(IntWidth::W8, Overflow::Checked))
+                .unwrap_err(),
+            Error::Overflow("100 + 28".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("-128", &settings(IntWidth::W8, Overflow::Checked)).unwrap_err(),
+            Error::Overflow("literal 128".to_owned())
+        );
+        assert_eq!(
+            estimate_expression("32767 + 2", &settings(IntWidth::W16, Overflow::Wrap))
+                .unwrap()
+                .result,
+            -32767
+        );
+        assert_eq!(
+            estimate_expression("2^31", &settings(IntWidth::W32, Overflow::Saturate))
+                .unwrap()
+                .result,
+            i128::from(i32::MAX)
+        );
+        let unsigned = Settings {
+            unsigned: true,
+            ..settings(IntWidth::W16, Overflow::Wrap)
+        };
+        assert_eq!(
+            estimate_expression("0 - 1", &unsigned).unwrap().result,
+            65535
+        );
+    }
+
+    #[test]
+    fn test_programmer_literals() {
+        let settings = Settings {
+            int_width: IntWidth::W8,
+            overflow: Overflow::Wrap,
+            programmer: true,
+            ..Default::default()
+        };
+        for (expr, result) in [
+            ("0xFF", -1),
+            ("200", -56),
+            ("-128", -128),
+            ("0x7F + 1", -128),
+        ] {
+            assert_eq!(
+                estimate_expression(expr, &settings).unwrap().result,
+                result,
+                "{expr}"
+            );
+        }
+        assert_eq!(
+            estimate_expression("256", &settings).unwrap_err(),
+            Error::Overflow("literal 256".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_promotion() {
+        let int = Number::Int(2);
+        let big = Number::Big(BigInt::from(2) << 100);
+        let decimal = Number::Decimal(Decimal::from_int(BigInt::from(2), 2));
+        let complex = Number::Complex(Box::new(Complex::i()));
+        let integers = Settings::default();
+        let decimals = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        let operands = [
+            (&int, Kind::Int),
+            (&big, Kind::Big),
+            (&decimal, Kind::Decimal),
+            (&complex, Kind::Complex),
+        ];
+        for (v1, kind1) in operands {
+            for (v2, kind2) in operands {
+                assert_eq!(
+                    Number::promote(&[v1, v2], &integers),
+                    kind1.max(kind2),
+                    "{v1} {v2}"
+                );
+                assert_eq!(
+                    Number::promote(&[v1, v2], &decimals),
+                    kind1.max(kind2).max(Kind::Decimal),
+                    "{v1} {v2}"
+                );
+            }
+        }
+
+        let promote = Settings {
+            overflow: Overflow::Promote,
+            ..Default::default()
+        };
+        let kind = |expr: &str, settings: &Settings| {
+            estimate_expression(expr, settings).unwrap().result.kind()
+        };
+        for (expr, result) in [
+            ("7 / 2", Kind::Int),
+            ("2^100 + 1", Kind::Big),
+            ("2^100 / 2^99", Kind::Int),
+            ("2^100 * i", Kind::Complex),
+            ("(1+i) * (1-i)", Kind::Int),
+        ] {
+            assert_eq!(kind(expr, &promote), result, "{expr}");
+        }
+        for (expr, result) in [
+            ("7 / 2", Kind::Decimal),
+            ("2 * i", Kind::Complex),
+            ("i * i", Kind::Decimal),
+        ] {
+            assert_eq!(kind(expr, &decimals), result, "{expr}");
+        }
+        assert_eq!(
+            estimate_expression("0.5 * i", &decimals).unwrap_err(),
+            Error::ComplexOperand("0.5 * i".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_compiled_expression() {
+        let settings = Settings::default();
+        let expression = CompiledExpression::new("x^2 + 2*x + 1").unwrap();
+        let mut context = Context::default();
+        for (x, result) in [(0, 1), (1, 4), (-3, 4), (9, 100)] {
+            context.assign("x", Number::Int(x));
+            assert_eq!(
+                expression.eval(&settings, &context).unwrap().result,
+                result,
+                "x = {x}"
+            );
+        }
+        assert_eq!(expression.rpn(), "x 2 ^ 2 x * + 1 +");
+
+        assert_eq!(
+            CompiledExpression::new("y + 1")
+                .unwrap()
+                .eval(&settings, &Context::default())
+                .unwrap_err(),
+            Error::UnknownVariable("y".to_owned())
+        );
+        assert!(CompiledExpression::new("1 +").is_err());
+    }
+
+    #[test]
+    fn test_rpn_string() {
+        assert_eq!(to_rpn_string("(3 + 4) * 2").unwrap(), "3 4 + 2 *");
+        assert_eq!(to_rpn_string("2 ^ 3 ^ 2").unwrap(), "2 3 2 ^ ^");
+        assert_eq!(to_rpn_string("max(1, -x)").unwrap(), "1 x neg max/2");
+        assert!(to_rpn_string("(3 + 4").is_err());
+    }
+
+    #[test]
+    fn test_rpn_to_infix() {
+        for (expr, infix) in [
+            ("(3 + 4) * 2", "(3 + 4) * 2"),
+            ("3+4*2", "3 + 4 * 2"),
+            ("10 - (4 - 3)", "10 - (4 - 3)"),
+            ("(10 - 4) - 3", "10 - 4 - 3"),
+            ("2 ^ 3 ^ 2", "2 ^ 3 ^ 2"),
+            ("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2"),
+            ("-2 ^ 2", "-2 ^ 2"),
+            ("(-2) ^ 2", "(-2) ^ 2"),
+            ("--x", "--x"),
+            ("√(4 + 5) * |x - 1|", "√(4 + 5) * |x - 1|"),
+            ("(1 || 0) && 3 xor 5", "(1 || 0) && 3 xor 5"),
+            ("1 ? 2 : (3 ? 4 : 5)", "1 ? 2 : 3 ? 4 : 5"),
+            ("(1 ? 2 : 3) ? 4 : 5", "(1 ? 2 : 3) ? 4 : 5"),
+            ("max(1, -x) + ans(2) * _", "max(1, -x) + ans(2) * _"),
+            ("f(2.5, 3i)", "f(2.5, 3i)"),
+        ] {
+            let rpn = to_rpn_string(expr).unwrap();
+            assert_eq!(rpn_to_infix(&rpn).unwrap(), infix, "RPN {rpn}");
+            assert_eq!(to_rpn_string(infix).unwrap(), rpn, "Infix {infix}");
+        }
+    }
+
+    #[test]
+    fn test_estimate_rpn() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+        context.assign("x", Number::Int(5));
+        for (rpn, result) in [
+            ("3 4 + 2 *", 14),
+            ("2 3 2 ^ ^", 512),
+            ("1 x neg max/2 # comment", 1),
+            ("x 5 - 1 2 ?:", 2),
+        ] {
+            assert_eq!(
+                estimate_rpn_in(rpn, &settings, &context).unwrap().result,
+                Number::Int(result),
+                "RPN {rpn}"
+            );
+        }
+        let evaluation = estimate_rpn_in("3 4 + 2 *", &settings, &context).unwrap();
+        assert_eq!(evaluation.rpn, "3 4 + 2 *");
+        assert_eq!(evaluation.stats.tokens, 5);
+        assert_eq!(
+            estimate_rpn_in("4 0 /", &settings, &context).map(|e| e.result),
+            Err(Error::DivisionByZero("4 / 0".to_owned()))
+        );
+        for rpn in ["3 4", "3 +", "(3 4 +)"] {
+            assert_eq!(
+                estimate_rpn_in(rpn, &settings, &context).map(|e| e.result),
+                Err(Error::InvalidRpn(rpn.to_owned())),
+                "RPN {rpn}"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_compiled_expression() {
+        let settings = Settings::default();
+        let mut context = Context::default();
+        context.assign("x", Number::Int(4));
+        let compiled = CompiledExpression::new("max(2, x) * 2.5 + -x").unwrap();
+        let json = serde_json::to_string(&compiled).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({ "rpn": [
+                { "Number": 2 },
+                { "Variable": "x" },
+                { "Call": ["Max", 2] },
+                { "Decimal": "2.5" },
+                { "Operator": "Prod" },
+                { "Variable": "x" },
+                { "Operator": "Neg" },
+                { "Operator": "Add" },
+            ] })
+        );
+
+        let restored: CompiledExpression = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.rpn(), compiled.rpn());
+        let settings = Settings {
+            decimals: Some(1),
+            ..settings
+        };
+        assert_eq!(
+            restored.eval(&settings, &context).unwrap().result,
+            compiled.eval(&settings, &context).unwrap().result
+        );
+
+        for json in [
+            r#"{ "rpn": [{ "Number": 2 }, { "Operator": "Add" }] }"#,
+            r#"{ "rpn": [{ "Decimal": "x" }] }"#,
+            r#"{ "rpn": [{ "Operator": "Power" }] }"#,
+        ] {
+            assert!(
+                serde_json::from_str::<CompiledExpression>(json).is_err(),
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rpn_to_infix_invalid() {
+        assert_eq!(rpn_to_infix("  "), Err(Error::EmptyExpression));
+        for rpn in [
+            "3 +", "3 4", "3 4 ?", "3 ( 4 +", "3 max", "1 max/3", "1 2 $",
+        ] {
+            assert_eq!(
+                rpn_to_infix(rpn),
+                Err(Error::InvalidRpn(rpn.to_owned())),
+                "RPN {rpn}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let spans: Vec<(usize, usize)> = tokenize("12 + max(3, 4)")
+            .unwrap()
+            .iter()
+            .map(|spanned| (spanned.span.start, spanned.span.end))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (0, 2),
+                (3, 4),
+                (5, 8),
+                (8, 9),
+                (9, 10),
+                (10, 11),
+                (12, 13),
+                (13, 14)
+            ]
+        );
+
+        assert_eq!(
+            estimate_expression("max 3", &Settings::default()).unwrap_err(),
+            Error::InvalidExpression {
+                message: "Function max takes its arguments in brackets".to_owned(),
+                position: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate("max(1, 2) + log(8, 2) * x"), Ok(()));
+
+        let diagnostics = validate("sqrt(1, 2) + max(3) - log(4)").unwrap_err();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "Function sqrt does not take 2 arguments",
+                    Some(Span { start: 0, end: 4 })
+                ),
+                (
+                    "Function log does not take 1 arguments",
+                    Some(Span { start: 22, end: 25 })
+                ),
+            ]
+        );
+
+        assert_eq!(
+            validate("4 + * 3"),
+            Err(vec![Diagnostic {
+                kind: "invalid syntax",
+                message: "Unexpected '*' at position 4".to_owned(),
+                span: Some(Span { start: 4, end: 5 }),
+            }])
+        );
+        // Evaluation errors are not looked for
+        assert_eq!(validate("1 / 0"), Ok(()));
+    }
 }
@@ -0,0 +1,264 @@
+use std::{cmp::Ordering, fmt};
+
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+use super::{
+    Error, IntType, Overflow, Settings,
+    complex::Complex,
+    date::{self, Date},
+    decimal::Decimal,
+    eval::DecimalLiteral,
+    matrix::Matrix,
+    unit::Quantity,
+};
+
+///
+/// Value handled by the evaluation. Integers live within the range of the configured
+/// integer type until an operation overflows with promotion enabled, then they move to an
+/// arbitrary precision integer. In decimal mode all values are decimals instead, while the
+/// integer mode gets decimals from literals with a fraction or an exponent, constants and
+/// functions whose results have a fraction, unless it is exact. Complex numbers come from
+/// the imaginary unit `i`, quantities from units written after a number as in `5 km`,
+/// dates from literals as in `2024-03-01`, vectors and matrices from brackets as in
+/// `[1, 2]`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Int(i128),
+    Big(BigInt),
+    Decimal(Decimal),
+    Complex(Box<Complex>),
+    Quantity(Box<Quantity>),
+    Date(Date),
+    Matrix(Box<Matrix>),
+}
+
+///
+/// Kind of a value. Operations are computed in the largest kind of their operands:
+///
+/// * integers stay integers, overflowing results being handled as the settings request,
+///   which may promote them to big integers
+/// * big integers come back to integers whenever the result fits again
+/// * decimals promote every integer operand to a decimal, `/` included, so division never
+///   truncates there. In decimal mode every value is one, and in integer mode they come
+///   from decimal literals, constants such as `pi` and functions such as `ln` or `mean`
+///   whose results have a fraction
+/// * complex numbers take any other operand as their real part, failing on decimals with a
+///   fraction
+/// * quantities carry the unit of the result along, converting between units of the same
+///   dimension
+/// * dates move by durations, and their differences are durations in days
+/// * vectors and matrices apply the operation to their entries, or multiply as matrices
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Kind {
+    Int,
+    Big,
+    Decimal,
+    Complex,
+    Quantity,
+    Date,
+    Matrix,
+}
+
+impl Number {
+    pub(super) fn kind(&self) -> Kind {
+        match self {
+            Number::Int(_) => Kind::Int,
+            Number::Big(_) => Kind::Big,
+            Number::Decimal(_) => Kind::Decimal,
+            Number::Complex(_) => Kind::Complex,
+            Number::Quantity(_) => Kind::Quantity,
+            Number::Date(_) => Kind::Date,
+            Number::Matrix(_) => Kind::Matrix,
+        }
+    }
+
+    ///
+    /// Kind an operation on the given operands is computed in
+    ///
+    pub(super) fn promote(operands: &[&Number], settings: &Settings) -> Kind {
+        let kind = operands.iter().map(|n| n.kind()).max().unwrap_or(Kind::Int);
+        match settings.decimals {
+            Some(_) => kind.max(Kind::Decimal),
+            None => kind,
+        }
+    }
+
+    pub(super) fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(n) => *n == 0,
+            Number::Big(n) => n.is_zero(),
+            Number::Decimal(d) => d.is_zero(),
+            Number::Complex(z) => z.is_zero(),
+            Number::Quantity(q) => q.value.is_zero(),
+            Number::Date(_) => false,
+            Number::Matrix(m) => m.entries().all(Number::is_zero),
+        }
+    }
+
+    ///
+    /// Integer value, the fraction of a decimal being dropped toward zero and the imaginary
+    /// part of a complex number and the unit of a quantity being ignored. Dates, vectors
+    /// and matrices have none and give 0
+    ///
+    pub(super) fn to_big(&self) -> BigInt {
+        match self {
+            Number::Int(n) => BigInt::from(*n),
+            Number::Big(n) => n.clone(),
+            Number::Decimal(d) => d.trunc(),
+            Number::Complex(z) => z.re.clone(),
+            Number::Quantity(q) => q.value.to_big(),
+            Number::Date(_) | Number::Matrix(_) => BigInt::zero(),
+        }
+    }
+
+    ///
+    /// Integer value, None for a decimal with a fraction, a complex number, a quantity, a
+    /// date or a matrix
+    ///
+    pub fn to_integer(&self) -> Option<BigInt> {
+        match self {
+            Number::Decimal(d) => d.integral(),
+            Number::Complex(_) | Number::Quantity(_) | Number::Date(_) | Number::Matrix(_) => None,
+            n => Some(n.to_big()),
+        }
+    }
+
+    ///
+    /// Value of a literal, which has to fit in the integer type unless promotion is enabled
+    /// or it is a bit pattern of the width in programmer mode
+    ///
+    pub(super) fn from_literal(n: i128, settings: &Settings) -> Result<Self, Error> {
+        if let Some(scale) = settings.decimals {
+            Ok(Number::Decimal(Decimal::from_int(n.into(), scale)))
+        } else if settings.int_type().contains(n) {
+            Ok(Number::Int(n))
+        } else if settings.programmer && settings.int_type().holds_pattern(n) {
+            Ok(Number::Int(settings.int_type().wrap(n)))
+        } else if settings.overflow == Overflow::Promote {
+            Ok(Number::Big(n.into()))
+        } else {
+            Err(Error::Overflow(format!("literal {n}")))
+        }
+    }
+
+    ///
+    /// Value of a literal with a fraction or an exponent, rounded to the precision of the
+    /// decimals. The exact integer mode only takes those which are integers, as `1e3`
+    ///
+    pub(super) fn from_decimal_literal(
+        literal: &DecimalLiteral,
+        settings: &Settings,
+    ) -> Result<Self, Error> {
+        if settings.exact && settings.decimals.is_none() {
+            let power = BigInt::from(10).pow(literal.fraction);
+            if !(&literal.digits % &power).is_zero() {
+                return Err(Error::FractionalLiteral(literal.literal.clone()));
+            }
+            let n = (&literal.digits / power)
+                .to_i128()
+                .ok_or_else(|| Error::Overflow(format!("literal {}", literal.literal)))?;
+            return Number::from_literal(n, settings);
+        }
+        Ok(Number::Decimal(Decimal::from_digits(
+            literal.digits.clone(),
+            literal.fraction,
+            settings.scale(),
+            settings.rounding,
+        )))
+    }
+
+    ///
+    /// Wrap a big integer, coming back to the integer type whenever the value fits again
+    ///
+    pub(super) fn from_big(value: BigInt, int_type: IntType) -> Self {
+        match value.to_i128().filter(|n| int_type.contains(*n)) {
+            Some(n) => Number::Int(n),
+            None => Number::Big(value),
+        }
+    }
+
+    ///
+    /// Fit a non negative result computed in arbitrary precision into the integer type,
+    /// handling overflow as requested by the settings
+    ///
+    pub(super) fn fit(
+        value: BigInt,
+        settings: &Settings,
+        computation: impl FnOnce() -> String,
+    ) -> Result<Self, Error> {
+        let int_type = settings.int_type();
+        match (value.to_i128(), settings.overflow) {
+            (Some(n), _) if int_type.contains(n) => Ok(Number::Int(n)),
+            (_, Overflow::Checked) => Err(Error::Overflow(computation())),
+            (_, Overflow::Promote) => Ok(Number::Big(value)),
+            (_, Overflow::Saturate) => Ok(Number::Int(int_type.clamp(i128::MAX))),
+            (_, Overflow::Wrap) => {
+                let low = (value & BigInt::from(u128::MAX))
+                    .to_u128()
+                    .expect("Masked to 128 bits");
+                Ok(Number::Int(int_type.wrap(low as i128)))
+            }
+        }
+    }
+}
+
+impl PartialEq<i128> for Number {
+    fn eq(&self, other: &i128) -> bool {
+        matches!(self, Number::Int(n) if n == other)
+    }
+}
+
+///
+/// Complex numbers, quantities, vectors and matrices are not ordered, dates only among
+/// themselves
+///
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(match (self, other) {
+            (Number::Date(_), _) | (_, Number::Date(_)) => return date::compare(self, other),
+            (Number::Complex(_) | Number::Quantity(_) | Number::Matrix(_), _)
+            | (_, Number::Complex(_) | Number::Quantity(_) | Number::Matrix(_)) => return None,
+            (Number::Int(a), Number::Int(b)) => a.cmp(b),
+            (Number::Decimal(a), Number::Decimal(b)) => a.compare(b),
+            (Number::Decimal(a), n) => a.compare(&Decimal::from_int(n.to_big(), a.scale)),
+            (n, Number::Decimal(b)) => Decimal::from_int(n.to_big(), b.scale).compare(b),
+            _ => self.to_big().cmp(&other.to_big()),
+        })
+    }
+}
+
+///
+/// Integers are written as numbers, big integers, decimals, complex numbers, quantities,
+/// dates and matrices as strings so that no reader loses digits
+///
+#[cfg(feature = "serde")]
+impl serde::Serialize for Number {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Number::Int(n) => serializer.serialize_i128(*n),
+            Number::Big(n) => serializer.serialize_str(&n.to_string()),
+            Number::Decimal(d) => serializer.serialize_str(&d.to_string()),
+            Number::Complex(z) => serializer.serialize_str(&z.to_string()),
+            Number::Quantity(q) => serializer.serialize_str(&q.to_string()),
+            Number::Date(date) => serializer.serialize_str(&date.to_string()),
+            Number::Matrix(m) => serializer.serialize_str(&m.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{n}"),
+            Number::Big(n) => write!(f, "{n}"),
+            Number::Decimal(d) => write!(f, "{d}"),
+            Number::Complex(z) => write!(f, "{z}"),
+            Number::Quantity(q) => write!(f, "{q}"),
+            Number::Date(date) => write!(f, "{date}"),
+            Number::Matrix(m) => write!(f, "{m}"),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use num_bigint::BigInt;
+
+use super::{
+    Error, Settings,
+    decimal::Decimal,
+    eval::{Token, parse_expr},
+    number::Number,
+    unit::{Quantity, Unit},
+};
+
+/// Namespace of the physical constants, as in `const.c`
+const PREFIX: &str = "const.";
+
+///
+/// Physical constant in SI units, written `const.` followed by its name
+///
+#[derive(Debug)]
+pub struct PhysicalConstant {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Value as a literal of the calculator, as `6.62607015e-34`
+    pub value: &'static str,
+    factors: &'static [(&'static str, i32)],
+}
+
+impl PhysicalConstant {
+    pub fn unit(&self) -> Unit {
+        Unit::new(self.factors)
+    }
+
+    ///
+    /// Value with its unit, which has the decimals of the integer mode unless it is an
+    /// integer that fits. Decimals are rounded to the precision without notice, as for
+    /// `pi`, and the exact integer mode refuses them
+    ///
+    pub(super) fn quantity(&self, settings: &Settings) -> Result<Number, Error> {
+        let value = match parse_expr(self.value)?.as_slice() {
+            [Token::Number(n)] => match Number::from_literal(*n, settings) {
+                // Integers wider than the integer type, as the speed of light on 16 bits
+                Err(Error::Overflow(_)) if !settings.exact => {
+                    Number::Decimal(Decimal::from_int(BigInt::from(*n), settings.scale()))
+                }
+                value => value?,
+            },
+            [Token::Decimal(decimal)] => match Number::from_decimal_literal(decimal, settings) {
+                Err(Error::FractionalLiteral(_)) => {
+                    return Err(Error::InexactConstant(format!("{PREFIX}{}", self.name)));
+                }
+                value => value?,
+            },
+            _ => unreachable!("Physical constants are written as literals"),
+        };
+        let unit = self.unit();
+        Ok(Number::Quantity(Box::new(Quantity { value, unit })))
+    }
+}
+
+const fn constant(
+    name: &'static str,
+    description: &'static str,
+    value: &'static str,
+    factors: &'static [(&'static str, i32)],
+) -> PhysicalConstant {
+    PhysicalConstant {
+        name,
+        description,
+        value,
+        factors,
+    }
+}
+
+/// CODATA 2018 values, the exact ones being those fixed by the definition of SI units
+const CONSTANTS: &[PhysicalConstant] = &[
+    constant(
+        "c",
+        "speed of light in vacuum",
+        "299792458",
+        &[("m", 1), ("s", -1)],
+    ),
+    constant(
+        "h",
+        "Planck constant",
+        "6.62607015e-34",
+        &[("J", 1), ("s", 1)],
+    ),
+    constant(
+        "G",
+        "Newtonian constant of gravitation",
+        "6.67430e-11",
+        &[("m", 3), ("kg", -1), ("s", -2)],
+    ),
+    constant("Na", "Avogadro constant", "6.02214076e23", &[("mol", -1)]),
+    constant(
+        "e",
+        "elementary charge",
+        "1.602176634e-19",
+        &[("A", 1), ("s", 1)],
+    ),
+    constant(
+        "k",
+        "Boltzmann constant",
+        "1.380649e-23",
+        &[("J", 1), ("K", -1)],
+    ),
+    constant(
+        "R",
+        "molar gas constant",
+        "8.31446261815324",
+        &[("J", 1), ("mol", -1), ("K", -1)],
+    ),
+    constant("me", "electron mass", "9.1093837015e-31", &[("kg", 1)]),
+    constant("mp", "proton mass", "1.67262192369e-27", &[("kg", 1)]),
+    constant(
+        "g",
+        "standard acceleration of gravity",
+        "9.80665",
+        &[("m", 1), ("s", -2)],
+    ),
+    constant("atm", "standard atmosphere", "101325", &[("Pa", 1)]),
+];
+
+///
+/// All the physical constants, in the order they are listed
+///
+pub fn physical_constants() -> &'static [PhysicalConstant] {
+    CONSTANTS
+}
+
+///
+/// Physical constant written as `const.name`, None for any other name
+///
+pub(super) fn named(name: &str) -> Option<&'static PhysicalConstant> {
+    let name = name.strip_prefix(PREFIX)?;
+    CONSTANTS.iter().find(|constant| constant.name == name)
+}
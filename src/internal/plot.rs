@@ -0,0 +1,314 @@
+use std::fmt;
+
+use num_traits::ToPrimitive;
+
+use super::{
+    Context, Error, Rounding, Settings,
+    eval::{Operator, is_variable_name, strip_comment},
+    number::Number,
+    polynomial::{INTEGER_DIGITS, evaluate_all, split_arguments},
+    rpn::Rpn,
+};
+
+/// Columns of a plot, each one showing the function at a point of the interval
+const WIDTH: usize = 60;
+/// Rows of a plot
+const HEIGHT: usize = 16;
+/// Fractional digits of the labels of the axes, at most
+const LABEL_DIGITS: u32 = 4;
+
+///
+/// Plot of an expression over an interval of its variable, drawn with characters
+///
+#[derive(Debug, PartialEq)]
+pub struct Plot {
+    /// Row of the point of each column counted from the bottom, None where the expression
+    /// is not defined
+    points: Vec<Option<usize>>,
+    /// Row of the x axis, when 0 is between the lowest and the highest value
+    x_axis: Option<usize>,
+    /// Column of the y axis, when 0 is in the interval
+    y_axis: Option<usize>,
+    /// Ends of the interval, rounded for the labels
+    x_range: (Number, Number),
+    /// Lowest and highest value, rounded for the labels
+    y_range: (Number, Number),
+}
+
+///
+/// Plot an expression followed by its variable and the ends of the interval, as in
+/// `x^2 - 2, x, -2, 2`. The expression is evaluated with decimals at each column, points
+/// where it is not defined, complex, a quantity or a vector are left out
+///
+pub fn plot(input: &str, settings: &Settings, context: &Context) -> Result<Plot, Error> {
+    let args = split_arguments(strip_comment(input));
+    let call = format!("plot({})", args.join(", "));
+    let [expr, variable, a, b] = args.as_slice() else {
+        return Err(Error::ArgumentCount {
+            function: "plot".to_owned(),
+            count: args.len(),
+        });
+    };
+    if !is_variable_name(variable) {
+        return Err(Error::InvalidAssignment((*variable).to_owned()));
+    }
+    let body = Rpn::new(expr)?;
+    let work = Settings {
+        decimals: Some(settings.decimals.unwrap_or(0).max(INTEGER_DIGITS)),
+        rounding: Rounding::HalfEven,
+        ..Default::default()
+    };
+    let bounds = evaluate_all(&[a, b], settings, context)?;
+    if bounds.iter().any(|n| matches!(n, Number::Complex(_))) {
+        return Err(Error::ComplexOperand(call));
+    }
+    if bounds.iter().any(|n| matches!(n, Number::Matrix(_))) {
+        return Err(Error::MatrixOperand(call));
+    }
+    if bounds.iter().any(|n| matches!(n, Number::Quantity(_))) {
+        return Err(Error::UnitOperand(call));
+    }
+    if bounds.iter().any(|n| matches!(n, Number::Date(_))) {
+        return Err(Error::DateOperand(call));
+    }
+    let (a, b) = (round(&bounds[0], &work)?, round(&bounds[1], &work)?);
+    if a >= b {
+        return Err(Error::MathDomain(call));
+    }
+
+    let width = Operator::Sub.execute(b.clone(), a.clone(), &work)?;
+    let last = Number::from_literal(WIDTH as i128 - 1, &work)?;
+    let mut scope = context.clone();
+    let mut values = vec![];
+    let mut error = None;
+    for column in 0..WIDTH {
+        let offset = Operator::Prod.execute(
+            width.clone(),
+            Number::from_literal(column as i128, &work)?,
+            &work,
+        )?;
+        let x = Operator::Add.execute(
+            a.clone(),
+            Operator::Div.execute(offset, last.clone(), &work)?,
+            &work,
+        )?;
+        scope.assign(variable, x);
+        values.push(match body.compute(&work, &scope) {
+            Ok(evaluation)
+                if !matches!(
+                    evaluation.result,
+                    Number::Complex(_) | Number::Quantity(_) | Number::Date(_) | Number::Matrix(_)
+                ) =>
+            {
+                Some(round(&evaluation.result, &work)?)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                error.get_or_insert(e);
+                None
+            }
+        });
+    }
+
+    let mut defined = values.iter().flatten();
+    let Some(first) = defined.next() else {
+        return Err(error.unwrap_or(Error::ComplexOperand(call)));
+    };
+    let (mut low, mut high) = (first.clone(), first.clone());
+    for value in defined {
+        if *value < low {
+            low = value.clone();
+        }
+        if *value > high {
+            high = value.clone();
+        }
+    }
+    // A constant is drawn in the middle of the rows
+    if low == high {
+        let one = Number::from_literal(1, &work)?;
+        low = Operator::Sub.execute(low, one.clone(), &work)?;
+        high = Operator::Add.execute(high, one, &work)?;
+    }
+    let zero = Number::from_literal(0, &work)?;
+    let points = values
+        .iter()
+        .map(|value| {
+            value
+                .as_ref()
+                .map(|value| cell(value, &low, &high, HEIGHT, &work))
+                .transpose()
+        })
+        .collect::<Result<Vec<Option<usize>>, Error>>()?;
+    let x_axis = (low <= zero && zero <= high)
+        .then(|| cell(&zero, &low, &high, HEIGHT, &work))
+        .transpose()?;
+    let y_axis = (a <= zero && zero <= b)
+        .then(|| cell(&zero, &a, &b, WIDTH, &work))
+        .transpose()?;
+
+    let labels = Settings {
+        decimals: Some(
+            settings
+                .decimals
+                .map_or(LABEL_DIGITS, |d| d.min(LABEL_DIGITS)),
+        ),
+        rounding: Rounding::HalfEven,
+        ..Default::default()
+    };
+    Ok(Plot {
+        points,
+        x_axis,
+        y_axis,
+        x_range: (round(&a, &labels)?, round(&b, &labels)?),
+        y_range: (round(&low, &labels)?, round(&high, &labels)?),
+    })
+}
+
+///
+/// Drawn with `*` for the points and `-` and `|` for the axes, the highest and lowest
+/// values labeled on the left and the ends of the interval below
+///
+impl fmt::Display for Plot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (low, high) = (self.y_range.0.to_string(), self.y_range.1.to_string());
+        let margin = low.len().max(high.len());
+        for row in (0..HEIGHT).rev() {
+            let label = match row {
+                0 => low.as_str(),
+                _ if row == HEIGHT - 1 => high.as_str(),
+                _ => "",
+            };
+            let line: String = (0..WIDTH)
+                .map(|column| {
+                    if self.points[column] == Some(row) {
+                        '*'
+                    } else if self.y_axis == Some(column) && self.x_axis == Some(row) {
+                        '+'
+                    } else if self.y_axis == Some(column) {
+                        '|'
+                    } else if self.x_axis == Some(row) {
+                        '-'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            writeln!(f, "{label:>margin$} |{}", line.trim_end())?;
+        }
+        writeln!(f, "{:>margin$} +{}", "", "-".repeat(WIDTH))?;
+        let (left, right) = (self.x_range.0.to_string(), self.x_range.1.to_string());
+        let gap = (WIDTH + 1).saturating_sub(left.len());
+        writeln!(f, "{:>margin$} {left}{right:>gap$}", "")
+    }
+}
+
+///
+/// Value rounded to the scale of the settings
+///
+fn round(value: &Number, settings: &Settings) -> Result<Number, Error> {
+    Operator::Add.execute(value.clone(), Number::from_literal(0, settings)?, settings)
+}
+
+///
+/// Index of the cell among the given count the value falls in, the low end of the range
+/// being the first one and the high end the last one
+///
+fn cell(
+    value: &Number,
+    low: &Number,
+    high: &Number,
+    cells: usize,
+    settings: &Settings,
+) -> Result<usize, Error> {
+    let offset = Operator::Prod.execute(
+        Operator::Sub.execute(value.clone(), low.clone(), settings)?,
+        Number::from_literal(cells as i128 - 1, settings)?,
+        settings,
+    )?;
+    let position = Operator::Div.execute(
+        offset,
+        Operator::Sub.execute(high.clone(), low.clone(), settings)?,
+        settings,
+    )?;
+    let integers = Settings {
+        decimals: Some(0),
+        rounding: Rounding::HalfEven,
+        ..Default::default()
+    };
+    Ok(round(&position, &integers)?
+        .to_big()
+        .to_usize()
+        .unwrap_or_default()
+        .min(cells - 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Context, Error, HEIGHT, Number, Settings, WIDTH, plot};
+
+    #[test]
+    fn test_plot() {
+        let settings = Settings::default();
+        let context = Context::default();
+
+        let line = plot("x, x, -1, 1", &settings, &context).unwrap();
+        assert_eq!(line.points[0], Some(0));
+        assert_eq!(line.points[WIDTH - 1], Some(HEIGHT - 1));
+        assert_eq!(line.x_axis, Some(8));
+        assert_eq!(line.y_axis, Some(30));
+        assert_eq!(
+            (line.y_range.0.to_string(), line.y_range.1.to_string()),
+            ("-1".to_owned(), "1".to_owned())
+        );
+        let drawn = line.to_string();
+        let rows: Vec<&str> = drawn.lines().collect();
+        assert_eq!(rows.len(), HEIGHT + 2);
+        assert!(rows[0].starts_with(" 1 |") && rows[0].ends_with('*'));
+        assert!(rows[HEIGHT - 1].starts_with("-1 |*"));
+        assert_eq!(rows[HEIGHT], format!("   +{}", "-".repeat(WIDTH)));
+        assert!(rows[HEIGHT + 1].starts_with("   -1") && rows[HEIGHT + 1].ends_with(" 1"));
+
+        // Integers are plotted with decimals, the labels are rounded
+        let parabola = plot("x^2 / 2, x, 0, 3", &settings, &context).unwrap();
+        assert_eq!(parabola.points[0], Some(0));
+        assert_eq!(parabola.y_range.1.to_string(), "4.5");
+        assert_eq!(parabola.y_axis, Some(0));
+
+        let constant = plot("k, x, 0, 1 # k", &settings, &{
+            let mut context = Context::default();
+            context.assign("k", Number::Int(2));
+            context
+        })
+        .unwrap();
+        assert!(constant.points.iter().all(|&row| row == Some(8)));
+
+        // Points where the expression is not defined are left out
+        let gaps = plot("1 / x, x, -1, 1", &settings, &context).unwrap();
+        assert!(gaps.points.iter().all(Option::is_some));
+        let gaps = plot("sqrt(x), x, -1, 1", &settings, &context).unwrap();
+        assert_eq!(gaps.points[0], None);
+        assert!(gaps.points[WIDTH - 1].is_some());
+
+        for (input, error) in [
+            (
+                "x, x, 1, 1",
+                Error::MathDomain("plot(x, x, 1, 1)".to_owned()),
+            ),
+            (
+                "x, x, 0",
+                Error::ArgumentCount {
+                    function: "plot".to_owned(),
+                    count: 3,
+                },
+            ),
+            ("x, 2, 0, 1", Error::InvalidAssignment("2".to_owned())),
+            ("y, x, 0, 1", Error::UnknownVariable("y".to_owned())),
+        ] {
+            assert_eq!(
+                plot(input, &settings, &context).unwrap_err(),
+                error,
+                "{input}"
+            );
+        }
+    }
+}
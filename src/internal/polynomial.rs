@@ -0,0 +1,744 @@
+use std::{cmp::Ordering, fmt};
+
+use super::{
+    CompiledExpression, Context, Error, Rounding, Settings, Warning,
+    ast::Ast,
+    eval::{Operator, is_variable_name, strip_comment},
+    number::Number,
+    parse_ast,
+};
+
+/// Digits computed beyond the precision of the roots, lost to cancellations
+pub(super) const GUARD_DIGITS: u32 = 20;
+/// Fractional digits roots are rounded to before telling whether they are integers
+pub(super) const INTEGER_DIGITS: u32 = 10;
+/// Steps of the numeric search of the roots of polynomials of degree 5 or more
+const MAX_ITERATIONS: usize = 1000;
+
+///
+/// Root of a polynomial, real when its imaginary part is 0
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub re: Number,
+    pub im: Number,
+}
+
+///
+/// Written as `a`, `bi` or `a + bi`, leaving out the parts which are 0 and an imaginary
+/// coefficient of 1, as complex numbers are
+///
+impl fmt::Display for Root {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let im = self.im.to_string();
+        let (sign, magnitude) = match im.strip_prefix('-') {
+            Some(magnitude) => ("-", magnitude),
+            None => ("+", im.as_str()),
+        };
+        let magnitude = if magnitude == "1" { "" } else { magnitude };
+        match (self.re.is_zero(), self.im.is_zero(), sign) {
+            (_, true, _) => write!(f, "{}", self.re),
+            (true, false, "-") => write!(f, "-{magnitude}i"),
+            (true, false, _) => write!(f, "{magnitude}i"),
+            (false, false, _) => write!(f, "{} {sign} {magnitude}i", self.re),
+        }
+    }
+}
+
+///
+/// Roots of a polynomial, each one as many times as its multiplicity, sorted by real then
+/// imaginary part
+///
+#[derive(Debug, PartialEq)]
+pub struct Roots {
+    pub values: Vec<Root>,
+    pub warnings: Vec<Warning>,
+}
+
+///
+/// Find the real and complex roots of a polynomial given by its coefficients, the highest
+/// degree first as in `1, -3, 2`, or as an expression followed by its variable as in
+/// `x^2 - 3*x + 2, x`. Degrees up to 4 are solved with the formulas, by Cardano for cubics
+/// and Ferrari for quartics, higher degrees by the Durand-Kerner iteration. Roots are
+/// rounded to the decimals, with integers exact roots stay integers and the others get the
+/// decimals of the integer mode
+///
+pub fn roots(input: &str, settings: &Settings, context: &Context) -> Result<Roots, Error> {
+    let args = split_arguments(strip_comment(input));
+    let call = format!("roots({})", args.join(", "));
+    let mut warnings = vec![];
+    let mut coefficients = match args.as_slice() {
+        [expr, variable] if is_variable_name(variable) => {
+            let ast = parse_ast(expr)?;
+            if ast.variables().iter().any(|name| name == variable) {
+                let unknown = [(*variable).to_owned()];
+                if ast.degree(&unknown).is_none() {
+                    return Err(Error::InvalidPolynomial {
+                        expr: (*expr).to_owned(),
+                        variable: (*variable).to_owned(),
+                    });
+                }
+                let mut coefficients = expand(&ast, &unknown, settings, context, &mut warnings)?;
+                coefficients.reverse();
+                coefficients
+            } else {
+                evaluate_all(&args, settings, context)?
+            }
+        }
+        _ => evaluate_all(&args, settings, context)?,
+    };
+    if coefficients
+        .iter()
+        .any(|coefficient| matches!(coefficient, Number::Complex(_)))
+    {
+        return Err(Error::ComplexOperand(call));
+    }
+    let leading = coefficients
+        .iter()
+        .position(|coefficient| !coefficient.is_zero())
+        .ok_or_else(|| Error::MathDomain(call.clone()))?;
+    coefficients.drain(..leading);
+
+    let scale = settings.scale().max(INTEGER_DIGITS) + GUARD_DIGITS;
+    let work = Work {
+        settings: Settings {
+            decimals: Some(scale),
+            rounding: Rounding::HalfEven,
+            ..Default::default()
+        },
+    };
+    let found = work
+        .solve(&coefficients)?
+        .ok_or_else(|| Error::NoConvergence(call.clone()))?;
+    let mut values = vec![];
+    for z in found {
+        values.push(Root {
+            re: decimal_result(z.re, settings, &call)?,
+            im: decimal_result(z.im, settings, &call)?,
+        });
+    }
+    values.sort_by(|a, b| {
+        a.re.partial_cmp(&b.re)
+            .unwrap_or(Ordering::Equal)
+            .then(a.im.partial_cmp(&b.im).unwrap_or(Ordering::Equal))
+    });
+    Ok(Roots { values, warnings })
+}
+
+///
+/// Arguments of the input, split on the commas outside of brackets
+///
+pub(super) fn split_arguments(input: &str) -> Vec<&str> {
+    let mut args = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in input.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(input[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(input[start..].trim());
+    args
+}
+
+pub(super) fn evaluate_all(
+    args: &[&str],
+    settings: &Settings,
+    context: &Context,
+) -> Result<Vec<Number>, Error> {
+    args.iter()
+        .map(|arg| {
+            Ok(CompiledExpression::new(arg)?
+                .eval(settings, context)?
+                .result)
+        })
+        .collect()
+}
+
+///
+/// Coefficients of a polynomial expression in the unknown, the lowest degree first. The
+/// expression is known to be a polynomial, made of sums, products, divisions by constants
+/// and powers to integer literals
+///
+fn expand(
+    ast: &Ast,
+    unknown: &[String; 1],
+    settings: &Settings,
+    context: &Context,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<Number>, Error> {
+    if ast.degree(unknown) == Some(0) {
+        return Ok(vec![ast.evaluate(settings, context)?]);
+    }
+    let zero = || Number::from_literal(0, settings);
+    let mut expand = |ast: &Ast| expand(ast, unknown, settings, context, warnings);
+    Ok(match ast {
+        Ast::Variable(_) => vec![zero()?, Number::from_literal(1, settings)?],
+        Ast::Unary(Operator::Neg, operand) => expand(operand)?
+            .into_iter()
+            .map(|coefficient| Operator::Neg.execute_unary(coefficient, settings))
+            .collect::<Result<_, _>>()?,
+        Ast::Binary(operator @ (Operator::Add | Operator::Sub), lhs, rhs) => {
+            let (mut lhs, mut rhs) = (expand(lhs)?, expand(rhs)?);
+            let degree = lhs.len().max(rhs.len());
+            lhs.resize_with(degree, || zero().expect("0 fits any integer type"));
+            rhs.resize_with(degree, || zero().expect("0 fits any integer type"));
+            lhs.into_iter()
+                .zip(rhs)
+                .map(|(a, b)| operator.execute(a, b, settings))
+                .collect::<Result<_, _>>()?
+        }
+        Ast::Binary(Operator::Prod, lhs, rhs) => {
+            let (lhs, rhs) = (expand(lhs)?, expand(rhs)?);
+            multiply(&lhs, &rhs, settings)?
+        }
+        Ast::Binary(Operator::Div, lhs, rhs) => {
+            let divisor = rhs.evaluate(settings, context)?;
+            let mut quotient = vec![];
+            for coefficient in expand(lhs)? {
+                if Operator::Div.truncates(&coefficient, &divisor) {
+                    if settings.exact {
+                        return Err(Error::InexactDivision(coefficient, divisor));
+                    }
+                    warnings.push(Warning::TruncatedDivision(
+                        coefficient.clone(),
+                        divisor.clone(),
+                    ));
+                }
+                quotient.push(Operator::Div.execute(coefficient, divisor.clone(), settings)?);
+            }
+            quotient
+        }
+        Ast::Binary(Operator::Pow, base, exponent) => {
+            let Ast::Number(exponent) = **exponent else {
+                unreachable!("Polynomials have integer literal exponents")
+            };
+            let base = expand(base)?;
+            let mut power = vec![Number::from_literal(1, settings)?];
+            for _ in 0..exponent {
+                power = multiply(&power, &base, settings)?;
+            }
+            power
+        }
+        node => unreachable!("Hit {node:?} expanding a polynomial"),
+    })
+}
+
+fn multiply(lhs: &[Number], rhs: &[Number], settings: &Settings) -> Result<Vec<Number>, Error> {
+    let mut product = vec![Number::from_literal(0, settings)?; lhs.len() + rhs.len() - 1];
+    for (i, a) in lhs.iter().enumerate() {
+        for (j, b) in rhs.iter().enumerate() {
+            let term = Operator::Prod.execute(a.clone(), b.clone(), settings)?;
+            product[i + j] = Operator::Add.execute(product[i + j].clone(), term, settings)?;
+        }
+    }
+    Ok(product)
+}
+
+///
+/// Value computed with guard digits rounded to the decimals, or when computing with
+/// integers to an integer if it is one and otherwise to the decimals of the integer mode,
+/// which the exact mode refuses
+///
+pub(super) fn decimal_result(
+    value: Number,
+    settings: &Settings,
+    call: &str,
+) -> Result<Number, Error> {
+    if settings.decimals.is_some() {
+        return Operator::Add.execute(value, Number::from_literal(0, settings)?, settings);
+    }
+    let check = Settings {
+        decimals: Some(INTEGER_DIGITS),
+        rounding: Rounding::HalfEven,
+        ..Default::default()
+    };
+    let checked = Operator::Add.execute(value.clone(), Number::from_literal(0, &check)?, &check)?;
+    match checked.to_integer().map(i128::try_from) {
+        Some(Ok(n)) => return Number::from_literal(n, settings),
+        Some(Err(_)) => return Err(Error::Overflow(call.to_owned())),
+        None if settings.exact => return Err(Error::InexactCall(call.to_owned())),
+        None => {}
+    }
+    let decimals = Settings {
+        decimals: Some(settings.scale()),
+        ..*settings
+    };
+    Operator::Add.execute(value, Number::from_literal(0, &decimals)?, &decimals)
+}
+
+///
+/// Complex value of the root finding, both parts decimals of the working precision
+///
+#[derive(Debug, Clone, PartialEq)]
+struct Value {
+    re: Number,
+    im: Number,
+}
+
+///
+/// Complex arithmetic on decimals of the working precision
+///
+struct Work {
+    settings: Settings,
+}
+
+impl Work {
+    fn int(&self, n: i128) -> Result<Value, Error> {
+        self.real(Number::from_literal(n, &self.settings)?)
+    }
+
+    fn real(&self, n: Number) -> Result<Value, Error> {
+        let zero = Number::from_literal(0, &self.settings)?;
+        Ok(Value {
+            re: Operator::Add.execute(n, zero.clone(), &self.settings)?,
+            im: zero,
+        })
+    }
+
+    fn op(&self, operator: Operator, a: &Number, b: &Number) -> Result<Number, Error> {
+        operator.execute(a.clone(), b.clone(), &self.settings)
+    }
+
+    fn add(&self, a: &Value, b: &Value) -> Result<Value, Error> {
+        Ok(Value {
+            re: self.op(Operator::Add, &a.re, &b.re)?,
+            im: self.op(Operator::Add, &a.im, &b.im)?,
+        })
+    }
+
+    fn sub(&self, a: &Value, b: &Value) -> Result<Value, Error> {
+        Ok(Value {
+            re: self.op(Operator::Sub, &a.re, &b.re)?,
+            im: self.op(Operator::Sub, &a.im, &b.im)?,
+        })
+    }
+
+    fn mul(&self, a: &Value, b: &Value) -> Result<Value, Error> {
+        let (ac, bd) = (
+            self.op(Operator::Prod, &a.re, &b.re)?,
+            self.op(Operator::Prod, &a.im, &b.im)?,
+        );
+        let (ad, bc) = (
+            self.op(Operator::Prod, &a.re, &b.im)?,
+            self.op(Operator::Prod, &a.im, &b.re)?,
+        );
+        Ok(Value {
+            re: self.op(Operator::Sub, &ac, &bd)?,
+            im: self.op(Operator::Add, &ad, &bc)?,
+        })
+    }
+
+    fn div(&self, a: &Value, b: &Value) -> Result<Value, Error> {
+        let norm = self.norm(b)?;
+        let conjugate = Value {
+            re: b.re.clone(),
+            im: Operator::Neg.execute_unary(b.im.clone(), &self.settings)?,
+        };
+        let numerator = self.mul(a, &conjugate)?;
+        Ok(Value {
+            re: self.op(Operator::Div, &numerator.re, &norm)?,
+            im: self.op(Operator::Div, &numerator.im, &norm)?,
+        })
+    }
+
+    fn scale(&self, a: &Value, k: i128) -> Result<Value, Error> {
+        self.mul(a, &self.int(k)?)
+    }
+
+    fn divide_by(&self, a: &Value, k: i128) -> Result<Value, Error> {
+        self.div(a, &self.int(k)?)
+    }
+
+    fn norm(&self, a: &Value) -> Result<Number, Error> {
+        let (re, im) = (
+            self.op(Operator::Prod, &a.re, &a.re)?,
+            self.op(Operator::Prod, &a.im, &a.im)?,
+        );
+        self.op(Operator::Add, &re, &im)
+    }
+
+    fn is_zero(&self, a: &Value) -> bool {
+        a.re.is_zero() && a.im.is_zero()
+    }
+
+    fn real_root(&self, operator: Operator, n: Number) -> Result<Number, Error> {
+        // Rounding may leave a tiny negative value where the exact one is 0
+        let zero = Number::from_literal(0, &self.settings)?;
+        let n = if operator == Operator::Sqrt && n < zero {
+            zero
+        } else {
+            n
+        };
+        operator.execute_unary(n, &self.settings)
+    }
+
+    ///
+    /// Principal square root, whose real part is not negative
+    ///
+    fn sqrt(&self, a: &Value) -> Result<Value, Error> {
+        let modulus = self.real_root(Operator::Sqrt, self.norm(a)?)?;
+        let two = Number::from_literal(2, &self.settings)?;
+        let half = |n: Number| self.op(Operator::Div, &n, &two);
+        let re = self.real_root(
+            Operator::Sqrt,
+            half(self.op(Operator::Add, &modulus, &a.re)?)?,
+        )?;
+        let im = self.real_root(
+            Operator::Sqrt,
+            half(self.op(Operator::Sub, &modulus, &a.re)?)?,
+        )?;
+        let zero = Number::from_literal(0, &self.settings)?;
+        Ok(Value {
+            re,
+            im: if a.im < zero {
+                Operator::Neg.execute_unary(im, &self.settings)?
+            } else {
+                im
+            },
+        })
+    }
+
+    ///
+    /// A cube root: the real one of real values, otherwise found by Newton's iteration
+    /// from a value of the right modulus whose argument is a quarter of the value's one
+    ///
+    fn cbrt(&self, a: &Value) -> Result<Value, Error> {
+        if a.im.is_zero() {
+            return Ok(Value {
+                re: self.real_root(Operator::Cbrt, a.re.clone())?,
+                im: a.im.clone(),
+            });
+        }
+        let modulus = self.real_root(Operator::Sqrt, self.norm(a)?)?;
+        let quarter = self.sqrt(&self.sqrt(a)?)?;
+        let length = self.real_root(Operator::Sqrt, self.norm(&quarter)?)?;
+        let target = self.real_root(Operator::Cbrt, modulus)?;
+        let ratio = self.op(Operator::Div, &target, &length)?;
+        let mut z = self.mul(&quarter, &self.real(ratio)?)?;
+        for _ in 0..MAX_ITERATIONS {
+            let cube = self.mul(&self.mul(&z, &z)?, &z)?;
+            let slope = self.scale(&self.mul(&z, &z)?, 3)?;
+            let next = self.sub(&z, &self.div(&self.sub(&cube, a)?, &slope)?)?;
+            if next == z {
+                break;
+            }
+            z = next;
+        }
+        Ok(z)
+    }
+
+    ///
+    /// Roots of the polynomial of the given coefficients, the highest degree first and not
+    /// 0. None when the numeric search does not settle
+    ///
+    fn solve(&self, coefficients: &[Number]) -> Result<Option<Vec<Value>>, Error> {
+        let leading = self.real(coefficients[0].clone())?;
+        let monic = coefficients[1..]
+            .iter()
+            .map(|coefficient| self.div(&self.real(coefficient.clone())?, &leading))
+            .collect::<Result<Vec<Value>, Error>>()?;
+        Ok(Some(match monic.as_slice() {
+            [] => vec![],
+            [a] => vec![self.sub(&self.int(0)?, a)?],
+            [b, c] => self.quadratic(b, c)?,
+            [b, c, d] => self.cubic(b, c, d)?,
+            [b, c, d, e] => self.quartic(b, c, d, e)?,
+            _ => return self.durand_kerner(&monic),
+        }))
+    }
+
+    ///
+    /// Roots of x² + bx + c
+    ///
+    fn quadratic(&self, b: &Value, c: &Value) -> Result<Vec<Value>, Error> {
+        let discriminant = self.sub(&self.mul(b, b)?, &self.scale(c, 4)?)?;
+        let root = self.sqrt(&discriminant)?;
+        let minus_b = self.sub(&self.int(0)?, b)?;
+        Ok(vec![
+            self.divide_by(&self.sub(&minus_b, &root)?, 2)?,
+            self.divide_by(&self.add(&minus_b, &root)?, 2)?,
+        ])
+    }
+
+    ///
+    /// Roots of x³ + bx² + cx + d by Cardano's formula on the depressed cubic t³ + pt + q,
+    /// where x = t - b/3
+    ///
+    fn cubic(&self, b: &Value, c: &Value, d: &Value) -> Result<Vec<Value>, Error> {
+        let shift = self.divide_by(b, 3)?;
+        let b2 = self.mul(b, b)?;
+        let p = self.sub(c, &self.divide_by(&b2, 3)?)?;
+        let q = self.add(
+            &self.sub(
+                &self.divide_by(&self.scale(&self.mul(&b2, b)?, 2)?, 27)?,
+                &self.divide_by(&self.mul(b, c)?, 3)?,
+            )?,
+            d,
+        )?;
+        let half_q = self.divide_by(&q, 2)?;
+        let p3 = self.mul(&self.mul(&p, &p)?, &p)?;
+        let discriminant =
+            self.sqrt(&self.add(&self.mul(&half_q, &half_q)?, &self.divide_by(&p3, 27)?)?)?;
+        let minus_half_q = self.sub(&self.int(0)?, &half_q)?;
+        let mut u = self.cbrt(&self.add(&minus_half_q, &discriminant)?)?;
+        if self.is_zero(&u) {
+            u = self.cbrt(&self.sub(&minus_half_q, &discriminant)?)?;
+        }
+        let depressed = if self.is_zero(&u) {
+            vec![self.int(0)?; 3]
+        } else {
+            let v = self.div(&self.sub(&self.int(0)?, &p)?, &self.scale(&u, 3)?)?;
+            // Primitive cube root of unity -1/2 + i√3/2, and its conjugate
+            let half_sqrt3 = self.op(
+                Operator::Div,
+                &self.real_root(Operator::Sqrt, Number::from_literal(3, &self.settings)?)?,
+                &Number::from_literal(2, &self.settings)?,
+            )?;
+            let minus_half = self.divide_by(&self.int(-1)?, 2)?.re;
+            let omega = Value {
+                re: minus_half.clone(),
+                im: half_sqrt3.clone(),
+            };
+            let conjugate = Value {
+                re: minus_half,
+                im: Operator::Neg.execute_unary(half_sqrt3, &self.settings)?,
+            };
+            vec![
+                self.add(&u, &v)?,
+                self.add(&self.mul(&u, &omega)?, &self.mul(&v, &conjugate)?)?,
+                self.add(&self.mul(&u, &conjugate)?, &self.mul(&v, &omega)?)?,
+            ]
+        };
+        depressed.iter().map(|t| self.sub(t, &shift)).collect()
+    }
+
+    ///
+    /// Roots of x⁴ + bx³ + cx² + dx + e by Ferrari's method on the depressed quartic
+    /// y⁴ + py² + qy + r, where x = y - b/4
+    ///
+    fn quartic(&self, b: &Value, c: &Value, d: &Value, e: &Value) -> Result<Vec<Value>, Error> {
+        let shift = self.divide_by(b, 4)?;
+        let b2 = self.mul(b, b)?;
+        let b3 = self.mul(&b2, b)?;
+        let p = self.sub(c, &self.divide_by(&self.scale(&b2, 3)?, 8)?)?;
+        let q = self.add(
+            &self.sub(d, &self.divide_by(&self.mul(b, c)?, 2)?)?,
+            &self.divide_by(&b3, 8)?,
+        )?;
+        let r = self.sub(
+            &self.add(
+                &self.sub(e, &self.divide_by(&self.mul(b, d)?, 4)?)?,
+                &self.divide_by(&self.mul(&b2, c)?, 16)?,
+            )?,
+            &self.divide_by(&self.scale(&self.mul(&b3, b)?, 3)?, 256)?,
+        )?;
+        let depressed = if self.is_zero(&q) {
+            // Biquadratic, a quadratic in y²
+            let mut roots = vec![];
+            for square in self.quadratic(&p, &r)? {
+                let root = self.sqrt(&square)?;
+                roots.push(self.sub(&self.int(0)?, &root)?);
+                roots.push(root);
+            }
+            roots
+        } else {
+            // Any root of the resolvent cubic is fine, none is 0 as q is not
+            let resolvent = self.cubic(
+                &p,
+                &self.sub(&self.divide_by(&self.mul(&p, &p)?, 4)?, &r)?,
+                &self.sub(&self.int(0)?, &self.divide_by(&self.mul(&q, &q)?, 8)?)?,
+            )?;
+            let mut m = resolvent[0].clone();
+            for candidate in &resolvent[1..] {
+                if self.norm(candidate)? > self.norm(&m)? {
+                    m = candidate.clone();
+                }
+            }
+            let s = self.sqrt(&self.scale(&m, 2)?)?;
+            let sum = self.add(&self.scale(&p, 2)?, &self.scale(&m, 2)?)?;
+            let twice_q_over_s = self.div(&self.scale(&q, 2)?, &s)?;
+            let mut roots = vec![];
+            for sign in [1, -1] {
+                let signed_s = self.scale(&s, sign)?;
+                let inner = self.add(&sum, &self.scale(&twice_q_over_s, sign)?)?;
+                let w = self.sqrt(&self.sub(&self.int(0)?, &inner)?)?;
+                roots.push(self.divide_by(&self.add(&signed_s, &w)?, 2)?);
+                roots.push(self.divide_by(&self.sub(&signed_s, &w)?, 2)?);
+            }
+            roots
+        };
+        depressed.iter().map(|y| self.sub(y, &shift)).collect()
+    }
+
+    ///
+    /// Roots of the monic polynomial of the given coefficients, the leading 1 left out,
+    /// improved together from the powers of 0.4 + 0.9i until they stop moving
+    ///
+    fn durand_kerner(&self, monic: &[Value]) -> Result<Option<Vec<Value>>, Error> {
+        let seed = Value {
+            re: self.divide_by(&self.int(2)?, 5)?.re,
+            im: self.divide_by(&self.int(9)?, 10)?.re,
+        };
+        let mut roots = vec![self.int(1)?];
+        while roots.len() < monic.len() {
+            let next = self.mul(roots.last().expect("Roots start with 1"), &seed)?;
+            roots.push(next);
+        }
+        // Moves smaller than the digits of the results are not worth another step
+        let coarse = Settings {
+            decimals: self.settings.decimals.map(|scale| scale - GUARD_DIGITS / 2),
+            ..self.settings
+        };
+        let settled = |n: &Number| -> Result<bool, Error> {
+            Ok(Operator::Add
+                .execute(n.clone(), Number::from_literal(0, &coarse)?, &coarse)?
+                .is_zero())
+        };
+        for _ in 0..MAX_ITERATIONS {
+            let mut done = true;
+            for k in 0..roots.len() {
+                let mut value = self.int(1)?;
+                for coefficient in monic {
+                    value = self.add(&self.mul(&value, &roots[k])?, coefficient)?;
+                }
+                let mut denominator = self.int(1)?;
+                for (_, other) in roots.iter().enumerate().filter(|(j, _)| *j != k) {
+                    denominator = self.mul(&denominator, &self.sub(&roots[k], other)?)?;
+                }
+                if self.is_zero(&denominator) {
+                    return Ok(None);
+                }
+                let step = self.div(&value, &denominator)?;
+                done &= settled(&step.re)? && settled(&step.im)?;
+                roots[k] = self.sub(&roots[k], &step)?;
+            }
+            if done {
+                return Ok(Some(roots));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Context, Error, Settings, roots};
+
+    fn found(input: &str, settings: &Settings) -> Vec<String> {
+        roots(input, settings, &Context::default())
+            .unwrap()
+            .values
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_roots() {
+        let settings = Settings {
+            decimals: Some(6),
+            ..Default::default()
+        };
+        for (input, expected) in [
+            ("1, -3, 2", vec!["1", "2"]),
+            ("x^2 - 3*x + 2, x", vec!["1", "2"]),
+            ("2, -4", vec!["2"]),
+            ("1, 0, 1", vec!["-i", "i"]),
+            ("1, 0, -2", vec!["-1.414214", "1.414214"]),
+            ("1, 2, 5", vec!["-1 - 2i", "-1 + 2i"]),
+            ("1, -6, 11, -6", vec!["1", "2", "3"]),
+            (
+                "1, 0, 0, -1",
+                vec!["-0.5 - 0.866025i", "-0.5 + 0.866025i", "1"],
+            ),
+            ("(x - 1)^3, x", vec!["1", "1", "1"]),
+            ("1, -10, 35, -50, 24", vec!["1", "2", "3", "4"]),
+            ("1, 0, 0, 0, -16", vec!["-2", "-2i", "2i", "2"]),
+            (
+                "y^4 + 1, y",
+                vec![
+                    "-0.707107 - 0.707107i",
+                    "-0.707107 + 0.707107i",
+                    "0.707107 - 0.707107i",
+                    "0.707107 + 0.707107i",
+                ],
+            ),
+            ("1, -15, 85, -225, 274, -120", vec!["1", "2", "3", "4", "5"]),
+            ("x^5 - x, x", vec!["-1", "-i", "0", "i", "1"]),
+            ("0, 0, 3", vec![]),
+        ] {
+            assert_eq!(found(input, &settings), expected, "{input}");
+        }
+
+        // Integer roots stay exact, others get the decimals of the integer mode
+        let integers = Settings::default();
+        for (input, expected) in [
+            ("(2*x - 4) * (x + 3), x", vec!["-3", "2"]),
+            ("x^2-3x+2, x", vec!["1", "2"]),
+            (
+                "1, 0, -2",
+                vec!["-1.4142135623730950488", "1.4142135623730950488"],
+            ),
+            (
+                "1, 1, 1",
+                vec![
+                    "-0.5 - 0.86602540378443864676i",
+                    "-0.5 + 0.86602540378443864676i",
+                ],
+            ),
+            (
+                "1, 2, 3, 4, 5, 6",
+                vec![
+                    "-1.49179798813990071061",
+                    "-0.80578646938903122411 - 1.22290471337440986015i",
+                    "-0.80578646938903122411 + 1.22290471337440986015i",
+                    "0.55168546345898157941 - 1.25334886027720613625i",
+                    "0.55168546345898157941 + 1.25334886027720613625i",
+                ],
+            ),
+        ] {
+            assert_eq!(found(input, &integers), expected, "{input}");
+        }
+        assert!(
+            roots("1, 0, -2", &integers, &Context::default())
+                .unwrap()
+                .warnings
+                .is_empty()
+        );
+        let exact = Settings {
+            exact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            roots("1, 0, -2", &exact, &Context::default()).unwrap_err(),
+            Error::InexactCall("roots(1, 0, -2)".to_owned())
+        );
+
+        for (input, error) in [
+            ("0, 0", Error::MathDomain("roots(0, 0)".to_owned())),
+            (
+                "x^2 + 1/x, x",
+                Error::InvalidPolynomial {
+                    expr: "x^2 + 1/x".to_owned(),
+                    variable: "x".to_owned(),
+                },
+            ),
+            ("1, 2*i", Error::ComplexOperand("roots(1, 2*i)".to_owned())),
+            ("1, y", Error::UnknownVariable("y".to_owned())),
+            ("", Error::EmptyExpression),
+        ] {
+            assert_eq!(
+                roots(input, &integers, &Context::default()).unwrap_err(),
+                error,
+                "{input}"
+            );
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use super::{
+    Error,
+    ast::Ast,
+    eval::{Operator, Spanned, Token},
+};
+
+///
+/// Binding powers of an infix operator on its left and on its right, from its precedence.
+/// The right power of a left associative operator is higher than its left one, the other
+/// way round for right associative operators. The conditional is right associative, its
+/// first branch being bracketed by `?` and `:`
+///
+fn infix_binding_power(operator: Operator) -> Option<(u8, u8)> {
+    if operator.is_unary()
+        || matches!(
+            operator,
+            Operator::LeftBracket | Operator::RightBracket | Operator::Colon
+        )
+    {
+        return None;
+    }
+    let power = 2 * operator.precedence();
+    Some(if operator.is_right_associative() {
+        (power + 1, power)
+    } else {
+        (power, power + 1)
+    })
+}
+
+///
+/// Binding power of a prefix operator on its operand. Negation, bitwise not and roots bind
+/// tighter than products and looser than powers, so `-2^2` is `-(2^2)`, while absolute
+/// values are applied to their bracketed operand before anything else
+///
+fn prefix_binding_power(operator: Operator) -> u8 {
+    2 * operator.precedence()
+}
+
+///
+/// Parser building the expression tree straight from the tokens, which expressions are
+/// compiled from
+///
+pub(super) fn parse(source: &str, tokens: &[Spanned]) -> Result<Ast, Error> {
+    check_brackets(source, tokens)?;
+    let mut parser = Parser {
+        source: source.chars().collect(),
+        tokens,
+        position: 0,
+    };
+    let ast = parser.expression(0)?;
+    match parser.next() {
+        None => Ok(ast),
+        Some(Token::Operator(Operator::RightBracket)) => Err(parser.unmatched(parser.position - 1)),
+        Some(_) => Err(parser.unexpected()),
+    }
+}
+
+///
+/// Check that every left bracket is closed by a right one and the other way round, so that
+/// the conversion to RPN always finds the bracket it pops. The innermost bracket left open
+/// is the one reported
+///
+fn check_brackets(expr: &str, tokens: &[Spanned]) -> Result<(), Error> {
+    let unmatched = |position: usize| Error::UnmatchedParen {
+        bracket: expr.chars().nth(position).unwrap_or('('),
+        position,
+    };
+    let mut open = vec![];
+    for spanned in tokens {
+        match spanned.token {
+            Token::Operator(Operator::LeftBracket) => open.push(spanned.span.start),
+            Token::Operator(Operator::RightBracket) => {
+                open.pop().ok_or_else(|| unmatched(spanned.span.start))?;
+            }
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(position) => Err(unmatched(position)),
+        None => Ok(()),
+    }
+}
+
+struct Parser<'a> {
+    source: Vec<char>,
+    tokens: &'a [Spanned],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position).map(|spanned| &spanned.token)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    ///
+    /// Error pointing at the token just read, or at the last one when the expression ended
+    ///
+    fn unexpected(&self) -> Error {
+        self.unexpected_at(self.position - 1)
+    }
+
+    fn unexpected_at(&self, index: usize) -> Error {
+        let span = self.tokens[index.min(self.tokens.len() - 1)].span;
+        Error::InvalidSyntax {
+            found: self.source[span.start..span.end].iter().collect(),
+            position: span.start,
+        }
+    }
+
+    ///
+    /// Bracket at the given index, which has no counterpart
+    ///
+    fn unmatched(&self, index: usize) -> Error {
+        let position = self.tokens[index].span.start;
+        Error::UnmatchedParen {
+            bracket: self.source[position],
+            position,
+        }
+    }
+
+    ///
+    /// Read the right bracket closing the one at the given index, which is blamed when the
+    /// expression ends first
+    ///
+    fn close(&mut self, opened: usize) -> Result<(), Error> {
+        match self.next() {
+            Some(Token::Operator(Operator::RightBracket)) => Ok(()),
+            Some(_) => Err(self.unexpected()),
+            None => Err(self.unmatched(opened)),
+        }
+    }
+
+    fn expression(&mut self, min_binding_power: u8) -> Result<Ast, Error> {
+        let mut lhs = match self.next() {
+            Some(Token::Number(n)) => Ast::Number(*n),
+            Some(Token::Imaginary(n)) => Ast::Imaginary(*n),
+            Some(Token::Decimal(decimal)) => Ast::Decimal(decimal.clone()),
+            Some(Token::Previous) => Ast::Previous,
+            Some(Token::Answer(k)) => Ast::Answer(*k),
+            Some(Token::Variable(name)) => Ast::Variable(name.clone()),
+            Some(Token::Unit(name)) => Ast::Unit(name.clone()),
+            Some(Token::HigherOrder(call)) => Ast::HigherOrder(call.clone()),
+            Some(Token::Operator(Operator::LeftBracket)) => {
+                let opened = self.position - 1;
+                let inner = self.expression(0)?;
+                self.close(opened)?;
+                inner
+            }
+            Some(Token::Operator(operator)) if operator.is_unary() => {
+                let operator = *operator;
+                Ast::Unary(
+                    operator,
+                    Box::new(self.expression(prefix_binding_power(operator))?),
+                )
+            }
+            Some(Token::Function(function)) => {
+                let function = *function;
+                Ast::Call(function, self.arguments()?)
+            }
+            Some(Token::UserFunction(name)) => {
+                let name = name.clone();
+                Ast::UserCall(name, self.arguments()?)
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        while let Some(Token::Operator(operator)) = self.peek() {
+            let operator = *operator;
+            let Some((left, right)) = infix_binding_power(operator) else {
+                break;
+            };
+            if left < min_binding_power {
+                break;
+            }
+            self.next();
+            if operator == Operator::Question {
+                let then = self.expression(0)?;
+                if self.next() != Some(&Token::Operator(Operator::Colon)) {
+                    return Err(self.unexpected());
+                }
+                let otherwise = self.expression(right)?;
+                lhs = Ast::Conditional(Box::new(lhs), Box::new(then), Box::new(otherwise));
+                continue;
+            }
+            let rhs = self.expression(right)?;
+            lhs = Ast::Binary(operator, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    ///
+    /// Bracketed, comma separated arguments of a function call, none in `rand()`
+    ///
+    fn arguments(&mut self) -> Result<Vec<Ast>, Error> {
+        if self.next() != Some(&Token::Operator(Operator::LeftBracket)) {
+            return Err(self.unexpected());
+        }
+        let opened = self.position - 1;
+        if self.peek() == Some(&Token::Operator(Operator::RightBracket)) {
+            self.next();
+            return Ok(vec![]);
+        }
+        let mut args = vec![self.expression(0)?];
+        while self.peek() == Some(&Token::Comma) {
+            self.next();
+            args.push(self.expression(0)?);
+        }
+        self.close(opened)?;
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Ast, Error, Operator, parse};
+    use crate::internal::{eval::tokenize, rpn::Rpn};
+
+    fn pratt(expr: &str) -> Result<Ast, Error> {
+        parse(expr, &tokenize(expr).unwrap())
+    }
+
+    #[test]
+    fn test_rpn_round_trip() {
+        for expr in [
+            "4 + 18/(9-3)",
+            "(3+4) +  7 *2 -1-9",
+            "2^3^2",
+            "-2^2 * -(4 - 1)",
+            "100 / 10 / 5 - 3 - 2",
+            "||2 - 5| - 10| * -|3|^2",
+            "√2^4 + ∛-27 * -√√16",
+            "(1 << 4) | 0x0F & ~3 xor 5 >> 1 + 1",
+            "|(-3 | 4)| << 2 & 12",
+            "0 || 2 && 3 | 4 || 5 - 5 && 0",
+            "1 ? 2 : 0 ? 3 : 4 + 5",
+            "(1 ? 0 ? 2 : 3 : 4) * 2 || 1 ? 7 : 8",
+            "sqrt(16) + max(3, 7) * 2",
+            "min(4, max(1, 2 - 5), -3)^2",
+            "floor(rand() * 6) + 1",
+            "3x^2 - 2.5x + 1",
+        ] {
+            let program = Rpn::new(expr).unwrap();
+            let ast = pratt(expr).unwrap();
+
+            // The tree rebuilt from the compiled RPN is the one it was generated from
+            assert_eq!(ast, program.ast().unwrap(), "{expr}");
+            assert_eq!(ast.rpn(), program.rpn(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_pratt_unbalanced_brackets() {
+        for (expr, bracket, position) in [
+            ("(3 + 4", '(', 0),
+            ("3 + 4)", ')', 5),
+            ("[(1) + 2", '[', 0),
+            ("max(1, 2", '(', 3),
+        ] {
+            assert_eq!(
+                pratt(expr),
+                Err(Error::UnmatchedParen { bracket, position }),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_pratt_power_of_negation() {
+        assert_eq!(
+            pratt("-2^2").unwrap(),
+            Ast::Unary(
+                Operator::Neg,
+                Box::new(Ast::Binary(
+                    Operator::Pow,
+                    Box::new(Ast::Number(2)),
+                    Box::new(Ast::Number(2))
+                ))
+            )
+        );
+    }
+}
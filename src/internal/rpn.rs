@@ -0,0 +1,837 @@
+use std::time::Instant;
+
+use log::{debug, warn};
+
+use super::{
+    Context, Error, Evaluation, Settings, Stats, TraceStep, Warning,
+    ast::Ast,
+    complex,
+    eval::{Operator, Token, is_variable_name, parse_expr, strip_comment, tokenize},
+    function::Function,
+    number::Number,
+    physics, pratt, unit,
+};
+
+/// Operators as they are written in RPN, brackets and `?` never reaching it
+const RPN_OPERATORS: [Operator; 26] = [
+    Operator::Abs,
+    Operator::Sqrt,
+    Operator::Cbrt,
+    Operator::Neg,
+    Operator::Pow,
+    Operator::Prod,
+    Operator::Div,
+    Operator::Mod,
+    Operator::Sub,
+    Operator::Add,
+    Operator::Not,
+    Operator::Shl,
+    Operator::Shr,
+    Operator::BitAnd,
+    Operator::Xor,
+    Operator::BitOr,
+    Operator::Lt,
+    Operator::Gt,
+    Operator::Le,
+    Operator::Ge,
+    Operator::Eq,
+    Operator::Ne,
+    Operator::And,
+    Operator::Or,
+    Operator::In,
+    Operator::Colon,
+];
+
+///
+/// Where the evaluation goes on once the condition of a conditional, or the left operand of
+/// `&&` or `||`, is on the stack, so that the operands which do not matter are skipped
+///
+#[derive(Debug, Clone, Copy)]
+enum Branch {
+    /// Condition of the conditional at `end`, whose second branch starts at `second`
+    Conditional { second: usize, end: usize },
+    /// Left operand of the logical operator at `end`
+    Logical { operator: Operator, end: usize },
+}
+
+///
+/// Expression compiled to Reverse Polish Notation, generated from the tree the Pratt parser
+/// builds, or read back from RPN
+///
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone)]
+pub(super) struct Rpn {
+    /// RPN tokens in evaluation order
+    output_queue: Vec<Token>,
+    tokens: Vec<Token>,
+}
+
+impl Rpn {
+    pub(super) fn new(expr: &str) -> Result<Self, Error> {
+        let tokens = tokenize(expr)?;
+        let ast = pratt::parse(expr, &tokens)?;
+        debug!("Expression tree is {ast:?}");
+        Ok(Self {
+            output_queue: ast.rpn_tokens(),
+            tokens: tokens.into_iter().map(|spanned| spanned.token).collect(),
+        })
+    }
+
+    ///
+    /// Read back a Reverse Polish Notation written as `rpn` writes it, space separated as
+    /// in `3 4 + 2 *`, with calls written as `max/2`
+    ///
+    pub(super) fn from_rpn(rpn: &str) -> Result<Self, Error> {
+        let rpn = strip_comment(rpn).trim();
+        if rpn.is_empty() {
+            return Err(Error::EmptyExpression);
+        }
+        let tokens = rpn_words(rpn)
+            .into_iter()
+            .map(|word| rpn_token(word).ok_or_else(|| Error::InvalidRpn(rpn.to_owned())))
+            .collect::<Result<Vec<Token>, Error>>()?;
+        Ok(Self::from_rpn_tokens(tokens))
+    }
+
+    ///
+    /// Expression made of its RPN tokens, in evaluation order, which also stand for the
+    /// tokens it was written with
+    ///
+    pub(super) fn from_rpn_tokens(tokens: Vec<Token>) -> Self {
+        Self {
+            output_queue: tokens.clone(),
+            tokens,
+        }
+    }
+
+    ///
+    /// RPN tokens in evaluation order
+    ///
+    #[cfg(feature = "serde")]
+    pub(super) fn rpn_tokens(&self) -> Vec<Token> {
+        self.output_queue.clone()
+    }
+
+    ///
+    /// Measure the expression from its Reverse Polish Notation, replaying the evaluation
+    /// order while tracking the depth of each operand instead of its value
+    ///
+    pub(super) fn stats(&self) -> Stats {
+        let mut depths = vec![];
+        let mut operations = 0;
+        for token in self.output_queue.iter() {
+            match token {
+                Token::Number(_)
+                | Token::Imaginary(_)
+                | Token::Decimal(_)
+                | Token::Previous
+                | Token::Answer(_)
+                | Token::Variable(_)
+                | Token::Unit(_)
+                | Token::HigherOrder(_) => depths.push(1),
+                Token::Function(_) | Token::UserFunction(_) | Token::Comma => {}
+                Token::Operator(_) | Token::Call(_, _) | Token::UserCall(_, _) => {
+                    let arity = match token {
+                        Token::Call(_, count) | Token::UserCall(_, count) => *count,
+                        Token::Operator(operator) => operator.arity(),
+                        _ => 2,
+                    };
+                    let depth = (0..arity).filter_map(|_| depths.pop()).max();
+                    depths.push(depth.unwrap_or(0) + 1);
+                    operations += 1;
+                }
+            }
+        }
+
+        Stats {
+            tokens: self.tokens.len(),
+            depth: depths.into_iter().max().unwrap_or(0),
+            operations,
+        }
+    }
+
+    ///
+    /// Rebuild the expression structure from its Reverse Polish Notation, each operator
+    /// taking its operands from the nodes built before it
+    ///
+    pub(super) fn ast(&self) -> Result<Ast, Error> {
+        let mut nodes = vec![];
+        for token in self.output_queue.iter() {
+            let node = match token {
+                Token::Number(n) => Ast::Number(*n),
+                Token::Imaginary(n) => Ast::Imaginary(*n),
+                Token::Decimal(decimal) => Ast::Decimal(decimal.clone()),
+                Token::Previous => Ast::Previous,
+                Token::Answer(k) => Ast::Answer(*k),
+                Token::Variable(name) => Ast::Variable(name.clone()),
+                Token::Unit(name) => Ast::Unit(name.clone()),
+                Token::HigherOrder(call) => Ast::HigherOrder(call.clone()),
+                Token::Operator(operator) if operator.is_unary() => match nodes.pop() {
+                    Some(operand) => Ast::Unary(*operator, Box::new(operand)),
+                    None => return Err(self.invalid_rpn()),
+                },
+                Token::Call(function, count) => {
+                    if nodes.len() < *count {
+                        return Err(self.invalid_rpn());
+                    }
+                    Ast::Call(*function, nodes.split_off(nodes.len() - count))
+                }
+                Token::UserCall(name, count) => {
+                    if nodes.len() < *count {
+                        return Err(self.invalid_rpn());
+                    }
+                    Ast::UserCall(name.clone(), nodes.split_off(nodes.len() - count))
+                }
+                Token::Operator(Operator::Colon) => match (nodes.pop(), nodes.pop(), nodes.pop()) {
+                    (Some(otherwise), Some(then), Some(condition)) => {
+                        Ast::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise))
+                    }
+                    _ => return Err(self.invalid_rpn()),
+                },
+                Token::Operator(
+                    Operator::LeftBracket | Operator::RightBracket | Operator::Question,
+                )
+                | Token::Function(_)
+                | Token::UserFunction(_)
+                | Token::Comma => {
+                    return Err(self.invalid_rpn());
+                }
+                Token::Operator(operator) => match (nodes.pop(), nodes.pop()) {
+                    (Some(rhs), Some(lhs)) => Ast::Binary(*operator, Box::new(lhs), Box::new(rhs)),
+                    _ => return Err(self.invalid_rpn()),
+                },
+            };
+            nodes.push(node);
+        }
+
+        match (nodes.pop(), nodes.is_empty()) {
+            (Some(root), true) => Ok(root),
+            _ => Err(self.invalid_rpn()),
+        }
+    }
+
+    ///
+    /// Branch decided by each token of the Reverse Polish Notation, if any, found by
+    /// tracking where each operand starts instead of its value
+    ///
+    fn branches(&self) -> Result<Vec<Option<Branch>>, Error> {
+        let mut branches = vec![None; self.output_queue.len()];
+        let mut starts: Vec<usize> = vec![];
+        for (index, token) in self.output_queue.iter().enumerate() {
+            let arity = match token {
+                Token::Operator(operator) => operator.arity(),
+                Token::Call(_, count) | Token::UserCall(_, count) => *count,
+                Token::Function(_) | Token::UserFunction(_) | Token::Comma => {
+                    return Err(self.invalid_rpn());
+                }
+                _ => 0,
+            };
+            if starts.len() < arity {
+                return Err(self.invalid_rpn());
+            }
+            let operands = starts.split_off(starts.len() - arity);
+            match token {
+                Token::Operator(Operator::Colon) => {
+                    branches[operands[1] - 1] = Some(Branch::Conditional {
+                        second: operands[2],
+                        end: index,
+                    })
+                }
+                Token::Operator(operator @ (Operator::And | Operator::Or)) => {
+                    branches[operands[1] - 1] = Some(Branch::Logical {
+                        operator: *operator,
+                        end: index,
+                    })
+                }
+                _ => {}
+            }
+            starts.push(operands.first().copied().unwrap_or(index));
+        }
+        Ok(branches)
+    }
+
+    ///
+    /// User functions called by the expression, by name and number of arguments
+    ///
+    pub(super) fn calls(&self) -> Vec<(String, usize)> {
+        self.output_queue
+            .iter()
+            .filter_map(|token| match token {
+                Token::UserCall(name, count) => Some((name.clone(), *count)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn invalid_rpn(&self) -> Error {
+        Error::InvalidRpn(self.rpn())
+    }
+
+    ///
+    /// Reverse Polish Notation of the expression in evaluation order, space separated
+    ///
+    pub(super) fn rpn(&self) -> String {
+        self.output_queue
+            .iter()
+            .map(label)
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    ///
+    /// Evaluate the Reverse Polish Notation, which is left as it is so that the expression
+    /// can be evaluated again. Only the branch of a conditional its condition picks is
+    /// evaluated, and the right operand of `&&` and `||` when the left one does not decide
+    ///
+    pub(super) fn compute(
+        &self,
+        settings: &Settings,
+        context: &Context,
+    ) -> Result<Evaluation, Error> {
+        let mut stack = vec![];
+        let mut warnings = vec![];
+        let stats = self.stats();
+        let rpn = self.rpn();
+        let mut trace = vec![];
+        let branches = self.branches()?;
+        // Jumps over the second branch of the conditionals whose first branch is evaluated
+        let mut skips: Vec<(usize, usize)> = vec![];
+        let mut index = 0;
+        let start = Instant::now();
+        while let Some(token) = self.output_queue.get(index).cloned() {
+            if let Some(&(from, to)) = skips.last()
+                && from == index
+            {
+                skips.pop();
+                index = self.follow(&branches, to, &mut stack, &mut skips, settings)?;
+                continue;
+            }
+            let token_label = settings.trace.then(|| label(&token));
+            match token {
+                Token::Number(n) => stack.push(Number::from_literal(n, settings)?),
+                Token::Imaginary(n) => stack.push(complex::imaginary(n, settings)),
+                Token::Decimal(decimal) => {
+                    stack.push(Number::from_decimal_literal(&decimal, settings)?)
+                }
+                Token::Previous => stack.push(context.previous()?),
+                Token::Answer(k) => stack.push(context.answer(k)?),
+                Token::Variable(name) => match context.constant(&name, settings) {
+                    // All the built-in constants are irrational, the integer mode computes
+                    // with their decimals unless results have to be exact. Decimals are
+                    // rounded to the precision without notice
+                    Some(_) if settings.exact && settings.decimals.is_none() => {
+                        return Err(Error::InexactConstant(name));
+                    }
+                    Some(value) => stack.push(value),
+                    None => match physics::named(&name) {
+                        Some(constant) => stack.push(constant.quantity(settings)?),
+                        None => stack.push(context.variable(&name)?),
+                    },
+                },
+                Token::Unit(name) => {
+                    stack.push(unit::named(&name).ok_or(Error::UnknownVariable(name))?)
+                }
+                Token::HigherOrder(call) => stack.push(call.call::<Rpn>(settings, context)?),
+                Token::Operator(operator) if operator.is_unary() => match stack.pop() {
+                    Some(v) => {
+                        check_root(&operator, &v, settings, &mut warnings)?;
+                        stack.push(operator.execute_unary(v, settings)?)
+                    }
+                    None => break,
+                },
+                Token::Call(function, count) => {
+                    function.check_arguments(count)?;
+                    if stack.len() < count {
+                        break;
+                    }
+                    let args = stack.split_off(stack.len() - count);
+                    if let Some(operator) = function.operator() {
+                        check_root(&operator, &args[0], settings, &mut warnings)?;
+                    } else if function.truncates(&args, settings) {
+                        return Err(Error::InexactCall(function.display(&args)));
+                    }
+                    stack.push(function.call(args, settings, context)?);
+                }
+                Token::UserCall(name, count) => {
+                    if stack.len() < count {
+                        break;
+                    }
+                    let args = stack.split_off(stack.len() - count);
+                    let call = context.call(&name, args, settings)?;
+                    warnings.extend(call.warnings);
+                    stack.push(call.result);
+                }
+                Token::Function(_) | Token::UserFunction(_) | Token::Comma => break,
+                // Only reached after the second branch, which the condition picked
+                Token::Operator(Operator::Colon) => {}
+                Token::Operator(operator) => {
+                    let v2_opt = stack.pop();
+                    let v1_opt = stack.pop();
+
+                    if let (Some(v1), Some(v2)) = (v1_opt, v2_opt) {
+                        if settings.exact
+                            && let Some(conversion) = unit::rounded_conversion(operator, &v1, &v2)
+                        {
+                            return Err(Error::InexactConversion(conversion));
+                        }
+                        if operator.truncates(&v1, &v2) {
+                            if settings.exact {
+                                return Err(Error::InexactDivision(v1, v2));
+                            }
+                            warn!("Division {v1} / {v2} truncated toward zero");
+                            warnings.push(Warning::TruncatedDivision(v1.clone(), v2.clone()));
+                        }
+                        stack.push(operator.execute(v1, v2, settings)?);
+                    } else {
+                        break;
+                    }
+                }
+            }
+            if let Some(token) = token_label {
+                trace.push(TraceStep {
+                    token,
+                    stack: stack.clone(),
+                    elapsed: start.elapsed(),
+                });
+            }
+            index = self.follow(&branches, index + 1, &mut stack, &mut skips, settings)?;
+        }
+
+        match stack.into_iter().next() {
+            Some(v) => Ok(Evaluation {
+                result: v,
+                warnings,
+                stats,
+                rpn,
+                trace,
+            }),
+            None => Err(Error::InvalidRpn(rpn)),
+        }
+    }
+
+    ///
+    /// Index of the token to evaluate once the one before `index` is, which skips the
+    /// operands the branch it ends does not need. A skipped operator may itself end
+    /// another branch
+    ///
+    fn follow(
+        &self,
+        branches: &[Option<Branch>],
+        mut index: usize,
+        stack: &mut Vec<Number>,
+        skips: &mut Vec<(usize, usize)>,
+        settings: &Settings,
+    ) -> Result<usize, Error> {
+        loop {
+            match branches[index - 1] {
+                Some(Branch::Conditional { second, end }) => {
+                    let condition = stack.pop().ok_or_else(|| self.invalid_rpn())?;
+                    if condition.is_zero() {
+                        return Ok(second);
+                    }
+                    skips.push((second, end + 1));
+                    return Ok(index);
+                }
+                Some(Branch::Logical { operator, end })
+                    if stack
+                        .last()
+                        .is_some_and(|lhs| lhs.is_zero() == (operator == Operator::And)) =>
+                {
+                    stack.pop();
+                    let result = i128::from(operator == Operator::Or);
+                    stack.push(Number::from_literal(result, settings)?);
+                    index = end + 1;
+                }
+                _ => return Ok(index),
+            }
+        }
+    }
+}
+
+///
+/// Reject or warn about a root that would be truncated, depending on the exact setting
+///
+fn check_root(
+    operator: &Operator,
+    v: &Number,
+    settings: &Settings,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Error> {
+    if let Some((root, radicand)) = operator.rounded_root(v) {
+        if settings.exact {
+            return Err(Error::InexactRoot(root, radicand));
+        }
+        warn!("Root {root}{radicand} truncated toward zero");
+        warnings.push(Warning::TruncatedRoot(root, radicand));
+    }
+    Ok(())
+}
+
+///
+/// Words of an RPN, split on the whitespace outside of brackets so that higher-order calls
+/// keep the expressions they are written with
+///
+fn rpn_words(rpn: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut depth = 0;
+    let mut start = None;
+    for (index, c) in rpn.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if c.is_whitespace() && depth <= 0 => {
+                if let Some(start) = start.take() {
+                    words.push(&rpn[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    words.extend(start.map(|start| &rpn[start..]));
+    words
+}
+
+///
+/// Token written as the word of an RPN, None when no token is written this way
+///
+fn rpn_token(word: &str) -> Option<Token> {
+    if let Some(operator) = RPN_OPERATORS
+        .into_iter()
+        .find(|operator| String::from(operator) == word)
+    {
+        return Some(Token::Operator(operator));
+    }
+    if let Some((name, count)) = word.split_once('/') {
+        let count = count.parse().ok()?;
+        return match Function::from_name(name) {
+            Some(function) => Some(Token::Call(function, count)),
+            None if is_variable_name(name) => Some(Token::UserCall(name.to_owned(), count)),
+            None => None,
+        };
+    }
+    match parse_expr(word).ok()?.as_slice() {
+        [
+            token @ (Token::Number(_)
+            | Token::Imaginary(_)
+            | Token::Decimal(_)
+            | Token::Previous
+            | Token::Answer(_)
+            | Token::Variable(_)
+            | Token::HigherOrder(_)),
+        ] => Some(token.clone()),
+        _ => None,
+    }
+}
+
+pub(super) fn label(token: &Token) -> String {
+    match token {
+        // Brackets never reach the queue, unbalanced ones being rejected beforehand
+        Token::Operator(Operator::LeftBracket) => "(".to_owned(),
+        Token::Operator(Operator::RightBracket) => ")".to_owned(),
+        token => token.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::internal::eval::parse_expr;
+
+    use super::{Ast, Context, Error, Number, Operator, Rpn, Settings, Stats, Token};
+
+    fn unexpected(found: &str, position: usize) -> Error {
+        Error::InvalidSyntax {
+            found: found.to_owned(),
+            position,
+        }
+    }
+
+    #[test]
+    fn test_rpn_from_expression_signed_negative() {
+        let expression = "4 + 18/(9--3)";
+
+        assert_eq!(
+            parse_expr(expression).unwrap(),
+            vec![
+                Token::Number(4),                        // 4
+                Token::Operator(Operator::Add),          // +
+                Token::Number(18),                       // 18
+                Token::Operator(Operator::Div),          // /
+                Token::Operator(Operator::LeftBracket),  // (
+                Token::Number(9),                        // 9
+                Token::Operator(Operator::Sub),          // -
+                Token::Operator(Operator::Neg),          // -
+                Token::Number(3),                        // 3
+                Token::Operator(Operator::RightBracket), // )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpn_from_expression_signed_positive() {
+        let expression = "4 + 18/(9-+3)";
+
+        assert_eq!(
+            parse_expr(expression).unwrap(),
+            vec![
+                Token::Number(4),                        // 4
+                Token::Operator(Operator::Add),          // +
+                Token::Number(18),                       // 18
+                Token::Operator(Operator::Div),          // /
+                Token::Operator(Operator::LeftBracket),  // (
+                Token::Number(9),                        // 9
+                Token::Operator(Operator::Sub),          // - (unary + is dropped)
+                Token::Number(3),                        // 3
+                Token::Operator(Operator::RightBracket), // )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpn_from_expression_leading_negative() {
+        let expression = "-(2+3)";
+
+        assert_eq!(
+            parse_expr(expression).unwrap(),
+            vec![
+                Token::Operator(Operator::Neg),          // -
+                Token::Operator(Operator::LeftBracket),  // (
+                Token::Number(2),                        // 2
+                Token::Operator(Operator::Add),          // +
+                Token::Number(3),                        // 3
+                Token::Operator(Operator::RightBracket), // )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpn_from_expression_invalid_prod() {
+        let expression = "4 + 18/(9-*3)";
+
+        assert_eq!(parse_expr(expression), Err(unexpected("*", 10)));
+    }
+
+    #[test]
+    fn test_rpn_from_expression_invalid_div() {
+        let expression = "4 + 18/(9-/3)";
+
+        assert_eq!(parse_expr(expression), Err(unexpected("/", 10)));
+    }
+
+    #[test]
+    fn test_rpn_from_expression_spaced_invalid_prod() {
+        assert_eq!(parse_expr("4 + * 3"), Err(unexpected("*", 4)));
+        assert_eq!(parse_expr("4 +* 3"), Err(unexpected("*", 3)));
+    }
+
+    #[test]
+    fn test_rpn_from_expression_spaced_invalid_div() {
+        assert_eq!(parse_expr("4 * / 3"), Err(unexpected("/", 4)));
+        assert_eq!(parse_expr("4 */ 3"), Err(unexpected("/", 3)));
+    }
+
+    #[test]
+    fn test_rpn_from_expression_spaced_invalid_bracket() {
+        assert_eq!(parse_expr("(4 - )"), Err(unexpected(")", 5)));
+        assert_eq!(parse_expr("(4 -)"), Err(unexpected(")", 4)));
+    }
+
+    #[test]
+    fn test_rpn_from_expression_trailing_operator() {
+        assert_eq!(
+            parse_expr("3 +"),
+            Err(Error::TrailingOperator {
+                operator: '+',
+                position: 2
+            })
+        );
+        assert_eq!(
+            parse_expr("4 * 2 -  "),
+            Err(Error::TrailingOperator {
+                operator: '-',
+                position: 6
+            })
+        );
+        assert_eq!(
+            parse_expr("-"),
+            Err(Error::TrailingOperator {
+                operator: '-',
+                position: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_rpn_from_expression_trailing_bracket() {
+        assert_eq!(
+            parse_expr("4 * ("),
+            Err(Error::TrailingOperator {
+                operator: '(',
+                position: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_rpn_unmatched_brackets() {
+        for (expr, bracket, position) in [
+            ("(1+2", '(', 0),
+            ("1+2)", ')', 3),
+            ("((1)", '(', 0),
+            ("(1) + (2", '(', 6),
+            ("1) + (2", ')', 1),
+            ("{1 + [2]", '{', 0),
+            ("max(1, 2))", ')', 9),
+        ] {
+            assert_eq!(
+                Rpn::new(expr).unwrap_err(),
+                Error::UnmatchedParen { bracket, position },
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rpn() {
+        let program = Rpn::new("4 + 18/(9-3)").unwrap();
+
+        assert_eq!(
+            program.output_queue,
+            vec![
+                Token::Number(4),
+                Token::Number(18),
+                Token::Number(9),
+                Token::Number(3),
+                Token::Operator(Operator::Sub),
+                Token::Operator(Operator::Div),
+                Token::Operator(Operator::Add),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpn_string() {
+        let program = Rpn::new("(3+4) + 7*2 - -1").unwrap();
+
+        assert_eq!(program.rpn(), "3 4 + 7 2 * + 1 neg -");
+    }
+
+    #[test]
+    fn test_rpn_precedence() {
+        for (expr, rpn) in [
+            ("2^3^2", "2 3 2 ^ ^"),
+            ("10 - 4 - 3", "10 4 - 3 -"),
+            ("-2^2", "2 2 ^ neg"),
+            ("1 | 2 & 3 << 1 + 1", "1 2 3 1 1 + << & |"),
+            ("1 || 0 && 0", "1 0 0 && ||"),
+            ("1 << 2 > 3 == 1 & 1", "1 2 << 3 > 1 == 1 &"),
+        ] {
+            assert_eq!(Rpn::new(expr).unwrap().rpn(), rpn, "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_rpn_function_calls() {
+        let program = Rpn::new("max(3, 7 - 1) * sqrt(4)").unwrap();
+
+        assert_eq!(program.rpn(), "3 7 1 - max/2 4 sqrt/1 *");
+    }
+
+    #[test]
+    fn test_stats() {
+        let program = Rpn::new("(3 + 4) * -2").unwrap();
+
+        assert_eq!(
+            program.stats(),
+            Stats {
+                tokens: 8,
+                depth: 3,
+                operations: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_ast() {
+        let program = Rpn::new("2 ^ 3 * -(4 - 1)").unwrap();
+
+        assert_eq!(
+            program.ast().unwrap(),
+            Ast::Binary(
+                Operator::Prod,
+                Box::new(Ast::Binary(
+                    Operator::Pow,
+                    Box::new(Ast::Number(2)),
+                    Box::new(Ast::Number(3))
+                )),
+                Box::new(Ast::Unary(
+                    Operator::Neg,
+                    Box::new(Ast::Binary(
+                        Operator::Sub,
+                        Box::new(Ast::Number(4)),
+                        Box::new(Ast::Number(1))
+                    ))
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let program = Rpn::new("2 * -3").unwrap();
+        let settings = Settings {
+            trace: true,
+            ..Default::default()
+        };
+        let steps = program
+            .compute(&settings, &Context::default())
+            .unwrap()
+            .trace
+            .into_iter()
+            .map(|step| (step.token, step.stack))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            steps,
+            vec![
+                ("2".to_owned(), vec![Number::Int(2)]),
+                ("3".to_owned(), vec![Number::Int(2), Number::Int(3)]),
+                ("neg".to_owned(), vec![Number::Int(2), Number::Int(-3)]),
+                ("*".to_owned(), vec![Number::Int(-6)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_result_computation() {
+        let program = Rpn::new("4 + 18/(9-3)").unwrap();
+
+        assert_eq!(
+            program
+                .compute(&Settings::default(), &Context::default())
+                .unwrap()
+                .result,
+            7
+        );
+    }
+
+    #[test]
+    fn test_branches_skipped() {
+        for (rpn, result) in [
+            ("0 1 0 / 5 ?:", 5),
+            ("1 5 1 0 / ?:", 5),
+            ("1 0 1 0 / 2 ?: 1 0 / ?: 3 *", 6),
+            ("0 1 0 / && 1 0 / 1 ?: 7 +", 8),
+            ("1 1 0 / ||", 1),
+            ("1 0 3 ?: 1 0 / 7 ?: 1 +", 8),
+            ("1 0 3 ?: 1 0 / &&", 0),
+        ] {
+            let evaluation = Rpn::from_rpn(rpn)
+                .unwrap()
+                .compute(&Settings::default(), &Context::default())
+                .unwrap();
+            assert_eq!(evaluation.result, result, "{rpn}");
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+
+use super::{
+    Context, Error, Settings, Warning,
+    eval::{Operator, is_variable_name, strip_comment},
+    number::Number,
+    polynomial::{evaluate_all, split_arguments},
+    rpn::Rpn,
+};
+
+/// Rows of a table, beyond which the range is refused
+const MAX_ROWS: usize = 1000;
+
+///
+/// Values of an expression for evenly spaced values of its variable
+///
+#[derive(Debug)]
+pub struct Table {
+    pub variable: String,
+    pub expr: String,
+    /// Each value of the variable with the value of the expression, or the error computing it
+    pub rows: Vec<(Number, Result<Number, Error>)>,
+    pub warnings: Vec<Warning>,
+    /// Whether the table was asked as comma-separated values
+    pub csv: bool,
+}
+
+///
+/// Tabulate an expression followed by its variable and the start, stop and step of its
+/// values, as in `x^2, x, 0, 1, 0.25`, optionally followed by `csv`. The variable goes
+/// from the start toward the stop, which is included when a step lands on it. Rows where
+/// the expression is not defined show the error
+///
+pub fn table(input: &str, settings: &Settings, context: &Context) -> Result<Table, Error> {
+    let args = split_arguments(strip_comment(input));
+    let call = format!("table({})", args.join(", "));
+    let (expr, variable, range, csv) = match args.as_slice() {
+        [expr, variable, range @ ..] if range.len() == 3 => (expr, variable, range, false),
+        [expr, variable, range @ .., "csv"] if range.len() == 3 => (expr, variable, range, true),
+        _ => {
+            return Err(Error::ArgumentCount {
+                function: "table".to_owned(),
+                count: args.len(),
+            });
+        }
+    };
+    if !is_variable_name(variable) {
+        return Err(Error::InvalidAssignment((*variable).to_owned()));
+    }
+    let body = Rpn::new(expr)?;
+    let range = evaluate_all(range, settings, context)?;
+    if range.iter().any(|n| matches!(n, Number::Complex(_))) {
+        return Err(Error::ComplexOperand(call));
+    }
+    if range.iter().any(|n| matches!(n, Number::Matrix(_))) {
+        return Err(Error::MatrixOperand(call));
+    }
+    if range.iter().any(|n| matches!(n, Number::Quantity(_))) {
+        return Err(Error::UnitOperand(call));
+    }
+    if range.iter().any(|n| matches!(n, Number::Date(_))) {
+        return Err(Error::DateOperand(call));
+    }
+    let [start, stop, step] = range.as_slice() else {
+        unreachable!("Three values were evaluated");
+    };
+    let zero = Number::from_literal(0, settings)?;
+    if step.is_zero() || (*step > zero && start > stop) || (*step < zero && start < stop) {
+        return Err(Error::MathDomain(call));
+    }
+
+    let mut scope = context.clone();
+    let mut rows = vec![];
+    let mut warnings = vec![];
+    for index in 0.. {
+        // Multiplying the step, rather than adding it up, keeps its rounding from piling up
+        let offset = Operator::Prod.execute(
+            step.clone(),
+            Number::from_literal(index, settings)?,
+            settings,
+        )?;
+        let x = Operator::Add.execute(start.clone(), offset, settings)?;
+        if (*step > zero && x > *stop) || (*step < zero && x < *stop) {
+            break;
+        }
+        if rows.len() == MAX_ROWS {
+            return Err(Error::TooManyTerms(call));
+        }
+        scope.assign(variable, x.clone());
+        let value = body.compute(settings, &scope).map(|evaluation| {
+            warnings.extend(evaluation.warnings);
+            evaluation.result
+        });
+        rows.push((x, value));
+    }
+    if rows.iter().all(|(_, value)| value.is_err())
+        && let Some((_, Err(e))) = rows.drain(..).next()
+    {
+        return Err(e);
+    }
+    Ok(Table {
+        variable: (*variable).to_owned(),
+        expr: (*expr).to_owned(),
+        rows,
+        warnings,
+        csv,
+    })
+}
+
+impl Table {
+    ///
+    /// Table as comma-separated values, the header first and the errors as their kind
+    ///
+    pub fn to_csv(&self) -> String {
+        self.csv_with(Number::to_string)
+    }
+
+    ///
+    /// Write the table with the given function writing its numbers, as two aligned columns
+    /// under a header, or as comma-separated values when asked so
+    ///
+    pub fn display_with(&self, cell: impl Fn(&Number) -> String) -> String {
+        if self.csv {
+            return self.csv_with(cell);
+        }
+        let cells: Vec<(String, String)> = self
+            .rows
+            .iter()
+            .map(|(x, value)| {
+                let value = match value {
+                    Ok(value) => cell(value),
+                    Err(e) => format!("error: {e}"),
+                };
+                (cell(x), value)
+            })
+            .collect();
+        let width = cells
+            .iter()
+            .map(|(x, _)| x.chars().count())
+            .chain([self.variable.chars().count()])
+            .max()
+            .unwrap_or_default();
+        let rule = cells
+            .iter()
+            .map(|(_, value)| value.chars().count())
+            .chain([self.expr.chars().count()])
+            .max()
+            .unwrap_or_default();
+        let mut table = format!("{:>width$} | {}\n", self.variable, self.expr);
+        table.push_str(&format!("{}-+-{}\n", "-".repeat(width), "-".repeat(rule)));
+        for (x, value) in cells {
+            table.push_str(&format!("{x:>width$} | {value}\n"));
+        }
+        table
+    }
+
+    fn csv_with(&self, cell: impl Fn(&Number) -> String) -> String {
+        let mut csv = format!("{},{}\n", field(&self.variable), field(&self.expr));
+        for (x, value) in &self.rows {
+            let value = match value {
+                Ok(value) => cell(value),
+                Err(e) => e.kind().to_owned(),
+            };
+            csv.push_str(&format!("{},{}\n", field(&cell(x)), field(&value)));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with(Number::to_string))
+    }
+}
+
+///
+/// Value written as a CSV field, quoted when it holds a comma or a quote
+///
+fn field(value: &str) -> String {
+    if value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Context, Error, Number, Settings, Warning, table};
+
+    #[test]
+    fn test_table() {
+        let settings = Settings::default();
+        let context = Context::default();
+
+        let squares = table("x^2, x, 1, 4, 1", &settings, &context).unwrap();
+        assert_eq!(
+            squares.to_string(),
+            "x | x^2\n--+----\n1 | 1\n2 | 4\n3 | 9\n4 | 16\n"
+        );
+        assert_eq!(squares.to_csv(), "x,x^2\n1,1\n2,4\n3,9\n4,16\n");
+
+        let decimals = Settings {
+            decimals: Some(2),
+            ..Default::default()
+        };
+        let halves = table("2 * t, t, 1, 0, -0.25, csv", &decimals, &context).unwrap();
+        assert!(halves.csv);
+        assert_eq!(
+            halves.to_string(),
+            "t,2 * t\n1,2\n0.75,1.5\n0.5,1\n0.25,0.5\n0,0\n"
+        );
+        // Numbers may be written as results are, a field holding a comma being quoted
+        assert_eq!(
+            halves.display_with(|n| n.to_string().replace('.', ",")),
+            "t,2 * t\n1,2\n\"0,75\",\"1,5\"\n\"0,5\",1\n\"0,25\",\"0,5\"\n0,0\n"
+        );
+        // The stop is left out when no step lands on it
+        let odd = table("x, x, 0, 5, 2", &settings, &context).unwrap();
+        assert_eq!(odd.rows.len(), 3);
+
+        let divisions = table("12 / x, x, -1, 2, 1", &settings, &context).unwrap();
+        assert_eq!(
+            divisions.to_string(),
+            format!(
+                " x | 12 / x\n---+-{}\n-1 | -12\n 0 | error: Division by zero computing 12 / 0\n \
+                 1 | 12\n 2 | 6\n",
+                "-".repeat(40)
+            )
+        );
+        assert_eq!(
+            divisions.to_csv(),
+            "x,12 / x\n-1,-12\n0,division by zero\n1,12\n2,6\n"
+        );
+        let truncated = table("5 / x, x, 2, 2, 1", &settings, &context).unwrap();
+        assert_eq!(
+            truncated.warnings,
+            [Warning::TruncatedDivision(Number::Int(5), Number::Int(2))]
+        );
+        assert_eq!(
+            table("max(x, 1), x, 0, 1, 1", &settings, &context)
+                .unwrap()
+                .to_csv(),
+            "x,\"max(x, 1)\"\n0,1\n1,1\n"
+        );
+
+        for (input, error) in [
+            (
+                "x, x, 0, 1, 0",
+                Error::MathDomain("table(x, x, 0, 1, 0)".to_owned()),
+            ),
+            (
+                "x, x, 1, 0, 1",
+                Error::MathDomain("table(x, x, 1, 0, 1)".to_owned()),
+            ),
+            (
+                "x, x, 0, 5000, 1",
+                Error::TooManyTerms("table(x, x, 0, 5000, 1)".to_owned()),
+            ),
+            (
+                "x, x, 0, 1",
+                Error::ArgumentCount {
+                    function: "table".to_owned(),
+                    count: 4,
+                },
+            ),
+            ("x, 2, 0, 1, 1", Error::InvalidAssignment("2".to_owned())),
+            ("y, x, 0, 1, 1", Error::UnknownVariable("y".to_owned())),
+        ] {
+            assert_eq!(
+                table(input, &settings, &context).unwrap_err(),
+                error,
+                "{input}"
+            );
+        }
+    }
+}
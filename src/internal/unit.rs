@@ -0,0 +1,724 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::{
+    Error, Settings, decimal::Decimal, eval::Operator, function::Function, number::Number,
+};
+
+///
+/// Exponents of length, mass, time, electric current, temperature, amount of substance and
+/// plane angle. Angles are kept apart from plain numbers, so that degrees only add up with
+/// angles
+///
+type Dimension = [i32; 7];
+
+const NONE: Dimension = [0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const ANGLE: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+
+///
+/// Unit written after a number, with its dimension and its size in SI units as a fraction.
+/// Angles are sized in degrees, as the radian is not a fraction of them
+///
+struct Definition {
+    name: &'static str,
+    dimension: Dimension,
+    scale: (u64, u64),
+}
+
+///
+/// Seconds written as hours, minutes and seconds, as in `1:45:30`
+///
+pub(super) const CLOCK: &str = "hms";
+
+///
+/// Arcseconds written as degrees, minutes and seconds, as in `45°30'15"`
+///
+pub(super) const DMS: &str = "dms";
+
+///
+/// Names of the radian, which marks a plain number as it is the angle of numbers, as in
+/// `sin(0.5 rad)`
+///
+const RADIANS: &[&str] = &["rad", "radian", "radians"];
+
+const UNITS: &[Definition] = &[
+    unit("m", LENGTH, 1, 1),
+    unit("km", LENGTH, 1000, 1),
+    unit("cm", LENGTH, 1, 100),
+    unit("mm", LENGTH, 1, 1000),
+    unit("mi", LENGTH, 1_609_344, 1000),
+    unit("yd", LENGTH, 9144, 10_000),
+    unit("ft", LENGTH, 3048, 10_000),
+    unit("in", LENGTH, 254, 10_000),
+    unit("kg", MASS, 1, 1),
+    unit("g", MASS, 1, 1000),
+    unit("mg", MASS, 1, 1_000_000),
+    unit("lb", MASS, 45_359_237, 100_000_000),
+    unit("oz", MASS, 45_359_237, 1_600_000_000),
+    unit("s", TIME, 1, 1),
+    unit("ms", TIME, 1, 1000),
+    unit("min", TIME, 60, 1),
+    unit("h", TIME, 3600, 1),
+    unit("day", TIME, 86_400, 1),
+    unit("week", TIME, 604_800, 1),
+    unit(CLOCK, TIME, 1, 1),
+    unit("L", VOLUME, 1, 1000),
+    unit("mL", VOLUME, 1, 1_000_000),
+    unit("mph", SPEED, 1_609_344, 3_600_000),
+    unit("Hz", FREQUENCY, 1, 1),
+    unit("N", FORCE, 1, 1),
+    unit("J", ENERGY, 1, 1),
+    unit("kJ", ENERGY, 1000, 1),
+    unit("cal", ENERGY, 4184, 1000),
+    unit("kcal", ENERGY, 4184, 1),
+    unit("kWh", ENERGY, 3_600_000, 1),
+    unit("W", POWER, 1, 1),
+    unit("kW", POWER, 1000, 1),
+    unit("Pa", PRESSURE, 1, 1),
+    unit("kPa", PRESSURE, 1000, 1),
+    unit("bar", PRESSURE, 100_000, 1),
+    unit("A", CURRENT, 1, 1),
+    unit("K", TEMPERATURE, 1, 1),
+    unit("mol", AMOUNT, 1, 1),
+    unit("deg", ANGLE, 1, 1),
+    unit("arcmin", ANGLE, 1, 60),
+    unit("arcsec", ANGLE, 1, 3600),
+    unit(DMS, ANGLE, 1, 3600),
+];
+
+const fn unit(
+    name: &'static str,
+    dimension: Dimension,
+    numerator: u64,
+    denominator: u64,
+) -> Definition {
+    Definition {
+        name,
+        dimension,
+        scale: (numerator, denominator),
+    }
+}
+
+///
+/// Long names and plurals read as the unit they stand for, as in `45 days`
+///
+const ALIASES: &[(&str, &str)] = &[
+    ("meter", "m"),
+    ("meters", "m"),
+    ("metre", "m"),
+    ("metres", "m"),
+    ("kilometer", "km"),
+    ("kilometers", "km"),
+    ("kilometre", "km"),
+    ("kilometres", "km"),
+    ("centimeter", "cm"),
+    ("centimeters", "cm"),
+    ("centimetre", "cm"),
+    ("centimetres", "cm"),
+    ("millimeter", "mm"),
+    ("millimeters", "mm"),
+    ("millimetre", "mm"),
+    ("millimetres", "mm"),
+    ("mile", "mi"),
+    ("miles", "mi"),
+    ("yard", "yd"),
+    ("yards", "yd"),
+    ("foot", "ft"),
+    ("feet", "ft"),
+    ("inch", "in"),
+    ("inches", "in"),
+    ("kilogram", "kg"),
+    ("kilograms", "kg"),
+    ("gram", "g"),
+    ("grams", "g"),
+    ("milligram", "mg"),
+    ("milligrams", "mg"),
+    ("pound", "lb"),
+    ("pounds", "lb"),
+    ("ounce", "oz"),
+    ("ounces", "oz"),
+    ("second", "s"),
+    ("seconds", "s"),
+    ("millisecond", "ms"),
+    ("milliseconds", "ms"),
+    ("minute", "min"),
+    ("minutes", "min"),
+    ("mins", "min"),
+    ("hour", "h"),
+    ("hours", "h"),
+    ("hr", "h"),
+    ("hrs", "h"),
+    ("days", "day"),
+    ("weeks", "week"),
+    ("liter", "L"),
+    ("liters", "L"),
+    ("litre", "L"),
+    ("litres", "L"),
+    ("degree", "deg"),
+    ("degrees", "deg"),
+];
+
+fn definition(name: &str) -> &'static Definition {
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, unit)| unit);
+    UNITS
+        .iter()
+        .find(|definition| definition.name == name)
+        .expect("Units are built from their definitions")
+}
+
+///
+/// Whether the name is the one of a unit, as `km` or `h`
+///
+pub(super) fn is_unit(name: &str) -> bool {
+    UNITS.iter().any(|definition| definition.name == name)
+        || ALIASES.iter().any(|(alias, _)| *alias == name)
+        || RADIANS.contains(&name)
+}
+
+///
+/// Product of units raised to integer powers, as `km/h` or `kg*m/s^2`, in the order they
+/// were first written
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Unit {
+    factors: Vec<(&'static str, i32)>,
+}
+
+impl Unit {
+    pub(super) fn new(factors: &[(&'static str, i32)]) -> Unit {
+        Unit {
+            factors: factors.to_vec(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    fn dimension(&self) -> Dimension {
+        let mut dimension = NONE;
+        for (name, exponent) in &self.factors {
+            for (total, base) in dimension.iter_mut().zip(definition(name).dimension) {
+                *total += base * exponent;
+            }
+        }
+        dimension
+    }
+
+    ///
+    /// Size of the unit in SI units, as a numerator and a denominator
+    ///
+    fn scale(&self) -> (BigInt, BigInt) {
+        let (mut numerator, mut denominator) = (BigInt::one(), BigInt::one());
+        for (name, exponent) in &self.factors {
+            let (n, d) = definition(name).scale;
+            let (n, d) = (
+                BigInt::from(n).pow(exponent.unsigned_abs()),
+                BigInt::from(d).pow(exponent.unsigned_abs()),
+            );
+            if *exponent > 0 {
+                (numerator, denominator) = (numerator * n, denominator * d);
+            } else {
+                (numerator, denominator) = (numerator * d, denominator * n);
+            }
+        }
+        (numerator, denominator)
+    }
+
+    ///
+    /// Product of the units, or quotient for a negative sign, factors of the same unit
+    /// adding up their exponents
+    ///
+    fn times(&self, other: &Unit, sign: i32) -> Unit {
+        let mut factors = self.factors.clone();
+        for &(name, exponent) in &other.factors {
+            match factors.iter_mut().find(|(factor, _)| *factor == name) {
+                Some((_, total)) => *total += sign * exponent,
+                None => factors.push((name, sign * exponent)),
+            }
+        }
+        factors.retain(|(_, exponent)| *exponent != 0);
+        Unit { factors }
+    }
+
+    fn pow(&self, k: i32) -> Unit {
+        Unit {
+            factors: self
+                .factors
+                .iter()
+                .map(|&(name, exponent)| (name, exponent * k))
+                .filter(|(_, exponent)| *exponent != 0)
+                .collect(),
+        }
+    }
+
+    ///
+    /// Unit whose k-th power is this one, None when an exponent is not a multiple of k
+    ///
+    fn root(&self, k: i32) -> Option<Unit> {
+        self.factors
+            .iter()
+            .map(|&(name, exponent)| (exponent % k == 0).then_some((name, exponent / k)))
+            .collect::<Option<_>>()
+            .map(|factors| Unit { factors })
+    }
+}
+
+///
+/// Written with `*` between the factors and `/` before the ones with a negative exponent,
+/// as in `kg*m/s^2`, or with negative exponents when all of them are, as in `s^-1`
+///
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let power = |name: &str, exponent: i32| match exponent {
+            1 => name.to_owned(),
+            k => format!("{name}^{k}"),
+        };
+        let (numerator, denominator): (Vec<_>, Vec<_>) =
+            self.factors.iter().partition(|(_, exponent)| *exponent > 0);
+        let join = |factors: &[&(&str, i32)], sign: i32| {
+            factors
+                .iter()
+                .map(|(name, exponent)| power(name, sign * exponent))
+                .collect::<Vec<String>>()
+                .join("*")
+        };
+        match (numerator.is_empty(), denominator.len()) {
+            (_, 0) => write!(f, "{}", join(&numerator, 1)),
+            (true, _) => write!(f, "{}", join(&denominator, 1)),
+            (false, 1) => write!(f, "{}/{}", join(&numerator, 1), join(&denominator, -1)),
+            (false, _) => write!(f, "{}/({})", join(&numerator, 1), join(&denominator, -1)),
+        }
+    }
+}
+
+///
+/// Number with a unit, as `5.3 km`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub(super) value: Number,
+    pub(super) unit: Unit,
+}
+
+impl Quantity {
+    ///
+    /// Whether the quantity is a duration written as hours, minutes and seconds
+    ///
+    pub(super) fn is_clock(&self) -> bool {
+        self.unit.factors == [(CLOCK, 1)]
+    }
+
+    ///
+    /// Whether the quantity is an angle written as degrees, minutes and seconds
+    ///
+    pub(super) fn is_dms(&self) -> bool {
+        self.unit.factors == [(DMS, 1)]
+    }
+}
+
+///
+/// Durations in `hms` are written as hours, minutes and seconds, as `1:45:30`, and angles
+/// in `dms` as degrees, minutes and seconds, as `45°30'15"`
+///
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sexagesimal = if self.is_clock() || self.is_dms() {
+            sexagesimal(&self.value)
+        } else {
+            None
+        };
+        match sexagesimal {
+            Some((sign, [hours, minutes, seconds], fraction)) if self.is_clock() => {
+                write!(f, "{sign}{hours}:{minutes:02}:{seconds:02}{fraction}")
+            }
+            Some((sign, [degrees, minutes, seconds], fraction)) => {
+                write!(f, "{sign}{degrees}°{minutes}'{seconds}{fraction}\"")
+            }
+            None => write!(f, "{} {}", self.value, self.unit),
+        }
+    }
+}
+
+///
+/// Sign, whole units of 3600, whole units of 60 and units left of a value in seconds or
+/// arcseconds, the fraction of a decimal going after the last ones. None for a value which
+/// is not real
+///
+fn sexagesimal(value: &Number) -> Option<(&'static str, [BigInt; 3], String)> {
+    let (whole, fraction) = match value {
+        Number::Int(_) | Number::Big(_) => (value.to_big(), String::new()),
+        Number::Decimal(d) => {
+            let written = d.to_string();
+            let fraction = written
+                .split_once('.')
+                .map(|(_, fraction)| format!(".{fraction}"))
+                .unwrap_or_default();
+            (d.trunc(), fraction)
+        }
+        _ => return None,
+    };
+    let sign = if value.to_string().starts_with('-') {
+        "-"
+    } else {
+        ""
+    };
+    let whole = whole.abs();
+    Some((
+        sign,
+        [&whole / 3600, &whole / 60 % 60, &whole % 60],
+        fraction,
+    ))
+}
+
+///
+/// Seconds of a clock literal, hours followed by minutes and seconds up to 59 separated
+/// by `:` as in `1:45:30`, at the start of the characters, along with its length
+///
+pub(super) fn clock_literal(chars: impl Iterator<Item = char> + Clone) -> Option<(i128, usize)> {
+    let hours: String = chars.clone().take_while(char::is_ascii_digit).collect();
+    let rest: Vec<char> = chars.skip(hours.len()).take(7).collect();
+    let shape = rest.len() >= 6
+        && rest[..6].iter().enumerate().all(|(index, c)| match index {
+            0 | 3 => *c == ':',
+            _ => c.is_ascii_digit(),
+        })
+        && !rest
+            .get(6)
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | ':'));
+    if !shape {
+        return None;
+    }
+    let number = |digits: &[char]| digits.iter().collect::<String>().parse::<i128>().ok();
+    let (minutes, seconds) = (number(&rest[1..3])?, number(&rest[4..6])?);
+    if minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let seconds = hours
+        .parse::<i128>()
+        .ok()?
+        .checked_mul(3600)?
+        .checked_add(minutes * 60 + seconds)?;
+    Some((seconds, hours.len() + 6))
+}
+
+///
+/// Arcseconds of a degrees, minutes and seconds literal, as in `45°30'15"` or `45d30m15s`,
+/// at the start of the characters, along with its length. Minutes and seconds up to 59 may
+/// be left out from the end, as in `45°30'` or `45°`
+///
+pub(super) fn dms_literal(chars: impl Iterator<Item = char>) -> Option<(i128, usize)> {
+    let chars: Vec<char> = chars.take_while(|c| !c.is_whitespace()).collect();
+    let digits = |from: usize| {
+        chars[from..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let number = |from: usize, count: usize| {
+        chars[from..from + count]
+            .iter()
+            .collect::<String>()
+            .parse::<i128>()
+            .ok()
+    };
+    let length = digits(0);
+    let markers = match chars.get(length) {
+        Some('°') => ['\'', '"'],
+        Some('d') => ['m', 's'],
+        _ => return None,
+    };
+    let mut arcseconds = number(0, length)?.checked_mul(3600)?;
+    let mut length = length + 1;
+    for (marker, size) in markers.into_iter().zip([60, 1]) {
+        let count = digits(length);
+        if count == 0 {
+            break;
+        }
+        let part = number(length, count)?;
+        if count > 2 || part > 59 || chars.get(length + count) != Some(&marker) {
+            return None;
+        }
+        arcseconds = arcseconds.checked_add(part * size)?;
+        length += count + 1;
+    }
+    let ended = !chars
+        .get(length)
+        .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '°' | '\'' | '"'));
+    ended.then_some((arcseconds, length))
+}
+
+///
+/// One of the unit of the given name, the value of the name unless a variable shadows it
+///
+pub(super) fn named(name: &str) -> Option<Number> {
+    if RADIANS.contains(&name) {
+        return Some(Number::Int(1));
+    }
+    is_unit(name).then(|| {
+        let definition = definition(name);
+        Number::Quantity(Box::new(Quantity {
+            value: Number::Int(1),
+            unit: Unit {
+                factors: vec![(definition.name, 1)],
+            },
+        }))
+    })
+}
+
+///
+/// Value and unit of an operand, numbers having no unit
+///
+fn parts(n: &Number) -> (Number, Unit) {
+    match n {
+        Number::Quantity(q) => (q.value.clone(), q.unit.clone()),
+        n => (n.clone(), Unit::default()),
+    }
+}
+
+///
+/// Value with the given unit, a plain number when the unit has no dimension left, the
+/// value being scaled by what remains of the units, as in `km/m`
+///
+fn quantity(value: Number, unit: Unit, settings: &Settings) -> Result<Number, Error> {
+    if unit.dimension() != NONE {
+        return Ok(Number::Quantity(Box::new(Quantity { value, unit })));
+    }
+    rescale(value, unit.scale(), settings)
+}
+
+///
+/// Ratio of the sizes of two units of the same dimension, which converts values in the
+/// first one to the second one
+///
+fn ratio(from: &Unit, to: &Unit) -> (BigInt, BigInt) {
+    let ((from_n, from_d), (to_n, to_d)) = (from.scale(), to.scale());
+    (from_n * to_d, from_d * to_n)
+}
+
+///
+/// Value multiplied by a fraction, integers which it leaves with a remainder going on with
+/// the decimals of the integer mode
+///
+fn rescale(
+    value: Number,
+    (numerator, denominator): (BigInt, BigInt),
+    settings: &Settings,
+) -> Result<Number, Error> {
+    if numerator == denominator {
+        return Ok(value);
+    }
+    let value = match rescale_truncates(&value, &(numerator.clone(), denominator.clone())) {
+        true => Number::Decimal(Decimal::from_int(value.to_big(), settings.scale())),
+        false => value,
+    };
+    let int_type = settings.int_type();
+    let scaled = Operator::Prod.execute(value, Number::from_big(numerator, int_type), settings)?;
+    Operator::Div.execute(scaled, Number::from_big(denominator, int_type), settings)
+}
+
+///
+/// Whether multiplying an integer value by the fraction leaves a remainder
+///
+fn rescale_truncates(value: &Number, (numerator, denominator): &(BigInt, BigInt)) -> bool {
+    matches!(value, Number::Int(_) | Number::Big(_))
+        && !(value.to_big() * numerator % denominator).is_zero()
+}
+
+///
+/// Apply a binary operator to operands of which at least one has a unit. Sums and
+/// remainders need units of the same dimension, the right operand being converted to the
+/// unit of the left one; products and quotients multiply or divide the units, and powers
+/// raise them to an integer exponent
+///
+pub(super) fn execute(
+    operator: Operator,
+    v1: Number,
+    v2: Number,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    let call = || format!("{v1} {} {v2}", String::from(&operator));
+    let ((a, unit_a), (b, unit_b)) = (parts(&v1), parts(&v2));
+    match operator {
+        Operator::Add | Operator::Sub | Operator::Mod => {
+            if unit_a.dimension() != unit_b.dimension() {
+                return Err(Error::IncompatibleUnits(call()));
+            }
+            let b = rescale(b, ratio(&unit_b, &unit_a), settings)?;
+            quantity(operator.execute(a, b, settings)?, unit_a, settings)
+        }
+        Operator::Prod | Operator::Div => {
+            let sign = if operator == Operator::Prod { 1 } else { -1 };
+            quantity(
+                operator.execute(a, b, settings)?,
+                unit_a.times(&unit_b, sign),
+                settings,
+            )
+        }
+        Operator::Pow if unit_b.is_empty() => {
+            let k = b
+                .to_integer()
+                .and_then(|k| k.to_i32())
+                .ok_or_else(|| Error::FractionalOperand(call()))?;
+            quantity(operator.execute(a, b, settings)?, unit_a.pow(k), settings)
+        }
+        Operator::In => converted(&v1, &v2, settings, call),
+        _ => Err(Error::UnitOperand(call())),
+    }
+}
+
+pub(super) fn execute_unary(
+    operator: Operator,
+    q: &Quantity,
+    settings: &Settings,
+) -> Result<Number, Error> {
+    let unit = match operator {
+        Operator::Neg | Operator::Abs => Some(q.unit.clone()),
+        Operator::Sqrt => q.unit.root(2),
+        Operator::Cbrt => q.unit.root(3),
+        _ => None,
+    }
+    .ok_or_else(|| Error::UnitOperand(format!("{}({q})", String::from(&operator))))?;
+    quantity(
+        operator.execute_unary(q.value.clone(), settings)?,
+        unit,
+        settings,
+    )
+}
+
+///
+/// Whether dividing the values of the operands loses a remainder
+///
+pub(super) fn truncates(operator: Operator, v1: &Number, v2: &Number) -> bool {
+    let ((a, _), (b, _)) = (parts(v1), parts(v2));
+    operator.truncates(&a, &b)
+}
+
+///
+/// Conversion between units of an operation which gives integers a fraction, written as
+/// the value and the unit it is converted to
+///
+pub(super) fn rounded_conversion(operator: Operator, v1: &Number, v2: &Number) -> Option<String> {
+    if !matches!(v1, Number::Quantity(_)) && !matches!(v2, Number::Quantity(_)) {
+        return None;
+    }
+    let ((a, unit_a), (b, unit_b)) = (parts(v1), parts(v2));
+    match operator {
+        Operator::Add | Operator::Sub | Operator::Mod => {
+            if unit_a.dimension() != unit_b.dimension() {
+                return None;
+            }
+            rescale_truncates(&b, &ratio(&unit_b, &unit_a)).then(|| format!("{v2} to {unit_a}"))
+        }
+        Operator::Prod | Operator::Div => {
+            let sign = if operator == Operator::Prod { 1 } else { -1 };
+            let unit = unit_a.times(&unit_b, sign);
+            if unit.dimension() != NONE || unit.is_empty() || b.is_zero() {
+                return None;
+            }
+            let value = match operator {
+                Operator::Prod => a.to_big() * b.to_big(),
+                _ => a.to_big() / b.to_big(),
+            };
+            rescale_truncates(&Number::Big(value.clone()), &unit.scale())
+                .then(|| format!("{value} {unit} to a number"))
+        }
+        Operator::In => {
+            convert_truncates(&[v1.clone(), v2.clone()]).then(|| format!("{v1} to {unit_b}"))
+        }
+        _ => None,
+    }
+}
+
+///
+/// Quantity converted to the unit of the target, as in `convert(100 mph, km/h)`
+///
+pub(super) fn convert(args: &[Number], settings: &Settings) -> Result<Number, Error> {
+    let [value, target] = args else {
+        unreachable!("Argument count was checked");
+    };
+    converted(value, target, settings, || Function::Convert.display(args))
+}
+
+///
+/// Quantity converted to the unit of the target, written as the given call in errors
+///
+fn converted(
+    value: &Number,
+    target: &Number,
+    settings: &Settings,
+    call: impl Fn() -> String,
+) -> Result<Number, Error> {
+    let ((value, from), (size, to)) = (parts(value), parts(target));
+    if from.dimension() != to.dimension() || to.is_empty() {
+        return Err(Error::IncompatibleUnits(call()));
+    }
+    let converted = rescale(value, ratio(&from, &to), settings)?;
+    Ok(Number::Quantity(Box::new(Quantity {
+        value: Operator::Div.execute(converted, size, settings)?,
+        unit: to,
+    })))
+}
+
+///
+/// Whether converting the quantity to the unit of the target gives an integer a fraction
+///
+pub(super) fn convert_truncates(args: &[Number]) -> bool {
+    let [value, target] = args else {
+        return false;
+    };
+    let ((value, from), (size, to)) = (parts(value), parts(target));
+    if from.dimension() != to.dimension() || size.is_zero() {
+        return false;
+    }
+    let (numerator, denominator) = ratio(&from, &to);
+    rescale_truncates(&value, &(numerator, denominator * size.to_big()))
+}
+
+///
+/// Length of a duration in days, as a numerator and a denominator, None for a quantity
+/// which is not a time
+///
+pub(super) fn days(q: &Quantity) -> Option<(BigInt, BigInt)> {
+    measure(q, TIME, "day")
+}
+
+///
+/// Size of an angle in degrees, as a numerator and a denominator, None for a quantity
+/// which is not an angle
+///
+pub(super) fn degrees(q: &Quantity) -> Option<(BigInt, BigInt)> {
+    measure(q, ANGLE, "deg")
+}
+
+fn measure(q: &Quantity, dimension: Dimension, unit: &'static str) -> Option<(BigInt, BigInt)> {
+    if q.unit.dimension() != dimension {
+        return None;
+    }
+    let (numerator, denominator) = ratio(&q.unit, &Unit::new(&[(unit, 1)]));
+    Some(match &q.value {
+        Number::Decimal(d) => (
+            &d.units * numerator,
+            denominator * BigInt::from(10).pow(d.scale),
+        ),
+        value => (value.to_big() * numerator, denominator),
+    })
+}
@@ -0,0 +1,86 @@
+//!
+//! Calculator: math expressions are split into tokens, parsed into a tree,
+//! compiled to Reverse Polish Notation and evaluated on integers, decimals or complex
+//! numbers depending on the settings
+//!
+//! ```
+//! use calculator::{Settings, evaluate};
+//!
+//! let evaluation = evaluate("3 + 4 * 2", &Settings::default()).unwrap();
+//! assert_eq!(evaluation.result.to_string(), "11");
+//! ```
+//!
+
+mod internal;
+
+pub use internal::{
+    Ast, AstFormat, Binding, CompiledExpression, Context, DEFAULT_ANSWERS, Date, DecimalLiteral,
+    DecimalSeparator, Diagnostic, Error, Evaluation, Function, HigherOrder, HigherOrderCall,
+    IntWidth, Matrix, Number, Operator, Overflow, PhysicalConstant, Plot, Quantity, Root, Roots,
+    Rounding, Settings, Solution, Span, Spanned, Statement, Stats, Table, Token, TraceStep, Unit,
+    Warning, dump_ast, estimate_expression as evaluate, estimate_expression_in as evaluate_in,
+    estimate_rpn_in as evaluate_rpn_in, explain_grouping, function_names, normalize_separators,
+    parenthesize, parse_ast, parse_statement, physical_constants, plot, roots, rpn_to_infix,
+    simplify, solve_linear, split_bindings, split_statements, strip_comment, table, to_fraction,
+    to_pattern, to_radix, to_rpn_string, tokenize, validate, verify_expression,
+};
+
+///
+/// Split an expression into its tokens, in the order they are written
+///
+pub fn parse(expr: &str) -> Result<Vec<Token>, Error> {
+    internal::parse_expr(expr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Context, Error, Function, Number, Operator, Settings, Token, evaluate, evaluate_in, parse,
+    };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("3 + max(4, 2)").unwrap(),
+            vec![
+                Token::Number(3),
+                Token::Operator(Operator::Add),
+                Token::Function(Function::Max),
+                Token::Operator(Operator::LeftBracket),
+                Token::Number(4),
+                Token::Comma,
+                Token::Number(2),
+                Token::Operator(Operator::RightBracket),
+            ]
+        );
+        assert_eq!(
+            parse("3 $").unwrap_err(),
+            Error::InvalidExpression {
+                message: "Unknown character $".to_owned(),
+                position: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let settings = Settings::default();
+        assert_eq!(evaluate("3 + 4 * 2", &settings).unwrap().result, 11);
+        assert_eq!(
+            evaluate("7 / 0", &settings).unwrap_err(),
+            Error::DivisionByZero("7 / 0".to_owned())
+        );
+
+        let mut context = Context::default();
+        context.assign("x", Number::Int(6));
+        context.push(Number::Int(2));
+        assert_eq!(
+            evaluate_in("x * ans", &settings, &context).unwrap().result,
+            12
+        );
+        assert_eq!(
+            evaluate_in("y", &settings, &context).unwrap_err(),
+            Error::UnknownVariable("y".to_owned())
+        );
+    }
+}
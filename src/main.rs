@@ -1,12 +1,42 @@
-use internal::estimate_expression;
+use calculator::{
+    AstFormat, Context, Error, Settings, Spanned, Statement, dump_ast, evaluate_in,
+    evaluate_rpn_in, explain_grouping, normalize_separators, parenthesize, parse_statement,
+    physical_constants, plot, roots, rpn_to_infix, simplify, solve_linear, split_bindings,
+    split_statements, strip_comment, table, to_rpn_string, tokenize, verify_expression,
+};
+use clap::ValueEnum;
+use cli::{Args, Command, Notation};
+use definitions::{Definitions, Saving};
+use format::Format;
+use history::History;
 use log::{LevelFilter, error, info};
+use rustyline::{DefaultEditor, error::ReadlineError};
+use session::Session;
 use signal_hook::{consts::SIGINT, iterator::Signals};
 use simple_logger::SimpleLogger;
-use std::{io, str::FromStr, sync::mpsc, thread};
+use std::{
+    io,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError, mpsc},
+    thread,
+    time::Instant,
+};
+use trace::Tracer;
 
-mod internal;
+mod bc;
+mod cli;
+mod definitions;
+mod diagnostic;
+mod format;
+mod help;
+mod history;
+mod quiz;
+mod session;
+mod trace;
 
 fn main() -> io::Result<()> {
+    let args = Args::parse_validated();
+
     let log_level = LevelFilter::from_str(std::env::var("RUST_LOG").unwrap_or_default().as_str())
         .unwrap_or(LevelFilter::Off);
     SimpleLogger::new()
@@ -17,39 +47,737 @@ fn main() -> io::Result<()> {
 
     let mut signals = Signals::new([SIGINT])?;
     let (termination_tx, termination_rx) = mpsc::channel::<()>();
+    let worker_termination_tx = termination_tx.clone();
+    let session = Arc::new(Mutex::new(Session::new()));
+    let worker_session = Arc::clone(&session);
+    let summary = args.summary;
 
     // Thread for handling termination signal
     thread::spawn(move || {
-        for sig in signals.forever() {
+        if let Some(sig) = signals.forever().next() {
             info!("Received signal {:?}", sig);
             let _ = termination_tx.send(());
-            return;
         }
     });
 
     // Thread for handling business logic
     thread::spawn(move || {
-        loop {
-            let mut buf = String::new();
-            println!("Waiting for user input:");
-            match io::stdin().read_line(&mut buf) {
-                Ok(_) => {
-                    info!("Input data -> {}", buf.trim());
-                    match estimate_expression(&buf.trim()) {
-                        Ok(res) => println!("Result of expression {} is {res}", buf.trim()),
-                        Err(e) => println!("Cannot estimate expression due to error {e}"),
+        match &args.command {
+            Some(Command::Quiz {
+                difficulty,
+                questions,
+            }) => quiz::run(*difficulty, *questions, &worker_session),
+            Some(Command::Bc { mathlib, files }) => bc::run(*mathlib, files, &worker_session),
+            None if !args.expressions.is_empty() => evaluate(&args, &worker_session),
+            None => repl(&args, &worker_session),
+        }
+        let _ = worker_termination_tx.send(());
+    });
+
+    if let Err(e) = termination_rx.recv() {
+        info!("Error receiving termination signal {e}. Killing process...");
+    }
+    if summary {
+        let session = session.lock().unwrap_or_else(PoisonError::into_inner);
+        println!("{}", session.summary());
+    }
+    Ok(())
+}
+
+///
+/// Evaluate expressions read from standard input until the input ends
+///
+fn repl(args: &Args, session: &Mutex<Session>) {
+    let mut repl = Repl::new(args, session);
+    repl.definitions = Definitions::open(&mut repl.context, !args.no_definitions);
+    // Lines are edited with the arrow keys, Home, End and Ctrl-W, earlier ones being
+    // recalled with up and down
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            error!("Error setting up line editing {e}");
+            return;
+        }
+    };
+    let history = History::open(&mut editor, !args.no_history);
+    loop {
+        println!("Waiting for user input:");
+        let read = match editor.readline("") {
+            Ok(typed) => match recall(&editor, typed) {
+                Some(typed) => {
+                    history.record(&mut editor, &typed);
+                    Ok(typed)
+                }
+                None => continue,
+            },
+            Err(e) => Err(e),
+        };
+        if repl.read(read) == Flow::Quit {
+            break;
+        }
+    }
+}
+
+///
+/// Run the lines given with `-e` as if they were typed, the saved definitions included
+///
+fn evaluate(args: &Args, session: &Mutex<Session>) {
+    let mut repl = Repl::new(args, session);
+    repl.definitions = Definitions::open(&mut repl.context, !args.no_definitions);
+    for line in &args.expressions {
+        if repl.line(line) == Flow::Quit {
+            break;
+        }
+    }
+}
+
+///
+/// Line to run once `:history` is handled: `:history` lists the lines typed so far, and
+/// `:history <n>` runs one of them again. None when there is nothing left to run
+///
+fn recall(editor: &DefaultEditor, typed: String) -> Option<String> {
+    match typed.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [":history"] => {
+            for line in history::list(editor.history().iter()) {
+                println!("{line}");
+            }
+            None
+        }
+        [":history", index] => match history::entry(editor.history().iter(), index) {
+            Ok(line) => {
+                println!("{line}");
+                Some(line)
+            }
+            Err(e) => {
+                println!("Cannot run history entry due to error {e}");
+                None
+            }
+        },
+        _ => Some(typed),
+    }
+}
+
+///
+/// Whether the REPL keeps reading lines after one is handled
+///
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+///
+/// State of the REPL carried from one line to the next
+///
+struct Repl<'a> {
+    args: &'a Args,
+    session: &'a Mutex<Session>,
+    settings: Settings,
+    context: Context,
+    /// Functions defined with `:define`, none until the saved ones are opened
+    definitions: Definitions,
+    format: Format,
+    notation: Notation,
+    tracer: Option<Tracer>,
+}
+
+impl<'a> Repl<'a> {
+    fn new(args: &'a Args, session: &'a Mutex<Session>) -> Self {
+        let mut context = Context::new(args.answers);
+        if let Some(seed) = args.seed {
+            context.seed(seed);
+        }
+        let format = Format {
+            sign: args.sign,
+            separator: args.decimal_separator,
+            precision: args.precision.map(|digits| digits as usize),
+            bits: args.programmer.then(|| args.int_width.bits()),
+            ..Default::default()
+        };
+        let notation = if args.rpn {
+            Notation::Rpn
+        } else {
+            Notation::Infix
+        };
+        Self {
+            args,
+            session,
+            settings: args.settings(),
+            context,
+            definitions: Definitions::default(),
+            format,
+            notation,
+            tracer: args.trace_format.map(|format| {
+                let tracer = Tracer::new(format);
+                #[cfg(feature = "serde")]
+                let tracer = tracer.with_file(args.trace_file.clone());
+                tracer
+            }),
+        }
+    }
+
+    ///
+    /// Handle what the line editor read
+    ///
+    fn read(&mut self, read: rustyline::Result<String>) -> Flow {
+        match read {
+            Ok(typed) => self.line(&typed),
+            // Ctrl-D and Ctrl-C end the session as the end of the input does
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => Flow::Quit,
+            Err(e) => {
+                error!("Error reading input data {e}");
+                Flow::Quit
+            }
+        }
+    }
+
+    ///
+    /// Run a typed line
+    ///
+    fn line(&mut self, typed: &str) -> Flow {
+        // Numbers written with the decimal separator of the locale are read with a point,
+        // results still naming the expression as it was typed
+        let buf = normalize_separators(typed, self.format.separator);
+        match buf.trim() {
+            // Blank and comment only lines are skipped
+            line if strip_comment(line).trim().is_empty() => {}
+            // Leaving the loop ends the session as the end of the input and SIGINT do, a
+            // variable named `q` being shown rather than taken for `quit`
+            line if matches!(strip_comment(line).trim(), "exit" | "quit") => return Flow::Quit,
+            line if strip_comment(line).trim() == "q"
+                && !self
+                    .context
+                    .variables()
+                    .iter()
+                    .any(|(name, _)| *name == "q") =>
+            {
+                return Flow::Quit;
+            }
+            line if line.starts_with(':') => command(
+                line,
+                &self.settings,
+                &self.session.lock().unwrap_or_else(PoisonError::into_inner),
+                &mut self.context,
+                &mut self.definitions,
+                &mut self.format,
+                &mut self.notation,
+            ),
+            // Statements separated by `;` run in order, until one of them fails
+            _ => {
+                let statements: Vec<_> = split_statements(typed)
+                    .into_iter()
+                    .zip(split_statements(&buf))
+                    .collect();
+                // Failures name the statement when there are several of them
+                let several = statements.len() > 1;
+                for (index, (typed, line)) in statements.into_iter().enumerate() {
+                    let name = match several {
+                        true => format!("statement {}", index + 1),
+                        false => "expression".to_owned(),
+                    };
+                    if !self.statement(typed, line, &name) {
+                        break;
                     }
                 }
-                Err(e) => error!("Error reading input data {e}"),
-            };
+            }
         }
-    });
+        Flow::Continue
+    }
+
+    ///
+    /// Run one statement of a line, telling whether the statements after it may run.
+    /// Failures are reported under the given name of the statement
+    ///
+    fn statement(&mut self, typed: &str, line: &str, name: &str) -> bool {
+        let line = line.trim();
+        if strip_comment(line).trim().is_empty() {
+            return true;
+        }
+        // Plots and tables are drawn rather than evaluated to a number
+        if let Some(args) = call(line, "plot") {
+            print_plot(line, args, &self.settings, &self.context);
+            return true;
+        }
+        if let Some(args) = call(line, "table") {
+            print_table(line, args, &self.settings, &self.context, &self.format);
+            return true;
+        }
+        info!("Input data -> {line}");
+        let (variable, expr) = match parse_statement(line) {
+            Ok(Statement::Expression(expr)) => (None, expr),
+            Ok(Statement::Assignment(variable, expr)) => (Some(variable), expr),
+            Ok(Statement::Definition { name, params, body }) => {
+                match self.context.define(name, &params, body) {
+                    Ok(()) => {
+                        println!("Defined function {name}({})", params.join(", "))
+                    }
+                    Err(e) => println!("Cannot define function due to error {e}"),
+                }
+                return true;
+            }
+            Err(e) => {
+                println!("Cannot estimate {name} due to error {e}");
+                return false;
+            }
+        };
+        // Tree dumps, explanations and verifications work on the infix notation
+        let infix = match self.notation {
+            Notation::Infix => Some(expr.to_owned()),
+            Notation::Rpn => rpn_to_infix(expr).ok(),
+        };
+        // Bindings of a where clause are left out of trees and groupings
+        let shown = infix
+            .as_deref()
+            .and_then(|infix| split_bindings(infix).ok())
+            .map(|(shown, _)| shown);
+        // Invalid expressions are reported by the evaluation right after
+        if let Some(format) = self.args.dump_ast
+            && let Some(shown) = shown
+            && let Ok(ast) = dump_ast(shown, format)
+        {
+            print!("{ast}");
+        }
+        // The reference evaluation has to draw the same random numbers
+        let reference = self.args.verify.then(|| self.context.snapshot());
+        let start = Instant::now();
+        let outcome = match self.notation {
+            Notation::Infix => evaluate_in(expr, &self.settings, &self.context),
+            Notation::Rpn => evaluate_rpn_in(expr, &self.settings, &self.context),
+        };
+        self.session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record(&outcome, start.elapsed());
+        if let Some(reference) = reference
+            && let Some(infix) = &infix
+        {
+            match verify_expression(infix, &self.settings, &reference, &outcome) {
+                Some(Ok(n)) => {
+                    println!("Verification failed: reference evaluator computed {n}")
+                }
+                Some(Err(e)) => {
+                    println!("Verification failed: reference evaluator failed with error {e}")
+                }
+                None => {}
+            }
+        }
+        match outcome {
+            Ok(mut evaluation) => {
+                if let Some(tracer) = self.tracer.as_mut()
+                    && let Err(e) = tracer.record(expr, std::mem::take(&mut evaluation.trace))
+                {
+                    error!("Error writing evaluation trace {e}");
+                }
+                for warning in &evaluation.warnings {
+                    println!("Warning: {warning}");
+                }
+                println!(
+                    "Result of expression {} is {}",
+                    typed.trim(),
+                    self.format.number(&evaluation.result)
+                );
+                if self.args.explain
+                    && let Some(shown) = shown
+                    && let Ok(Some(grouping)) = explain_grouping(shown)
+                {
+                    println!("Note: evaluated as {grouping}");
+                }
+                if self.args.show_rpn {
+                    println!("RPN: {}", evaluation.rpn);
+                }
+                if self.args.stats {
+                    println!("Stats: {}", evaluation.stats);
+                }
+                if let Some(variable) = variable {
+                    self.context.assign(variable, evaluation.result.clone());
+                }
+                self.context.push(evaluation.result);
+                true
+            }
+            Err(e) => {
+                println!("Cannot estimate {name} due to error {e}");
+                if let Some(diagnostic) = diagnostic::render(expr, &e) {
+                    println!("{diagnostic}");
+                }
+                false
+            }
+        }
+    }
+}
 
-    match termination_rx.recv() {
-        Ok(_) => return Ok(()),
+///
+/// Run a REPL command, i.e. an input line starting with `:`
+///
+fn command(
+    line: &str,
+    settings: &Settings,
+    session: &Session,
+    context: &mut Context,
+    definitions: &mut Definitions,
+    format: &mut Format,
+    notation: &mut Notation,
+) {
+    match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [":help"] => print!("{}", help::help()),
+        [":vars"] => {
+            for (name, value) in context.variables() {
+                println!("{name} = {}", format.number(value));
+            }
+        }
+        [":funcs"] => {
+            for function in context.functions() {
+                println!("{function}");
+            }
+        }
+        [":define"] => {
+            for line in definitions.lines() {
+                println!("{line}");
+            }
+        }
+        [":define", ..] => {
+            match definitions.define(context, line.strip_prefix(":define").unwrap_or_default()) {
+                Ok((signature, Saving::Saved)) => {
+                    println!("Defined and saved function {signature}")
+                }
+                Ok((signature, Saving::Unsaved)) => {
+                    println!(
+                        "Defined function {signature} for this session, definitions not being saved"
+                    );
+                }
+                Ok((signature, Saving::Failed(e))) => println!(
+                    "Defined function {signature} for this session, as it cannot be saved due to error {e}"
+                ),
+                Err(e) => println!("Cannot define function due to error {e}"),
+            }
+        }
+        [":clear"] => {
+            context.reset();
+            definitions.restore(context);
+        }
+        [":stats"] => print!("{session}"),
+        [":consts"] => {
+            for constant in physical_constants() {
+                println!(
+                    "const.{} = {} {} ({})",
+                    constant.name,
+                    constant.value,
+                    constant.unit(),
+                    constant.description
+                );
+            }
+        }
+        [":ans", "list"] => {
+            for (index, answer) in context.answers().enumerate() {
+                println!("ans({}) = {}", index + 1, format.number(answer));
+            }
+        }
+        [":ans", "clear"] => context.clear(),
+        [":bits"] => match context.answers().next() {
+            Some(answer) => {
+                let bits = format.bits.unwrap_or(settings.int_width.bits());
+                match format::bit_view(answer, bits) {
+                    Ok(view) => println!("{view}"),
+                    Err(reason) => println!("Cannot show the bits of {answer}, {reason}"),
+                }
+            }
+            None => println!("Cannot show the bits of the last result, as there is none"),
+        },
+        [":format"] => println!("{format}"),
+        [":mode"] => println!(
+            "mode: {}",
+            notation
+                .to_possible_value()
+                .expect("Notations are never skipped")
+                .get_name()
+        ),
+        [":mode", mode] => match Notation::from_str(mode, true) {
+            Ok(mode) => *notation = mode,
+            Err(e) => println!("Cannot change mode due to error {e}"),
+        },
+        [":rpn", ..] => match to_rpn_string(line.strip_prefix(":rpn").unwrap_or_default().trim()) {
+            Ok(rpn) => println!("RPN: {rpn}"),
+            Err(e) => println!("Cannot convert expression due to error {e}"),
+        },
+        [":infix", ..] => {
+            match rpn_to_infix(line.strip_prefix(":infix").unwrap_or_default().trim()) {
+                Ok(infix) => println!("Infix: {infix}"),
+                Err(e) => println!("Cannot convert RPN due to error {e}"),
+            }
+        }
+        [":base", base] => {
+            if let Err(e) = format.set("base", base) {
+                println!("Cannot change format due to error {e}");
+            }
+        }
+        [":precision", digits] => {
+            if let Err(e) = format.set("precision", digits) {
+                println!("Cannot change format due to error {e}");
+            }
+        }
+        [":notation", notation] => {
+            if let Err(e) = format.set("notation", notation) {
+                println!("Cannot change format due to error {e}");
+            }
+        }
+        [":format", option, value] => {
+            if let Err(e) = format.set(option, value) {
+                println!("Cannot change format due to error {e}");
+            }
+        }
+        [":ast", ..] => {
+            match dump_ast(
+                line.strip_prefix(":ast").unwrap_or_default().trim(),
+                AstFormat::Sexpr,
+            ) {
+                Ok(ast) => print!("{ast}"),
+                Err(e) => println!("Cannot build expression tree due to error {e}"),
+            }
+        }
+        [":dot", ..] => {
+            match dump_ast(
+                line.strip_prefix(":dot").unwrap_or_default().trim(),
+                AstFormat::Dot,
+            ) {
+                Ok(dot) => print!("{dot}"),
+                Err(e) => println!("Cannot build expression tree due to error {e}"),
+            }
+        }
+        [":explain", ..] => {
+            match parenthesize(line.strip_prefix(":explain").unwrap_or_default().trim()) {
+                Ok(parenthesized) => println!("{parenthesized}"),
+                Err(e) => println!("Cannot build expression tree due to error {e}"),
+            }
+        }
+        [":simplify", ..] => {
+            match simplify(line.strip_prefix(":simplify").unwrap_or_default().trim()) {
+                Ok(simplified) => println!("{simplified}"),
+                Err(e) => println!("Cannot build expression tree due to error {e}"),
+            }
+        }
+        [":solve", ..] => {
+            let system = line.strip_prefix(":solve").unwrap_or_default();
+            match solve_linear(system, settings, context) {
+                Ok(solution) => {
+                    for warning in &solution.warnings {
+                        println!("Warning: {warning}");
+                    }
+                    for (name, value) in &solution.values {
+                        println!("{name} = {}", format.number(value));
+                    }
+                }
+                Err(e) => println!("Cannot solve system due to error {e}"),
+            }
+        }
+        [":roots", ..] => {
+            let polynomial = line.strip_prefix(":roots").unwrap_or_default();
+            match roots(polynomial, settings, context) {
+                Ok(roots) => {
+                    for warning in &roots.warnings {
+                        println!("Warning: {warning}");
+                    }
+                    let values: Vec<String> =
+                        roots.values.iter().map(ToString::to_string).collect();
+                    if values.is_empty() {
+                        println!("No roots");
+                    } else {
+                        println!("Roots: {}", values.join(", "));
+                    }
+                }
+                Err(e) => println!("Cannot find roots due to error {e}"),
+            }
+        }
+        [":tokens", ..] => print_tokens(line.strip_prefix(":tokens").unwrap_or_default().trim()),
+        // The line editor handles it before the line gets here, which it never reaches
+        // with -e or the bc scripts
+        [":history", ..] => println!("Cannot use :history without the line editor of the REPL"),
+        _ => match help::suggestion(line) {
+            Some(usage) => println!("Unknown command {line}, did you mean {usage}?"),
+            None => println!("Unknown command {line}"),
+        },
+    }
+}
+
+///
+/// Draw the plot of a `plot(expr, x, a, b)` statement, given what follows its opening
+/// bracket
+///
+fn print_plot(statement: &str, args: &str, settings: &Settings, context: &Context) {
+    match call_arguments(statement, args).and_then(|args| plot(args, settings, context)) {
+        Ok(plot) => print!("{plot}"),
+        Err(e) => println!("Cannot plot expression due to error {e}"),
+    }
+}
+
+///
+/// Print the table of a `table(expr, x, start, stop, step)` statement, given what follows
+/// its opening bracket, its numbers written as results are
+///
+fn print_table(
+    statement: &str,
+    args: &str,
+    settings: &Settings,
+    context: &Context,
+    format: &Format,
+) {
+    match call_arguments(statement, args).and_then(|args| table(args, settings, context)) {
+        Ok(table) => {
+            for warning in &table.warnings {
+                println!("Warning: {warning}");
+            }
+            print!("{}", table.display_with(|n| format.number(n)));
+        }
+        Err(e) => println!("Cannot tabulate expression due to error {e}"),
+    }
+}
+
+///
+/// What follows the opening bracket of a statement calling the given command as a
+/// function, as in `plot (x, x, 0, 1)`
+///
+fn call<'a>(statement: &'a str, name: &str) -> Option<&'a str> {
+    statement.strip_prefix(name)?.trim_start().strip_prefix('(')
+}
+
+///
+/// Arguments of a call up to its closing bracket, the comment ending the statement left out
+///
+fn call_arguments<'a>(statement: &str, args: &'a str) -> Result<&'a str, Error> {
+    strip_comment(args)
+        .trim_end()
+        .strip_suffix(')')
+        .ok_or_else(|| Error::UnmatchedParen {
+            bracket: '(',
+            position: statement[..statement.len() - args.len() - 1]
+                .chars()
+                .count(),
+        })
+}
+
+///
+/// Print the tokens an expression is split into, each with the characters it was read from
+///
+fn print_tokens(expr: &str) {
+    match token_lines(expr) {
+        Ok(lines) => {
+            for line in lines {
+                println!("{line}");
+            }
+        }
         Err(e) => {
-            info!("Error receiving termination signal {e}. Killing process...");
-            return Ok(());
+            println!("Cannot split expression due to error {e}");
+            if let Some(diagnostic) = diagnostic::render(expr, &e) {
+                println!("{diagnostic}");
+            }
         }
     }
 }
+
+///
+/// One line per token, with its span counted in characters, the characters it was read from
+/// and the token
+///
+fn token_lines(expr: &str) -> Result<Vec<String>, Error> {
+    let chars: Vec<char> = expr.chars().collect();
+    Ok(tokenize(expr)?
+        .into_iter()
+        .map(|Spanned { token, span }| {
+            let text: String = chars[span.start..span.end].iter().collect();
+            format!("  {}..{}\t{text}\t{token:?}", span.start, span.end)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Args, Error, Flow, Repl, Session, call, call_arguments, token_lines};
+    use clap::Parser;
+    use rustyline::error::ReadlineError;
+    use std::sync::Mutex;
+
+    fn args() -> Args {
+        Args::parse_from(["calculator", "--seed", "1"])
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        let (args, session) = (args(), Mutex::new(Session::new()));
+        let mut repl = Repl::new(&args, &session);
+        // Ctrl-D
+        assert_eq!(repl.read(Err(ReadlineError::Eof)), Flow::Quit);
+        // Ctrl-C
+        assert_eq!(repl.read(Err(ReadlineError::Interrupted)), Flow::Quit);
+    }
+
+    #[test]
+    fn test_lines() {
+        let (args, session) = (args(), Mutex::new(Session::new()));
+        let mut repl = Repl::new(&args, &session);
+        assert_eq!(repl.read(Ok("1 + 2".to_owned())), Flow::Continue);
+        assert_eq!(repl.read(Ok("# comment".to_owned())), Flow::Continue);
+        assert_eq!(repl.read(Ok("1 / 0".to_owned())), Flow::Continue);
+        assert_eq!(repl.read(Ok(":ans clear".to_owned())), Flow::Continue);
+        assert_eq!(repl.read(Ok("2 * 3".to_owned())), Flow::Continue);
+        let answers: Vec<String> = repl.context.answers().map(ToString::to_string).collect();
+        assert_eq!(answers, ["6"]);
+    }
+    #[test]
+    fn test_exit() {
+        let (args, session) = (args(), Mutex::new(Session::new()));
+        for line in ["exit", "quit", "q", "  quit # done"] {
+            let mut repl = Repl::new(&args, &session);
+            assert_eq!(repl.read(Ok(line.to_owned())), Flow::Quit, "{line}");
+        }
+        let mut repl = Repl::new(&args, &session);
+        assert_eq!(repl.read(Ok("q = 4".to_owned())), Flow::Continue);
+        assert_eq!(repl.read(Ok("q".to_owned())), Flow::Continue);
+        assert_eq!(repl.context.answers().next().unwrap().to_string(), "4");
+        assert_eq!(repl.read(Ok("exit".to_owned())), Flow::Quit);
+    }
+    #[test]
+    fn test_calls() {
+        assert_eq!(call("plot(x, x, 0, 1)", "plot"), Some("x, x, 0, 1)"));
+        assert_eq!(call("plot (x, x, 0, 1)", "plot"), Some("x, x, 0, 1)"));
+        assert_eq!(call("plotted + 1", "plot"), None);
+        assert_eq!(call("table", "table"), None);
+        assert_eq!(
+            call_arguments("plot(x, x, 0, 1) # parabola", "x, x, 0, 1) # parabola").unwrap(),
+            "x, x, 0, 1"
+        );
+        assert!(matches!(
+            call_arguments("plot (x, x, 0", "x, x, 0"),
+            Err(Error::UnmatchedParen {
+                bracket: '(',
+                position: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn test_statements() {
+        let (args, session) = (args(), Mutex::new(Session::new()));
+        let mut repl = Repl::new(&args, &session);
+        assert_eq!(
+            repl.read(Ok("a = 3; plot (a*x, x, 0, 1); b = 4;; a*b".to_owned())),
+            Flow::Continue
+        );
+        assert_eq!(repl.context.answers().next().unwrap().to_string(), "12");
+        // Statements after a failing one are left out
+        assert_eq!(repl.read(Ok("1 / 0; a = 5".to_owned())), Flow::Continue);
+        assert_eq!(repl.context.variables()[0].1.to_string(), "3");
+    }
+    #[test]
+    fn test_token_lines() {
+        // Spans count characters, so the text of each token is the one it was read from
+        assert_eq!(
+            token_lines("2 × 3 ÷ π").unwrap(),
+            [
+                "  0..1\t2\tNumber(2)",
+                "  2..3\t×\tOperator(Prod)",
+                "  4..5\t3\tNumber(3)",
+                "  6..7\t÷\tOperator(Div)",
+                "  8..9\tπ\tVariable(\"π\")",
+            ]
+        );
+        assert!(token_lines("2 × $").is_err());
+    }
+}
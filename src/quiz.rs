@@ -0,0 +1,205 @@
+use std::{
+    io,
+    ops::RangeInclusive,
+    sync::{Mutex, PoisonError},
+    time::Instant,
+};
+
+use clap::ValueEnum;
+use log::error;
+use rand::{Rng, seq::IndexedRandom};
+
+use calculator::{Evaluation, Settings, evaluate};
+
+use crate::session::Session;
+
+///
+/// How hard the generated quiz expressions are
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Difficulty {
+    /// Additions and subtractions of small numbers
+    Easy,
+    /// The four operations with brackets
+    #[default]
+    Medium,
+    /// Longer expressions with powers and negative numbers
+    Hard,
+}
+
+impl Difficulty {
+    fn operands(self) -> RangeInclusive<usize> {
+        match self {
+            Difficulty::Easy => 2..=3,
+            Difficulty::Medium => 3..=4,
+            Difficulty::Hard => 4..=6,
+        }
+    }
+
+    fn numbers(self) -> RangeInclusive<i64> {
+        match self {
+            Difficulty::Easy => 1..=10,
+            Difficulty::Medium => 1..=20,
+            Difficulty::Hard => -20..=30,
+        }
+    }
+
+    fn operators(self) -> &'static [char] {
+        match self {
+            Difficulty::Easy => &['+', '-'],
+            Difficulty::Medium | Difficulty::Hard => &['+', '-', '*', '/'],
+        }
+    }
+
+    ///
+    /// Probability of wrapping a sub-expression in brackets
+    ///
+    fn brackets(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.0,
+            Difficulty::Medium => 0.3,
+            Difficulty::Hard => 0.5,
+        }
+    }
+
+    ///
+    /// Probability of a leaf being a small power instead of a number
+    ///
+    fn powers(self) -> f64 {
+        match self {
+            Difficulty::Easy | Difficulty::Medium => 0.0,
+            Difficulty::Hard => 0.2,
+        }
+    }
+}
+
+///
+/// Ask the given number of questions on standard input, checking each answer against
+/// the evaluator. The answers computed are recorded in the session
+///
+pub(crate) fn run(difficulty: Difficulty, questions: usize, session: &Mutex<Session>) {
+    let mut rng = rand::rng();
+    let mut score = 0;
+    for index in 1..=questions {
+        let start = Instant::now();
+        let (expr, evaluation) = question(&mut rng, difficulty);
+        let elapsed = start.elapsed();
+        let answer = evaluation.result.clone();
+        session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record(&Ok(evaluation), elapsed);
+        println!("Question {index}/{questions}: {expr} = ?");
+
+        let mut buf = String::new();
+        match io::stdin().read_line(&mut buf) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                error!("Error reading input data {e}");
+                break;
+            }
+        }
+        match buf.trim().parse::<i128>() {
+            Ok(value) if answer == value => {
+                score += 1;
+                println!("Correct!");
+            }
+            Ok(_) => println!("Wrong, {expr} = {answer}"),
+            Err(_) => println!("Answer {} is not an integer, {expr} = {answer}", buf.trim()),
+        }
+    }
+    println!("Score: {score}/{questions}");
+}
+
+///
+/// Draw expressions until one evaluates exactly, so every question has an integer answer
+///
+fn question<R: Rng>(rng: &mut R, difficulty: Difficulty) -> (String, Evaluation) {
+    let settings = Settings {
+        exact: true,
+        ..Default::default()
+    };
+    loop {
+        let operands = rng.random_range(difficulty.operands());
+        let expr = generate(rng, difficulty, operands);
+        if let Ok(evaluation) = evaluate(&expr, &settings) {
+            return (expr, evaluation);
+        }
+    }
+}
+
+///
+/// Build a random expression made of the given number of operands. Divisors are always
+/// single non zero leaves, so a generated expression never divides by zero
+///
+fn generate<R: Rng>(rng: &mut R, difficulty: Difficulty, operands: usize) -> String {
+    if operands == 1 {
+        return leaf(rng, difficulty);
+    }
+
+    let operator = *difficulty
+        .operators()
+        .choose(rng)
+        .expect("Every difficulty has operators");
+    let left = match operator {
+        '/' => operands - 1,
+        _ => rng.random_range(1..operands),
+    };
+    let lhs = operand(rng, difficulty, left);
+    let rhs = operand(rng, difficulty, operands - left);
+    format!("{lhs} {operator} {rhs}")
+}
+
+fn operand<R: Rng>(rng: &mut R, difficulty: Difficulty, operands: usize) -> String {
+    let expr = generate(rng, difficulty, operands);
+    if operands > 1 && rng.random_bool(difficulty.brackets()) {
+        format!("({expr})")
+    } else {
+        expr
+    }
+}
+
+fn leaf<R: Rng>(rng: &mut R, difficulty: Difficulty) -> String {
+    if rng.random_bool(difficulty.powers()) {
+        return format!("{}^{}", rng.random_range(2..=5), rng.random_range(2..=3));
+    }
+    loop {
+        let n = rng.random_range(difficulty.numbers());
+        if n != 0 {
+            return n.to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::{Difficulty, question};
+    use calculator::{Settings, evaluate};
+
+    #[test]
+    fn test_questions_have_exact_answers() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+            for _ in 0..50 {
+                let (expr, answer) = question(&mut rng, difficulty);
+                let evaluation = evaluate(&expr, &Settings::default()).unwrap();
+
+                assert_eq!(evaluation.result, answer.result);
+                assert!(evaluation.warnings.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_easy_questions_only_add_and_subtract() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let (expr, _) = question(&mut rng, Difficulty::Easy);
+
+            assert!(!expr.contains(['*', '/', '^', '(']), "{expr}");
+        }
+    }
+}
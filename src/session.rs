@@ -0,0 +1,116 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use calculator::{Error, Evaluation, Number};
+
+///
+/// Running totals of the expressions evaluated during a REPL session
+///
+#[derive(Debug)]
+pub(crate) struct Session {
+    started: Instant,
+    evaluated: usize,
+    /// Number of failed evaluations by kind of error
+    errors: BTreeMap<&'static str, usize>,
+    min: Option<Number>,
+    max: Option<Number>,
+    /// Time spent evaluating, reading input excluded
+    computing: Duration,
+}
+
+impl Session {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            evaluated: 0,
+            errors: BTreeMap::new(),
+            min: None,
+            max: None,
+            computing: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn record(&mut self, outcome: &Result<Evaluation, Error>, elapsed: Duration) {
+        self.evaluated += 1;
+        self.computing += elapsed;
+        match outcome {
+            Ok(evaluation) => {
+                let result = &evaluation.result;
+                if self.min.as_ref().is_none_or(|min| result < min) {
+                    self.min = Some(result.clone());
+                }
+                if self.max.as_ref().is_none_or(|max| result > max) {
+                    self.max = Some(result.clone());
+                }
+            }
+            Err(e) => *self.errors.entry(e.kind()).or_default() += 1,
+        }
+    }
+
+    fn failed(&self) -> usize {
+        self.errors.values().sum()
+    }
+
+    ///
+    /// One line recap of the session, printed on exit
+    ///
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "Session summary: {} expressions evaluated, {} failed, {:.2?} elapsed",
+            self.evaluated,
+            self.failed(),
+            self.started.elapsed()
+        )
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Expressions evaluated: {}", self.evaluated)?;
+        if self.evaluated > 0 {
+            writeln!(
+                f,
+                "Errors: {} ({:.1}%)",
+                self.failed(),
+                100.0 * self.failed() as f64 / self.evaluated as f64
+            )?;
+        }
+        for (kind, count) in &self.errors {
+            writeln!(f, "  {kind}: {count}")?;
+        }
+        if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            writeln!(f, "Min result: {min}, max result: {max}")?;
+        }
+        writeln!(f, "Computing time: {:?}", self.computing)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Session;
+    use calculator::{Settings, evaluate};
+
+    #[test]
+    fn test_session_stats() {
+        let mut session = Session::new();
+        for expr in ["4 * 5", "3 - 10", "1 +", "7", "(", "2^100"] {
+            let outcome = evaluate(expr, &Settings::default());
+            session.record(&outcome, Duration::from_millis(2));
+        }
+
+        assert_eq!(
+            session.to_string(),
+            "Expressions evaluated: 6\n\
+             Errors: 3 (50.0%)\n  \
+             overflow: 1\n  \
+             trailing operator: 2\n\
+             Min result: -7, max result: 20\n\
+             Computing time: 12ms\n"
+        );
+    }
+}
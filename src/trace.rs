@@ -0,0 +1,96 @@
+use std::io;
+#[cfg(feature = "serde")]
+use std::{fs, path::PathBuf};
+
+use clap::ValueEnum;
+
+use calculator::TraceStep;
+
+///
+/// How evaluation traces are written
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum TraceFormat {
+    /// One line per step on standard output
+    Text,
+    /// JSON document with the steps of every evaluated expression
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, serde::Serialize)]
+struct ExpressionTrace {
+    expression: String,
+    steps: Vec<TraceStep>,
+}
+
+///
+/// Collects the evaluation steps of a session and writes them in the requested format
+///
+#[derive(Debug)]
+pub(crate) struct Tracer {
+    format: TraceFormat,
+    /// File the JSON traces are written to, standard output when None
+    #[cfg(feature = "serde")]
+    file: Option<PathBuf>,
+    #[cfg(feature = "serde")]
+    traces: Vec<ExpressionTrace>,
+}
+
+impl Tracer {
+    pub(crate) fn new(format: TraceFormat) -> Self {
+        Self {
+            format,
+            #[cfg(feature = "serde")]
+            file: None,
+            #[cfg(feature = "serde")]
+            traces: vec![],
+        }
+    }
+
+    ///
+    /// Write the JSON traces to a file rather than to standard output
+    ///
+    #[cfg(feature = "serde")]
+    pub(crate) fn with_file(self, file: Option<PathBuf>) -> Self {
+        Self { file, ..self }
+    }
+
+    ///
+    /// Write the steps of an expression. With a trace file the whole session is written
+    /// again, so that the file always holds a valid JSON array
+    ///
+    #[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+    pub(crate) fn record(&mut self, expression: &str, steps: Vec<TraceStep>) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Text => {
+                println!("Trace:");
+                for step in &steps {
+                    let stack = step
+                        .stack
+                        .iter()
+                        .map(|n| n.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    println!("  {} -> [{stack}] ({:?})", step.token, step.elapsed);
+                }
+            }
+            #[cfg(feature = "serde")]
+            TraceFormat::Json => {
+                let trace = ExpressionTrace {
+                    expression: expression.to_owned(),
+                    steps,
+                };
+                match &self.file {
+                    None => println!("{}", serde_json::to_string(&trace)?),
+                    Some(file) => {
+                        self.traces.push(trace);
+                        fs::write(file, serde_json::to_string_pretty(&self.traces)?)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}